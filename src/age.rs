@@ -0,0 +1,343 @@
+use std::fmt::{self, Debug, Display, Formatter};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::crypto::{self, chacha20_poly1305, hkdf_sha256, hmac_sha256, x25519, x25519_base};
+use crate::{PanicReport, RecipientError, ReportFormatter};
+
+/// Encrypts what another formatter writes, so that only whoever holds the private key for one of
+/// the recipients, such as the service the reports are collected by, can read them, and whatever
+/// ends up on the user's disk is no use to anyone else.
+///
+/// Each report is its own file in the [age](https://age-encryption.org/v1) format, for X25519
+/// recipients, which `age --decrypt` and the libraries for it can decrypt. age files can't be
+/// appended to one another, so use it with [`handlers::directory`](crate::handlers::directory),
+/// which saves each report on its own, rather than [`handlers::file`](crate::handlers::file).
+///
+/// ## Example
+/// ```
+/// # use evac::{handlers, AgeRecipient, Encrypted, EvacBuilder, TextFormatter};
+/// // The public key, whose private key only the service collecting reports has
+/// let recipient: AgeRecipient =
+///     "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p".parse()?;
+/// let directory = std::env::temp_dir().join("my-app-crashes");
+/// let formatter = Encrypted::new(TextFormatter::new(), recipient);
+///
+/// EvacBuilder::new()
+///   .with_report_handler(handlers::directory(directory, "log.age", formatter))
+///   .register(())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Encrypted<F> {
+    formatter: F,
+    recipients: Vec<AgeRecipient>,
+}
+
+impl<F: ReportFormatter> Encrypted<F> {
+    /// Encrypts to `recipient`.
+    pub fn new(formatter: F, recipient: AgeRecipient) -> Self {
+        Self {
+            formatter,
+            recipients: vec![recipient],
+        }
+    }
+
+    /// Encrypts to `recipient` as well, so that either of their private keys can decrypt reports,
+    /// such as for a key kept offline in case the service's is lost.
+    pub fn recipient(mut self, recipient: AgeRecipient) -> Self {
+        self.recipients.push(recipient);
+
+        self
+    }
+}
+
+impl<F: ReportFormatter> ReportFormatter for Encrypted<F> {
+    fn format(&self, report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()> {
+        let mut formatted = Vec::new();
+        self.formatter.format(report, &mut formatted)?;
+
+        out.write_all(&encrypt(&self.recipients, &formatted)?)
+    }
+}
+
+/// An age X25519 public key, as `age-keygen` prints them, see [`Encrypted`]. They're parsed from
+/// strings, such as `age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AgeRecipient([u8; 32]);
+
+const HRP: &str = "age";
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+impl FromStr for AgeRecipient {
+    type Err = RecipientError;
+
+    fn from_str(recipient: &str) -> Result<Self, Self::Err> {
+        let error = |reason| RecipientError {
+            recipient: recipient.to_string(),
+            reason,
+        };
+
+        // Bech32, with either case, but not both
+        if recipient.chars().any(|c| c.is_ascii_lowercase())
+            && recipient.chars().any(|c| c.is_ascii_uppercase())
+        {
+            return Err(error("mixes upper and lower case"));
+        }
+        let lowercase = recipient.to_ascii_lowercase();
+        let data = lowercase
+            .strip_prefix("age1")
+            .ok_or_else(|| error("doesn't start with `age1`"))?;
+        let values = data
+            .bytes()
+            .map(|c| {
+                CHARSET
+                    .iter()
+                    .position(|&valid| valid == c)
+                    .map(|v| v as u8)
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(|| error("has characters bech32 doesn't use"))?;
+        if values.len() < 6 || polymod(&bech32_values(&values)) != 1 {
+            return Err(error("has the wrong checksum"));
+        }
+
+        let key = regroup(&values[..values.len() - 6], 5, 8, false)
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| error("isn't 32 bytes long"))?;
+        // Points of small order would leave every report with the same, public, key
+        if x25519(&[1; 32], &key) == [0; 32] {
+            return Err(error("isn't a usable X25519 key"));
+        }
+
+        Ok(Self(key))
+    }
+}
+
+impl Display for AgeRecipient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut values = regroup(&self.0, 8, 5, true).unwrap();
+        let checksum = polymod(&bech32_values(&[&values[..], &[0; 6]].concat())) ^ 1;
+        values.extend((0..6).map(|i| (checksum >> (5 * (5 - i))) as u8 & 31));
+
+        f.write_str(HRP)?;
+        f.write_str("1")?;
+        for value in values {
+            write!(f, "{}", CHARSET[value as usize] as char)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for AgeRecipient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("AgeRecipient")
+            .field(&self.to_string())
+            .finish()
+    }
+}
+
+/// The human-readable part, expanded as the checksum covers it, followed by `values`.
+fn bech32_values(values: &[u8]) -> Vec<u8> {
+    let mut expanded: Vec<u8> = HRP.bytes().map(|c| c >> 5).collect();
+    expanded.push(0);
+    expanded.extend(HRP.bytes().map(|c| c & 31));
+    expanded.extend(values);
+    expanded
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+
+    values.iter().fold(1, |check, &value| {
+        let top = check >> 25;
+        let check = (check & 0x1ff_ffff) << 5 ^ value as u32;
+        (0..5)
+            .filter(|i| (top >> i) & 1 == 1)
+            .fold(check, |check, i| check ^ GENERATOR[i])
+    })
+}
+
+/// Regroups `values` of `from` bits each into ones of `to` bits, padding the last with zeros if
+/// `pad`, or otherwise turning down any that's left over unless it's zero padding.
+fn regroup(values: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc = 0u32;
+    let mut bits = 0;
+    let mut out = vec![];
+    for &value in values {
+        acc = (acc << from | value as u32) & ((1 << (from + to - 1)) - 1);
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            out.push((acc >> bits) as u8 & ((1 << to) - 1) as u8);
+        }
+    }
+
+    if pad && bits > 0 {
+        out.push((acc << (to - bits)) as u8 & ((1 << to) - 1) as u8);
+    } else if !pad && (bits >= from || acc & ((1 << bits) - 1) != 0) {
+        return None;
+    }
+    Some(out)
+}
+
+/// How much of the plaintext goes into each chunk of the payload.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Encrypts `plaintext` as an age file for `recipients`.
+fn encrypt(recipients: &[AgeRecipient], plaintext: &[u8]) -> io::Result<Vec<u8>> {
+    let mut file_key = [0; 16];
+    crypto::random(&mut file_key)?;
+
+    // The file key, wrapped for each recipient with a key only they can work out
+    let mut out = b"age-encryption.org/v1\n".to_vec();
+    for AgeRecipient(recipient) in recipients {
+        let mut ephemeral = [0; 32];
+        crypto::random(&mut ephemeral)?;
+        let share = x25519_base(&ephemeral);
+
+        let salt = [&share[..], &recipient[..]].concat();
+        let wrap_key = hkdf_sha256(
+            &salt,
+            &x25519(&ephemeral, recipient),
+            b"age-encryption.org/v1/X25519",
+        );
+        let body = chacha20_poly1305(&wrap_key, &[0; 12], &file_key);
+
+        out.extend(format!("-> X25519 {}\n{}\n", base64(&share), base64(&body)).bytes());
+    }
+    out.extend(b"---");
+    let mac = hmac_sha256(&hkdf_sha256(&[], &file_key, b"header"), &out);
+    out.extend(format!(" {}\n", base64(&mac)).bytes());
+
+    let mut nonce = [0; 16];
+    crypto::random(&mut nonce)?;
+    out.extend(nonce);
+    let payload_key = hkdf_sha256(&nonce, &file_key, b"payload");
+
+    // Chunks are numbered, and the last is marked as such, so that none can be left out
+    let chunks = plaintext.len().div_ceil(CHUNK_SIZE).max(1);
+    for i in 0..chunks {
+        let chunk = &plaintext[i * CHUNK_SIZE..((i + 1) * CHUNK_SIZE).min(plaintext.len())];
+
+        let mut chunk_nonce = [0; 12];
+        chunk_nonce[3..11].copy_from_slice(&(i as u64).to_be_bytes());
+        chunk_nonce[11] = (i + 1 == chunks) as u8;
+        out.extend(chacha20_poly1305(&payload_key, &chunk_nonce, chunk));
+    }
+
+    Ok(out)
+}
+
+/// Base64 without padding, as age uses it.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let values = regroup(bytes, 8, 6, true).unwrap();
+    values
+        .into_iter()
+        .map(|value| ALPHABET[value as usize] as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::iter;
+
+    use ::age::x25519::Identity;
+
+    use super::*;
+
+    /// Decrypts `file` as age itself does, with `identity`.
+    fn decrypt(file: &[u8], identity: &Identity) -> Result<Vec<u8>, ::age::DecryptError> {
+        let decryptor = ::age::Decryptor::new(file)?;
+        let mut plaintext = vec![];
+        decryptor
+            .decrypt(iter::once(identity as &dyn ::age::Identity))?
+            .read_to_end(&mut plaintext)?;
+
+        Ok(plaintext)
+    }
+
+    fn recipient(identity: &Identity) -> AgeRecipient {
+        identity.to_public().to_string().parse().unwrap()
+    }
+
+    #[test]
+    fn reads_and_writes_recipients_as_age_does() {
+        let identity = Identity::generate();
+        let text = identity.to_public().to_string();
+
+        assert_eq!(recipient(&identity).to_string(), text);
+        assert_eq!(
+            text.to_ascii_uppercase().parse::<AgeRecipient>().unwrap(),
+            recipient(&identity)
+        );
+    }
+
+    #[test]
+    fn refuses_recipients_that_arent_right() {
+        let reason = |recipient: &str| recipient.parse::<AgeRecipient>().unwrap_err().reason;
+        let valid = "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p";
+
+        assert_eq!(
+            reason("age1QL3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p"),
+            "mixes upper and lower case"
+        );
+        assert_eq!(
+            reason(&valid.replacen("age1", "ssh1", 1)),
+            "doesn't start with `age1`"
+        );
+        assert_eq!(
+            reason(&valid.replacen('l', "b", 1)),
+            "has characters bech32 doesn't use"
+        );
+        assert_eq!(
+            reason(&valid.replacen("ql3z", "ql3y", 1)),
+            "has the wrong checksum"
+        );
+        assert_eq!(reason("age1qmk"), "has the wrong checksum");
+        // 16 bytes, with the right checksum
+        assert_eq!(
+            reason("age1qurswpc8qurswpc8qurswpc8qu5cpqmk"),
+            "isn't 32 bytes long"
+        );
+        assert_eq!(
+            reason(&AgeRecipient([0; 32]).to_string()),
+            "isn't a usable X25519 key"
+        );
+    }
+
+    #[test]
+    fn encrypts_files_age_decrypts() {
+        let identity = Identity::generate();
+
+        for len in [0, 1, CHUNK_SIZE, CHUNK_SIZE + 1, 2 * CHUNK_SIZE + 17] {
+            let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let file = encrypt(&[recipient(&identity)], &plaintext).unwrap();
+
+            assert_eq!(decrypt(&file, &identity).unwrap(), plaintext, "{len} bytes");
+        }
+    }
+
+    #[test]
+    fn encrypts_files_each_recipient_decrypts() {
+        let (first, second) = (Identity::generate(), Identity::generate());
+        let file = encrypt(
+            &[recipient(&first), recipient(&second)],
+            b"thread 'main' panicked",
+        )
+        .unwrap();
+
+        assert_eq!(decrypt(&file, &first).unwrap(), b"thread 'main' panicked");
+        assert_eq!(decrypt(&file, &second).unwrap(), b"thread 'main' panicked");
+        assert!(decrypt(&file, &Identity::generate()).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt::{self, Display, Formatter};
+
+/// Where one of the process's threads was when the panic was handled, see
+/// [`PanicReport::threads`](crate::PanicReport::threads).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct ThreadTrace {
+    /// The thread's ID, as the OS knows it.
+    pub tid: i32,
+    /// The thread's name, as the OS knows it, which may be cut short.
+    pub name: Option<String>,
+    /// What the thread was doing as far as the OS is concerned, such as `S` for sleeping or `D`
+    /// for waiting on IO.
+    pub state: Option<char>,
+    /// Whether this is the thread that panicked.
+    pub panicking: bool,
+    /// The thread's stack, innermost first. Empty if the thread didn't answer in time, such as if
+    /// it blocks signals.
+    pub frames: Vec<StackFrame>,
+    /// How many of its outermost frames were left out, see
+    /// [`ReportLimits::max_frames`](crate::ReportLimits::max_frames).
+    pub truncated_frames: usize,
+}
+
+/// A single frame of a [`ThreadTrace`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct StackFrame {
+    /// The frame's instruction pointer.
+    pub ip: usize,
+    /// The symbol the instruction pointer falls in, if the dynamic linker knows of it. Symbols
+    /// that aren't exported can be looked up from `ip` afterwards, such as with `addr2line`.
+    pub symbol: Option<String>,
+}
+
+impl Display for ThreadTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = self.name.as_deref().unwrap_or("<unnamed>");
+        write!(f, "thread '{name}' ({})", self.tid)?;
+        if let Some(state) = self.state {
+            write!(f, " [{state}]")?;
+        }
+        if self.panicking {
+            write!(f, " (panicking)")?;
+        }
+        writeln!(f)?;
+
+        if self.frames.is_empty() {
+            return writeln!(f, "  <no response>");
+        }
+        for (index, frame) in self.frames.iter().enumerate() {
+            match &frame.symbol {
+                Some(symbol) => writeln!(f, "  {index:>3}: {:#018x} {symbol}", frame.ip)?,
+                None => writeln!(f, "  {index:>3}: {:#018x}", frame.ip)?,
+            }
+        }
+        if self.truncated_frames > 0 {
+            writeln!(
+                f,
+                "       [truncated: {} more frames]",
+                self.truncated_frames
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Traces every thread in the process. Gives nothing where that isn't supported, which is
+/// anywhere but Linux with glibc.
+pub(crate) fn capture() -> Vec<ThreadTrace> {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    return linux::capture();
+
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    Vec::new()
+}
+
+/// Each thread is asked to trace itself from a signal handler, one at a time, as there's no
+/// unwinding another thread's stack from the outside without a debugger.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+mod linux {
+    use std::ffi::CStr;
+    use std::fs;
+    use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+    use std::sync::{Mutex, OnceLock, PoisonError};
+    use std::time::{Duration, Instant};
+    use std::{mem, ptr, thread};
+
+    use super::{StackFrame, ThreadTrace};
+
+    /// How deep each thread's stack is traced.
+    const MAX_FRAMES: usize = 64;
+    /// How long a thread has to answer before it's given up on.
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    /// What [`TARGET`] holds while no thread is being traced.
+    const IDLE: i32 = 0;
+    /// What [`TARGET`] holds while the thread is writing out its stack.
+    const CLAIMED: i32 = -1;
+    /// What [`TARGET`] holds once the thread has written out its stack.
+    const DONE: i32 = -2;
+
+    /// The thread being traced, or where that's got to.
+    static TARGET: AtomicI32 = AtomicI32::new(IDLE);
+    static FRAMES: [AtomicUsize; MAX_FRAMES] = [const { AtomicUsize::new(0) }; MAX_FRAMES];
+    static DEPTH: AtomicUsize = AtomicUsize::new(0);
+    /// Held while tracing, as there's only the one set of statics to trace into.
+    static TRACING: Mutex<()> = Mutex::new(());
+    /// The signal the handler is installed for, once it is.
+    static SIGNAL: OnceLock<Option<i32>> = OnceLock::new();
+
+    pub(super) fn capture() -> Vec<ThreadTrace> {
+        let _tracing = TRACING.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let Ok(tasks) = fs::read_dir("/proc/self/task") else {
+            return Vec::new();
+        };
+        let mut tids: Vec<i32> = tasks
+            .filter_map(|task| task.ok()?.file_name().to_str()?.parse().ok())
+            .collect();
+        tids.sort_unstable();
+
+        let Some(signal) = *SIGNAL.get_or_init(install) else {
+            return Vec::new();
+        };
+
+        // A thread given up on part way through an earlier trace may since have finished, or still
+        // be writing into the statics, in which case no other thread can be asked until it's done
+        let _ = TARGET.compare_exchange(DONE, IDLE, Ordering::AcqRel, Ordering::Acquire);
+        let mut stuck = TARGET.load(Ordering::Acquire) != IDLE;
+
+        // SAFETY: These have no preconditions
+        let (pid, current) = unsafe { (libc::getpid(), libc::gettid()) };
+        tids.into_iter()
+            .map(|tid| {
+                let frames = match tid == current {
+                    true => own_frames(),
+                    false if stuck => Vec::new(),
+                    false => request(pid, tid, signal).unwrap_or_else(|| {
+                        stuck = true;
+                        Vec::new()
+                    }),
+                };
+
+                let stat = fs::read_to_string(format!("/proc/self/task/{tid}/stat")).ok();
+                ThreadTrace {
+                    tid,
+                    name: fs::read_to_string(format!("/proc/self/task/{tid}/comm"))
+                        .ok()
+                        .map(|name| name.trim_end().to_owned()),
+                    // The state follows the name, which is in parentheses and may contain them
+                    state: stat.and_then(|stat| stat.rsplit_once(')')?.1.trim().chars().next()),
+                    panicking: tid == current,
+                    frames: frames.into_iter().map(resolve).collect(),
+                    truncated_frames: 0,
+                }
+            })
+            .collect()
+    }
+
+    /// Installs the handler, unless the application has one of its own for the signal. It's left
+    /// installed, as a thread that had the signal blocked may still receive it later, which would
+    /// otherwise end the process.
+    fn install() -> Option<i32> {
+        // A realtime signal, so as not to be mistaken for anything the application handles
+        let signal = libc::SIGRTMAX() - 1;
+
+        // SAFETY: The handler only touches atomics and calls `backtrace`, which is called here
+        // first so that it's done loading anything it needs
+        unsafe {
+            let mut frames = [ptr::null_mut(); 1];
+            libc::backtrace(frames.as_mut_ptr(), 1);
+
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = trace as extern "C" fn(libc::c_int) as usize;
+            action.sa_flags = libc::SA_RESTART | libc::SA_ONSTACK;
+            libc::sigemptyset(&mut action.sa_mask);
+
+            let mut previous: libc::sigaction = mem::zeroed();
+            if libc::sigaction(signal, &action, &mut previous) != 0 {
+                return None;
+            }
+
+            // Someone else's, so put back as it was
+            if previous.sa_sigaction != libc::SIG_DFL {
+                libc::sigaction(signal, &previous, ptr::null_mut());
+                return None;
+            }
+
+            Some(signal)
+        }
+    }
+
+    /// Asks the thread `tid` to trace itself, and waits for it to. Gives `None` if it's given up
+    /// on part way through, as it may yet write into the statics.
+    fn request(pid: i32, tid: i32, signal: i32) -> Option<Vec<usize>> {
+        TARGET.store(tid, Ordering::Release);
+
+        // SAFETY: The handler for `signal` is installed. This only fails if the thread has since
+        // exited
+        if unsafe { libc::tgkill(pid, tid, signal) } != 0 {
+            TARGET.store(IDLE, Ordering::Release);
+            return Some(Vec::new());
+        }
+
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            match TARGET.load(Ordering::Acquire) {
+                DONE => break,
+                // Once it's started, it can't be stopped, so it's left to finish whenever it does
+                CLAIMED if Instant::now() >= deadline => return None,
+                CLAIMED => {}
+                // Unless it starts right as this gives up on it
+                _ if Instant::now() >= deadline
+                    && TARGET
+                        .compare_exchange(tid, IDLE, Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok() =>
+                {
+                    return Some(Vec::new());
+                }
+                _ => {}
+            }
+
+            thread::yield_now();
+        }
+
+        let depth = DEPTH.load(Ordering::Acquire);
+        let frames = FRAMES[..depth]
+            .iter()
+            .map(|frame| frame.load(Ordering::Relaxed))
+            .collect();
+        TARGET.store(IDLE, Ordering::Release);
+
+        Some(frames)
+    }
+
+    /// The signal handler, which traces the thread it's run on if it's the one asked for.
+    extern "C" fn trace(_: libc::c_int) {
+        // SAFETY: `gettid` is async-signal-safe
+        let tid = unsafe { libc::gettid() };
+        // Too late, or a stray signal
+        if TARGET
+            .compare_exchange(tid, CLAIMED, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return;
+        }
+
+        let mut frames = [ptr::null_mut(); MAX_FRAMES];
+        // SAFETY: `frames` holds `MAX_FRAMES` entries
+        let depth = unsafe { libc::backtrace(frames.as_mut_ptr(), MAX_FRAMES as libc::c_int) };
+        let depth = usize::try_from(depth).unwrap_or(0);
+
+        // The first frame is this handler's own
+        let frames = frames.get(1..depth).unwrap_or_default();
+        for (slot, frame) in FRAMES.iter().zip(frames) {
+            slot.store(*frame as usize, Ordering::Relaxed);
+        }
+        DEPTH.store(frames.len(), Ordering::Release);
+        TARGET.store(DONE, Ordering::Release);
+    }
+
+    fn own_frames() -> Vec<usize> {
+        let mut frames = [ptr::null_mut(); MAX_FRAMES];
+        // SAFETY: `frames` holds `MAX_FRAMES` entries
+        let depth = unsafe { libc::backtrace(frames.as_mut_ptr(), MAX_FRAMES as libc::c_int) };
+        let depth = usize::try_from(depth).unwrap_or(0);
+
+        // Skipping this function's own frame
+        frames
+            .get(1..depth)
+            .unwrap_or_default()
+            .iter()
+            .map(|frame| *frame as usize)
+            .collect()
+    }
+
+    fn resolve(ip: usize) -> StackFrame {
+        // SAFETY: `dladdr` only reads the loaded objects' symbol tables, and the name it gives is
+        // copied out straight away
+        let symbol = unsafe {
+            let mut info: libc::Dl_info = mem::zeroed();
+            match libc::dladdr(ip as *const libc::c_void, &mut info) {
+                0 => None,
+                _ if info.dli_sname.is_null() => None,
+                _ => Some(
+                    CStr::from_ptr(info.dli_sname)
+                        .to_string_lossy()
+                        .into_owned(),
+                ),
+            }
+        };
+
+        StackFrame { ip, symbol }
+    }
+}
@@ -0,0 +1,81 @@
+/// What was built, and how, see [`app_metadata!`](crate::app_metadata!).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct AppMetadata {
+    /// The crate's name, from `Cargo.toml`.
+    pub name: &'static str,
+    /// The crate's version, from `Cargo.toml`.
+    pub version: &'static str,
+    /// The target triple it was built for, such as `x86_64-unknown-linux-gnu`. Only known with
+    /// build script support, see [`build::emit_metadata`](crate::build::emit_metadata).
+    pub target: Option<&'static str>,
+    /// The profile it was built with, `debug` or `release`. Without build script support, it's
+    /// told from whether debug assertions are on.
+    pub profile: &'static str,
+    /// The optimization level it was built with, such as `3`. Only known with build script support.
+    pub opt_level: Option<&'static str>,
+    /// The git commit it was built from. Only known with build script support, and if it was built
+    /// from a git checkout.
+    pub git_commit: Option<&'static str>,
+}
+
+impl AppMetadata {
+    #[doc(hidden)]
+    pub const fn __new(
+        name: &'static str,
+        version: &'static str,
+        target: Option<&'static str>,
+        profile: &'static str,
+        opt_level: Option<&'static str>,
+        git_commit: Option<&'static str>,
+    ) -> Self {
+        Self {
+            name,
+            version,
+            target,
+            profile,
+            opt_level,
+            git_commit,
+        }
+    }
+}
+
+/// Bakes in what the calling crate is, and how it was built, as [`AppMetadata`] to hand to
+/// [`EvacBuilder::app_metadata`](crate::EvacBuilder::app_metadata).
+///
+/// The name, version and profile are always known. The target, optimization level and git commit
+/// need the crate's build script to call [`build::emit_metadata`](crate::build::emit_metadata),
+/// with evac as a build dependency.
+///
+/// ## Example
+/// ```
+/// # use evac::EvacBuilder;
+/// EvacBuilder::new()
+///   .with_report_handler(|report, _: &mut ()| {
+///     if let Some(app) = report.app_metadata() {
+///       eprintln!("{} {} ({})", app.name, app.version, app.profile);
+///     }
+///     Ok(())
+///   })
+///   .app_metadata(evac::app_metadata!())
+///   .register(())?;
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+#[macro_export]
+macro_rules! app_metadata {
+    () => {
+        $crate::AppMetadata::__new(
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            option_env!("EVAC_BUILD_TARGET"),
+            match option_env!("EVAC_BUILD_PROFILE") {
+                Some(profile) => profile,
+                None if cfg!(debug_assertions) => "debug",
+                None => "release",
+            },
+            option_env!("EVAC_BUILD_OPT_LEVEL"),
+            option_env!("EVAC_BUILD_GIT_COMMIT"),
+        )
+    };
+}
@@ -0,0 +1,287 @@
+//! Sends panics to Azure Application Insights as exceptions, see
+//! [`handlers::app_insights`](crate::handlers::app_insights).
+
+use std::collections::BTreeMap;
+use std::io;
+
+use serde::Serialize;
+
+use crate::format::Rfc3339;
+use crate::{fingerprint, http, json, PanicReport};
+
+/// Where telemetry goes without an `IngestionEndpoint`.
+const DEFAULT_ENDPOINT: &str = "https://dc.services.visualstudio.com";
+
+/// The most frames a stack is sent with, as Application Insights won't take much more.
+const MAX_FRAMES: usize = 100;
+
+/// What a connection string says about where telemetry goes, and for which resource.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Connection {
+    pub(crate) key: String,
+    pub(crate) endpoint: String,
+}
+
+impl Connection {
+    /// Parses `connection`, such as
+    /// `InstrumentationKey=00000000-0000-0000-0000-000000000000;IngestionEndpoint=https://...`, or
+    /// an instrumentation key by itself.
+    pub(crate) fn parse(connection: &str) -> io::Result<Self> {
+        let invalid = |reason| io::Error::new(io::ErrorKind::InvalidInput, reason);
+
+        let mut key = None;
+        let mut endpoint = None;
+        if !connection.contains('=') {
+            key = Some(connection.trim().to_string());
+        }
+        for pair in connection.split(';').filter(|pair| pair.contains('=')) {
+            let (name, value) = pair.split_once('=').unwrap_or_default();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "instrumentationkey" => key = Some(value.trim().to_string()),
+                "ingestionendpoint" => endpoint = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+
+        let key = key
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| invalid("the connection string has no instrumentation key"))?;
+        if !key.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+            return Err(invalid("the instrumentation key isn't valid"));
+        }
+
+        Ok(Self {
+            key,
+            endpoint: endpoint.unwrap_or_else(|| DEFAULT_ENDPOINT.to_string()),
+        })
+    }
+
+    /// Where to send telemetry to, or any `endpoint` instead of the connection's own.
+    pub(crate) fn track_url(&self, endpoint: Option<&str>) -> io::Result<http::Url> {
+        let endpoint = endpoint.unwrap_or(&self.endpoint);
+
+        http::Url::parse(&format!("{}/v2/track", endpoint.trim_end_matches('/')))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope<'a> {
+    name: String,
+    time: String,
+    i_key: &'a str,
+    tags: BTreeMap<&'static str, String>,
+    data: Data<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Data<'a> {
+    base_type: &'static str,
+    base_data: ExceptionData<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExceptionData<'a> {
+    ver: u8,
+    exceptions: [ExceptionDetails<'a>; 1],
+    severity_level: u8,
+    problem_id: &'a str,
+    properties: BTreeMap<&'a str, &'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExceptionDetails<'a> {
+    id: u32,
+    type_name: &'static str,
+    message: &'a str,
+    has_full_stack: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stack: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    parsed_stack: Vec<StackFrame<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StackFrame<'a> {
+    level: usize,
+    method: &'a str,
+    assembly: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file_name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<u32>,
+}
+
+/// What the telemetry is tagged with, for the app's role, and the instance of it.
+pub(crate) struct Roles<'a> {
+    pub(crate) role: Option<&'a str>,
+    pub(crate) instance: Option<&'a str>,
+}
+
+/// `report` as an exception telemetry item for the resource `key`.
+pub(crate) fn telemetry(
+    report: &PanicReport<'_>,
+    key: &str,
+    roles: Roles<'_>,
+) -> io::Result<Vec<u8>> {
+    let env = |name| {
+        std::env::var(name)
+            .ok()
+            .filter(|value: &String| !value.is_empty())
+    };
+    let app = report.app_metadata();
+
+    let mut tags = BTreeMap::new();
+    // As App Service names the site and the instance
+    let role = roles
+        .role
+        .map(str::to_string)
+        .or_else(|| env("WEBSITE_SITE_NAME"))
+        .or_else(|| app.map(|app| app.name.to_string()))
+        .or_else(crate::handlers::executable_name);
+    #[cfg(feature = "sysinfo")]
+    let hostname = report.system_info().and_then(|host| host.hostname.clone());
+    #[cfg(not(feature = "sysinfo"))]
+    let hostname = None;
+    let instance = roles
+        .instance
+        .map(str::to_string)
+        .or_else(|| env("WEBSITE_INSTANCE_ID"))
+        .or(hostname)
+        .or_else(|| env("HOSTNAME"));
+    if let Some(role) = role {
+        tags.insert("ai.cloud.role", role);
+    }
+    if let Some(instance) = instance {
+        tags.insert("ai.cloud.roleInstance", instance);
+    }
+    if let Some(app) = app {
+        tags.insert("ai.application.ver", app.version.to_string());
+    }
+    tags.insert(
+        "ai.internal.sdkVersion",
+        format!("evac:{}", env!("CARGO_PKG_VERSION")),
+    );
+
+    let mut properties: BTreeMap<&str, &str> = report
+        .annotations()
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    properties.insert("thread", report.thread_name().unwrap_or("<unnamed>"));
+    let location = report.location().map(|location| location.to_string());
+    if let Some(location) = &location {
+        properties.insert("location", location);
+    }
+
+    let backtrace = report.backtrace_text();
+    let frames = backtrace
+        .as_deref()
+        .map(fingerprint::located)
+        .unwrap_or_default();
+    let parsed_stack: Vec<StackFrame<'_>> = frames
+        .iter()
+        .take(MAX_FRAMES)
+        .enumerate()
+        .map(|(level, frame)| StackFrame {
+            level,
+            method: frame.symbol,
+            assembly: assembly(frame.symbol),
+            file_name: frame.file,
+            line: frame.line,
+        })
+        .collect();
+
+    let envelope = Envelope {
+        name: format!(
+            "Microsoft.ApplicationInsights.{}.Exception",
+            key.replace('-', "")
+        ),
+        time: Rfc3339(report.timestamp()).to_string(),
+        i_key: key,
+        tags,
+        data: Data {
+            base_type: "ExceptionData",
+            base_data: ExceptionData {
+                ver: 2,
+                exceptions: [ExceptionDetails {
+                    id: 1,
+                    type_name: "panic",
+                    message: report.message().unwrap_or("Box<dyn Any>"),
+                    has_full_stack: frames.len() <= MAX_FRAMES,
+                    // The backtrace as it is, when there's no telling its frames apart
+                    stack: backtrace.clone().filter(|_| frames.is_empty()),
+                    parsed_stack,
+                }],
+                // Critical
+                severity_level: 4,
+                problem_id: report.fingerprint(),
+                properties,
+            },
+        },
+    };
+
+    json::to_vec(&envelope)
+}
+
+/// The crate `symbol` is from, as the closest thing to an assembly, such as `my_app` for
+/// `my_app::main` or `<my_app::Config as core::fmt::Debug>::fmt`.
+fn assembly(symbol: &str) -> &str {
+    let symbol = symbol
+        .trim_start_matches(['<', '&'])
+        .trim_start_matches("dyn ");
+    let end = symbol.find(['<', '>', ' ']).unwrap_or(symbol.len());
+
+    symbol[..end].split("::").next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "00000000-0000-0000-0000-000000000000";
+
+    #[test]
+    fn sends_to_the_global_endpoint_by_default() {
+        let connection = Connection::parse(KEY).unwrap();
+        assert_eq!(connection.key, KEY);
+
+        let url = connection.track_url(None).unwrap();
+        assert_eq!(url.authority, "dc.services.visualstudio.com");
+        assert_eq!(url.path, "/v2/track");
+    }
+
+    #[test]
+    fn sends_to_the_connection_strings_endpoint() {
+        let connection = Connection::parse(&format!(
+            "InstrumentationKey={KEY};IngestionEndpoint=https://westeurope-5.in.applicationinsights.azure.com/;LiveEndpoint=https://westeurope.livediagnostics.monitor.azure.com/"
+        ))
+        .unwrap();
+        let url = connection.track_url(None).unwrap();
+        assert_eq!(
+            url.authority,
+            "westeurope-5.in.applicationinsights.azure.com"
+        );
+        assert_eq!(url.path, "/v2/track");
+
+        let url = connection
+            .track_url(Some("http://egress-proxy.internal:8080"))
+            .unwrap();
+        assert_eq!(url.authority, "egress-proxy.internal:8080");
+    }
+
+    #[test]
+    fn refuses_connection_strings_without_a_valid_key() {
+        for connection in [
+            "",
+            "IngestionEndpoint=https://example.com",
+            "InstrumentationKey=not a key",
+        ] {
+            assert!(Connection::parse(connection).is_err(), "{connection}");
+        }
+    }
+}
@@ -0,0 +1,750 @@
+//! Just enough of MessagePack and CBOR serializers for reports, for when JSON is too large. Both
+//! share everything but how each value's header is encoded, see [`Encoding`].
+//!
+//! Structs are written as maps keyed by field name, in the order they're serialized, and enum
+//! variants other than unit ones as a map from the variant's name to its contents, as with JSON.
+
+use std::io::{self, Write};
+use std::marker::PhantomData;
+
+use serde::ser::{self, Error as _, Serialize};
+
+use crate::json::Error;
+
+/// Writes `value` in encoding `E`.
+pub(crate) fn to_writer<E, W, T>(writer: &mut W, value: &T) -> io::Result<()>
+where
+    E: Encoding,
+    W: Write + ?Sized,
+    T: Serialize + ?Sized,
+{
+    let mut encoded = vec![];
+    value
+        .serialize(Binary::<E>::new(&mut encoded))
+        .map_err(|Error(e)| e)?;
+
+    writer.write_all(&encoded)
+}
+
+/// What's being given a length.
+#[derive(Clone, Copy)]
+pub(crate) enum Header {
+    Str,
+    Bytes,
+    Array,
+    /// Counted in entries, not keys and values.
+    Map,
+}
+
+/// How a binary format encodes each kind of value.
+pub(crate) trait Encoding {
+    fn nil(out: &mut Vec<u8>);
+    fn bool(out: &mut Vec<u8>, v: bool);
+    fn uint(out: &mut Vec<u8>, v: u64);
+    /// Only called with negative numbers, as the rest are given to [`uint`](Self::uint).
+    fn negative(out: &mut Vec<u8>, v: i64);
+    fn f32(out: &mut Vec<u8>, v: f32);
+    fn f64(out: &mut Vec<u8>, v: f64);
+    /// Starts a value of `len` bytes, elements or entries, which follow.
+    fn header(out: &mut Vec<u8>, header: Header, len: usize) -> Result<(), Error>;
+}
+
+/// MessagePack, see <https://github.com/msgpack/msgpack/blob/master/spec.md>.
+#[cfg(feature = "msgpack")]
+pub(crate) struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Encoding for MessagePack {
+    fn nil(out: &mut Vec<u8>) {
+        out.push(0xc0);
+    }
+
+    fn bool(out: &mut Vec<u8>, v: bool) {
+        out.push(if v { 0xc3 } else { 0xc2 });
+    }
+
+    fn uint(out: &mut Vec<u8>, v: u64) {
+        if v < 0x80 {
+            out.push(v as u8);
+        } else if let Ok(v) = u8::try_from(v) {
+            out.extend([0xcc, v]);
+        } else if let Ok(v) = u16::try_from(v) {
+            out.push(0xcd);
+            out.extend(v.to_be_bytes());
+        } else if let Ok(v) = u32::try_from(v) {
+            out.push(0xce);
+            out.extend(v.to_be_bytes());
+        } else {
+            out.push(0xcf);
+            out.extend(v.to_be_bytes());
+        }
+    }
+
+    fn negative(out: &mut Vec<u8>, v: i64) {
+        if v >= -32 {
+            out.push(v as u8);
+        } else if let Ok(v) = i8::try_from(v) {
+            out.extend([0xd0, v as u8]);
+        } else if let Ok(v) = i16::try_from(v) {
+            out.push(0xd1);
+            out.extend(v.to_be_bytes());
+        } else if let Ok(v) = i32::try_from(v) {
+            out.push(0xd2);
+            out.extend(v.to_be_bytes());
+        } else {
+            out.push(0xd3);
+            out.extend(v.to_be_bytes());
+        }
+    }
+
+    fn f32(out: &mut Vec<u8>, v: f32) {
+        out.push(0xca);
+        out.extend(v.to_be_bytes());
+    }
+
+    fn f64(out: &mut Vec<u8>, v: f64) {
+        out.push(0xcb);
+        out.extend(v.to_be_bytes());
+    }
+
+    fn header(out: &mut Vec<u8>, header: Header, len: usize) -> Result<(), Error> {
+        // The marker for a length that fits in the marker itself, then those for 8, 16 and 32 bits
+        let (fixed, m8, m16, m32) = match header {
+            Header::Str => (Some((0xa0, 31)), Some(0xd9), 0xda, 0xdb),
+            Header::Bytes => (None, Some(0xc4), 0xc5, 0xc6),
+            Header::Array => (Some((0x90, 15)), None, 0xdc, 0xdd),
+            Header::Map => (Some((0x80, 15)), None, 0xde, 0xdf),
+        };
+
+        if let Some((fixed, _)) = fixed.filter(|&(_, max)| len <= max) {
+            out.push(fixed | len as u8);
+        } else if let (Some(m8), Ok(len)) = (m8, u8::try_from(len)) {
+            out.extend([m8, len]);
+        } else if let Ok(len) = u16::try_from(len) {
+            out.push(m16);
+            out.extend(len.to_be_bytes());
+        } else if let Ok(len) = u32::try_from(len) {
+            out.push(m32);
+            out.extend(len.to_be_bytes());
+        } else {
+            return Err(Error::custom("too long for MessagePack"));
+        }
+
+        Ok(())
+    }
+}
+
+/// CBOR, see RFC 8949.
+#[cfg(feature = "cbor")]
+pub(crate) struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Cbor {
+    /// Writes the initial byte for `major` type, and the argument `n` that follows it.
+    fn head(out: &mut Vec<u8>, major: u8, n: u64) {
+        let major = major << 5;
+        if n < 24 {
+            out.push(major | n as u8);
+        } else if let Ok(n) = u8::try_from(n) {
+            out.extend([major | 24, n]);
+        } else if let Ok(n) = u16::try_from(n) {
+            out.push(major | 25);
+            out.extend(n.to_be_bytes());
+        } else if let Ok(n) = u32::try_from(n) {
+            out.push(major | 26);
+            out.extend(n.to_be_bytes());
+        } else {
+            out.push(major | 27);
+            out.extend(n.to_be_bytes());
+        }
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl Encoding for Cbor {
+    fn nil(out: &mut Vec<u8>) {
+        out.push(0xf6);
+    }
+
+    fn bool(out: &mut Vec<u8>, v: bool) {
+        out.push(if v { 0xf5 } else { 0xf4 });
+    }
+
+    fn uint(out: &mut Vec<u8>, v: u64) {
+        Self::head(out, 0, v);
+    }
+
+    fn negative(out: &mut Vec<u8>, v: i64) {
+        // Encoded as -1 - n
+        Self::head(out, 1, !v as u64);
+    }
+
+    fn f32(out: &mut Vec<u8>, v: f32) {
+        out.push(0xfa);
+        out.extend(v.to_be_bytes());
+    }
+
+    fn f64(out: &mut Vec<u8>, v: f64) {
+        out.push(0xfb);
+        out.extend(v.to_be_bytes());
+    }
+
+    fn header(out: &mut Vec<u8>, header: Header, len: usize) -> Result<(), Error> {
+        let major = match header {
+            Header::Bytes => 2,
+            Header::Str => 3,
+            Header::Array => 4,
+            Header::Map => 5,
+        };
+        Self::head(out, major, len as u64);
+
+        Ok(())
+    }
+}
+
+struct Binary<'a, E> {
+    out: &'a mut Vec<u8>,
+    encoding: PhantomData<E>,
+}
+
+impl<'a, E: Encoding> Binary<'a, E> {
+    fn new(out: &'a mut Vec<u8>) -> Self {
+        Self {
+            out,
+            encoding: PhantomData,
+        }
+    }
+
+    fn string(self, v: &str) -> Result<(), Error> {
+        E::header(self.out, Header::Str, v.len())?;
+        self.out.extend_from_slice(v.as_bytes());
+
+        Ok(())
+    }
+
+    fn int(self, v: i64) -> Result<(), Error> {
+        match u64::try_from(v) {
+            Ok(v) => E::uint(self.out, v),
+            Err(_) => E::negative(self.out, v),
+        }
+
+        Ok(())
+    }
+
+    /// Starts a one-entry map from `variant` to its contents.
+    fn variant(self, variant: &'static str) -> Result<Self, Error> {
+        E::header(self.out, Header::Map, 1)?;
+        Binary::<E>::new(self.out).string(variant)?;
+
+        Ok(self)
+    }
+
+    fn compound(self, header: Header) -> Compound<'a, E> {
+        Compound {
+            out: self.out,
+            header,
+            len: 0,
+            items: vec![],
+            encoding: PhantomData,
+        }
+    }
+}
+
+impl<'a, E: Encoding> ser::Serializer for Binary<'a, E> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a, E>;
+    type SerializeTuple = Compound<'a, E>;
+    type SerializeTupleStruct = Compound<'a, E>;
+    type SerializeTupleVariant = Compound<'a, E>;
+    type SerializeMap = Compound<'a, E>;
+    type SerializeStruct = Compound<'a, E>;
+    type SerializeStructVariant = Compound<'a, E>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        E::bool(self.out, v);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.int(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.int(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.int(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.int(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Error> {
+        let v = i64::try_from(v).map_err(Error::custom)?;
+        self.int(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        E::uint(self.out, v);
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Error> {
+        let v = u64::try_from(v).map_err(Error::custom)?;
+        self.serialize_u64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        E::f32(self.out, v);
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        E::f64(self.out, v);
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.string(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.string(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        E::header(self.out, Header::Bytes, v.len())?;
+        self.out.extend_from_slice(v);
+
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        E::nil(self.out);
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), Error> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.string(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self.variant(variant)?)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Ok(self.compound(Header::Array))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Ok(self.variant(variant)?.compound(Header::Array))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(self.compound(Header::Map))
+    }
+
+    fn serialize_struct(self, _: &'static str, len: usize) -> Result<Self::SerializeStruct, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Ok(self.variant(variant)?.compound(Header::Map))
+    }
+}
+
+/// An array or map being written. Its contents are held back until it's finished, as both formats
+/// give the length first, and it isn't always known up front.
+pub(crate) struct Compound<'a, E> {
+    out: &'a mut Vec<u8>,
+    header: Header,
+    /// How many elements, or entries, have been written.
+    len: usize,
+    items: Vec<u8>,
+    encoding: PhantomData<E>,
+}
+
+impl<E: Encoding> Compound<'_, E> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.len += 1;
+        value.serialize(Binary::<E>::new(&mut self.items))
+    }
+
+    fn key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        key.serialize(Binary::<E>::new(&mut self.items))
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.key(key)?;
+        self.element(value)
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        E::header(self.out, self.header, self.len)?;
+        self.out.extend_from_slice(&self.items);
+
+        Ok(())
+    }
+}
+
+impl<E: Encoding> ser::SerializeSeq for Compound<'_, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<E: Encoding> ser::SerializeTuple for Compound<'_, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<E: Encoding> ser::SerializeTupleStruct for Compound<'_, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<E: Encoding> ser::SerializeTupleVariant for Compound<'_, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<E: Encoding> ser::SerializeMap for Compound<'_, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<E: Encoding> ser::SerializeStruct for Compound<'_, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<E: Encoding> ser::SerializeStructVariant for Compound<'_, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize, Serializer};
+
+    use super::*;
+    use crate::report::testing::with_report;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Variant {
+        Unit,
+        Newtype(u8),
+        Tuple(u8, String),
+        Struct { field: bool },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Sample {
+        bools: (bool, bool),
+        integers: Vec<i64>,
+        unsigned: (u8, u16, u32, u64),
+        floats: (f32, f64),
+        text: String,
+        long_text: String,
+        nothing: Option<u8>,
+        list: Vec<u16>,
+        long_list: Vec<u8>,
+        /// Serialized without a length up front.
+        #[serde(serialize_with = "unsized_seq")]
+        filtered: Vec<u32>,
+        map: BTreeMap<String, i32>,
+        variants: Vec<Variant>,
+    }
+
+    fn unsized_seq<S: Serializer>(values: &[u32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().filter(|_| true))
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            bools: (true, false),
+            // Either side of each width's bounds
+            integers: vec![
+                0,
+                -1,
+                -32,
+                -33,
+                127,
+                128,
+                -128,
+                -129,
+                255,
+                256,
+                65_535,
+                65_536,
+                -32_769,
+                i32::MIN.into(),
+                i32::MIN as i64 - 1,
+                u32::MAX.into(),
+                u32::MAX as i64 + 1,
+                i64::MIN,
+                i64::MAX,
+            ],
+            unsigned: (u8::MAX, u16::MAX, u32::MAX, u64::MAX),
+            floats: (1.5, -0.1),
+            text: "thread 'main' panicked é😀".into(),
+            long_text: "x".repeat(70_000),
+            nothing: None,
+            list: vec![1, 2, 3],
+            long_list: vec![7; 300],
+            filtered: vec![4, 5, 6],
+            map: [("a".into(), 1), ("b".into(), -2)].into(),
+            variants: vec![
+                Variant::Unit,
+                Variant::Newtype(1),
+                Variant::Tuple(2, "two".into()),
+                Variant::Struct { field: true },
+            ],
+        }
+    }
+
+    fn encode<E: Encoding, T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+        let mut out = vec![];
+        to_writer::<E, _, _>(&mut out, value).unwrap();
+        out
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn encodes_message_pack_as_the_spec_does() {
+        fn msgpack<T: Serialize>(value: T) -> Vec<u8> {
+            encode::<MessagePack, _>(&value)
+        }
+
+        assert_eq!(msgpack(()), [0xc0]);
+        assert_eq!(msgpack(true), [0xc3]);
+        assert_eq!(msgpack(127u8), [0x7f]);
+        assert_eq!(msgpack(128u8), [0xcc, 0x80]);
+        assert_eq!(msgpack(-1i8), [0xff]);
+        assert_eq!(msgpack(-33i8), [0xd0, 0xdf]);
+        assert_eq!(msgpack("a"), [0xa1, b'a']);
+        assert_eq!(msgpack(vec![1u8, 2]), [0x92, 1, 2]);
+        assert_eq!(msgpack(1.5f64), [0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn writes_what_rmp_serde_reads_back() {
+        let encoded = encode::<MessagePack, _>(&sample());
+
+        assert_eq!(rmp_serde::from_slice::<Sample>(&encoded).unwrap(), sample());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn writes_reports_rmp_serde_reads_back() {
+        let (ours, theirs) = with_report("a \"quoted\"\tmessage", |report| {
+            report.annotate("user.id", "42");
+            (
+                encode::<MessagePack, _>(&*report),
+                serde_json::to_value(&*report).unwrap(),
+            )
+        });
+
+        assert_eq!(
+            rmp_serde::from_slice::<serde_json::Value>(&ours).unwrap(),
+            theirs
+        );
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn encodes_cbor_as_rfc_8949_does() {
+        fn hex<T: Serialize>(value: T) -> String {
+            let encoded = encode::<Cbor, _>(&value);
+            encoded.iter().map(|byte| format!("{byte:02x}")).collect()
+        }
+
+        // From the RFC's appendix A
+        assert_eq!(hex(0u8), "00");
+        assert_eq!(hex(23u8), "17");
+        assert_eq!(hex(24u8), "1818");
+        assert_eq!(hex(1000u16), "1903e8");
+        assert_eq!(hex(1_000_000u32), "1a000f4240");
+        assert_eq!(hex(1_000_000_000_000u64), "1b000000e8d4a51000");
+        assert_eq!(hex(u64::MAX), "1bffffffffffffffff");
+        assert_eq!(hex(-1i8), "20");
+        assert_eq!(hex(-100i8), "3863");
+        assert_eq!(hex(-1000i16), "3903e7");
+        assert_eq!(hex(1.1f64), "fb3ff199999999999a");
+        assert_eq!(hex(false), "f4");
+        assert_eq!(hex(()), "f6");
+        assert_eq!(hex(""), "60");
+        assert_eq!(hex("IETF"), "6449455446");
+        assert_eq!(hex("\u{6c34}"), "63e6b0b4");
+        assert_eq!(hex(vec![1u8, 2, 3]), "83010203");
+        assert_eq!(hex(Vec::<u8>::new()), "80");
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn writes_what_ciborium_reads_back() {
+        let encoded = encode::<Cbor, _>(&sample());
+
+        assert_eq!(
+            ciborium::from_reader::<Sample, _>(&encoded[..]).unwrap(),
+            sample()
+        );
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn writes_reports_ciborium_reads_back() {
+        let (ours, theirs) = with_report("a \"quoted\"\tmessage", |report| {
+            report.annotate("user.id", "42");
+            (
+                encode::<Cbor, _>(&*report),
+                serde_json::to_value(&*report).unwrap(),
+            )
+        });
+
+        assert_eq!(
+            ciborium::from_reader::<serde_json::Value, _>(&ours[..]).unwrap(),
+            theirs
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! A trail of what the application was doing leading up to a panic, for the handlers to find on
+//! the report, see [`PanicReport::breadcrumbs`](crate::PanicReport::breadcrumbs).
+//!
+//! Only the latest [`CAPACITY`] are kept, in a ring buffer that every thread adds to without
+//! locking. They're taken out of it when a panic is reported, so each panic only gets the ones
+//! since the last.
+//!
+//! ## Example
+//! ```
+//! evac::breadcrumbs::add("http", "GET /users/42");
+//! evac::breadcrumbs::add("db", format!("query took {}ms", 12));
+//! ```
+
+use std::borrow::Cow;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
+use std::time::SystemTime;
+
+/// How many breadcrumbs are kept.
+pub const CAPACITY: usize = 100;
+
+/// The buffer, each slot owning the breadcrumb it points to, if any.
+static SLOTS: [AtomicPtr<Breadcrumb>; CAPACITY] =
+    [const { AtomicPtr::new(ptr::null_mut()) }; CAPACITY];
+/// How many breadcrumbs have ever been added, which decides the next one's slot.
+static ADDED: AtomicU64 = AtomicU64::new(0);
+
+/// Something the application was doing, see [`add`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Breadcrumb {
+    /// What part of the application it came from, such as `http`.
+    pub category: Cow<'static, str>,
+    pub message: Cow<'static, str>,
+    /// When it was added.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::report::serialize_millis")
+    )]
+    pub timestamp: SystemTime,
+    /// The order it was added in, relative to the others.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    sequence: u64,
+}
+
+/// Records that the application is doing something, pushing out the oldest breadcrumb once there
+/// are [`CAPACITY`] of them.
+pub fn add(category: impl Into<Cow<'static, str>>, message: impl Into<Cow<'static, str>>) {
+    let sequence = ADDED.fetch_add(1, Ordering::Relaxed);
+    let breadcrumb = Box::new(Breadcrumb {
+        category: category.into(),
+        message: message.into(),
+        timestamp: SystemTime::now(),
+        sequence,
+    });
+
+    let slot = &SLOTS[(sequence % CAPACITY as u64) as usize];
+    let replaced = slot.swap(Box::into_raw(breadcrumb), Ordering::AcqRel);
+    if !replaced.is_null() {
+        // SAFETY: Pointers only ever get into a slot from `Box::into_raw`, and whoever swaps one
+        // out owns it
+        drop(unsafe { Box::from_raw(replaced) });
+    }
+}
+
+/// Takes every breadcrumb out of the buffer, oldest first.
+pub(crate) fn drain() -> Vec<Breadcrumb> {
+    let mut breadcrumbs: Vec<_> = SLOTS
+        .iter()
+        .filter_map(|slot| {
+            let taken = slot.swap(ptr::null_mut(), Ordering::AcqRel);
+            // SAFETY: As in `add`
+            (!taken.is_null()).then(|| *unsafe { Box::from_raw(taken) })
+        })
+        .collect();
+    breadcrumbs.sort_unstable_by_key(|breadcrumb| breadcrumb.sequence);
+
+    breadcrumbs
+}
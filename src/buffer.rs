@@ -0,0 +1,125 @@
+//! The latest reports, kept in memory, for the application to show while it keeps running, such
+//! as on an admin or debug endpoint of a server that catches panics with
+//! [`catch_unwind`](std::panic::catch_unwind), see [`handlers::memory_buffer`].
+//!
+//! ## Example
+//! ```
+//! # use evac::{handlers, EvacBuilder};
+//! EvacBuilder::new()
+//!   .with_report_handler(handlers::memory_buffer(20))
+//!   .register(())?;
+//!
+//! let _ = std::panic::catch_unwind(|| panic!("request handler failed"));
+//!
+//! for report in evac::buffer::recent() {
+//!   println!("{}: {}", report.fingerprint, report.message.as_deref().unwrap_or("no message"));
+//! }
+//! # Ok::<(), evac::RegisterError>(())
+//! ```
+//!
+//! [`handlers::memory_buffer`]: crate::handlers::memory_buffer
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+use crate::breadcrumbs::Breadcrumb;
+use crate::{AppMetadata, PanicReport, PayloadType};
+
+/// The reports, oldest first, and how many of them are kept.
+static BUFFER: Mutex<Buffer> = Mutex::new(Buffer {
+    reports: VecDeque::new(),
+    capacity: 0,
+});
+
+struct Buffer {
+    reports: VecDeque<RecentReport>,
+    capacity: usize,
+}
+
+/// The reports in the buffer, oldest first. Gives none until a
+/// [`handlers::memory_buffer`](crate::handlers::memory_buffer) handler has handled a panic.
+pub fn recent() -> Vec<RecentReport> {
+    let buffer = BUFFER.lock().unwrap_or_else(PoisonError::into_inner);
+
+    buffer.reports.iter().cloned().collect()
+}
+
+/// Empties the buffer, such as once the reports in it have been looked into.
+pub fn clear() {
+    BUFFER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .reports
+        .clear();
+}
+
+/// Makes the buffer hold up to `capacity` reports, dropping the oldest ones it has over that.
+pub(crate) fn set_capacity(capacity: usize) {
+    let mut buffer = BUFFER.lock().unwrap_or_else(PoisonError::into_inner);
+    buffer.capacity = capacity;
+
+    let excess = buffer.reports.len().saturating_sub(capacity);
+    buffer.reports.drain(..excess);
+}
+
+/// Adds `report` to the buffer, pushing out the oldest one, if it's full.
+pub(crate) fn push(report: &PanicReport<'_>) {
+    let recent = RecentReport::from(report);
+
+    let mut buffer = BUFFER.lock().unwrap_or_else(PoisonError::into_inner);
+    if buffer.capacity == 0 {
+        return;
+    }
+    if buffer.reports.len() >= buffer.capacity {
+        buffer.reports.pop_front();
+    }
+    buffer.reports.push_back(recent);
+}
+
+/// A [`PanicReport`] as it was when it was buffered, see [`recent`]. With the `serde` feature, it
+/// can be serialized, such as to answer a request for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct RecentReport {
+    pub message: Option<String>,
+    pub payload_type: PayloadType,
+    /// Where it panicked, such as `src/main.rs:10:5`.
+    pub location: Option<String>,
+    pub thread_name: Option<String>,
+    pub pid: u32,
+    /// When the panic was reported.
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::report::serialize_millis")
+    )]
+    pub timestamp: SystemTime,
+    pub backtrace: Option<String>,
+    pub app_metadata: Option<AppMetadata>,
+    pub breadcrumbs: Vec<Breadcrumb>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::report::serialize_pairs")
+    )]
+    pub annotations: Vec<(String, String)>,
+    pub fingerprint: String,
+}
+
+impl From<&PanicReport<'_>> for RecentReport {
+    fn from(report: &PanicReport<'_>) -> Self {
+        Self {
+            message: report.message().map(str::to_string),
+            payload_type: report.payload_type(),
+            location: report.location().map(|location| location.to_string()),
+            thread_name: report.thread_name().map(str::to_string),
+            pid: report.pid(),
+            timestamp: report.timestamp(),
+            backtrace: report.backtrace_text(),
+            app_metadata: report.app_metadata().copied(),
+            breadcrumbs: report.breadcrumbs().to_vec(),
+            annotations: report.annotations().to_vec(),
+            fingerprint: report.fingerprint().to_string(),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Support for [`app_metadata!`](crate::app_metadata!) from build scripts.
+
+use std::env;
+use std::process::Command;
+
+/// Passes what only build scripts are told about the build on to
+/// [`app_metadata!`](crate::app_metadata!). Meant to be called from the crate's `build.rs`, with
+/// evac as a build dependency.
+///
+/// It also has Cargo rerun the build script whenever the commit changes. As with any
+/// `rerun-if-changed`, that stops Cargo rerunning it for other changes, unless the script asks for
+/// them too.
+///
+/// ## Example
+/// ```no_run
+/// // In build.rs's `main`
+/// evac::build::emit_metadata();
+/// ```
+pub fn emit_metadata() {
+    for (var, key) in [
+        ("TARGET", "EVAC_BUILD_TARGET"),
+        ("PROFILE", "EVAC_BUILD_PROFILE"),
+        ("OPT_LEVEL", "EVAC_BUILD_OPT_LEVEL"),
+    ] {
+        if let Ok(value) = env::var(var) {
+            println!("cargo:rustc-env={key}={value}");
+        }
+    }
+
+    // Not being in a git checkout, or not having git, just leaves the commit out
+    let commit = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=EVAC_BUILD_GIT_COMMIT={}", commit.trim());
+
+        // Rebuilt whenever the commit changes
+        if let Ok(dir) = Command::new("git")
+            .args(["rev-parse", "--git-dir"])
+            .output()
+        {
+            let dir = String::from_utf8_lossy(&dir.stdout);
+            println!("cargo:rerun-if-changed={}/HEAD", dir.trim());
+            println!("cargo:rerun-if-changed={}/refs", dir.trim());
+        }
+    }
+}
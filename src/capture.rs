@@ -0,0 +1,196 @@
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use crate::filter;
+
+/// What a redacted value is replaced with.
+pub(crate) const REDACTED: &str = "[redacted]";
+
+/// Which environment variables are captured, and which have their values hidden, see
+/// [`EvacBuilder::capture_env`](crate::EvacBuilder::capture_env).
+///
+/// Variables are picked out by name, with globs in which `*` matches any run of characters and
+/// `?` matches any one, ignoring case. Variables whose names look like they hold credentials,
+/// matching `*TOKEN*`, `*SECRET*`, `*KEY*` or `*PASSWORD*`, are always redacted.
+///
+/// ## Example
+/// ```
+/// # use evac::EnvCapture;
+/// let env = EnvCapture::new()
+///   .allow("APP_*")
+///   .allow("RUST_LOG")
+///   .deny("APP_INTERNAL_*")
+///   .redact("APP_DATABASE_URL");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvCapture {
+    allowed: Vec<String>,
+    denied: Vec<String>,
+    redacted: Vec<String>,
+}
+
+impl EnvCapture {
+    /// Captures every variable, redacting the ones that look like credentials.
+    pub fn new() -> Self {
+        Self {
+            allowed: vec![],
+            denied: vec![],
+            redacted: ["*TOKEN*", "*SECRET*", "*KEY*", "*PASSWORD*"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+
+    /// Only captures variables matching `pattern`, or any of the other allowed patterns.
+    pub fn allow(mut self, pattern: impl Into<String>) -> Self {
+        self.allowed.push(pattern.into());
+
+        self
+    }
+
+    /// Leaves out variables matching `pattern`, even if they're allowed.
+    pub fn deny(mut self, pattern: impl Into<String>) -> Self {
+        self.denied.push(pattern.into());
+
+        self
+    }
+
+    /// Hides the values of variables matching `pattern`, on top of the ones that look like
+    /// credentials.
+    pub fn redact(mut self, pattern: impl Into<String>) -> Self {
+        self.redacted.push(pattern.into());
+
+        self
+    }
+
+    /// Reads the variables, as of now. Names and values that aren't valid unicode are captured
+    /// lossily.
+    pub(crate) fn capture(&self) -> Vec<(String, String)> {
+        let matches = |patterns: &[String], name: &str| {
+            patterns
+                .iter()
+                .any(|pattern| filter::name_matches(pattern, name))
+        };
+
+        let mut vars: Vec<_> = std::env::vars_os()
+            .filter_map(|(name, value)| {
+                let name = name.to_string_lossy().into_owned();
+                if !self.allowed.is_empty() && !matches(&self.allowed, &name) {
+                    return None;
+                }
+                if matches(&self.denied, &name) {
+                    return None;
+                }
+
+                let value = match matches(&self.redacted, &name) {
+                    true => REDACTED.to_string(),
+                    false => value.to_string_lossy().into_owned(),
+                };
+
+                Some((name, value))
+            })
+            .collect();
+        vars.sort_unstable();
+
+        vars
+    }
+}
+
+impl Default for EnvCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decides whether an argument is redacted, see [`ArgsCapture::redact_if`].
+type ArgPredicate = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Which command-line arguments have their values hidden, see
+/// [`EvacBuilder::capture_args`](crate::EvacBuilder::capture_args).
+///
+/// The argument after a flag that looks like it's followed by a credential, `--password`,
+/// `--token`, `--secret` or `--api-key`, is always redacted, as is the value in the
+/// `--password=...` form.
+///
+/// ## Example
+/// ```
+/// # use evac::ArgsCapture;
+/// let args = ArgsCapture::new()
+///   .redact_after("--db-url")
+///   .redact_if(|arg| arg.starts_with("postgres://"));
+/// ```
+#[derive(Clone)]
+pub struct ArgsCapture {
+    flags: Vec<String>,
+    predicates: Vec<ArgPredicate>,
+}
+
+impl ArgsCapture {
+    /// Captures every argument, redacting the ones that look like credentials.
+    pub fn new() -> Self {
+        Self {
+            flags: ["--password", "--token", "--secret", "--api-key"]
+                .map(String::from)
+                .to_vec(),
+            predicates: vec![],
+        }
+    }
+
+    /// Hides whatever follows `flag`, be it the next argument or after an `=`.
+    pub fn redact_after(mut self, flag: impl Into<String>) -> Self {
+        self.flags.push(flag.into());
+
+        self
+    }
+
+    /// Hides any argument `predicate` picks out.
+    pub fn redact_if<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.predicates.push(Arc::new(predicate));
+
+        self
+    }
+
+    /// Reads the arguments, the program's name first. Arguments that aren't valid unicode are
+    /// captured lossily.
+    pub(crate) fn capture(&self) -> Vec<String> {
+        let mut after_flag = false;
+
+        std::env::args_os()
+            .map(|arg| {
+                let arg = arg.to_string_lossy().into_owned();
+                let redacted = after_flag || self.predicates.iter().any(|redact| redact(&arg));
+                after_flag = self.flags.contains(&arg);
+
+                if redacted {
+                    return REDACTED.to_string();
+                }
+
+                // The `--flag=value` form only hides the value
+                match arg.split_once('=') {
+                    Some((flag, _)) if self.flags.iter().any(|f| f == flag) => {
+                        format!("{flag}={REDACTED}")
+                    }
+                    _ => arg,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for ArgsCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for ArgsCapture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArgsCapture")
+            .field("flags", &self.flags)
+            .field("predicates", &self.predicates.len())
+            .finish()
+    }
+}
@@ -0,0 +1,154 @@
+//! Pushes reports to CloudWatch Logs, see
+//! [`handlers::cloudwatch_logs`](crate::handlers::cloudwatch_logs).
+
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::aws::{self, AwsCredentials};
+use crate::{http, json};
+
+/// Where events go, and how requests for them are signed.
+pub(crate) struct Stream<'a> {
+    pub(crate) url: &'a http::Url,
+    pub(crate) group: &'a str,
+    pub(crate) stream: &'a str,
+    pub(crate) region: &'a str,
+    pub(crate) credentials: &'a AwsCredentials,
+    /// Whether to create the stream if it doesn't exist.
+    pub(crate) create: bool,
+    pub(crate) timeout: Duration,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PutLogEvents<'a> {
+    log_group_name: &'a str,
+    log_stream_name: &'a str,
+    log_events: [LogEvent<'a>; 1],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence_token: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct LogEvent<'a> {
+    timestamp: u64,
+    message: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateLogStream<'a> {
+    log_group_name: &'a str,
+    log_stream_name: &'a str,
+}
+
+/// An error, along with the status the service answered with, if it did, as retries go by.
+type Failed = (Option<u16>, io::Error);
+
+impl Stream<'_> {
+    /// Puts `message` in the stream as an event of its own, as of `time`, creating the stream
+    /// first, if need be, and following the sequence token the service expects, for streams that
+    /// still have them.
+    pub(crate) fn put(&self, message: &str, time: SystemTime) -> Result<(), Failed> {
+        let timestamp = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let mut sequence_token = None;
+        let mut created = false;
+        // Rarely more than twice, for a new stream, then a stale token
+        for _ in 0..4 {
+            let body = json::to_vec(&PutLogEvents {
+                log_group_name: self.group,
+                log_stream_name: self.stream,
+                log_events: [LogEvent { timestamp, message }],
+                sequence_token: sequence_token.as_deref(),
+            })
+            .map_err(|e| (None, e))?;
+
+            let (status, answer) = self.call("PutLogEvents", &body)?;
+            let answer = match status {
+                200..=299 => return Ok(()),
+                _ => String::from_utf8_lossy(&answer).into_owned(),
+            };
+            match error_type(&answer).as_deref() {
+                // From an earlier attempt that seemed to fail
+                Some("DataAlreadyAcceptedException") => return Ok(()),
+                Some("InvalidSequenceTokenException") => {
+                    sequence_token = json::string_field(&answer, "expectedSequenceToken");
+                    if sequence_token.is_none() {
+                        return Err((Some(status), error(status, &answer)));
+                    }
+                }
+                Some("ResourceNotFoundException") if self.create && !created => {
+                    self.create_stream()?;
+                    created = true;
+                }
+                _ => return Err((Some(status), error(status, &answer))),
+            }
+        }
+
+        Err((
+            None,
+            io::Error::other("CloudWatch Logs kept turning the event away"),
+        ))
+    }
+
+    fn create_stream(&self) -> Result<(), Failed> {
+        let body = json::to_vec(&CreateLogStream {
+            log_group_name: self.group,
+            log_stream_name: self.stream,
+        })
+        .map_err(|e| (None, e))?;
+
+        let (status, answer) = self.call("CreateLogStream", &body)?;
+        let answer = String::from_utf8_lossy(&answer);
+        match status {
+            200..=299 => Ok(()),
+            // Created by another process in the meantime
+            _ if error_type(&answer).as_deref() == Some("ResourceAlreadyExistsException") => Ok(()),
+            _ => Err((Some(status), error(status, &answer))),
+        }
+    }
+
+    /// Makes a request for `action`, such as `PutLogEvents`, with `body`.
+    fn call(&self, action: &str, body: &[u8]) -> Result<(u16, Vec<u8>), Failed> {
+        let headers = aws::sign(
+            "POST",
+            "logs",
+            self.url,
+            vec![
+                ("content-type", "application/x-amz-json-1.1".to_string()),
+                ("x-amz-target", format!("Logs_20140328.{action}")),
+            ],
+            body,
+            self.region,
+            self.credentials,
+            SystemTime::now(),
+        );
+
+        http::request("POST", self.url, &headers, body, self.timeout).map_err(|e| (None, e))
+    }
+}
+
+/// The kind of error in `answer`, such as `ResourceNotFoundException`, without any namespace.
+fn error_type(answer: &str) -> Option<String> {
+    let kind = json::string_field(answer, "__type")?;
+
+    Some(kind.rsplit('#').next().unwrap_or_default().to_string())
+}
+
+fn error(status: u16, answer: &str) -> io::Error {
+    let message =
+        json::string_field(answer, "message").or_else(|| json::string_field(answer, "Message"));
+    let kind = error_type(answer);
+
+    io::Error::other(match (kind, message) {
+        (Some(kind), Some(message)) => format!("CloudWatch Logs answered with {kind}: {message}"),
+        (Some(kind), None) => format!("CloudWatch Logs answered with {kind}"),
+        _ => format!("CloudWatch Logs answered with status {status}"),
+    })
+}
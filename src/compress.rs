@@ -0,0 +1,250 @@
+use std::io::{self, Write};
+
+use crate::{PanicReport, ReportFormatter};
+
+/// Compresses what another formatter writes, such as for large reports with many threads or
+/// attachments, so that they take less room on disk or on the wire. Each report is compressed on
+/// its own, as a whole gzip member or zstd frame, so that a file of them one after another still
+/// decompresses as one.
+///
+/// It's a [`ReportFormatter`] like any other, so it can be given to any handler that takes one,
+/// see [`gzip`](Compressed::gzip) and [`zstd`](Compressed::zstd).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Compressed<F> {
+    formatter: F,
+    codec: Codec,
+    level: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Codec {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl<F: ReportFormatter> Compressed<F> {
+    /// Compresses with gzip, at level 6 unless told otherwise.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{handlers, Compressed, EvacBuilder, TextFormatter};
+    /// let path = std::env::temp_dir().join("crashes.log.gz");
+    ///
+    /// EvacBuilder::new()
+    ///   .with_report_handler(handlers::file(path, Compressed::gzip(TextFormatter::new()).level(9)))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    #[cfg(feature = "gzip")]
+    pub fn gzip(formatter: F) -> Self {
+        Self {
+            formatter,
+            codec: Codec::Gzip,
+            level: 6,
+        }
+    }
+
+    /// Compresses with zstd, at level 3 unless told otherwise. Literals are stored as they are, so
+    /// gzip makes text smaller at the same speed, but zstd's frames are quicker to decompress.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{handlers, Compressed, EvacBuilder, TextFormatter};
+    /// let path = std::env::temp_dir().join("crashes.log.zst");
+    ///
+    /// EvacBuilder::new()
+    ///   .with_report_handler(handlers::file(path, Compressed::zstd(TextFormatter::compact())))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    #[cfg(feature = "zstd")]
+    pub fn zstd(formatter: F) -> Self {
+        Self {
+            formatter,
+            codec: Codec::Zstd,
+            level: 3,
+        }
+    }
+
+    /// Compresses at `level`, trading time in the hook for smaller reports. For gzip it's from 0,
+    /// which stores reports as they are, to 9, and for zstd it's from 1 to 19. Levels out of range
+    /// are taken as the nearest in range.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = match self.codec {
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => level.min(9),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => level.clamp(1, 19),
+        };
+
+        self
+    }
+}
+
+impl<F: ReportFormatter> ReportFormatter for Compressed<F> {
+    fn format(&self, report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()> {
+        let mut formatted = Vec::new();
+        self.formatter.format(report, &mut formatted)?;
+
+        let compressed = match self.codec {
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => crate::gzip::compress(&formatted, self.level),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => crate::zstd::compress(&formatted, self.level),
+        };
+
+        out.write_all(&compressed)
+    }
+}
+
+/// A run of bytes that repeats an earlier one, see [`find_matches`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Match {
+    /// Where it starts.
+    pub(crate) pos: usize,
+    pub(crate) len: usize,
+    /// How far back the earlier run starts.
+    pub(crate) distance: usize,
+}
+
+/// What [`find_matches`] may find, as the formats allow.
+pub(crate) struct Limits {
+    pub(crate) min_len: usize,
+    pub(crate) max_len: usize,
+    pub(crate) max_distance: usize,
+    /// Matches don't run across multiples of this, if it's given, such as for formats that can't
+    /// have them run from one block into the next.
+    pub(crate) boundary: Option<usize>,
+    /// How many earlier runs starting with the same bytes are looked at for each match.
+    pub(crate) depth: usize,
+    /// Whether a match is put off by a byte when the next one would be longer.
+    pub(crate) lazy: bool,
+}
+
+const HASH_BITS: u32 = 15;
+
+/// Finds runs of `data` that repeat earlier ones, in order, for LZ77-style compression.
+pub(crate) fn find_matches(data: &[u8], limits: &Limits) -> Vec<Match> {
+    const NONE: usize = usize::MAX;
+
+    let hash = |pos: usize| {
+        let bytes = u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], 0]);
+        (bytes.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+    };
+    // The latest position with each hash, and the one before each position with the same hash
+    let mut head = vec![NONE; 1 << HASH_BITS];
+    let mut prev = vec![NONE; data.len()];
+    let insert = |pos: usize, head: &mut [usize], prev: &mut [usize]| {
+        if pos + 3 <= data.len() {
+            let h = hash(pos);
+            prev[pos] = head[h];
+            head[h] = pos;
+        }
+    };
+
+    let longest = |pos: usize, head: &[usize], prev: &[usize]| {
+        let mut max_len = limits.max_len.min(data.len() - pos);
+        if let Some(boundary) = limits.boundary {
+            max_len = max_len.min(boundary - pos % boundary);
+        }
+        if max_len < limits.min_len || pos + 3 > data.len() {
+            return None;
+        }
+
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = head[hash(pos)];
+        for _ in 0..limits.depth {
+            if candidate == NONE || pos - candidate > limits.max_distance {
+                break;
+            }
+
+            let len = data[candidate..]
+                .iter()
+                .zip(&data[pos..pos + max_len])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if len >= limits.min_len && best.is_none_or(|(best, _)| len > best) {
+                best = Some((len, pos - candidate));
+                if len == max_len {
+                    break;
+                }
+            }
+
+            candidate = prev[candidate];
+        }
+
+        best
+    };
+
+    let mut matches = vec![];
+    let mut pos = 0;
+    while pos < data.len() {
+        let found = longest(pos, &head, &prev);
+        insert(pos, &mut head, &mut prev);
+
+        let Some((mut len, mut distance)) = found else {
+            pos += 1;
+            continue;
+        };
+        // Put off for a longer match starting at the next byte, if there is one
+        let mut start = pos;
+        if limits.lazy && pos + 1 < data.len() {
+            if let Some((next_len, next_distance)) = longest(pos + 1, &head, &prev) {
+                if next_len > len {
+                    (start, len, distance) = (pos + 1, next_len, next_distance);
+                    insert(pos + 1, &mut head, &mut prev);
+                }
+            }
+        }
+
+        matches.push(Match {
+            pos: start,
+            len,
+            distance,
+        });
+        for covered in start + 1..start + len {
+            insert(covered, &mut head, &mut prev);
+        }
+        pos = start + len;
+    }
+
+    matches
+}
+
+/// Data for tests to compress.
+#[cfg(test)]
+pub(crate) mod testing {
+    /// What reports are like, and what they're not, each with what it is: nothing, a single byte,
+    /// text, noise that doesn't compress, runs longer than any match, and enough of each to span
+    /// several blocks of either format.
+    pub(crate) fn samples() -> Vec<(&'static str, Vec<u8>)> {
+        let text = "thread 'main' panicked at src/main.rs:12:5:\ncalled `Option::unwrap()` on a \
+            `None` value\nstack backtrace:\n   0: rust_begin_unwind\n   1: core::panicking::panic\n";
+
+        // An LCG, so that the noise is the same each time
+        let mut state = 0x2545_f491_u32;
+        let noise: Vec<u8> = (0..200_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect();
+        let mixed: Vec<u8> = noise
+            .chunks(1000)
+            .zip(text.as_bytes().chunks(40).cycle())
+            .flat_map(|(noise, text)| [&noise[..100], text, text].concat())
+            .collect();
+
+        vec![
+            ("empty", vec![]),
+            ("one byte", b"a".to_vec()),
+            ("text", text.as_bytes().to_vec()),
+            ("repeated text", text.repeat(5000).into_bytes()),
+            ("noise", noise),
+            ("a run", vec![0; 300_000]),
+            ("mixed", mixed),
+        ]
+    }
+}
@@ -0,0 +1,874 @@
+//! Putting a pipeline together from a description of it, such as a config file, so that what
+//! happens on a panic can be changed without a rebuild.
+//!
+//! A [`Config`] can be deserialized from any format serde supports. The handlers it names are
+//! looked up in a [`HandlerRegistry`], which the application fills in ahead of time, starting
+//! from evac's own handlers if it likes, see [`HandlerRegistry::with_builtin_handlers`]. In TOML:
+//!
+//! ```toml
+//! error_policy = "continue"
+//! after_panic = { exit = 70 }
+//! sample_rate = 0.5
+//! max_reports = { max = 5, per_secs = 60 }
+//! report_limits = { max_message_len = 65536, max_report_size = 4194304 }
+//!
+//! [[handlers]]
+//! handler = "directory"
+//! priority = "critical"
+//! options = { dir = "/var/crash", format = "json" }
+//!
+//! [[handlers]]
+//! handler = "sentry"
+//! priority = "low"
+//! retries = 2
+//! backoff_ms = 250
+//! skip_if_message_matches = ["connection reset"]
+//! options = { dsn = "https://0123456789abcdef@o0.ingest.sentry.io/0", environment = "production" }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+use std::panic::PanicHookInfo;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::{
+    handlers, AfterPanic, BacktraceMode, Criticality, DeadlineAction, ErrorPolicy, EvacBuilder,
+    FilterError, PanicHandler, PanicReport, Position, Priority, ReportFormatter, ReportHandler,
+    ReportLimits, Retry, TextFormatter,
+};
+
+/// A pipeline, as described in a config file.
+///
+/// ## Example
+/// ```
+/// # use evac::config::{Config, HandlerConfig, HandlerRegistry};
+/// let registry = HandlerRegistry::<()>::new().with_handler("stderr", |info, _| {
+///   eprintln!("{info}");
+///   Ok(())
+/// });
+///
+/// // Usually deserialized instead
+/// let mut config = Config::default();
+/// config.handlers.push(HandlerConfig::new("stderr"));
+///
+/// config.build(&registry)?.register(())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[non_exhaustive]
+pub struct Config {
+    /// The handlers to run, in order within their priority.
+    pub handlers: Vec<HandlerConfig>,
+    /// See [`EvacBuilder::preserve_existing_hook`].
+    pub preserve_existing_hook: Option<Position>,
+    /// See [`EvacBuilder::error_policy`].
+    pub error_policy: Option<ErrorPolicy>,
+    /// See [`EvacBuilder::critical_error_policy`].
+    pub critical_error_policy: Option<ErrorPolicy>,
+    /// See [`EvacBuilder::isolate_handlers`].
+    pub isolate_handlers: bool,
+    /// See [`EvacBuilder::deadline`].
+    pub deadline: Option<DeadlineConfig>,
+    /// See [`EvacBuilder::after_panic`].
+    pub after_panic: Option<AfterPanic>,
+    /// See [`EvacBuilder::sample_rate`].
+    pub sample_rate: Option<f64>,
+    /// See [`EvacBuilder::max_reports_per`].
+    pub max_reports: Option<MaxReportsConfig>,
+    /// See [`EvacBuilder::deduplicate`], in seconds.
+    pub deduplicate_secs: Option<u64>,
+    /// See [`EvacBuilder::capture_backtrace`].
+    pub capture_backtrace: Option<BacktraceMode>,
+    /// See [`EvacBuilder::report_limits`].
+    pub report_limits: Option<ReportLimits>,
+}
+
+/// A single handler in a [`Config`].
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct HandlerConfig {
+    /// The name the handler was registered under in the [`HandlerRegistry`].
+    pub handler: String,
+    /// The name the handler goes by in the pipeline, the same as `handler` if not given. Lets the
+    /// same handler be used twice, with different options.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// See [`EvacBuilder::handler_error_policy`].
+    #[serde(default)]
+    pub on_error: Option<ErrorPolicy>,
+    /// See [`EvacBuilder::handler_criticality`].
+    #[serde(default)]
+    pub criticality: Criticality,
+    /// See [`Retry::retries`].
+    #[serde(default)]
+    pub retries: u32,
+    /// See [`Retry::backoff`], in milliseconds.
+    #[serde(default)]
+    pub backoff_ms: u64,
+    /// See [`EvacBuilder::only_if_message_matches`].
+    #[serde(default)]
+    pub only_if_message_matches: Vec<String>,
+    /// See [`EvacBuilder::skip_if_message_matches`].
+    #[serde(default)]
+    pub skip_if_message_matches: Vec<String>,
+    /// Passed to the handler's factory, see [`HandlerRegistry::with_factory`], and for evac's own
+    /// handlers, [`HandlerRegistry::with_builtin_handlers`].
+    #[serde(default)]
+    pub options: Options,
+}
+
+impl HandlerConfig {
+    /// Runs the handler registered under `handler`, with everything else left as the default.
+    pub fn new(handler: impl Into<String>) -> Self {
+        Self {
+            handler: handler.into(),
+            name: None,
+            priority: Priority::default(),
+            on_error: None,
+            criticality: Criticality::default(),
+            retries: 0,
+            backoff_ms: 0,
+            only_if_message_matches: vec![],
+            skip_if_message_matches: vec![],
+            options: Options::new(),
+        }
+    }
+}
+
+/// See [`EvacBuilder::deadline`].
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeadlineConfig {
+    /// The deadline, in milliseconds.
+    pub ms: u64,
+    pub action: DeadlineAction,
+}
+
+/// See [`EvacBuilder::max_reports_per`].
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaxReportsConfig {
+    pub max: u32,
+    /// The window the reports are counted over, in seconds.
+    pub per_secs: u64,
+}
+
+/// A handler's options, see [`HandlerRegistry::with_factory`].
+pub type Options = BTreeMap<String, Value>;
+
+/// An option's value.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Value>),
+    Table(BTreeMap<String, Value>),
+}
+
+/// The type of closures that make a handler from its options, see
+/// [`HandlerRegistry::with_factory`].
+pub type HandlerFactory<T, E = Box<dyn Error>> = Box<
+    dyn Fn(&Options) -> Result<PanicHandler<T, E>, Box<dyn Error + Send + Sync>>
+        + 'static
+        + Send
+        + Sync,
+>;
+
+/// The type of closures that make a report handler from its options, see
+/// [`HandlerRegistry::with_report_factory`].
+pub type ReportHandlerFactory<T, E = Box<dyn Error>> = Box<
+    dyn Fn(&Options) -> Result<ReportHandler<T, E>, Box<dyn Error + Send + Sync>>
+        + 'static
+        + Send
+        + Sync,
+>;
+
+/// Either kind of factory a handler can be registered with.
+enum Factory<T: 'static, E: 'static> {
+    Single(HandlerFactory<T, E>),
+    Report(ReportHandlerFactory<T, E>),
+}
+
+/// The handlers a [`Config`] can pick from, by name.
+pub struct HandlerRegistry<T: 'static, E: 'static = Box<dyn Error>> {
+    factories: HashMap<String, Factory<T, E>>,
+}
+
+impl<T: Send + 'static, E: Display + 'static> HandlerRegistry<T, E> {
+    /// Constructs an empty registry.
+    pub fn new() -> Self {
+        Self {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Registers a handler that doesn't take any options.
+    pub fn with_handler<F>(self, name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + Clone + Send + Sync + 'static,
+    {
+        self.with_factory(name, move |_| {
+            let handler: PanicHandler<T, E> = Box::new(handler.clone());
+            Ok(handler)
+        })
+    }
+
+    /// Registers a report handler that doesn't take any options, see
+    /// [`EvacBuilder::with_report_handler`].
+    pub fn with_report_handler<F>(self, name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&mut PanicReport<'_>, &mut T) -> Result<(), E> + Clone + Send + Sync + 'static,
+    {
+        self.with_report_factory(name, move |_| {
+            let handler: ReportHandler<T, E> = Box::new(handler.clone());
+            Ok(handler)
+        })
+    }
+
+    /// Registers a closure that makes a handler from the options it's given in the config. It's
+    /// called once per use of the handler.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use evac::config::{HandlerRegistry, Value};
+    /// # use evac::PanicHandler;
+    /// let registry = HandlerRegistry::<()>::new().with_factory("write-report", |options| {
+    ///   let dir = match options.get("dir") {
+    ///     Some(Value::String(dir)) => PathBuf::from(dir),
+    ///     Some(_) => return Err("`dir` must be a string".into()),
+    ///     None => std::env::temp_dir(),
+    ///   };
+    ///
+    ///   let handler: PanicHandler<()> = Box::new(move |info, _| {
+    ///     std::fs::write(dir.join("report.txt"), info.to_string())?;
+    ///     Ok(())
+    ///   });
+    ///   Ok(handler)
+    /// });
+    /// ```
+    pub fn with_factory<F>(mut self, name: impl Into<String>, factory: F) -> Self
+    where
+        F: Fn(&Options) -> Result<PanicHandler<T, E>, Box<dyn Error + Send + Sync>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.factories
+            .insert(name.into(), Factory::Single(Box::new(factory)));
+
+        self
+    }
+
+    /// Registers a closure that makes a report handler from the options it's given in the
+    /// config, as [`HandlerRegistry::with_factory`] does for handlers.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::config::{HandlerRegistry, Value};
+    /// # use evac::ReportHandler;
+    /// let registry = HandlerRegistry::<()>::new().with_report_factory("annotate", |options| {
+    ///   let Some(Value::String(build)) = options.get("build").cloned() else {
+    ///     return Err("`build` must be a string".into());
+    ///   };
+    ///
+    ///   let handler: ReportHandler<()> = Box::new(move |report, _| {
+    ///     report.annotate("build", build.clone());
+    ///     Ok(())
+    ///   });
+    ///   Ok(handler)
+    /// });
+    /// ```
+    pub fn with_report_factory<F>(mut self, name: impl Into<String>, factory: F) -> Self
+    where
+        F: Fn(&Options) -> Result<ReportHandler<T, E>, Box<dyn Error + Send + Sync>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.factories
+            .insert(name.into(), Factory::Report(Box::new(factory)));
+
+        self
+    }
+}
+
+impl<T: Send + 'static, E: Display + From<io::Error> + 'static> HandlerRegistry<T, E> {
+    /// Registers evac's own [`handlers`] under their names, such as `file`, `directory`,
+    /// `http_upload` and `sentry`, taking what they're built with as options. Handlers for
+    /// features that aren't enabled are left out. A name registered later replaces the built-in
+    /// one.
+    ///
+    /// | Handler | Options |
+    /// |---|---|
+    /// | `stderr` | `format` |
+    /// | `console` | |
+    /// | `file` | `path`, `format` |
+    /// | `directory` | `dir`, `extension` (`log`), `format` |
+    /// | `file_dump` | `dir`, `format` |
+    /// | `json_file` | `path` |
+    /// | `k8s_termination_log` | `path` (Kubernetes' default) |
+    /// | `syslog` | `facility` (`user`), `ident` |
+    /// | `journald`, `log`, `tracing` | |
+    /// | `http_upload` | `url`, `headers`, `timeout_ms` |
+    /// | `webhook` | `url`, `template` (`slack`, `discord` or `teams`) |
+    /// | `sentry` | `dsn`, `environment`, `tags`, `timeout_ms` |
+    /// | `otlp` | `endpoint`, `service`, `headers` |
+    ///
+    /// `format` is `text`, the default, `compact` or `json`, and `headers` and `tags` are tables
+    /// of strings. Options a handler doesn't take are an error, as are missing ones without a
+    /// default.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::config::{Config, HandlerConfig, HandlerRegistry, Value};
+    /// let registry = HandlerRegistry::<()>::new().with_builtin_handlers();
+    ///
+    /// let mut config = Config::default();
+    /// let mut directory = HandlerConfig::new("directory");
+    /// let dir = std::env::temp_dir().join("my-app-crashes");
+    /// directory.options.insert("dir".into(), Value::String(dir.display().to_string()));
+    /// directory.options.insert("format".into(), Value::String("json".into()));
+    /// config.handlers.push(directory);
+    /// config.handlers.push(HandlerConfig::new("console"));
+    ///
+    /// config.build(&registry)?.register(())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_builtin_handlers(self) -> Self {
+        let registry = self
+            .with_report_factory("stderr", |options| {
+                known(options, &["format"])?;
+
+                Ok(Box::new(handlers::stderr(formatter(options)?)))
+            })
+            .with_report_factory("console", |options| {
+                known(options, &[])?;
+
+                Ok(Box::new(handlers::console()))
+            })
+            .with_report_factory("file", |options| {
+                known(options, &["path", "format"])?;
+
+                Ok(Box::new(handlers::file(
+                    required(options, "path")?,
+                    formatter(options)?,
+                )))
+            })
+            .with_report_factory("directory", |options| {
+                known(options, &["dir", "extension", "format"])?;
+                let extension = string(options, "extension")?.unwrap_or("log");
+
+                Ok(Box::new(handlers::directory(
+                    required(options, "dir")?,
+                    extension,
+                    formatter(options)?,
+                )))
+            })
+            .with_report_factory("file_dump", |options| {
+                known(options, &["dir", "format"])?;
+
+                Ok(Box::new(handlers::file_dump(
+                    required(options, "dir")?,
+                    formatter(options)?,
+                )))
+            })
+            .with_report_factory("json_file", |options| {
+                known(options, &["path"])?;
+
+                Ok(Box::new(handlers::json_file(required(options, "path")?)))
+            })
+            .with_report_factory("k8s_termination_log", |options| {
+                known(options, &["path"])?;
+                let handler: ReportHandler<T, E> = match string(options, "path")? {
+                    Some(path) => Box::new(handlers::k8s_termination_log_at(path)),
+                    None => Box::new(handlers::k8s_termination_log()),
+                };
+
+                Ok(handler)
+            });
+
+        #[cfg(all(unix, feature = "syslog"))]
+        let registry = registry.with_report_factory("syslog", |options| {
+            known(options, &["facility", "ident"])?;
+            let facility = match string(options, "facility")? {
+                Some(facility) => self::facility(facility)?,
+                None => handlers::Facility::User,
+            };
+
+            Ok(Box::new(handlers::syslog(
+                facility,
+                string(options, "ident")?.unwrap_or(env!("CARGO_PKG_NAME")),
+            )))
+        });
+        #[cfg(all(target_os = "linux", feature = "journald"))]
+        let registry = registry.with_report_factory("journald", |options| {
+            known(options, &[])?;
+
+            Ok(Box::new(handlers::journald()))
+        });
+        #[cfg(feature = "log")]
+        let registry = registry.with_report_factory("log", |options| {
+            known(options, &[])?;
+
+            Ok(Box::new(handlers::log()))
+        });
+        #[cfg(feature = "tracing")]
+        let registry = registry.with_report_factory("tracing", |options| {
+            known(options, &[])?;
+
+            Ok(Box::new(handlers::tracing()))
+        });
+
+        #[cfg(feature = "http")]
+        let registry = registry
+            .with_report_factory("http_upload", |options| {
+                known(options, &["url", "headers", "timeout_ms"])?;
+                let mut config = handlers::UploadConfig::new();
+                for (name, value) in table(options, "headers")? {
+                    config = config.header(name, value);
+                }
+                if let Some(ms) = millis(options, "timeout_ms")? {
+                    config = config.timeout(ms);
+                }
+
+                Ok(Box::new(handlers::http_upload(
+                    required(options, "url")?,
+                    config,
+                )?))
+            })
+            .with_report_factory("webhook", |options| {
+                known(options, &["url", "template"])?;
+                let template = match required(options, "template")? {
+                    "slack" => handlers::WebhookTemplate::Slack,
+                    "discord" => handlers::WebhookTemplate::Discord,
+                    "teams" => handlers::WebhookTemplate::Teams,
+                    other => return Err(format!("`template` can't be `{other}`").into()),
+                };
+
+                Ok(Box::new(handlers::webhook(
+                    required(options, "url")?,
+                    template,
+                )?))
+            });
+        #[cfg(feature = "sentry")]
+        let registry = registry.with_report_factory("sentry", |options| {
+            known(options, &["dsn", "environment", "tags", "timeout_ms"])?;
+            let mut config = handlers::SentryConfig::new();
+            if let Some(environment) = string(options, "environment")? {
+                config = config.environment(environment);
+            }
+            for (key, value) in table(options, "tags")? {
+                config = config.tag(key, value);
+            }
+            if let Some(ms) = millis(options, "timeout_ms")? {
+                config = config.timeout(ms);
+            }
+
+            Ok(Box::new(handlers::sentry(
+                required(options, "dsn")?,
+                config,
+            )?))
+        });
+        #[cfg(feature = "otlp")]
+        let registry = registry.with_report_factory("otlp", |options| {
+            known(options, &["endpoint", "service", "headers"])?;
+            let mut config = handlers::OtlpConfig::new();
+            if let Some(service) = string(options, "service")? {
+                config = config.service(service);
+            }
+            for (name, value) in table(options, "headers")? {
+                config = config.header(name, value);
+            }
+
+            Ok(Box::new(handlers::otlp(
+                required(options, "endpoint")?,
+                config,
+            )?))
+        });
+
+        registry
+    }
+}
+
+impl<T: Send + 'static, E: Display + 'static> Default for HandlerRegistry<T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The error for a built-in handler's options that aren't right.
+type OptionError = Box<dyn Error + Send + Sync>;
+
+/// Fails if `options` has one that isn't in `allowed`.
+fn known(options: &Options, allowed: &[&str]) -> Result<(), OptionError> {
+    match options.keys().find(|key| !allowed.contains(&key.as_str())) {
+        Some(key) => Err(format!("unknown option `{key}`").into()),
+        None => Ok(()),
+    }
+}
+
+/// The string option `key`, if it's given.
+fn string<'a>(options: &'a Options, key: &str) -> Result<Option<&'a str>, OptionError> {
+    match options.get(key) {
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(format!("`{key}` must be a string").into()),
+        None => Ok(None),
+    }
+}
+
+/// The string option `key`, which must be given.
+fn required<'a>(options: &'a Options, key: &str) -> Result<&'a str, OptionError> {
+    string(options, key)?.ok_or_else(|| format!("`{key}` is required").into())
+}
+
+/// The option `key`, a number of milliseconds, if it's given.
+#[cfg(feature = "http")]
+fn millis(options: &Options, key: &str) -> Result<Option<Duration>, OptionError> {
+    match options.get(key) {
+        Some(Value::Integer(ms)) => match u64::try_from(*ms) {
+            Ok(ms) => Ok(Some(Duration::from_millis(ms))),
+            Err(_) => Err(format!("`{key}` can't be negative").into()),
+        },
+        Some(_) => Err(format!("`{key}` must be an integer").into()),
+        None => Ok(None),
+    }
+}
+
+/// The option `key`, a table of strings, such as headers, empty if it isn't given.
+#[cfg(feature = "http")]
+fn table<'a>(options: &'a Options, key: &str) -> Result<Vec<(&'a str, &'a str)>, OptionError> {
+    match options.get(key) {
+        Some(Value::Table(table)) => table
+            .iter()
+            .map(|(name, value)| match value {
+                Value::String(value) => Ok((name.as_str(), value.as_str())),
+                _ => Err(format!("`{key}.{name}` must be a string").into()),
+            })
+            .collect(),
+        Some(_) => Err(format!("`{key}` must be a table").into()),
+        None => Ok(vec![]),
+    }
+}
+
+/// The formatter the `format` option names, text if it isn't given.
+fn formatter(options: &Options) -> Result<impl ReportFormatter, OptionError> {
+    let formatter: Box<dyn ReportFormatter> = match string(options, "format")? {
+        None | Some("text") => Box::new(TextFormatter::new()),
+        Some("compact") => Box::new(TextFormatter::compact()),
+        Some("json") => Box::new(crate::JsonFormatter),
+        Some(other) => return Err(format!("`format` can't be `{other}`").into()),
+    };
+
+    Ok(move |report: &PanicReport<'_>, out: &mut dyn Write| formatter.format(report, out))
+}
+
+/// The syslog facility named `name`, as in `syslog.conf`.
+#[cfg(all(unix, feature = "syslog"))]
+fn facility(name: &str) -> Result<handlers::Facility, OptionError> {
+    use handlers::Facility;
+
+    Ok(match name {
+        "user" => Facility::User,
+        "mail" => Facility::Mail,
+        "daemon" => Facility::Daemon,
+        "auth" => Facility::Auth,
+        "syslog" => Facility::Syslog,
+        "lpr" => Facility::Lpr,
+        "news" => Facility::News,
+        "uucp" => Facility::Uucp,
+        "cron" => Facility::Cron,
+        "authpriv" => Facility::AuthPriv,
+        "ftp" => Facility::Ftp,
+        "local0" => Facility::Local0,
+        "local1" => Facility::Local1,
+        "local2" => Facility::Local2,
+        "local3" => Facility::Local3,
+        "local4" => Facility::Local4,
+        "local5" => Facility::Local5,
+        "local6" => Facility::Local6,
+        "local7" => Facility::Local7,
+        other => return Err(format!("`facility` can't be `{other}`").into()),
+    })
+}
+
+impl Config {
+    /// Puts together the pipeline the config describes.
+    ///
+    /// ## Errors
+    /// Fails if the config names a handler that isn't in `registry`, one of the handlers'
+    /// factories rejects its options, or one of its message filters isn't a supported pattern.
+    pub fn build<T, E>(
+        &self,
+        registry: &HandlerRegistry<T, E>,
+    ) -> Result<EvacBuilder<T, E>, ConfigError>
+    where
+        T: Send + 'static,
+        E: Display + 'static,
+    {
+        let mut builder = EvacBuilder::default();
+
+        for config in &self.handlers {
+            let Some(factory) = registry.factories.get(&config.handler) else {
+                return Err(ConfigError::UnknownHandler {
+                    handler: config.handler.clone(),
+                });
+            };
+            let options = |error| ConfigError::Options {
+                handler: config.handler.clone(),
+                error,
+            };
+
+            let name = config
+                .name
+                .clone()
+                .unwrap_or_else(|| config.handler.clone());
+            builder = match factory {
+                Factory::Single(factory) => {
+                    builder.with_named_handler(&name, factory(&config.options).map_err(options)?)
+                }
+                Factory::Report(factory) => builder
+                    .with_named_report_handler(&name, factory(&config.options).map_err(options)?),
+            };
+
+            builder = builder
+                .handler_priority(&name, config.priority)
+                .handler_criticality(&name, config.criticality)
+                .handler_retry(
+                    &name,
+                    Retry::retries(config.retries)
+                        .backoff(Duration::from_millis(config.backoff_ms)),
+                );
+            if let Some(policy) = config.on_error {
+                builder = builder.handler_error_policy(&name, policy);
+            }
+            let filter = |error| ConfigError::Filter {
+                handler: config.handler.clone(),
+                error,
+            };
+            if !config.only_if_message_matches.is_empty() {
+                builder = builder
+                    .only_if_message_matches(&name, &config.only_if_message_matches)
+                    .map_err(filter)?;
+            }
+            if !config.skip_if_message_matches.is_empty() {
+                builder = builder
+                    .skip_if_message_matches(&name, &config.skip_if_message_matches)
+                    .map_err(filter)?;
+            }
+        }
+
+        if let Some(position) = self.preserve_existing_hook {
+            builder = builder.preserve_existing_hook(position);
+        }
+        if let Some(policy) = self.error_policy {
+            builder = builder.error_policy(policy);
+        }
+        if let Some(policy) = self.critical_error_policy {
+            builder = builder.critical_error_policy(policy);
+        }
+        if self.isolate_handlers {
+            builder = builder.isolate_handlers();
+        }
+        if let Some(deadline) = self.deadline {
+            builder = builder.deadline(Duration::from_millis(deadline.ms), deadline.action);
+        }
+        if let Some(after_panic) = self.after_panic {
+            builder = builder.after_panic(after_panic);
+        }
+        if let Some(rate) = self.sample_rate {
+            builder = builder.sample_rate(rate);
+        }
+        if let Some(max) = self.max_reports {
+            builder = builder.max_reports_per(max.max, Duration::from_secs(max.per_secs));
+        }
+        if let Some(secs) = self.deduplicate_secs {
+            builder = builder.deduplicate(Duration::from_secs(secs));
+        }
+        if let Some(mode) = self.capture_backtrace {
+            builder = builder.capture_backtrace(mode);
+        }
+        if let Some(limits) = self.report_limits {
+            builder = builder.report_limits(limits);
+        }
+
+        Ok(builder)
+    }
+}
+
+/// Returned by [`Config::build`] when the config can't be put together.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The config names a handler that isn't in the registry.
+    UnknownHandler { handler: String },
+    /// A handler's factory rejected its options.
+    Options {
+        handler: String,
+        error: Box<dyn Error + Send + Sync>,
+    },
+    /// One of a handler's message filters isn't one evac supports.
+    Filter { handler: String, error: FilterError },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::UnknownHandler { handler } => {
+                write!(f, "no handler is registered as `{handler}`")
+            }
+            ConfigError::Options { handler, error } => {
+                write!(f, "invalid options for handler `{handler}`: {error}")
+            }
+            ConfigError::Filter { handler, error } => {
+                write!(f, "invalid filter for handler `{handler}`: {error}")
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::UnknownHandler { .. } => None,
+            ConfigError::Options { error, .. } => Some(&**error),
+            ConfigError::Filter { error, .. } => Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::report::testing::with_report;
+
+    /// The built-in report handler `handler`, as made from `options`.
+    fn builtin(handler: &str, options: &[(&str, Value)]) -> Result<ReportHandler<()>, OptionError> {
+        let registry = HandlerRegistry::<()>::new().with_builtin_handlers();
+        let Some(Factory::Report(factory)) = registry.factories.get(handler) else {
+            panic!("`{handler}` isn't a built-in report handler");
+        };
+        let options = options
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect();
+
+        factory(&options)
+    }
+
+    #[test]
+    fn builds_a_builtin_handler_from_its_options() {
+        let path = std::env::temp_dir().join(format!("evac-config-{}.log", std::process::id()));
+        let handler = builtin(
+            "file",
+            &[
+                ("path", Value::String(path.display().to_string())),
+                ("format", Value::String("compact".into())),
+            ],
+        )
+        .unwrap();
+
+        let result = with_report("disk on fire", move |report| {
+            handler(report, &mut ()).map_err(|e| e.to_string())
+        });
+        let written = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(written.lines().count(), 1);
+        assert!(written.contains("disk on fire"));
+    }
+
+    #[test]
+    fn rejects_builtin_options_that_arent_right() {
+        let error = |handler, options: &[(&str, Value)]| {
+            builtin(handler, options).err().unwrap().to_string()
+        };
+
+        assert_eq!(error("file", &[]), "`path` is required");
+        assert_eq!(
+            error("file", &[("path", Value::Integer(1))]),
+            "`path` must be a string"
+        );
+        assert_eq!(
+            error("console", &[("color", Value::Bool(true))]),
+            "unknown option `color`"
+        );
+        assert_eq!(
+            error("stderr", &[("format", Value::String("yaml".into()))]),
+            "`format` can't be `yaml`"
+        );
+    }
+
+    #[test]
+    fn builds_the_pipeline_through_the_builder() {
+        let registry = HandlerRegistry::<()>::new()
+            .with_handler("first", |_, _| Ok(()))
+            .with_report_handler("second", |_, _| Ok(()));
+        let mut config = Config::default();
+        config.handlers.push(HandlerConfig::new("first"));
+        let mut second = HandlerConfig::new("second");
+        second.name = Some("renamed".into());
+        second.priority = Priority::Critical;
+        second.retries = 2;
+        config.handlers.push(second);
+        config.sample_rate = Some(0.25);
+        config.deduplicate_secs = Some(60);
+
+        let builder = config.build(&registry).unwrap();
+        let handlers: Vec<_> = builder
+            .handlers
+            .iter()
+            .map(|entry| (entry.name.as_deref(), entry.priority, entry.retry))
+            .collect();
+
+        assert_eq!(
+            handlers,
+            [
+                (Some("first"), Priority::Normal, Retry::default()),
+                (Some("renamed"), Priority::Critical, Retry::retries(2)),
+            ]
+        );
+        assert_eq!(builder.sample_rate, Some(0.25));
+        assert_eq!(builder.dedup_window, Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn refuses_a_handler_that_isnt_registered() {
+        let mut config = Config::default();
+        config.handlers.push(HandlerConfig::new("upload"));
+
+        let error = config.build(&HandlerRegistry::<()>::new()).err().unwrap();
+
+        assert!(matches!(error, ConfigError::UnknownHandler { handler } if handler == "upload"));
+    }
+
+    #[test]
+    fn refuses_a_filter_that_isnt_supported() {
+        let registry = HandlerRegistry::<()>::new().with_handler("upload", |_, _| Ok(()));
+        let mut config = Config::default();
+        let mut upload = HandlerConfig::new("upload");
+        upload.skip_if_message_matches = vec!["(unclosed".into()];
+        config.handlers.push(upload);
+
+        let error = config.build(&registry).err().unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "invalid filter for handler `upload`: invalid pattern `(unclosed`: unclosed `(`"
+        );
+    }
+}
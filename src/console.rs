@@ -0,0 +1,109 @@
+//! Writes reports for people at a terminal, in place of std's own output, see
+//! [`handlers::console`](crate::handlers::console).
+
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+
+use crate::{fingerprint, handlers, PanicReport};
+
+const PANICKED: &str = "1;31";
+const LOCATION: &str = "33";
+const MESSAGE: &str = "1";
+const FAINT: &str = "2";
+const NOTE: &str = "1;36";
+
+/// Writes `report`, in color if `color`.
+pub(crate) fn write(report: &PanicReport<'_>, out: &mut dyn Write, color: bool) -> io::Result<()> {
+    let style = |style| color.then_some(style);
+
+    let thread = report.thread_name().unwrap_or("<unnamed>");
+    write!(
+        out,
+        "\nthread '{thread}' {}",
+        paint(style(PANICKED), "panicked")
+    )?;
+    if let Some(location) = report.location() {
+        write!(out, " at {}", paint(style(LOCATION), location))?;
+    }
+    writeln!(out, ":")?;
+    let message = report.message().unwrap_or("Box<dyn Any>");
+    for line in message.lines() {
+        writeln!(out, "  {}", paint(style(MESSAGE), line))?;
+    }
+
+    if let Some(backtrace) = report.backtrace_text() {
+        writeln!(out, "\nbacktrace:")?;
+
+        // Only the application's frames, with where they are
+        let mut hidden = 0;
+        let mut shown = false;
+        for line in backtrace.lines() {
+            match fingerprint::frame(line) {
+                Some((_, symbol)) if fingerprint::is_runtime(symbol) => {
+                    hidden += 1;
+                    shown = false;
+                }
+                Some((index, symbol)) => {
+                    writeln!(
+                        out,
+                        "  {} {symbol}",
+                        paint(style(FAINT), format_args!("{index:>3}:"))
+                    )?;
+                    shown = true;
+                }
+                // Along with any note that it was cut short
+                None if shown || line.trim_start().starts_with("[truncated: ") => {
+                    writeln!(out, "        {}", paint(style(FAINT), line.trim_start()))?;
+                }
+                None => {}
+            }
+        }
+        if hidden > 0 {
+            writeln!(
+                out,
+                "  {}",
+                paint(
+                    style(FAINT),
+                    format_args!("({hidden} frames from std, evac and the runtime hidden)")
+                )
+            )?;
+        }
+    }
+
+    let saved: Vec<_> = report
+        .annotations()
+        .iter()
+        .filter(|(key, _)| key == handlers::SAVED_TO)
+        .collect();
+    if !saved.is_empty() {
+        writeln!(out)?;
+    }
+    for (_, path) in saved {
+        writeln!(
+            out,
+            "{} report saved to {path}",
+            paint(style(NOTE), "note:")
+        )?;
+    }
+
+    writeln!(out)
+}
+
+/// `text`, in `style` if it's given, as an ANSI SGR code.
+fn paint<T: Display>(style: Option<&'static str>, text: T) -> Painted<T> {
+    Painted { style, text }
+}
+
+struct Painted<T> {
+    style: Option<&'static str>,
+    text: T,
+}
+
+impl<T: Display> Display for Painted<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.style {
+            Some(style) => write!(f, "\x1b[{style}m{}\x1b[0m", self.text),
+            None => write!(f, "{}", self.text),
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use std::cell::UnsafeCell;
+use std::error::Error;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, PoisonError, Weak};
+use std::time::{Duration, Instant};
+
+/// What a panicking thread does when another thread is already using the context, see
+/// [`EvacBuilder::on_contention`](crate::EvacBuilder::on_contention).
+///
+/// The context is only ever handed to one pipeline at a time, so when several threads panic at
+/// once their pipelines take turns. If the thread that panicked is itself holding the context,
+/// waiting would never end, so the pipeline is always skipped in that case.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Contention {
+    /// Wait for the other pipeline to finish, however long it takes.
+    #[default]
+    Wait,
+    /// Wait for up to the given duration, then give up and skip the handlers.
+    Timeout(Duration),
+    /// Skip the handlers straight away.
+    Skip,
+}
+
+/// Why the context couldn't be locked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Contended {
+    /// Another thread held onto it for longer than the [`Contention`] policy allows.
+    Busy,
+    /// The current thread is the one holding it.
+    Reentrant,
+}
+
+/// Supplies the pipeline's context from somewhere other than evac, such as a global, a
+/// thread-local, or an application's service registry. See
+/// [`EvacBuilder::register_provider`](crate::EvacBuilder::register_provider).
+///
+/// Rather than returning a `&mut T`, which `&self` couldn't soundly hand out to several panicking
+/// threads at once, the provider lends the context to a callback. It's responsible for its own
+/// synchronization; [`Contention`] policies don't apply.
+///
+/// ## Example
+/// ```
+/// # use std::error::Error;
+/// # use std::sync::Mutex;
+/// # use evac::{ContextProvider, EvacBuilder};
+/// struct Session {
+///   user: Option<String>,
+/// }
+///
+/// static SESSION: Mutex<Session> = Mutex::new(Session { user: None });
+///
+/// struct GlobalSession;
+///
+/// impl ContextProvider<Session> for GlobalSession {
+///   fn provide(&self, f: &mut dyn FnMut(&mut Session)) -> Result<(), Box<dyn Error>> {
+///     let mut session = SESSION.try_lock().map_err(|_| "session is locked")?;
+///     f(&mut session);
+///     Ok(())
+///   }
+/// }
+///
+/// EvacBuilder::new()
+///   .with_handler(|_, session: &mut Session| {
+///     eprintln!("panicked during {:?}'s session", session.user);
+///     Ok(())
+///   })
+///   .register_provider(GlobalSession)?;
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+pub trait ContextProvider<T>: Send + Sync + 'static {
+    /// Calls `f` with the context. The error is reported in place of running the handlers.
+    fn provide(&self, f: &mut dyn FnMut(&mut T)) -> Result<(), Box<dyn Error>>;
+}
+
+/// Somewhere the pipeline gets its context from.
+pub(crate) trait Source<T>: Send + Sync + 'static {
+    /// Lends the context to `f` for the duration of the handlers.
+    fn lend(&self, contention: Contention, f: &mut dyn FnMut(&mut T)) -> Result<(), Unavailable>;
+}
+
+/// Why the handlers couldn't be given the context.
+pub(crate) enum Unavailable {
+    Contended(Contended),
+    /// The context failed to initialize just now.
+    Init(Box<dyn Error>),
+    /// The context failed to initialize during an earlier panic.
+    InitFailed,
+    /// A [`ContextProvider`] couldn't provide the context.
+    Provider(Box<dyn Error>),
+}
+
+impl From<Contended> for Unavailable {
+    fn from(contended: Contended) -> Self {
+        Unavailable::Contended(contended)
+    }
+}
+
+impl<T: Send + 'static> Source<T> for ContextLock<T> {
+    fn lend(&self, contention: Contention, f: &mut dyn FnMut(&mut T)) -> Result<(), Unavailable> {
+        f(&mut *self.lock(contention)?);
+
+        Ok(())
+    }
+}
+
+impl<T: Send + 'static> Source<T> for Arc<ContextLock<T>> {
+    fn lend(&self, contention: Contention, f: &mut dyn FnMut(&mut T)) -> Result<(), Unavailable> {
+        (**self).lend(contention, f)
+    }
+}
+
+/// Shared access to a registered pipeline's context, returned by
+/// [`EvacBuilder::register_with_handle`](crate::EvacBuilder::register_with_handle). Lets the
+/// application keep the context up to date with whatever the handlers should know about.
+///
+/// ## Example
+/// ```
+/// # use evac::EvacBuilder;
+/// #[derive(Default)]
+/// struct State {
+///   request_id: Option<u64>,
+/// }
+///
+/// let (_evac, state) = EvacBuilder::new()
+///   .with_handler(|_, state: &mut State| {
+///     eprintln!("panicked while handling request {:?}", state.request_id);
+///     Ok(())
+///   })
+///   .register_with_handle(State::default())?;
+///
+/// state.update(|state| state.request_id = Some(42));
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+pub struct ContextHandle<T> {
+    ctx: Arc<ContextLock<T>>,
+}
+
+impl<T> ContextHandle<T> {
+    pub(crate) fn new(ctx: Arc<ContextLock<T>>) -> Self {
+        Self { ctx }
+    }
+
+    /// Calls `f` with the context. While `f` runs, panicking threads wait as per the pipeline's
+    /// [`Contention`] policy, and a panic inside `f` skips the handlers, since the context is in
+    /// an unknown state.
+    ///
+    /// ## Panics
+    /// Panics if called from within `f`, or from a handler of the same pipeline.
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        match self.ctx.lock(Contention::Wait) {
+            Ok(mut ctx) => f(&mut ctx),
+            Err(_) => panic!("the context is already in use by this thread"),
+        }
+    }
+}
+
+impl<T> Clone for ContextHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            ctx: Arc::clone(&self.ctx),
+        }
+    }
+}
+
+/// Observes state owned elsewhere, see
+/// [`EvacBuilder::register_weak`](crate::EvacBuilder::register_weak). Each panic gets its own
+/// upgraded reference, so there's nothing to contend over.
+pub(crate) struct WeakSource<S>(pub(crate) Weak<S>);
+
+impl<S: Send + Sync + 'static> Source<Option<Arc<S>>> for WeakSource<S> {
+    fn lend(
+        &self,
+        _: Contention,
+        f: &mut dyn FnMut(&mut Option<Arc<S>>),
+    ) -> Result<(), Unavailable> {
+        f(&mut self.0.upgrade());
+
+        Ok(())
+    }
+}
+
+/// Adapts a [`ContextProvider`] into a [`Source`].
+pub(crate) struct Provided<P>(pub(crate) P);
+
+impl<T, P: ContextProvider<T>> Source<T> for Provided<P> {
+    fn lend(&self, _: Contention, f: &mut dyn FnMut(&mut T)) -> Result<(), Unavailable> {
+        self.0.provide(f).map_err(Unavailable::Provider)
+    }
+}
+
+/// A context that's built the first time it's needed, see
+/// [`EvacBuilder::register_lazy`](crate::EvacBuilder::register_lazy).
+pub(crate) struct Lazy<T, F> {
+    state: ContextLock<LazyState<T, F>>,
+}
+
+enum LazyState<T, F> {
+    Pending(F),
+    Ready(T),
+    Failed,
+}
+
+impl<T, F> Lazy<T, F> {
+    pub(crate) fn new(init: F) -> Self {
+        Self {
+            state: ContextLock::new(LazyState::Pending(init)),
+        }
+    }
+}
+
+impl<T, F> Source<T> for Lazy<T, F>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Box<dyn Error>> + Send + 'static,
+{
+    fn lend(&self, contention: Contention, f: &mut dyn FnMut(&mut T)) -> Result<(), Unavailable> {
+        let mut state = self.state.lock(contention)?;
+
+        if matches!(*state, LazyState::Pending(_)) {
+            let LazyState::Pending(init) = std::mem::replace(&mut *state, LazyState::Failed) else {
+                unreachable!()
+            };
+
+            *state = LazyState::Ready(init().map_err(Unavailable::Init)?);
+        }
+
+        match &mut *state {
+            LazyState::Ready(ctx) => f(ctx),
+            _ => return Err(Unavailable::InitFailed),
+        }
+
+        Ok(())
+    }
+}
+
+/// A mutex for the pipeline's context, which knows which thread holds it and can be waited on
+/// with a deadline.
+pub(crate) struct ContextLock<T> {
+    value: UnsafeCell<T>,
+    /// The thread currently holding the context, if any.
+    owner: Mutex<Option<usize>>,
+    released: Condvar,
+}
+
+// Access to `value` is guarded by `owner`, same as a `Mutex<T>`
+unsafe impl<T: Send> Sync for ContextLock<T> {}
+
+impl<T> ContextLock<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+            owner: Mutex::new(None),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Locks the context, waiting on other threads as per `contention`.
+    pub(crate) fn lock(&self, contention: Contention) -> Result<ContextGuard<'_, T>, Contended> {
+        let this_thread = thread_mark();
+        let deadline = match contention {
+            Contention::Timeout(timeout) => Instant::now().checked_add(timeout),
+            _ => None,
+        };
+
+        // Nothing but this function touches `owner`, so it can't be poisoned in any way that
+        // matters
+        let mut owner = self.owner.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            match *owner {
+                None => break,
+                Some(thread) if thread == this_thread => return Err(Contended::Reentrant),
+                Some(_) => {}
+            }
+
+            owner = match (contention, deadline) {
+                (Contention::Skip, _) => return Err(Contended::Busy),
+                (Contention::Timeout(_), Some(deadline)) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(Contended::Busy);
+                    }
+
+                    self.released
+                        .wait_timeout(owner, remaining)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+                // Waiting forever, or for so long that it might as well be
+                _ => self
+                    .released
+                    .wait(owner)
+                    .unwrap_or_else(PoisonError::into_inner),
+            };
+        }
+
+        *owner = Some(this_thread);
+
+        Ok(ContextGuard { lock: self })
+    }
+}
+
+/// Exclusive access to the context, released on drop.
+pub(crate) struct ContextGuard<'a, T> {
+    lock: &'a ContextLock<T>,
+}
+
+impl<T> Deref for ContextGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // The guard only exists while this thread owns the context
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> DerefMut for ContextGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // The guard only exists while this thread owns the context
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for ContextGuard<'_, T> {
+    fn drop(&mut self) {
+        let mut owner = self
+            .lock
+            .owner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *owner = None;
+        self.lock.released.notify_one();
+    }
+}
+
+/// A number unique to the current thread for as long as it's alive.
+fn thread_mark() -> usize {
+    thread_local! {
+        static MARK: u8 = const { 0 };
+    }
+
+    MARK.with(|mark| mark as *const u8 as usize)
+}
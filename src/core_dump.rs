@@ -0,0 +1,27 @@
+/// Ends the process in a way the OS records a core dump for, see
+/// [`AfterPanic::CoreDump`](crate::AfterPanic::CoreDump).
+pub(crate) fn raise() -> ! {
+    #[cfg(unix)]
+    // SAFETY: These only change process-wide settings, which nothing is going to rely on anymore
+    unsafe {
+        // Allow for as big a core as we're allowed to
+        let mut limit = std::mem::zeroed::<libc::rlimit>();
+        if libc::getrlimit(libc::RLIMIT_CORE, &mut limit) == 0 {
+            limit.rlim_cur = limit.rlim_max;
+            libc::setrlimit(libc::RLIMIT_CORE, &limit);
+        }
+
+        // Undo anything that would keep SIGABRT from ending the process
+        libc::signal(libc::SIGABRT, libc::SIG_DFL);
+        let mut mask = std::mem::zeroed::<libc::sigset_t>();
+        libc::sigemptyset(&mut mask);
+        libc::sigaddset(&mut mask, libc::SIGABRT);
+        libc::pthread_sigmask(libc::SIG_UNBLOCK, &mask, std::ptr::null_mut());
+
+        libc::raise(libc::SIGABRT);
+    }
+
+    // Elsewhere, aborting already produces the platform's crash dump, such as through Windows
+    // Error Reporting. It's also what's left if the signal somehow didn't end the process
+    std::process::abort()
+}
@@ -0,0 +1,155 @@
+//! Telling when the process keeps crashing soon after starting, see
+//! [`EvacBuilder::crash_loop`](crate::EvacBuilder::crash_loop).
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The most recent [`Status`], once a pipeline tracking crash loops has been built.
+static STATUS: Mutex<Option<Status>> = Mutex::new(None);
+
+/// Whether the process is in a crash loop, as of starting up or the panic being handled. Gives
+/// `None` until a builder with [`EvacBuilder::crash_loop`](crate::EvacBuilder::crash_loop) has
+/// been registered.
+///
+/// ## Example
+/// ```
+/// # use evac::crash_loop::CrashLoop;
+/// # use evac::EvacBuilder;
+/// # let state = std::env::temp_dir().join("evac-status-example");
+/// EvacBuilder::new()
+///   .with_handler(|_, _: &mut ()| Ok(()))
+///   .crash_loop(CrashLoop::new(state))
+///   .register(())?;
+///
+/// if evac::crash_loop::status().is_some_and(|status| status.in_crash_loop) {
+///   eprintln!("starting without plugins");
+/// }
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+pub fn status() -> Option<Status> {
+    *STATUS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// How the process has been crashing lately, see [`status`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Status {
+    /// How many panics in a row there have been, each soon enough after the last, up to the
+    /// [threshold](CrashLoop::threshold), as no more are kept. If the latest was too long ago,
+    /// there's no run of panics anymore, and this is 0.
+    pub consecutive: u32,
+    /// Whether there were enough of them to be a crash loop.
+    pub in_crash_loop: bool,
+}
+
+/// Where panics are recorded, and what counts as a crash loop.
+///
+/// ## Example
+/// ```
+/// # use std::time::Duration;
+/// # use evac::crash_loop::CrashLoop;
+/// // Five panics, each within two minutes of the last
+/// let crash_loop = CrashLoop::new("/var/lib/my-app/crashes").threshold(5, Duration::from_secs(120));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CrashLoop {
+    path: PathBuf,
+    crashes: u32,
+    within: Duration,
+}
+
+impl CrashLoop {
+    /// Records panics to the file at `path`, counting three panics within a minute of each other
+    /// as a crash loop. The file only ever holds a handful of timestamps.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            crashes: 3,
+            within: Duration::from_secs(60),
+        }
+    }
+
+    /// Counts `crashes` panics in a row as a crash loop, if each is `within` the last.
+    pub fn threshold(mut self, crashes: u32, within: Duration) -> Self {
+        self.crashes = crashes.max(1);
+        self.within = within;
+
+        self
+    }
+
+    /// Reads back the panics recorded by earlier runs, and works out the status from them.
+    pub(crate) fn start(self) -> Tracker {
+        // A missing or mangled file just means there's nothing to go on
+        let recorded = fs::read_to_string(&self.path).unwrap_or_default();
+        let crashes = recorded
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect();
+
+        let tracker = Tracker {
+            config: self,
+            crashes: Mutex::new(crashes),
+        };
+        tracker.update(&tracker.lock());
+
+        tracker
+    }
+}
+
+/// Records each panic, see [`CrashLoop`].
+pub(crate) struct Tracker {
+    config: CrashLoop,
+    /// When the latest panics happened, in milliseconds since the Unix epoch, oldest first.
+    crashes: Mutex<Vec<u64>>,
+}
+
+impl Tracker {
+    /// Records a panic happening now.
+    pub(crate) fn record(&self) {
+        let mut crashes = self.lock();
+        crashes.push(now());
+
+        // Only as many as it takes to spot a crash loop are needed
+        let excess = crashes.len().saturating_sub(self.config.crashes as usize);
+        crashes.drain(..excess);
+
+        let recorded: String = crashes.iter().map(|crash| format!("{crash}\n")).collect();
+        // There's nowhere to report this to, and it mustn't hold up the handlers
+        let _ = fs::write(&self.config.path, recorded);
+
+        self.update(&crashes);
+    }
+
+    fn update(&self, crashes: &[u64]) {
+        let within = self.config.within.as_millis() as u64;
+
+        // Count back from now for as long as the gaps are short enough
+        let mut consecutive = 0;
+        let mut later = now();
+        for &crash in crashes.iter().rev() {
+            if later.saturating_sub(crash) > within {
+                break;
+            }
+            consecutive += 1;
+            later = crash;
+        }
+
+        *STATUS.lock().unwrap_or_else(PoisonError::into_inner) = Some(Status {
+            consecutive,
+            in_crash_loop: consecutive >= self.config.crashes,
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<u64>> {
+        self.crashes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
@@ -0,0 +1,221 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+use crate::{isolate, DeadlineAction};
+
+/// How the thread handlers run on is set up, see
+/// [`EvacBuilder::crash_thread`](crate::EvacBuilder::crash_thread).
+///
+/// ## Example
+/// ```
+/// # use std::time::Duration;
+/// # use evac::{CrashThread, DeadlineAction};
+/// let crash_thread = CrashThread::new()
+///   .stack_size(16 * 1024 * 1024)
+///   .timeout(Duration::from_secs(30), DeadlineAction::Abort);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CrashThread {
+    stack_size: usize,
+    timeout: Option<(Duration, DeadlineAction)>,
+}
+
+impl CrashThread {
+    /// A crash thread with an 8 MiB stack, which is waited on for as long as it takes.
+    pub fn new() -> Self {
+        Self {
+            stack_size: 8 * 1024 * 1024,
+            timeout: None,
+        }
+    }
+
+    /// Sets the size of the crash thread's stack, in bytes.
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = stack_size;
+
+        self
+    }
+
+    /// Only waits `timeout` for the handlers, after which the process is ended as per `action`.
+    /// The panicking thread can't carry on without them, as they're still using its panic.
+    pub fn timeout(mut self, timeout: Duration, action: DeadlineAction) -> Self {
+        self.timeout = Some((timeout, action));
+
+        self
+    }
+
+    /// Spawns the thread. Returns `None` if it can't be spawned.
+    pub(crate) fn spawn(self) -> Option<CrashWorker> {
+        let shared = Arc::new(Shared {
+            slot: Mutex::new(Slot::default()),
+            changed: Condvar::new(),
+        });
+
+        let worked = Arc::clone(&shared);
+        thread::Builder::new()
+            .name("evac-crash".into())
+            .stack_size(self.stack_size)
+            .spawn(move || worked.work())
+            .ok()?;
+
+        Some(CrashWorker {
+            shared,
+            turn: Mutex::new(()),
+            timeout: self.timeout,
+        })
+    }
+}
+
+impl Default for CrashThread {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+thread_local! {
+    /// On the crash thread, the thread whose panic is being handled.
+    static ON_BEHALF_OF: RefCell<Option<Thread>> = const { RefCell::new(None) };
+}
+
+/// The thread whose panic is being handled, which is the current one unless this is the crash
+/// thread.
+pub(crate) fn panicking_thread() -> Thread {
+    ON_BEHALF_OF
+        .with(|thread| thread.borrow().clone())
+        .unwrap_or_else(thread::current)
+}
+
+/// A spawned crash thread.
+pub(crate) struct CrashWorker {
+    shared: Arc<Shared>,
+    /// Held by the panicking thread whose job is in the slot.
+    turn: Mutex<()>,
+    timeout: Option<(Duration, DeadlineAction)>,
+}
+
+struct Shared {
+    slot: Mutex<Slot>,
+    changed: Condvar,
+}
+
+/// Where jobs are handed over. It's only ever written in place, so handing one over doesn't
+/// allocate.
+#[derive(Default)]
+struct Slot {
+    job: Option<Job>,
+    /// The thread the job is for.
+    panicking: Option<Thread>,
+    done: bool,
+    shutdown: bool,
+}
+
+/// A job on the panicking thread's stack.
+struct Job(*mut (dyn FnMut() + 'static));
+
+// The panicking thread doesn't touch the job until the crash thread is done with it
+unsafe impl Send for Job {}
+
+impl CrashWorker {
+    /// Runs `job` on the crash thread, waiting for it to finish. If it doesn't in time, this
+    /// doesn't return, as the process is ended instead.
+    pub(crate) fn run(&self, job: &mut dyn FnMut()) {
+        let _turn = self.turn.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // SAFETY: This doesn't return until the crash thread is done with the job, so it lives
+        // for as long as it's used. Only the lifetime is changed
+        let job: *mut (dyn FnMut() + 'static) = unsafe { std::mem::transmute(job) };
+
+        let mut slot = self.shared.lock();
+        slot.job = Some(Job(job));
+        slot.panicking = Some(thread::current());
+        slot.done = false;
+        self.shared.changed.notify_all();
+
+        match self.timeout {
+            None => {
+                while !slot.done {
+                    slot = self
+                        .shared
+                        .changed
+                        .wait(slot)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+            }
+            Some((timeout, action)) => {
+                let (waited, result) = self
+                    .shared
+                    .changed
+                    .wait_timeout_while(slot, timeout, |slot| !slot.done)
+                    .unwrap_or_else(PoisonError::into_inner);
+
+                if result.timed_out() {
+                    drop(waited);
+                    let _ =
+                        io::stderr().write_all(b"evac: the crash thread ran past its timeout\n");
+
+                    match action {
+                        DeadlineAction::Abort => std::process::abort(),
+                        DeadlineAction::Exit(code) => std::process::exit(code),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for CrashWorker {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Slot> {
+        self.slot.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn work(&self) {
+        // Panics in here are caught below, not handled again
+        isolate::mark_isolated();
+        // Set up ahead of time, as doing so may allocate
+        ON_BEHALF_OF.with(|_| {});
+
+        let mut slot = self.lock();
+        loop {
+            if slot.shutdown {
+                return;
+            }
+
+            let Some(job) = slot.job.take() else {
+                slot = self
+                    .changed
+                    .wait(slot)
+                    .unwrap_or_else(PoisonError::into_inner);
+                continue;
+            };
+            let panicking = slot.panicking.take();
+
+            drop(slot);
+            ON_BEHALF_OF.with(|thread| *thread.borrow_mut() = panicking);
+
+            // SAFETY: The panicking thread is waiting on us, see `CrashWorker::run`
+            let job = unsafe { &mut *job.0 };
+            // Handlers are caught one by one, so this is only what's run around them
+            if let Err(message) = isolate::catch(job) {
+                let _ = writeln!(
+                    io::stderr(),
+                    "evac: the pipeline panicked on the crash thread, skipping the rest: {message}"
+                );
+            }
+
+            ON_BEHALF_OF.with(|thread| thread.borrow_mut().take());
+            slot = self.lock();
+            slot.done = true;
+            self.changed.notify_all();
+        }
+    }
+}
@@ -0,0 +1,664 @@
+//! The primitives age needs, see [`crate::age`]: SHA-256 and HKDF, ChaCha20-Poly1305 (RFC 8439),
+//! and X25519 (RFC 7748), of which signing requests to AWS needs the first. Nothing here branches
+//! on or indexes by secrets.
+#![cfg_attr(not(feature = "age"), allow(dead_code))]
+
+use std::io;
+
+/// Fills `buf` from the OS's secure random number generator.
+pub(crate) fn random(buf: &mut [u8]) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Read;
+
+        std::fs::File::open("/dev/urandom")?.read_exact(buf)
+    }
+
+    #[cfg(windows)]
+    {
+        #[link(name = "bcrypt")]
+        extern "system" {
+            fn BCryptGenRandom(
+                algorithm: *mut std::ffi::c_void,
+                buffer: *mut u8,
+                len: u32,
+                flags: u32,
+            ) -> i32;
+        }
+        const BCRYPT_USE_SYSTEM_PREFERRED_RNG: u32 = 2;
+
+        for chunk in buf.chunks_mut(u32::MAX as usize) {
+            // SAFETY: The buffer is valid for `len` bytes, and no algorithm handle is needed with
+            // the system's preferred generator
+            let status = unsafe {
+                BCryptGenRandom(
+                    std::ptr::null_mut(),
+                    chunk.as_mut_ptr(),
+                    chunk.len() as u32,
+                    BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+                )
+            };
+            if status != 0 {
+                return Err(io::Error::other("BCryptGenRandom failed"));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = buf;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "no secure random number generator on this platform",
+        ))
+    }
+}
+
+pub(crate) fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+        0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+        0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+        0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+        0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+        0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+        0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    // Padded with a 1 bit, then zeros, then the length in bits
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend((data.len() as u64 * 8).to_be_bytes());
+
+    for block in padded.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+
+            (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+        }
+
+        for (word, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+pub(crate) fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner: Vec<u8> = block.iter().map(|byte| byte ^ 0x36).collect();
+    inner.extend(message);
+    let mut outer: Vec<u8> = block.iter().map(|byte| byte ^ 0x5c).collect();
+    outer.extend(sha256(&inner));
+
+    sha256(&outer)
+}
+
+/// HKDF-SHA256 (RFC 5869), for a single 32-byte key.
+pub(crate) fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8]) -> [u8; 32] {
+    let prk = hmac_sha256(salt, ikm);
+
+    let mut message = info.to_vec();
+    message.push(1);
+    hmac_sha256(&prk, &message)
+}
+
+fn chacha20_block(key: &[u8; 32], counter: u32, nonce: &[u8; 12]) -> [u8; 64] {
+    let mut state = [0u32; 16];
+    state[..4].copy_from_slice(&[0x61707865, 0x3320646e, 0x79622d32, 0x6b206574]);
+    for (i, word) in key.chunks_exact(4).enumerate() {
+        state[4 + i] = u32::from_le_bytes(word.try_into().unwrap());
+    }
+    state[12] = counter;
+    for (i, word) in nonce.chunks_exact(4).enumerate() {
+        state[13 + i] = u32::from_le_bytes(word.try_into().unwrap());
+    }
+
+    let quarter_round = |x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize| {
+        x[a] = x[a].wrapping_add(x[b]);
+        x[d] = (x[d] ^ x[a]).rotate_left(16);
+        x[c] = x[c].wrapping_add(x[d]);
+        x[b] = (x[b] ^ x[c]).rotate_left(12);
+        x[a] = x[a].wrapping_add(x[b]);
+        x[d] = (x[d] ^ x[a]).rotate_left(8);
+        x[c] = x[c].wrapping_add(x[d]);
+        x[b] = (x[b] ^ x[c]).rotate_left(7);
+    };
+    let mut x = state;
+    for _ in 0..10 {
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 1, 5, 9, 13);
+        quarter_round(&mut x, 2, 6, 10, 14);
+        quarter_round(&mut x, 3, 7, 11, 15);
+        quarter_round(&mut x, 0, 5, 10, 15);
+        quarter_round(&mut x, 1, 6, 11, 12);
+        quarter_round(&mut x, 2, 7, 8, 13);
+        quarter_round(&mut x, 3, 4, 9, 14);
+    }
+
+    let mut block = [0; 64];
+    for (i, bytes) in block.chunks_exact_mut(4).enumerate() {
+        bytes.copy_from_slice(&x[i].wrapping_add(state[i]).to_le_bytes());
+    }
+    block
+}
+
+fn poly1305(key: &[u8; 32], message: &[u8]) -> [u8; 16] {
+    const MASK: u32 = 0x3ff_ffff;
+    let le32 = |bytes: &[u8], at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+
+    // r, clamped, in 26-bit limbs
+    let r = [
+        le32(key, 0) & 0x3ff_ffff,
+        (le32(key, 3) >> 2) & 0x3ff_ff03,
+        (le32(key, 6) >> 4) & 0x3ff_c0ff,
+        (le32(key, 9) >> 6) & 0x3f0_3fff,
+        (le32(key, 12) >> 8) & 0x00f_ffff,
+    ]
+    .map(u64::from);
+    let s = [r[1] * 5, r[2] * 5, r[3] * 5, r[4] * 5];
+
+    let mut h = [0u32; 5];
+    for chunk in message.chunks(16) {
+        let mut block = [0u8; 17];
+        block[..chunk.len()].copy_from_slice(chunk);
+        block[chunk.len()] = 1;
+
+        h[0] += le32(&block, 0) & MASK;
+        h[1] += (le32(&block, 3) >> 2) & MASK;
+        h[2] += (le32(&block, 6) >> 4) & MASK;
+        h[3] += (le32(&block, 9) >> 6) & MASK;
+        h[4] += (le32(&block, 12) >> 8) | (block[16] as u32) << 24;
+
+        let h64 = h.map(u64::from);
+        let mut d = [
+            h64[0] * r[0] + h64[1] * s[3] + h64[2] * s[2] + h64[3] * s[1] + h64[4] * s[0],
+            h64[0] * r[1] + h64[1] * r[0] + h64[2] * s[3] + h64[3] * s[2] + h64[4] * s[1],
+            h64[0] * r[2] + h64[1] * r[1] + h64[2] * r[0] + h64[3] * s[3] + h64[4] * s[2],
+            h64[0] * r[3] + h64[1] * r[2] + h64[2] * r[1] + h64[3] * r[0] + h64[4] * s[3],
+            h64[0] * r[4] + h64[1] * r[3] + h64[2] * r[2] + h64[3] * r[1] + h64[4] * r[0],
+        ];
+        for i in 0..4 {
+            d[i + 1] += d[i] >> 26;
+            h[i] = d[i] as u32 & MASK;
+        }
+        h[4] = d[4] as u32 & MASK;
+        h[0] += (d[4] >> 26) as u32 * 5;
+        h[1] += h[0] >> 26;
+        h[0] &= MASK;
+    }
+
+    // Fully reduced, then less p if it's at least p, without branching on which
+    for i in 1..5 {
+        h[i] += h[i - 1] >> 26;
+        h[i - 1] &= MASK;
+    }
+    h[0] += (h[4] >> 26) * 5;
+    h[4] &= MASK;
+    h[1] += h[0] >> 26;
+    h[0] &= MASK;
+
+    let mut g = [0u32; 5];
+    let mut carry = 5;
+    for i in 0..5 {
+        g[i] = h[i].wrapping_add(carry);
+        carry = g[i] >> 26;
+        g[i] &= MASK;
+    }
+    g[4] = g[4].wrapping_add(carry << 26).wrapping_sub(1 << 26);
+    let use_g = (g[4] >> 31).wrapping_sub(1);
+    for i in 0..5 {
+        h[i] = (h[i] & !use_g) | (g[i] & use_g);
+    }
+
+    let words = [
+        h[0] | h[1] << 26,
+        h[1] >> 6 | h[2] << 20,
+        h[2] >> 12 | h[3] << 14,
+        h[3] >> 18 | h[4] << 8,
+    ];
+    let mut tag = [0; 16];
+    let mut carry = 0u64;
+    for (i, word) in words.into_iter().enumerate() {
+        let sum = word as u64 + le32(key, 16 + 4 * i) as u64 + carry;
+        tag[4 * i..4 * i + 4].copy_from_slice(&(sum as u32).to_le_bytes());
+        carry = sum >> 32;
+    }
+    tag
+}
+
+/// Encrypts `plaintext` with ChaCha20-Poly1305 and no associated data, returning the ciphertext
+/// followed by its tag.
+pub(crate) fn chacha20_poly1305(key: &[u8; 32], nonce: &[u8; 12], plaintext: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(plaintext.len() + 16);
+    for (i, chunk) in plaintext.chunks(64).enumerate() {
+        let keystream = chacha20_block(key, i as u32 + 1, nonce);
+        out.extend(chunk.iter().zip(keystream).map(|(byte, key)| byte ^ key));
+    }
+
+    let poly_key: [u8; 32] = chacha20_block(key, 0, nonce)[..32].try_into().unwrap();
+    let mut mac_data = out.clone();
+    mac_data.resize(out.len().div_ceil(16) * 16, 0);
+    mac_data.extend(0u64.to_le_bytes());
+    mac_data.extend((out.len() as u64).to_le_bytes());
+    out.extend(poly1305(&poly_key, &mac_data));
+
+    out
+}
+
+/// An element of the field of integers modulo 2^255 - 19, in 51-bit limbs.
+#[derive(Clone, Copy)]
+struct Field([u64; 5]);
+
+impl Field {
+    const MASK: u64 = (1 << 51) - 1;
+    const ZERO: Field = Field([0; 5]);
+    const ONE: Field = Field([1, 0, 0, 0, 0]);
+
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let le64 = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+
+        Self([
+            le64(0) & Self::MASK,
+            (le64(6) >> 3) & Self::MASK,
+            (le64(12) >> 6) & Self::MASK,
+            (le64(19) >> 1) & Self::MASK,
+            (le64(24) >> 12) & Self::MASK,
+        ])
+    }
+
+    fn to_bytes(self) -> [u8; 32] {
+        let mut limbs = self.carry().0;
+
+        // Less p if it's at least p, by adding 19 and seeing if that carries past 2^255
+        let mut q = (limbs[0] + 19) >> 51;
+        for limb in &limbs[1..] {
+            q = (limb + q) >> 51;
+        }
+        limbs[0] += 19 * q;
+        for i in 0..4 {
+            limbs[i + 1] += limbs[i] >> 51;
+            limbs[i] &= Self::MASK;
+        }
+        limbs[4] &= Self::MASK;
+
+        let mut bytes = [0; 32];
+        let mut acc = 0u128;
+        let mut acc_bits = 0;
+        let mut out = 0;
+        for limb in limbs {
+            acc |= (limb as u128) << acc_bits;
+            acc_bits += 51;
+            while acc_bits >= 8 && out < 32 {
+                bytes[out] = acc as u8;
+                acc >>= 8;
+                acc_bits -= 8;
+                out += 1;
+            }
+        }
+        if out < 32 {
+            bytes[out] = acc as u8;
+        }
+        bytes
+    }
+
+    /// Carries each limb's excess into the next, so that they're back to about 51 bits.
+    fn carry(self) -> Self {
+        let mut limbs = self.0;
+        for i in 0..4 {
+            limbs[i + 1] += limbs[i] >> 51;
+            limbs[i] &= Self::MASK;
+        }
+        limbs[0] += 19 * (limbs[4] >> 51);
+        limbs[4] &= Self::MASK;
+
+        Self(limbs)
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self(std::array::from_fn(|i| self.0[i] + other.0[i])).carry()
+    }
+
+    fn sub(self, other: Self) -> Self {
+        // Adds 4p first, so that limbs don't go below zero
+        const FOUR_P: [u64; 5] = [
+            0x1f_ffff_ffff_ffb4,
+            0x1f_ffff_ffff_fffc,
+            0x1f_ffff_ffff_fffc,
+            0x1f_ffff_ffff_fffc,
+            0x1f_ffff_ffff_fffc,
+        ];
+
+        Self(std::array::from_fn(|i| self.0[i] + FOUR_P[i] - other.0[i])).carry()
+    }
+
+    fn mul(self, other: Self) -> Self {
+        let a = self.0.map(u128::from);
+        let b = other.0.map(u128::from);
+        let b19 = b.map(|limb| limb * 19);
+
+        let c = [
+            a[0] * b[0] + a[4] * b19[1] + a[3] * b19[2] + a[2] * b19[3] + a[1] * b19[4],
+            a[1] * b[0] + a[0] * b[1] + a[4] * b19[2] + a[3] * b19[3] + a[2] * b19[4],
+            a[2] * b[0] + a[1] * b[1] + a[0] * b[2] + a[4] * b19[3] + a[3] * b19[4],
+            a[3] * b[0] + a[2] * b[1] + a[1] * b[2] + a[0] * b[3] + a[4] * b19[4],
+            a[4] * b[0] + a[3] * b[1] + a[2] * b[2] + a[1] * b[3] + a[0] * b[4],
+        ];
+
+        Self::reduce_wide(c)
+    }
+
+    fn mul_small(self, n: u64) -> Self {
+        Self::reduce_wide(self.0.map(|limb| limb as u128 * n as u128))
+    }
+
+    fn reduce_wide(mut c: [u128; 5]) -> Self {
+        for i in 0..4 {
+            c[i + 1] += c[i] >> 51;
+            c[i] &= Self::MASK as u128;
+        }
+        c[0] += 19 * (c[4] >> 51);
+        c[4] &= Self::MASK as u128;
+        c[1] += c[0] >> 51;
+        c[0] &= Self::MASK as u128;
+
+        Self(c.map(|limb| limb as u64))
+    }
+
+    fn square(self) -> Self {
+        self.mul(self)
+    }
+
+    /// The inverse, as self^(p - 2). The exponent is fixed, so this takes the same time for any
+    /// input.
+    fn invert(self) -> Self {
+        // p - 2 = 2^255 - 21 has every bit from 254 down set, but bits 4 and 2
+        let mut result = Self::ONE;
+        for bit in (0..255).rev() {
+            result = result.square();
+            if bit != 4 && bit != 2 {
+                result = result.mul(self);
+            }
+        }
+        result
+    }
+
+    /// Swaps `a` and `b` if `swap` is 1, without branching on it.
+    fn swap(a: &mut Self, b: &mut Self, swap: u64) {
+        let mask = swap.wrapping_neg();
+        for i in 0..5 {
+            let t = mask & (a.0[i] ^ b.0[i]);
+            a.0[i] ^= t;
+            b.0[i] ^= t;
+        }
+    }
+}
+
+/// X25519 of `scalar` and the point with u-coordinate `point`.
+pub(crate) fn x25519(scalar: &[u8; 32], point: &[u8; 32]) -> [u8; 32] {
+    let mut k = *scalar;
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+
+    // The Montgomery ladder
+    let x1 = Field::from_bytes(point);
+    let (mut x2, mut z2, mut x3, mut z3) = (Field::ONE, Field::ZERO, x1, Field::ONE);
+    let mut swap = 0;
+    for t in (0..255).rev() {
+        let bit = ((k[t / 8] >> (t % 8)) & 1) as u64;
+        swap ^= bit;
+        Field::swap(&mut x2, &mut x3, swap);
+        Field::swap(&mut z2, &mut z3, swap);
+        swap = bit;
+
+        let a = x2.add(z2);
+        let aa = a.square();
+        let b = x2.sub(z2);
+        let bb = b.square();
+        let e = aa.sub(bb);
+        let c = x3.add(z3);
+        let d = x3.sub(z3);
+        let da = d.mul(a);
+        let cb = c.mul(b);
+        x3 = da.add(cb).square();
+        z3 = x1.mul(da.sub(cb).square());
+        x2 = aa.mul(bb);
+        z2 = e.mul(aa.add(e.mul_small(121_665)));
+    }
+    Field::swap(&mut x2, &mut x3, swap);
+    Field::swap(&mut z2, &mut z3, swap);
+
+    x2.mul(z2.invert()).to_bytes()
+}
+
+/// The X25519 public key for `secret`.
+pub(crate) fn x25519_base(secret: &[u8; 32]) -> [u8; 32] {
+    let mut base = [0; 32];
+    base[0] = 9;
+
+    x25519(secret, &base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn key(text: &str) -> [u8; 32] {
+        hex(text).try_into().unwrap()
+    }
+
+    #[test]
+    fn sha256_matches_fips_180() {
+        assert_eq!(
+            sha256(b"abc")[..],
+            hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert_eq!(
+            sha256(b"")[..],
+            hex("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+        // Long enough for the padding to need a block of its own
+        assert_eq!(
+            sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")[..],
+            hex("248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1")
+        );
+        assert_eq!(
+            sha256(&[b'a'; 1_000_000])[..],
+            hex("cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0")
+        );
+    }
+
+    #[test]
+    fn hmac_sha256_matches_rfc_4231() {
+        assert_eq!(
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?")[..],
+            hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+        // A key longer than a block, which is hashed first
+        assert_eq!(
+            hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )[..],
+            hex("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54")
+        );
+    }
+
+    #[test]
+    fn hkdf_sha256_matches_rfc_5869() {
+        let salt: Vec<u8> = (0..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+
+        // The first 32 bytes of the test cases' output
+        assert_eq!(
+            hkdf_sha256(&salt, &[0x0b; 22], &info)[..],
+            hex("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf")
+        );
+        assert_eq!(
+            hkdf_sha256(&[], &[0x0b; 22], &[])[..],
+            hex("8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d")
+        );
+    }
+
+    #[test]
+    fn chacha20_matches_rfc_8439() {
+        let key: [u8; 32] = std::array::from_fn(|i| i as u8);
+        let nonce = hex("000000090000004a00000000").try_into().unwrap();
+
+        assert_eq!(
+            chacha20_block(&key, 1, &nonce)[..],
+            hex(concat!(
+                "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e",
+                "d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e",
+            ))
+        );
+    }
+
+    #[test]
+    fn poly1305_matches_rfc_8439() {
+        let key = key("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b");
+
+        assert_eq!(
+            poly1305(&key, b"Cryptographic Forum Research Group")[..],
+            hex("a8061dc1305136c6c22b8baf0c0127a9")
+        );
+    }
+
+    #[test]
+    fn chacha20_poly1305_matches_rfc_8439() {
+        let key: [u8; 32] = std::array::from_fn(|i| 0x80 + i as u8);
+        let nonce = hex("070000004041424344454647").try_into().unwrap();
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one \
+            tip for the future, sunscreen would be it.";
+
+        // The RFC's example has associated data, which only changes the tag, so that's the one
+        // other implementations give for none
+        assert_eq!(
+            chacha20_poly1305(&key, &nonce, plaintext),
+            hex(concat!(
+                "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6",
+                "3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36",
+                "92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc",
+                "3ff4def08e4b7a9de576d26586cec64b6116",
+                "6a23a4681fd59456aea1d29f82477216",
+            ))
+        );
+    }
+
+    #[test]
+    fn x25519_matches_rfc_7748() {
+        assert_eq!(
+            x25519(
+                &key("a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4"),
+                &key("e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c"),
+            )[..],
+            hex("c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552")
+        );
+        // The point's top bit is set, which is to be ignored
+        assert_eq!(
+            x25519(
+                &key("4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d"),
+                &key("e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493"),
+            )[..],
+            hex("95cbde9476e8907d7aade45cb4b873f88b595a68799fa152e6f8f7647aac7957")
+        );
+    }
+
+    #[test]
+    fn x25519_iterates_as_rfc_7748_does() {
+        let (mut scalar, mut point) = ([0; 32], [0; 32]);
+        scalar[0] = 9;
+        point[0] = 9;
+
+        let mut results = vec![];
+        for i in 1..=1000 {
+            let next = x25519(&scalar, &point);
+            point = scalar;
+            scalar = next;
+            if i == 1 || i == 1000 {
+                results.push(scalar);
+            }
+        }
+
+        assert_eq!(
+            results,
+            [
+                key("422c8e7a6227d7bca1350b3e2bb7279f7897b87bb6854b783c60e80311ae3079"),
+                key("684cf59ba83309552800ef566f2f4d3c1c3887c49360e3875f2eb94d99532c51"),
+            ]
+        );
+    }
+
+    #[test]
+    fn x25519_agrees_on_rfc_7748s_shared_secret() {
+        let alice = key("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+        let bob = key("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+
+        assert_eq!(
+            x25519_base(&alice),
+            key("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+        );
+        assert_eq!(
+            x25519_base(&bob),
+            key("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+        );
+        let shared = key("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+        assert_eq!(x25519(&alice, &x25519_base(&bob)), shared);
+        assert_eq!(x25519(&bob, &x25519_base(&alice)), shared);
+    }
+}
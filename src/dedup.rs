@@ -0,0 +1,183 @@
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::panic::PanicHookInfo;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::filter;
+
+thread_local! {
+    /// While handlers run for a deduplicated panic, how many times it's been seen.
+    static OCCURRENCES: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// How many times the panic being handled has happened since it was last handled, counting this
+/// time. Only known to handlers of a [deduplicating](crate::EvacBuilder::deduplicate) pipeline;
+/// otherwise, and outside of handlers, it's `None`.
+///
+/// ## Example
+/// ```
+/// # use std::time::Duration;
+/// # use evac::EvacBuilder;
+/// EvacBuilder::new()
+///   .with_handler(|info, _: &mut ()| {
+///     let seen = evac::occurrences().unwrap_or(1);
+///     eprintln!("{info} (seen {seen} times)");
+///     Ok(())
+///   })
+///   .deduplicate(Duration::from_secs(60))
+///   .register(())?;
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+pub fn occurrences() -> Option<u64> {
+    OCCURRENCES.with(Cell::get)
+}
+
+/// How many different panics are kept track of at once. Past that, the one that's gone longest
+/// without happening is forgotten, along with how many times it was repeated.
+const MAX_TRACKED: usize = 1024;
+
+/// Keeps track of which panics have been seen, by their message and location.
+pub(crate) struct Dedup {
+    window: Duration,
+    seen: Mutex<HashMap<u64, Seen>>,
+}
+
+struct Seen {
+    /// When the panic was last handled.
+    handled: Instant,
+    /// When it last happened, handled or not.
+    last: Instant,
+    /// How many times it's happened since, without being handled.
+    repeats: u64,
+}
+
+impl Dedup {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            window,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts the panic, and returns how many times it's happened since it was last handled if
+    /// it's to be handled again, or `None` if it's a repeat.
+    pub(crate) fn admit(&self, info: &PanicHookInfo<'_>) -> Option<u64> {
+        self.admit_at(fingerprint(info), Instant::now())
+    }
+
+    /// Counts the panic with `fingerprint`, as of `now`, as per [`Dedup::admit`].
+    fn admit_at(&self, fingerprint: u64, now: Instant) -> Option<u64> {
+        let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // Anything that's not been repeated within the window won't be suppressed anymore
+        seen.retain(|_, seen| seen.repeats > 0 || now.duration_since(seen.handled) < self.window);
+        // Repeats are kept until the panic is handled again, which may be never, so there's a cap
+        if seen.len() >= MAX_TRACKED && !seen.contains_key(&fingerprint) {
+            let stalest = seen
+                .iter()
+                .min_by_key(|(_, seen)| seen.last)
+                .map(|(&fingerprint, _)| fingerprint);
+            if let Some(stalest) = stalest {
+                seen.remove(&stalest);
+            }
+        }
+
+        let seen = match seen.entry(fingerprint) {
+            Entry::Occupied(seen) => seen.into_mut(),
+            Entry::Vacant(seen) => {
+                seen.insert(Seen {
+                    handled: now,
+                    last: now,
+                    repeats: 0,
+                });
+                return Some(1);
+            }
+        };
+
+        seen.last = now;
+        if now.duration_since(seen.handled) < self.window {
+            seen.repeats += 1;
+            return None;
+        }
+
+        let occurrences = seen.repeats + 1;
+        *seen = Seen {
+            handled: now,
+            last: now,
+            repeats: 0,
+        };
+
+        Some(occurrences)
+    }
+}
+
+/// Sets what [`occurrences`] returns until the returned guard is dropped.
+pub(crate) fn enter(occurrences: Option<u64>) -> Entered {
+    OCCURRENCES.with(|current| current.set(occurrences));
+
+    Entered
+}
+
+pub(crate) struct Entered;
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        OCCURRENCES.with(|current| current.set(None));
+    }
+}
+
+/// Identifies a panic by its message and where it happened.
+fn fingerprint(info: &PanicHookInfo<'_>) -> u64 {
+    // A fixed key, so that fingerprints are the same every time
+    let mut hasher = DefaultHasher::new();
+    filter::message(info).hash(&mut hasher);
+    info.location()
+        .map(|location| (location.file(), location.line(), location.column()))
+        .hash(&mut hasher);
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn counts_repeats_until_the_window_is_up() {
+        let dedup = Dedup::new(WINDOW);
+        let start = Instant::now();
+
+        assert_eq!(dedup.admit_at(1, start), Some(1));
+        assert_eq!(dedup.admit_at(1, start + WINDOW / 2), None);
+        assert_eq!(dedup.admit_at(2, start + WINDOW / 2), Some(1));
+        assert_eq!(dedup.admit_at(1, start + WINDOW * 3 / 4), None);
+        assert_eq!(dedup.admit_at(1, start + WINDOW), Some(3));
+        assert_eq!(dedup.admit_at(1, start + WINDOW * 3), Some(1));
+    }
+
+    #[test]
+    fn forgets_the_stalest_panic_past_the_cap() {
+        let dedup = Dedup::new(WINDOW);
+        let start = Instant::now();
+
+        // All repeated, so that none of them expire
+        for fingerprint in 0..MAX_TRACKED as u64 {
+            let now = start + Duration::from_millis(fingerprint);
+            dedup.admit_at(fingerprint, now);
+            dedup.admit_at(fingerprint, now);
+        }
+        let later = start + WINDOW * 2;
+        assert_eq!(dedup.admit_at(u64::MAX, later), Some(1));
+
+        assert_eq!(dedup.seen.lock().unwrap().len(), MAX_TRACKED);
+        // The first lost its repeat, the rest still have theirs
+        assert_eq!(dedup.admit_at(0, later), Some(1));
+        assert_eq!(dedup.admit_at(2, later), Some(2));
+    }
+}
@@ -0,0 +1,44 @@
+use std::ffi::OsStr;
+
+/// Turns evac's handlers off when set, see
+/// [`EvacBuilder::ignore_environment`](crate::EvacBuilder::ignore_environment).
+const DISABLE: &str = "EVAC_DISABLE";
+/// A comma-separated list of the only handlers to run, by name.
+const ONLY: &str = "EVAC_ONLY";
+/// Prints how each handler went when set.
+const VERBOSE: &str = "EVAC_VERBOSE";
+
+/// The environment variables the hook honors, as of the panic being handled.
+#[derive(Default)]
+pub(crate) struct Environment {
+    pub(crate) disabled: bool,
+    only: Option<String>,
+    pub(crate) verbose: bool,
+}
+
+impl Environment {
+    /// Reads the variables. Unset ones don't allocate, so this is free unless they're in use.
+    pub(crate) fn read() -> Self {
+        Self {
+            disabled: flag(DISABLE),
+            only: std::env::var(ONLY).ok(),
+            verbose: flag(VERBOSE),
+        }
+    }
+
+    /// Whether the handler registered under `name` may run.
+    pub(crate) fn allows(&self, name: Option<&str>) -> bool {
+        let Some(only) = &self.only else {
+            return true;
+        };
+
+        name.is_some_and(|name| only.split(',').any(|allowed| allowed.trim() == name))
+    }
+}
+
+/// Whether the variable is set to anything but nothing, `0` or `false`.
+fn flag(key: &str) -> bool {
+    std::env::var_os(key).is_some_and(|value| {
+        !(value.is_empty() || value == "0" || value.eq_ignore_ascii_case(OsStr::new("false")))
+    })
+}
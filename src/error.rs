@@ -0,0 +1,243 @@
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// Returned by [`EvacBuilder::register`](crate::EvacBuilder::register) when the hook can't be
+/// installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RegisterError {
+    /// Another evac hook is already installed. Use
+    /// [`EvacBuilder::force_register`](crate::EvacBuilder::force_register) to replace it anyways.
+    AlreadyRegistered,
+    /// The existing hook was asked to run after the handlers, which
+    /// [`EvacBuilder::register_incremental`](crate::EvacBuilder::register_incremental) can't do,
+    /// as the hook it wraps always runs first.
+    HookAfter,
+}
+
+impl Display for RegisterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterError::AlreadyRegistered => {
+                write!(f, "an evac panic hook is already installed")
+            }
+            RegisterError::HookAfter => write!(
+                f,
+                "incremental registrations always run the existing hook first"
+            ),
+        }
+    }
+}
+
+impl Error for RegisterError {}
+
+/// Returned from an extension handler when the [`Extensions`](crate::Extensions) context has no
+/// value of the type it needs, see
+/// [`EvacBuilder::with_extension_handler`](crate::EvacBuilder::with_extension_handler).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingExtension {
+    /// The name of the missing type.
+    pub type_name: &'static str,
+}
+
+impl Display for MissingExtension {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "no `{}` was provided in the context", self.type_name)
+    }
+}
+
+impl Error for MissingExtension {}
+
+/// Returned by [`Scrubber::rule`](crate::Scrubber::rule) when its pattern isn't a regular
+/// expression it supports, and as part of a [`FilterError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    pub pattern: String,
+    /// What's wrong with it.
+    pub reason: &'static str,
+}
+
+impl Display for PatternError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid pattern `{}`: {}", self.pattern, self.reason)
+    }
+}
+
+impl Error for PatternError {}
+
+/// Returned by [`EvacBuilder::only_if_message_matches`](crate::EvacBuilder::only_if_message_matches)
+/// and [`EvacBuilder::skip_if_message_matches`](crate::EvacBuilder::skip_if_message_matches) when
+/// the filter can't be added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterError {
+    /// No handler is registered under the name the filter is for.
+    UnknownHandler { name: String },
+    /// One of the patterns isn't a regular expression evac supports.
+    Pattern(PatternError),
+}
+
+impl Display for FilterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::UnknownHandler { name } => {
+                write!(f, "no handler is registered as `{name}`")
+            }
+            FilterError::Pattern(error) => Display::fmt(error, f),
+        }
+    }
+}
+
+impl Error for FilterError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FilterError::UnknownHandler { .. } => None,
+            FilterError::Pattern(error) => Some(error),
+        }
+    }
+}
+
+impl From<PatternError> for FilterError {
+    fn from(error: PatternError) -> Self {
+        FilterError::Pattern(error)
+    }
+}
+
+/// Returned when parsing an [`AgeRecipient`](crate::AgeRecipient) from a string that isn't one.
+#[cfg(feature = "age")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecipientError {
+    pub recipient: String,
+    /// What's wrong with it.
+    pub reason: &'static str,
+}
+
+#[cfg(feature = "age")]
+impl Display for RecipientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid age recipient `{}`: {}",
+            self.recipient, self.reason
+        )
+    }
+}
+
+#[cfg(feature = "age")]
+impl Error for RecipientError {}
+
+/// Something that went wrong while handling a panic, see
+/// [`EvacBuilder::error_sink`](crate::EvacBuilder::error_sink).
+///
+/// `E` is the error type returned by the handlers, `Box<dyn Error>` by default.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum HandlerError<'a, E = Box<dyn Error>> {
+    /// A handler returned an error.
+    Handler {
+        /// The handler's name, if it was added as a named handler.
+        name: Option<&'a str>,
+        /// The handler's position in the order the handlers ran in.
+        index: usize,
+        error: &'a E,
+    },
+    /// A handler panicked, see
+    /// [`EvacBuilder::isolate_handlers`](crate::EvacBuilder::isolate_handlers).
+    Panicked {
+        /// The handler's name, if it was added as a named handler.
+        name: Option<&'a str>,
+        /// The handler's position in the order the handlers ran in.
+        index: usize,
+        /// The panic's message.
+        message: &'a str,
+    },
+    /// A finalizer returned an error.
+    Finalizer {
+        /// The finalizer's position in the order the finalizers ran in.
+        index: usize,
+        error: &'a E,
+    },
+    /// One of the panicking thread's own handlers returned an error, see
+    /// [`thread::with_handlers`](crate::thread::with_handlers).
+    Thread {
+        /// The handler's position in the order the thread's handlers ran in.
+        index: usize,
+        error: &'a (dyn Error + 'static),
+    },
+    /// The context couldn't be initialized or provided, so the handlers were skipped.
+    Context { error: &'a (dyn Error + 'static) },
+    /// The handlers were skipped without running.
+    Skipped(Skipped),
+}
+
+/// Why the handlers were skipped, see [`HandlerError::Skipped`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Skipped {
+    /// Another thread was still handling its panic, as per the
+    /// [`Contention`](crate::Contention) policy.
+    Busy,
+    /// The thread that panicked was using the context at the time.
+    Reentrant,
+    /// The context failed to initialize during an earlier panic.
+    ContextFailed,
+}
+
+impl<E: Display> Display for HandlerError<'_, E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            HandlerError::Handler {
+                name: Some(name),
+                error,
+                ..
+            } => write!(f, "Error encountered in panic handler `{name}`: {error}"),
+            HandlerError::Handler { index, error, .. } => {
+                write!(f, "Error encountered in panic handler #{index}: {error}")
+            }
+            HandlerError::Panicked {
+                name: Some(name),
+                message,
+                ..
+            } => write!(f, "Panic handler `{name}` panicked: {message}"),
+            HandlerError::Panicked { index, message, .. } => {
+                write!(f, "Panic handler #{index} panicked: {message}")
+            }
+            HandlerError::Finalizer { index, error } => {
+                write!(f, "Error encountered in panic finalizer #{index}: {error}")
+            }
+            HandlerError::Thread { index, error } => {
+                write!(
+                    f,
+                    "Error encountered in thread panic handler #{index}: {error}"
+                )
+            }
+            HandlerError::Context { error } => {
+                write!(
+                    f,
+                    "Error encountered getting panic handler context: {error}"
+                )
+            }
+            HandlerError::Skipped(Skipped::Busy) => write!(
+                f,
+                "Panic handlers skipped, another thread is still handling its panic"
+            ),
+            HandlerError::Skipped(Skipped::Reentrant) => write!(
+                f,
+                "Panic handlers skipped, this thread was using the context"
+            ),
+            HandlerError::Skipped(Skipped::ContextFailed) => write!(
+                f,
+                "Panic handlers skipped, their context failed to initialize"
+            ),
+        }
+    }
+}
+
+// `Box<dyn Error>` itself isn't an `Error`, so handler errors can't be given as the source
+impl<E: Debug + Display> Error for HandlerError<'_, E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HandlerError::Thread { error, .. } | HandlerError::Context { error } => Some(*error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Writes reports to the Windows Event Log, see
+//! [`handlers::windows_event_log`](crate::handlers::windows_event_log).
+
+use std::ffi::c_void;
+use std::io;
+use std::ptr;
+
+use crate::{PanicReport, ReportFormatter, TextFormatter};
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn RegisterEventSourceW(server: *const u16, source: *const u16) -> *mut c_void;
+    fn ReportEventW(
+        log: *mut c_void,
+        kind: u16,
+        category: u16,
+        id: u32,
+        sid: *mut c_void,
+        strings: u16,
+        data_len: u32,
+        string: *const *const u16,
+        data: *mut c_void,
+    ) -> i32;
+    fn DeregisterEventSource(log: *mut c_void) -> i32;
+}
+
+const EVENTLOG_ERROR_TYPE: u16 = 1;
+
+/// The ID events are written with.
+const EVENT_ID: u32 = 1;
+
+/// How long an event's text is kept to, in UTF-16 code units, as the Event Log takes no more.
+const MAX_LEN: usize = 31_000;
+
+/// Writes `report` to the Application log as an error, from `source`.
+pub(crate) fn write(report: &PanicReport<'_>, source: &str) -> io::Result<()> {
+    // In full, as the Event Log has room for it, and shows it as it is
+    let mut text = Vec::new();
+    TextFormatter::new().format(report, &mut text)?;
+    let text = String::from_utf8_lossy(&text).replace('\n', "\r\n");
+
+    let mut text: Vec<u16> = text.encode_utf16().take(MAX_LEN).collect();
+    // Rather than end on half a surrogate pair
+    if text
+        .last()
+        .is_some_and(|unit| (0xd800..0xdc00).contains(unit))
+    {
+        text.pop();
+    }
+    text.push(0);
+    let source: Vec<u16> = source.encode_utf16().chain([0]).collect();
+
+    // SAFETY: Both strings are NUL-terminated, and outlive the calls, and the handle is closed
+    // before returning
+    unsafe {
+        let log = RegisterEventSourceW(ptr::null(), source.as_ptr());
+        if log.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        let strings = [text.as_ptr()];
+        let reported = ReportEventW(
+            log,
+            EVENTLOG_ERROR_TYPE,
+            0,
+            EVENT_ID,
+            ptr::null_mut(),
+            1,
+            0,
+            strings.as_ptr(),
+            ptr::null_mut(),
+        );
+        let error = io::Error::last_os_error();
+        DeregisterEventSource(log);
+
+        match reported {
+            0 => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
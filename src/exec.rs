@@ -0,0 +1,172 @@
+//! Runs an external program on each panic, see [`handlers::exec`](crate::handlers::exec).
+
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use crate::PanicReport;
+
+/// Stands for where the report was written to, as an argument names it.
+const REPORT_PATH: &str = "{report_path}";
+/// Stands for the report's fingerprint.
+const FINGERPRINT: &str = "{fingerprint}";
+/// Stands for the ID of the process that panicked.
+const PID: &str = "{pid}";
+
+/// How often to check whether the program has exited yet.
+const POLL: Duration = Duration::from_millis(10);
+
+/// The program [`handlers::exec`](crate::handlers::exec) runs, with what arguments, and for how
+/// long at most.
+///
+/// Arguments can hold placeholders, which are replaced for each panic:
+/// - `{report_path}`, with the path to a temporary file holding the report, which is deleted once
+///   the program exits. The report is given on the program's standard input otherwise.
+/// - `{fingerprint}`, with the report's [fingerprint](PanicReport::fingerprint).
+/// - `{pid}`, with the ID of the process that panicked.
+///
+/// ## Example
+/// ```
+/// # use std::time::Duration;
+/// # use evac::handlers::ExecCommand;
+/// let command = ExecCommand::new("/usr/local/bin/page-oncall")
+///   .args(["--service", "my-app", "--dedup-key", "{fingerprint}", "--attach", "{report_path}"])
+///   .timeout(Duration::from_secs(5));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecCommand {
+    program: OsString,
+    args: Vec<String>,
+    timeout: Duration,
+}
+
+impl ExecCommand {
+    /// Runs `program`, looked up in `PATH` as the shell would if it isn't a path itself, with no
+    /// arguments, for up to 10 seconds.
+    pub fn new(program: impl Into<OsString>) -> Self {
+        Self {
+            program: program.into(),
+            args: vec![],
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Passes `arg` to the program, after the ones before it.
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+
+        self
+    }
+
+    /// Passes each of `args` to the program, after the ones before them.
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+
+        self
+    }
+
+    /// Kills the program if it's still running `timeout` after it was started, taking it to have
+    /// failed.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+
+        self
+    }
+
+    /// Runs the program for `report`, which was formatted as `formatted`, giving an error if it
+    /// couldn't be started, timed out, or exited unsuccessfully.
+    pub(crate) fn run(&self, report: &PanicReport<'_>, formatted: &[u8]) -> io::Result<()> {
+        let temp = match self.args.iter().any(|arg| arg.contains(REPORT_PATH)) {
+            true => Some(TempReport::write(report, formatted)?),
+            false => None,
+        };
+
+        let pid = report.pid().to_string();
+        let mut command = Command::new(&self.program);
+        for arg in &self.args {
+            let mut arg = arg
+                .replace(FINGERPRINT, report.fingerprint())
+                .replace(PID, &pid);
+            if let Some(temp) = &temp {
+                arg = arg.replace(REPORT_PATH, &temp.0.to_string_lossy());
+            }
+            command.arg(arg);
+        }
+        command.stdin(match temp {
+            Some(_) => Stdio::null(),
+            None => Stdio::piped(),
+        });
+
+        let mut child = command.spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            let formatted = formatted.to_vec();
+            // On a thread of its own, so that a program that doesn't read it all can't hold up
+            // the timeout. Once the program exits, writing fails, and the thread ends
+            std::thread::Builder::new()
+                .name("evac-exec".to_string())
+                .spawn(move || {
+                    let _ = stdin.write_all(&formatted);
+                })?;
+        }
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "`{}` didn't exit within {:?}",
+                        self.program.to_string_lossy(),
+                        self.timeout
+                    ),
+                ));
+            }
+            std::thread::sleep(POLL);
+        };
+
+        match status.success() {
+            true => Ok(()),
+            false => Err(io::Error::other(format!(
+                "`{}` exited with {status}",
+                self.program.to_string_lossy()
+            ))),
+        }
+    }
+}
+
+/// A report written to a temporary file, deleted once dropped.
+struct TempReport(PathBuf);
+
+impl TempReport {
+    fn write(report: &PanicReport<'_>, formatted: &[u8]) -> io::Result<Self> {
+        // Shared by every command, so that none of them can pick the same name
+        static WRITTEN: AtomicU64 = AtomicU64::new(0);
+
+        let millis = report
+            .timestamp()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let count = WRITTEN.fetch_add(1, Ordering::Relaxed);
+        let path =
+            std::env::temp_dir().join(format!("evac-{millis}-{}-{count}.report", report.pid()));
+        crate::handlers::write_new(&path, formatted)?;
+
+        Ok(Self(path))
+    }
+}
+
+impl Drop for TempReport {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
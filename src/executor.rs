@@ -0,0 +1,72 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// Wakes the thread blocking on a future.
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Drives `future` to completion on the current thread, giving up after `timeout`. This is as
+/// minimal an executor as it gets, so with the `tokio` feature, futures are run on a tokio
+/// runtime instead: the current one if the panic happened on one of its threads, or a fresh
+/// single-threaded one.
+pub(crate) fn block_on<F: Future>(future: F, timeout: Duration) -> Option<F::Output> {
+    #[cfg(feature = "tokio")]
+    {
+        use tokio::runtime::{Builder, Handle};
+
+        match Handle::try_current() {
+            // Its block_on would panic, as this thread is already in a runtime. The runtime's
+            // other threads may still drive its IO and timers, and if not, the timeout ends it
+            Ok(handle) => {
+                let _entered = handle.enter();
+                poll_until(future, timeout)
+            }
+            Err(_) => {
+                let Ok(runtime) = Builder::new_current_thread().enable_all().build() else {
+                    return poll_until(future, timeout);
+                };
+                // The timeout needs to be made within the runtime
+                runtime
+                    .block_on(async { tokio::time::timeout(timeout, future).await })
+                    .ok()
+            }
+        }
+    }
+
+    #[cfg(not(feature = "tokio"))]
+    poll_until(future, timeout)
+}
+
+fn poll_until<F: Future>(future: F, timeout: Duration) -> Option<F::Output> {
+    let deadline = Instant::now() + timeout;
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return Some(output);
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return None;
+        }
+
+        // Spurious wakeups only cost an extra poll
+        thread::park_timeout(remaining);
+    }
+}
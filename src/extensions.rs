@@ -0,0 +1,78 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+
+/// A context made up of values of different types, at most one of each. Lets handlers from
+/// unrelated crates share an [`EvacBuilder`](crate::EvacBuilder) without agreeing on a single
+/// context type; see [`EvacBuilder::with_extension_handler`](crate::EvacBuilder::with_extension_handler).
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send>>,
+}
+
+impl Extensions {
+    /// Constructs an empty set of extensions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a value, returning the previous value of the same type if there was one.
+    pub fn insert<C: Send + 'static>(&mut self, value: C) -> Option<C> {
+        self.map
+            .insert(TypeId::of::<C>(), Box::new(value))
+            .and_then(|prev| prev.downcast().ok())
+            .map(|prev| *prev)
+    }
+
+    /// Inserts a value, builder-style.
+    pub fn with<C: Send + 'static>(mut self, value: C) -> Self {
+        self.insert(value);
+
+        self
+    }
+
+    /// Gets a reference to the value of type `C`, if there is one.
+    pub fn get<C: Send + 'static>(&self) -> Option<&C> {
+        self.map
+            .get(&TypeId::of::<C>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Gets a mutable reference to the value of type `C`, if there is one.
+    pub fn get_mut<C: Send + 'static>(&mut self) -> Option<&mut C> {
+        self.map
+            .get_mut(&TypeId::of::<C>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    /// Removes and returns the value of type `C`, if there is one.
+    pub fn remove<C: Send + 'static>(&mut self) -> Option<C> {
+        self.map
+            .remove(&TypeId::of::<C>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Whether there's a value of type `C`.
+    pub fn contains<C: Send + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<C>())
+    }
+
+    /// The number of values held.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Whether no values are held.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl Debug for Extensions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish_non_exhaustive()
+    }
+}
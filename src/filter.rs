@@ -0,0 +1,132 @@
+use std::panic::PanicHookInfo;
+
+use crate::crash_thread;
+use crate::regex::Regex;
+
+/// The panic's message, if it has one. Panics with a payload other than a string don't.
+pub(crate) fn message<'a>(info: &'a PanicHookInfo<'_>) -> Option<&'a str> {
+    let payload = info.payload();
+
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+}
+
+/// Whether the panic's message has a match for any of `patterns` in it.
+pub(crate) fn message_matches(info: &PanicHookInfo<'_>, patterns: &[Regex]) -> bool {
+    message(info).is_some_and(|message| patterns.iter().any(|pattern| pattern.is_match(message)))
+}
+
+/// Whether the panic happened in a file matching `pattern`. A pattern with a `*` or `?` in it is a
+/// glob over the whole path, in which `*` matches any run of characters, `/`s included, and `?`
+/// matches any one. Otherwise, it's a prefix of the path. Either way, `/` and `\` are taken to be
+/// the same.
+pub(crate) fn location_matches(info: &PanicHookInfo<'_>, pattern: &str) -> bool {
+    let Some(location) = info.location() else {
+        return false;
+    };
+    let path = location.file().as_bytes();
+    let pattern = pattern.as_bytes();
+
+    match pattern.iter().any(|&b| b == b'*' || b == b'?') {
+        true => glob(pattern, path, same),
+        false => path.len() >= pattern.len() && path.iter().zip(pattern).all(|(&a, &b)| same(a, b)),
+    }
+}
+
+fn same(a: u8, b: u8) -> bool {
+    a == b || (a == b'/' || a == b'\\') && (b == b'/' || b == b'\\')
+}
+
+/// Whether `text` matches `pattern`, a glob as per [`location_matches`], with `eq` deciding which
+/// characters are the same.
+fn glob(pattern: &[u8], text: &[u8], eq: fn(u8, u8) -> bool) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // Where the last `*` was, and how much of the text it's taken so far
+    let mut star = None;
+
+    while s < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, s));
+                p += 1;
+            }
+            Some(&b) if b == b'?' || eq(b, text[s]) => {
+                p += 1;
+                s += 1;
+            }
+            // Let the last `*` take one more character, and try again from there
+            _ => match star {
+                Some((star_p, star_s)) => {
+                    star = Some((star_p, star_s + 1));
+                    p = star_p + 1;
+                    s = star_s + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&b| b == b'*')
+}
+
+/// Whether the thread that panicked has a name matching `pattern`, which is a glob as per
+/// [`location_matches`]. Unnamed threads never match.
+pub(crate) fn thread_matches(pattern: &str) -> bool {
+    crash_thread::panicking_thread()
+        .name()
+        .is_some_and(|name| glob(pattern.as_bytes(), name.as_bytes(), same))
+}
+
+/// Whether `name` matches `pattern`, a glob as per [`location_matches`], ignoring case.
+pub(crate) fn name_matches(pattern: &str, name: &str) -> bool {
+    glob(pattern.as_bytes(), name.as_bytes(), |a, b| {
+        a.eq_ignore_ascii_case(&b)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing::with_report;
+    use crate::{EvacBuilder, FilterError};
+
+    /// Whether the message of a panic with `message` matches any of `patterns`.
+    fn matches(message: &'static str, patterns: &[&str]) -> bool {
+        let patterns: Vec<_> = patterns.iter().map(|p| Regex::new(p).unwrap()).collect();
+
+        with_report(message, move |report| {
+            message_matches(report.info(), &patterns)
+        })
+    }
+
+    #[test]
+    fn matches_messages_against_patterns() {
+        assert!(matches("connection reset by peer", &["reset by"]));
+        assert!(matches(
+            "invariant #12 broken",
+            &["nope", r"invariant #\d+"]
+        ));
+        assert!(matches("Broken pipe", &["(?i)^broken pipe$"]));
+        assert!(!matches("connection reset by peer", &["^reset"]));
+        assert!(!matches("failed (os error 32)", &[r"\(os error 104\)"]));
+        assert!(!matches("anything", &[]));
+    }
+
+    #[test]
+    fn refuses_to_filter_a_handler_that_isnt_there() {
+        let error = EvacBuilder::<()>::new()
+            .with_named_handler("upload", |_, _| Ok(()))
+            .only_if_message_matches("uplaod", ["invariant"])
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error,
+            FilterError::UnknownHandler {
+                name: "uplaod".into()
+            }
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use crate::PanicReport;
+
+type CustomGrouping = Arc<dyn Fn(&PanicReport<'_>) -> Option<String> + Send + Sync>;
+
+/// How panics are grouped together, by the [fingerprint](PanicReport::fingerprint) each report
+/// is given, see [`EvacBuilder::grouping`](crate::EvacBuilder::grouping).
+///
+/// By default, panics are the same if their messages are the same, once numbers are taken out of
+/// them, they happened at the same place, and the innermost 5 frames of their backtraces that are
+/// the application's, rather than std's or evac's, are for the same functions. Fingerprints are
+/// worked out the same way on every platform and with every version of Rust, so that they can be
+/// grouped by wherever reports are collected.
+///
+/// ## Example
+/// ```
+/// # use evac::Grouping;
+/// let grouping = Grouping::new()
+///   .frames(3)
+///   .in_app("my_app::")
+///   // Timeouts are all the same problem, wherever they happen
+///   .with(|report| {
+///     let message = report.message()?;
+///     message.contains("timed out").then(|| "timeout".to_string())
+///   });
+/// ```
+#[derive(Clone)]
+pub struct Grouping {
+    frames: usize,
+    location: bool,
+    message: bool,
+    in_app: Vec<String>,
+    custom: Vec<CustomGrouping>,
+}
+
+impl Default for Grouping {
+    fn default() -> Self {
+        Self {
+            frames: 5,
+            location: true,
+            message: true,
+            in_app: vec![],
+            custom: vec![],
+        }
+    }
+}
+
+impl Grouping {
+    /// Groups by message, location and the innermost 5 frames that are the application's.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Groups by the innermost `frames` frames that are the application's, or none at all if it's
+    /// 0.
+    pub fn frames(mut self, frames: usize) -> Self {
+        self.frames = frames;
+
+        self
+    }
+
+    /// Doesn't group by where the panic happened, such as so that panics stay grouped together
+    /// when the code they're in moves around.
+    pub fn ignore_location(mut self) -> Self {
+        self.location = false;
+
+        self
+    }
+
+    /// Doesn't group by the panic's message, such as for messages that hold too much of what
+    /// changes from one panic to the next.
+    pub fn ignore_message(mut self) -> Self {
+        self.message = false;
+
+        self
+    }
+
+    /// Only counts frames whose symbols start with `prefix`, such as `my_app::`, or any of the
+    /// other prefixes given, as the application's. Until this is first called, it's any frame that
+    /// isn't std's, evac's, or the runtime's.
+    pub fn in_app(mut self, prefix: impl Into<String>) -> Self {
+        self.in_app.push(prefix.into());
+
+        self
+    }
+
+    /// Groups by whatever `grouping` gives instead, if it gives anything, trying each given in
+    /// the order they were given, such as to group panics that are known to be the same problem.
+    pub fn with<F>(mut self, grouping: F) -> Self
+    where
+        F: Fn(&PanicReport<'_>) -> Option<String> + Send + Sync + 'static,
+    {
+        self.custom.push(Arc::new(grouping));
+
+        self
+    }
+
+    /// Works out `report`'s fingerprint.
+    pub(crate) fn fingerprint(&self, report: &PanicReport<'_>) -> String {
+        let mut hash = Fnv::default();
+
+        if let Some(custom) = self.custom.iter().find_map(|custom| custom(report)) {
+            hash.write("custom");
+            hash.write(&custom);
+            return hash.finish();
+        }
+
+        if self.message {
+            hash.write("message");
+            hash.write(&normalize(report.message().unwrap_or("Box<dyn Any>")));
+        }
+        if let Some(location) = report.location().filter(|_| self.location) {
+            hash.write("location");
+            hash.write(location.file());
+            hash.write(&location.line().to_string());
+        }
+        if let Some(backtrace) = report.backtrace().filter(|_| self.frames > 0) {
+            let backtrace = backtrace.to_string();
+            for symbol in symbols(&backtrace)
+                .filter(|symbol| self.is_in_app(symbol))
+                .take(self.frames)
+            {
+                hash.write("frame");
+                hash.write(symbol);
+            }
+        }
+
+        hash.finish()
+    }
+
+    fn is_in_app(&self, symbol: &str) -> bool {
+        if !self.in_app.is_empty() {
+            return self
+                .in_app
+                .iter()
+                .any(|prefix| symbol.starts_with(prefix.as_str()));
+        }
+
+        !is_runtime(symbol)
+    }
+}
+
+impl Debug for Grouping {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Grouping")
+            .field("frames", &self.frames)
+            .field("location", &self.location)
+            .field("message", &self.message)
+            .field("in_app", &self.in_app)
+            .field("custom", &self.custom.len())
+            .finish()
+    }
+}
+
+/// Takes numbers out of `message`, such as indices, lengths, and addresses, which tend to differ
+/// between panics that are otherwise the same.
+fn normalize(message: &str) -> String {
+    let mut normalized = String::with_capacity(message.len());
+    let mut chars = message.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !c.is_ascii_digit() {
+            normalized.push(c);
+            continue;
+        }
+
+        // Hexadecimal too, if it's marked as such
+        let hex = c == '0' && chars.next_if(|&c| c == 'x').is_some();
+        while chars
+            .next_if(|c| c.is_ascii_digit() || (hex && c.is_ascii_hexdigit()))
+            .is_some()
+        {}
+        normalized.push_str(if hex { "<hex>" } else { "<n>" });
+    }
+
+    normalized
+}
+
+/// Whether `symbol` is std's, evac's, or the runtime's, rather than the application's.
+pub(crate) fn is_runtime(symbol: &str) -> bool {
+    const RUNTIME: [&str; 6] = ["std::", "core::", "alloc::", "evac::", "backtrace::", "__"];
+    let runtime = |path: &str| RUNTIME.iter().any(|prefix| path.starts_with(prefix));
+
+    let path = symbol.strip_prefix('<').unwrap_or(symbol);
+    // Trait impls are the type's, such as `<alloc::boxed::Box<F> as Fn>::call`, and std's for its
+    // own types, such as `<usize as core::slice::index::SliceIndex<[T]>>::index`
+    if let Some((ty, _)) = path.split_once(" as ") {
+        return runtime(ty) || !ty.contains("::");
+    }
+
+    runtime(path)
+        || matches!(
+            symbol,
+            "main" | "_start" | "rust_begin_unwind" | "rust_panic" | "<unknown>"
+        )
+}
+
+/// The symbols of each of the frames in `backtrace`, as std writes them, innermost first, without
+/// their hashes.
+fn symbols(backtrace: &str) -> impl Iterator<Item = &str> {
+    backtrace.lines().filter_map(|line| Some(frame(line)?.1))
+}
+
+/// The index and symbol of the frame `line` starts, as std writes it, such as `  3: my_app::main`,
+/// without its hash.
+pub(crate) fn frame(line: &str) -> Option<(&str, &str)> {
+    let (index, symbol) = line.trim_start().split_once(": ")?;
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    // Such as `::h0123456789abcdef`, which changes with every build
+    let symbol = match symbol.rsplit_once("::h") {
+        Some((path, hash)) if hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            path
+        }
+        _ => symbol,
+    };
+    Some((index, symbol.trim()))
+}
+
+/// A frame of a backtrace, and where it is, if std knew.
+#[cfg(any(feature = "sentry", feature = "gcp", feature = "app-insights"))]
+pub(crate) struct Located<'a> {
+    pub(crate) symbol: &'a str,
+    pub(crate) file: Option<&'a str>,
+    pub(crate) line: Option<u32>,
+    pub(crate) column: Option<u32>,
+}
+
+/// The frames of `backtrace`, innermost first, along with where each is.
+#[cfg(any(feature = "sentry", feature = "gcp", feature = "app-insights"))]
+pub(crate) fn located(backtrace: &str) -> Vec<Located<'_>> {
+    let mut frames: Vec<Located<'_>> = vec![];
+    for line in backtrace.lines() {
+        if let Some((_, symbol)) = frame(line) {
+            frames.push(Located {
+                symbol,
+                file: None,
+                line: None,
+                column: None,
+            });
+            continue;
+        }
+
+        // Where the frame before it is, such as `at ./src/main.rs:2:56`
+        let Some(frame) = frames.last_mut().filter(|frame| frame.file.is_none()) else {
+            continue;
+        };
+        let Some(at) = line.trim_start().strip_prefix("at ") else {
+            continue;
+        };
+        let mut parts = at.rsplitn(3, ':');
+        let (column, line, file) = (parts.next(), parts.next(), parts.next());
+        match (file, line.and_then(|line| line.parse().ok())) {
+            (Some(file), Some(line)) => {
+                frame.file = Some(file);
+                frame.line = Some(line);
+                frame.column = column.and_then(|column| column.parse().ok());
+            }
+            _ => frame.file = Some(at),
+        }
+    }
+
+    frames
+}
+
+/// 64-bit FNV-1a, which is simple enough to never change, unlike std's hashers.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    /// Hashes `part`, followed by a separator, so that parts can't run into one another.
+    fn write(&mut self, part: &str) {
+        for byte in part.bytes().chain([0]) {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(self) -> String {
+        format!("{:016x}", self.0)
+    }
+}
@@ -0,0 +1,314 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::PanicReport;
+
+/// Turns a [`PanicReport`] into what's written out, so that the same handler can write reports in
+/// whatever form is wanted, see [`handlers`](crate::handlers).
+///
+/// Closures taking the report and where to write it work as formatters too.
+///
+/// ## Example
+/// ```
+/// # use evac::{handlers, EvacBuilder, PanicReport};
+/// EvacBuilder::new()
+///   .with_report_handler(handlers::stderr(
+///     |report: &PanicReport<'_>, out: &mut dyn std::io::Write| {
+///       writeln!(out, "crashed: {}", report.message().unwrap_or("?"))
+///     },
+///   ))
+///   .register(())?;
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+pub trait ReportFormatter: Send + Sync + 'static {
+    /// Writes out `report`. Text formats should end it with a newline, so that reports written one
+    /// after another stay apart.
+    fn format(&self, report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()>;
+}
+
+impl<F> ReportFormatter for F
+where
+    F: Fn(&PanicReport<'_>, &mut dyn Write) -> io::Result<()> + Send + Sync + 'static,
+{
+    fn format(&self, report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()> {
+        self(report, out)
+    }
+}
+
+/// Writes reports as text meant for people, either in full or on a single line.
+///
+/// ## Example
+/// ```
+/// # use evac::{handlers, EvacBuilder, TextFormatter};
+/// EvacBuilder::new()
+///   .with_report_handler(handlers::stderr(TextFormatter::compact()))
+///   .register(())?;
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TextFormatter {
+    compact: bool,
+}
+
+impl TextFormatter {
+    /// Writes everything the report holds, one fact per line, with a blank line after.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes the panic, where it happened and any annotations on a single line, such as for logs
+    /// that are read a line at a time.
+    pub fn compact() -> Self {
+        Self { compact: true }
+    }
+}
+
+impl ReportFormatter for TextFormatter {
+    fn format(&self, report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()> {
+        match self.compact {
+            true => compact(report, out),
+            false => full(report, out),
+        }
+    }
+}
+
+fn compact(report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()> {
+    let thread = report.thread_name().unwrap_or("<unnamed>");
+    write!(
+        out,
+        "{} thread '{thread}' panicked",
+        Rfc3339(report.timestamp())
+    )?;
+    if let Some(location) = report.location() {
+        write!(out, " at {location}")?;
+    }
+    // Kept to the one line
+    let message = report.message().unwrap_or("Box<dyn Any>");
+    write!(out, ": {}", message.escape_default())?;
+
+    write!(out, " (pid {}", report.pid())?;
+    for (key, value) in report.annotations() {
+        write!(out, ", {key}={}", value.escape_default())?;
+    }
+    writeln!(out, ")")
+}
+
+fn full(report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()> {
+    let thread = report.thread_name().unwrap_or("<unnamed>");
+    write!(out, "thread '{thread}' panicked")?;
+    if let Some(location) = report.location() {
+        write!(out, " at {location}")?;
+    }
+    writeln!(out, ":")?;
+    writeln!(out, "{}", report.message().unwrap_or("Box<dyn Any>"))?;
+    writeln!(out)?;
+
+    writeln!(out, "time: {}", Rfc3339(report.timestamp()))?;
+    match report.parent_pid() {
+        Some(parent) => writeln!(out, "process: {} (parent {parent})", report.pid())?,
+        None => writeln!(out, "process: {}", report.pid())?,
+    }
+    writeln!(out, "uptime: {:.3?}", report.uptime())?;
+    writeln!(out, "fingerprint: {}", report.fingerprint())?;
+
+    if let Some(app) = report.app_metadata() {
+        write!(out, "app: {} {} ({}", app.name, app.version, app.profile)?;
+        if let Some(target) = app.target {
+            write!(out, ", {target}")?;
+        }
+        if let Some(commit) = app.git_commit {
+            write!(out, ", commit {commit}")?;
+        }
+        writeln!(out, ")")?;
+    }
+
+    #[cfg(feature = "sysinfo")]
+    if let Some(host) = report.system_info() {
+        let unknown = |fact: &Option<String>| fact.clone().unwrap_or_else(|| "?".to_string());
+        writeln!(
+            out,
+            "host: {} {} on {}, kernel {}",
+            unknown(&host.os_name),
+            unknown(&host.os_version),
+            host.arch,
+            unknown(&host.kernel),
+        )?;
+        if let Some(container) = host.container {
+            writeln!(out, "container: {container:?}")?;
+        }
+    }
+
+    if !report.args().is_empty() {
+        writeln!(out, "args: {}", report.args().join(" "))?;
+    }
+
+    pairs(out, "annotations", report.annotations(), ": ")?;
+    pairs(out, "environment", report.env_vars(), "=")?;
+
+    if !report.breadcrumbs().is_empty() {
+        writeln!(out, "breadcrumbs:")?;
+        for breadcrumb in report.breadcrumbs() {
+            writeln!(
+                out,
+                "  {} [{}] {}",
+                Rfc3339(breadcrumb.timestamp),
+                breadcrumb.category,
+                breadcrumb.message
+            )?;
+        }
+    }
+
+    if !report.attachments().is_empty() {
+        writeln!(out, "attachments:")?;
+        for attachment in report.attachments() {
+            write!(
+                out,
+                "  {} ({} bytes",
+                attachment.name,
+                attachment.data.len()
+            )?;
+            if attachment.truncated > 0 {
+                write!(out, ", truncated: {} more bytes", attachment.truncated)?;
+            }
+            writeln!(out, ")")?;
+        }
+    }
+
+    if let Some(backtrace) = report.backtrace_text() {
+        writeln!(out, "backtrace:")?;
+        writeln!(out, "{backtrace}")?;
+    }
+
+    #[cfg(feature = "all-threads")]
+    for thread in report.threads() {
+        writeln!(out, "{thread}")?;
+    }
+
+    writeln!(out)
+}
+
+/// The panic and where it happened, on one line, such as for logs that take a line per entry.
+pub(crate) fn summary(report: &PanicReport<'_>) -> String {
+    let thread = report.thread_name().unwrap_or("<unnamed>");
+    let mut summary = format!("thread '{thread}' panicked");
+    if let Some(location) = report.location() {
+        summary += &format!(" at {location}");
+    }
+    let message = report.message().unwrap_or("Box<dyn Any>");
+    summary += &format!(": {}", message.escape_default());
+
+    summary
+}
+
+fn pairs(
+    out: &mut dyn Write,
+    title: &str,
+    pairs: &[(String, String)],
+    separator: &str,
+) -> io::Result<()> {
+    if pairs.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "{title}:")?;
+    for (key, value) in pairs {
+        writeln!(out, "  {key}{separator}{value}")?;
+    }
+
+    Ok(())
+}
+
+/// Writes reports as JSON, one per line, see [`handlers::json_file`](crate::handlers::json_file).
+#[cfg(feature = "serde")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct JsonFormatter;
+
+#[cfg(feature = "serde")]
+impl ReportFormatter for JsonFormatter {
+    fn format(&self, report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()> {
+        crate::json::to_writer(out, report)?;
+        writeln!(out)
+    }
+}
+
+/// Writes reports as MessagePack, for when JSON is too large. Reports hold the same fields as with
+/// [`JsonFormatter`], in the same order, as a map keyed by field name, and each is a single value,
+/// so that reports written one after another can be read back one at a time.
+///
+/// ## Example
+/// ```
+/// # use evac::{handlers, EvacBuilder, MessagePackFormatter};
+/// let path = std::env::temp_dir().join("crashes.msgpack");
+///
+/// EvacBuilder::new()
+///   .with_report_handler(handlers::file(path, MessagePackFormatter))
+///   .register(())?;
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MessagePackFormatter;
+
+#[cfg(feature = "msgpack")]
+impl ReportFormatter for MessagePackFormatter {
+    fn format(&self, report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()> {
+        crate::binary::to_writer::<crate::binary::MessagePack, _, _>(out, report)
+    }
+}
+
+/// Writes reports as CBOR, for when JSON is too large. Reports hold the same fields as with
+/// [`JsonFormatter`], in the same order, as a map keyed by field name, and each is a single value,
+/// so that reports written one after another can be read back one at a time.
+///
+/// ## Example
+/// ```
+/// # use evac::{handlers, EvacBuilder, CborFormatter};
+/// let path = std::env::temp_dir().join("crashes.cbor");
+///
+/// EvacBuilder::new()
+///   .with_report_handler(handlers::file(path, CborFormatter))
+///   .register(())?;
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+#[cfg(feature = "cbor")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CborFormatter;
+
+#[cfg(feature = "cbor")]
+impl ReportFormatter for CborFormatter {
+    fn format(&self, report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()> {
+        crate::binary::to_writer::<crate::binary::Cbor, _, _>(out, report)
+    }
+}
+
+/// Shows a time as UTC, to the millisecond, such as `2024-05-01T12:30:00.250Z`.
+pub(crate) struct Rfc3339(pub(crate) SystemTime);
+
+impl Display for Rfc3339 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let since_epoch = self.0.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since_epoch.as_secs();
+        let (days, secs) = (secs / 86_400, secs % 86_400);
+
+        // Days since the epoch to a civil date, after Howard Hinnant's `civil_from_days`
+        let z = days as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+            secs / 3_600,
+            secs % 3_600 / 60,
+            secs % 60,
+            since_epoch.subsec_millis()
+        )
+    }
+}
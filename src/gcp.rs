@@ -0,0 +1,205 @@
+//! Reports panics to Google Cloud Error Reporting, see
+//! [`handlers::gcp_error_reporting`](crate::handlers::gcp_error_reporting).
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::format::Rfc3339;
+use crate::{fingerprint, http, json, PanicReport, ReportFormatter};
+
+/// Where the metadata server is, unless `GCE_METADATA_HOST` says otherwise.
+const METADATA_HOST: &str = "metadata.google.internal";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Event<'a> {
+    #[serde(rename = "@type", skip_serializing_if = "Option::is_none")]
+    kind: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    severity: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event_time: Option<String>,
+    service_context: ServiceContext,
+    message: String,
+    context: Context<'a>,
+}
+
+#[derive(Serialize)]
+struct ServiceContext {
+    service: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Context<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report_location: Option<SourceLocation<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SourceLocation<'a> {
+    file_path: &'a str,
+    line_number: u32,
+    function_name: String,
+}
+
+/// `report` as a `ReportedErrorEvent`, from `service`, such as `my-app`, at `version`, if known.
+pub(crate) fn event<'a>(
+    report: &'a PanicReport<'_>,
+    service: Option<&str>,
+    version: Option<&str>,
+) -> Event<'a> {
+    let app = report.app_metadata();
+    let service = service
+        .map(str::to_string)
+        // As Cloud Run and Knative name the service
+        .or_else(|| {
+            std::env::var("K_SERVICE")
+                .ok()
+                .filter(|name| !name.is_empty())
+        })
+        .or_else(|| app.map(|app| app.name.to_string()))
+        .or_else(crate::handlers::executable_name)
+        .unwrap_or_else(|| "unknown".to_string());
+    let version = version
+        .map(str::to_string)
+        .or_else(|| app.map(|app| app.version.to_string()));
+
+    let function = report
+        .backtrace_text()
+        .and_then(|backtrace| {
+            fingerprint::located(&backtrace)
+                .into_iter()
+                .find(|frame| !fingerprint::is_runtime(frame.symbol))
+                .map(|frame| frame.symbol.to_string())
+        })
+        .unwrap_or_default();
+
+    Event {
+        kind: None,
+        severity: None,
+        event_time: Some(Rfc3339(report.timestamp()).to_string()),
+        service_context: ServiceContext { service, version },
+        message: message(report),
+        context: Context {
+            report_location: report.location().map(|location| SourceLocation {
+                file_path: location.file(),
+                line_number: location.line(),
+                function_name: function,
+            }),
+        },
+    }
+}
+
+/// The panic, and the backtrace, if there is one, laid out as a Go panic is, as that's the closest
+/// of the languages Error Reporting can read stack traces of, and group errors by.
+fn message(report: &PanicReport<'_>) -> String {
+    let mut message = format!("panic: {}", crate::format::summary(report));
+    let Some(backtrace) = report.backtrace_text() else {
+        return message;
+    };
+
+    message += "\n\ngoroutine 1 [running]:";
+    for frame in fingerprint::located(&backtrace) {
+        let file = frame.file.unwrap_or("??");
+        let line = frame.line.unwrap_or(0);
+        let _ = write!(message, "\n{}(...)\n\t{file}:{line} +0x0", frame.symbol);
+    }
+
+    message
+}
+
+/// A token for the instance's service account, from the metadata server, as on GKE, Cloud Run,
+/// and Compute Engine.
+pub(crate) fn metadata_token(timeout: Duration) -> io::Result<String> {
+    let host = std::env::var("GCE_METADATA_HOST")
+        .ok()
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| METADATA_HOST.to_string());
+    let url = http::Url::parse(&format!(
+        "http://{host}/computeMetadata/v1/instance/service-accounts/default/token"
+    ))?;
+
+    let headers = [("Metadata-Flavor".to_string(), "Google".to_string())];
+    let (status, body) = http::request("GET", &url, &headers, &[], timeout)?;
+    if !(200..=299).contains(&status) {
+        return Err(io::Error::other(format!(
+            "the metadata server answered with status {status}"
+        )));
+    }
+
+    // Such as `{"access_token":"ya29.c.0123","expires_in":3599,...}`
+    json::string_field(&String::from_utf8_lossy(&body), "access_token")
+        .filter(|token| !token.is_empty() && !token.contains(char::is_control))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the metadata server's answer holds no access token",
+            )
+        })
+}
+
+/// Writes reports as structured log entries that Cloud Logging hands on to Error Reporting, one
+/// per line, for workloads whose output it collects, such as on GKE or Cloud Run, without calling
+/// the API.
+///
+/// Each is a `ReportedErrorEvent`, as
+/// [`handlers::gcp_error_reporting`](crate::handlers::gcp_error_reporting) sends, with severity
+/// `CRITICAL`.
+///
+/// ## Example
+/// ```
+/// # use evac::{handlers, EvacBuilder, ErrorReportingFormatter};
+/// EvacBuilder::new()
+///   .with_report_handler(handlers::writer(std::io::stdout(), ErrorReportingFormatter::new()))
+///   .register(())?;
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ErrorReportingFormatter {
+    service: Option<String>,
+    version: Option<String>,
+}
+
+impl ErrorReportingFormatter {
+    /// Names the service as Cloud Run does, in `K_SERVICE`, or after the app, and gives the
+    /// app's version, if its [`AppMetadata`](crate::AppMetadata) is known.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names the service `service`, such as `my-app`, which errors are grouped under.
+    pub fn service(mut self, service: impl Into<String>) -> Self {
+        self.service = Some(service.into());
+
+        self
+    }
+
+    /// Gives the service's version as `version`, such as a release or a commit.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+
+        self
+    }
+}
+
+impl ReportFormatter for ErrorReportingFormatter {
+    fn format(&self, report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()> {
+        let mut event = event(report, self.service.as_deref(), self.version.as_deref());
+        event.kind = Some(
+            "type.googleapis.com/google.devtools.clouderrorreporting.v1beta1.ReportedErrorEvent",
+        );
+        event.severity = Some("CRITICAL");
+        // Cloud Logging takes the time from the entry
+        event.event_time = None;
+
+        out.write_all(&json::to_vec(&event)?)?;
+        writeln!(out)
+    }
+}
@@ -0,0 +1,127 @@
+//! Submitting reports through a gRPC client of the application's own, see
+//! [`handlers::grpc`](crate::handlers::grpc).
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+
+use crate::executor;
+
+/// The codes of the statuses a call can end with, as gRPC numbers them.
+const CODES: [&str; 17] = [
+    "OK",
+    "CANCELLED",
+    "UNKNOWN",
+    "INVALID_ARGUMENT",
+    "DEADLINE_EXCEEDED",
+    "NOT_FOUND",
+    "ALREADY_EXISTS",
+    "PERMISSION_DENIED",
+    "RESOURCE_EXHAUSTED",
+    "FAILED_PRECONDITION",
+    "ABORTED",
+    "OUT_OF_RANGE",
+    "UNIMPLEMENTED",
+    "INTERNAL",
+    "UNAVAILABLE",
+    "DATA_LOSS",
+    "UNAUTHENTICATED",
+];
+/// The code for `UNKNOWN`.
+const UNKNOWN: u16 = 2;
+/// The code for `DEADLINE_EXCEEDED`.
+const DEADLINE_EXCEEDED: u16 = 4;
+
+/// How a call failed, as the client gives it, such as from a `tonic::Status`, see
+/// [`handlers::grpc`](crate::handlers::grpc).
+///
+/// ## Example
+/// ```
+/// # use evac::handlers::GrpcStatus;
+/// // Such as `GrpcStatus::new(status.code() as i32, status.message())`
+/// let status = GrpcStatus::new(14, "connection refused");
+/// assert_eq!(status.to_string(), "UNAVAILABLE: connection refused");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GrpcStatus {
+    code: i32,
+    message: String,
+}
+
+impl GrpcStatus {
+    /// A status with `code`, such as `14` for `UNAVAILABLE`, and `message`.
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// The status's code.
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    /// What the status says went wrong.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for GrpcStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match usize::try_from(self.code)
+            .ok()
+            .and_then(|code| CODES.get(code))
+        {
+            Some(name) => write!(f, "{name}: {}", self.message),
+            None => write!(f, "code {}: {}", self.code, self.message),
+        }
+    }
+}
+
+impl Error for GrpcStatus {}
+
+/// Makes the call `submit` makes with `body`, giving it `timeout` both as the call's deadline,
+/// and to finish in, giving the status's code, if it failed, on failure.
+pub(crate) fn call<F, Fut>(
+    submit: &F,
+    body: &[u8],
+    timeout: Duration,
+) -> Result<(), (Option<u16>, io::Error)>
+where
+    F: Fn(Vec<u8>, Duration) -> Fut,
+    Fut: Future<Output = Result<(), GrpcStatus>>,
+{
+    match executor::block_on(submit(body.to_vec(), timeout), timeout) {
+        Some(Ok(())) => Ok(()),
+        Some(Err(status)) => {
+            // As codes no client gives are nothing to go by
+            let code = u16::try_from(status.code)
+                .ok()
+                .filter(|&code| usize::from(code) < CODES.len())
+                .unwrap_or(UNKNOWN);
+            let kind = match code {
+                DEADLINE_EXCEEDED => io::ErrorKind::TimedOut,
+                _ => io::ErrorKind::Other,
+            };
+            Err((Some(code), io::Error::new(kind, status.to_string())))
+        }
+        None => Err((
+            Some(DEADLINE_EXCEEDED),
+            io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the call didn't finish within its deadline",
+            ),
+        )),
+    }
+}
+
+/// Whether a call that failed with the given code may succeed if made again, as gRPC's own
+/// retry guidance has it.
+pub(crate) fn retryable((code, _): &(Option<u16>, io::Error)) -> bool {
+    // DEADLINE_EXCEEDED, RESOURCE_EXHAUSTED, ABORTED, and UNAVAILABLE
+    matches!(code, Some(4 | 8 | 10 | 14))
+}
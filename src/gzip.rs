@@ -0,0 +1,511 @@
+//! Just enough of a gzip compressor for reports, see RFC 1951 for DEFLATE and RFC 1952 for gzip.
+//! Blocks use their own Huffman codes, or are stored as they are at level 0, or if that would be
+//! smaller.
+
+use crate::compress::{self, Limits, Match};
+
+/// How many symbols go into each block, each with its own codes.
+const BLOCK_SYMBOLS: usize = 1 << 15;
+
+/// Where each length code's lengths start, from code 257, and how many extra bits follow it.
+const LENGTHS: [(u16, u8); 29] = [
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 1),
+    (13, 1),
+    (15, 1),
+    (17, 1),
+    (19, 2),
+    (23, 2),
+    (27, 2),
+    (31, 2),
+    (35, 3),
+    (43, 3),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 4),
+    (115, 4),
+    (131, 5),
+    (163, 5),
+    (195, 5),
+    (227, 5),
+    (258, 0),
+];
+
+/// Where each distance code's distances start, and how many extra bits follow it.
+const DISTANCES: [(u16, u8); 30] = [
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 1),
+    (7, 1),
+    (9, 2),
+    (13, 2),
+    (17, 3),
+    (25, 3),
+    (33, 4),
+    (49, 4),
+    (65, 5),
+    (97, 5),
+    (129, 6),
+    (193, 6),
+    (257, 7),
+    (385, 7),
+    (513, 8),
+    (769, 8),
+    (1025, 9),
+    (1537, 9),
+    (2049, 10),
+    (3073, 10),
+    (4097, 11),
+    (6145, 11),
+    (8193, 12),
+    (12289, 12),
+    (16385, 13),
+    (24577, 13),
+];
+
+/// The order code length codes' lengths are written in.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Compresses `data` into a single gzip member, at `level` from 0 to 9.
+pub(crate) fn compress(data: &[u8], level: u32) -> Vec<u8> {
+    // No file name or modification time, and an unknown OS
+    let extra_flags = match level {
+        9 => 2,
+        1 => 4,
+        _ => 0,
+    };
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, extra_flags, 255];
+
+    let mut bits = BitWriter::default();
+    match level {
+        0 => stored(&mut bits, data, true),
+        level => deflate(&mut bits, data, level),
+    }
+    out.extend(bits.finish());
+
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out
+}
+
+/// A literal byte, or a match of `length` repeating what's `distance` back.
+#[derive(Clone, Copy)]
+enum Symbol {
+    Literal(u8),
+    Match { length: u16, distance: u16 },
+}
+
+fn deflate(bits: &mut BitWriter, data: &[u8], level: u32) {
+    let limits = Limits {
+        min_len: 3,
+        max_len: 258,
+        max_distance: 32_768,
+        boundary: None,
+        depth: 2 << level.min(12),
+        lazy: level >= 4,
+    };
+
+    let mut symbols = Vec::with_capacity(data.len() / 2);
+    let mut pos = 0;
+    for Match {
+        pos: start,
+        len,
+        distance,
+    } in compress::find_matches(data, &limits)
+    {
+        symbols.extend(data[pos..start].iter().map(|&byte| Symbol::Literal(byte)));
+        symbols.push(Symbol::Match {
+            length: len as u16,
+            distance: distance as u16,
+        });
+        pos = start + len;
+    }
+    symbols.extend(data[pos..].iter().map(|&byte| Symbol::Literal(byte)));
+
+    if symbols.is_empty() {
+        return stored(bits, &[], true);
+    }
+
+    let mut pos = 0;
+    let blocks = symbols.len().div_ceil(BLOCK_SYMBOLS);
+    for (i, block) in symbols.chunks(BLOCK_SYMBOLS).enumerate() {
+        let size: usize = block
+            .iter()
+            .map(|symbol| match symbol {
+                Symbol::Literal(_) => 1,
+                Symbol::Match { length, .. } => *length as usize,
+            })
+            .sum();
+        let last = i + 1 == blocks;
+
+        // Stored instead, if compressing it doesn't pay off
+        let mut compressed = BitWriter::default();
+        huffman_block(&mut compressed, block, last);
+        if compressed.len() > size + 5 * size.div_ceil(65_535) + 1 {
+            stored(bits, &data[pos..pos + size], last);
+        } else {
+            bits.append(&compressed);
+        }
+
+        pos += size;
+    }
+}
+
+/// Writes `data` as stored blocks.
+fn stored(bits: &mut BitWriter, data: &[u8], last: bool) {
+    let chunks = data.len().div_ceil(65_535).max(1);
+    for i in 0..chunks {
+        let chunk = &data[(i * 65_535).min(data.len())..((i + 1) * 65_535).min(data.len())];
+
+        bits.write((last && i + 1 == chunks) as u32, 1);
+        bits.write(0, 2);
+        bits.align();
+        bits.write(chunk.len() as u32, 16);
+        bits.write(!chunk.len() as u32 & 0xffff, 16);
+        for &byte in chunk {
+            bits.write(byte as u32, 8);
+        }
+    }
+}
+
+/// Where `value` falls in `table`, and how far past the start of its range it is.
+fn code(table: &[(u16, u8)], value: u16) -> (usize, u16) {
+    let code = table.partition_point(|&(start, _)| start <= value) - 1;
+
+    (code, value - table[code].0)
+}
+
+/// Writes `symbols` as a block with its own Huffman codes.
+fn huffman_block(bits: &mut BitWriter, symbols: &[Symbol], last: bool) {
+    let mut literal_counts = [0u32; 286];
+    let mut distance_counts = [0u32; 30];
+    for symbol in symbols {
+        match *symbol {
+            Symbol::Literal(byte) => literal_counts[byte as usize] += 1,
+            Symbol::Match { length, distance } => {
+                literal_counts[257 + code(&LENGTHS, length).0] += 1;
+                distance_counts[code(&DISTANCES, distance).0] += 1;
+            }
+        }
+    }
+    literal_counts[256] = 1;
+    // Codes with fewer than 2 symbols aren't complete, which some decoders turn down
+    for counts in [&mut literal_counts[..], &mut distance_counts[..]] {
+        for i in 0..2 {
+            if counts.iter().filter(|&&count| count > 0).count() < 2 && counts[i] == 0 {
+                counts[i] = 1;
+            }
+        }
+    }
+
+    let literal_lengths = code_lengths(&literal_counts, 15);
+    let distance_lengths = code_lengths(&distance_counts, 15);
+    let literals = literal_lengths.iter().rposition(|&len| len > 0).unwrap() + 1;
+    let distances = distance_lengths.iter().rposition(|&len| len > 0).unwrap() + 1;
+
+    // Both codes' lengths, run-length encoded as code length symbols and their extra bits
+    let lengths: Vec<u8> = [&literal_lengths[..literals], &distance_lengths[..distances]].concat();
+    let mut runs = vec![];
+    let mut i = 0;
+    while i < lengths.len() {
+        let len = lengths[i];
+        let run = lengths[i..].iter().take_while(|&&next| next == len).count();
+        match (len, run) {
+            (0, 11..) => {
+                let run = run.min(138);
+                runs.push((18, run as u32 - 11, 7));
+                i += run;
+            }
+            (0, 3..) => {
+                runs.push((17, run as u32 - 3, 3));
+                i += run;
+            }
+            (_, 4..) => {
+                runs.push((len, 0, 0));
+                let run = (run - 1).min(6);
+                runs.push((16, run as u32 - 3, 2));
+                i += run + 1;
+            }
+            _ => {
+                runs.push((len, 0, 0));
+                i += 1;
+            }
+        }
+    }
+
+    let mut length_counts = [0u32; 19];
+    for &(symbol, _, _) in &runs {
+        length_counts[symbol as usize] += 1;
+    }
+    let length_lengths = code_lengths(&length_counts, 7);
+    let length_codes = CODE_LENGTH_ORDER
+        .iter()
+        .rposition(|&symbol| length_lengths[symbol] > 0)
+        .unwrap()
+        .max(3)
+        + 1;
+
+    bits.write(last as u32, 1);
+    bits.write(2, 2);
+    bits.write(literals as u32 - 257, 5);
+    bits.write(distances as u32 - 1, 5);
+    bits.write(length_codes as u32 - 4, 4);
+    for &symbol in &CODE_LENGTH_ORDER[..length_codes] {
+        bits.write(length_lengths[symbol] as u32, 3);
+    }
+
+    let length_code = canonical(&length_lengths);
+    for &(symbol, extra, extra_bits) in &runs {
+        bits.code(
+            length_code[symbol as usize],
+            length_lengths[symbol as usize],
+        );
+        bits.write(extra, extra_bits);
+    }
+
+    let literal_code = canonical(&literal_lengths);
+    let distance_code = canonical(&distance_lengths);
+    for symbol in symbols {
+        match *symbol {
+            Symbol::Literal(byte) => {
+                let byte = byte as usize;
+                bits.code(literal_code[byte], literal_lengths[byte]);
+            }
+            Symbol::Match { length, distance } => {
+                let (symbol, extra) = code(&LENGTHS, length);
+                bits.code(literal_code[257 + symbol], literal_lengths[257 + symbol]);
+                bits.write(extra as u32, LENGTHS[symbol].1);
+
+                let (symbol, extra) = code(&DISTANCES, distance);
+                bits.code(distance_code[symbol], distance_lengths[symbol]);
+                bits.write(extra as u32, DISTANCES[symbol].1);
+            }
+        }
+    }
+    bits.code(literal_code[256], literal_lengths[256]);
+}
+
+/// Works out Huffman code lengths for symbols seen `counts` times, none longer than `max`.
+fn code_lengths(counts: &[u32], max: u8) -> Vec<u8> {
+    let mut lengths = vec![0u8; counts.len()];
+    let mut used: Vec<usize> = (0..counts.len()).filter(|&i| counts[i] > 0).collect();
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+        return lengths;
+    }
+
+    // Huffman's algorithm, over nodes that are either a symbol or a pair of nodes
+    let mut parents = vec![usize::MAX; used.len()];
+    let mut queue: Vec<(u32, usize)> = used
+        .iter()
+        .enumerate()
+        .map(|(node, &i)| (counts[i], node))
+        .collect();
+    while queue.len() > 1 {
+        queue.sort_unstable_by(|a, b| b.cmp(a));
+        let (a_count, a) = queue.pop().unwrap();
+        let (b_count, b) = queue.pop().unwrap();
+
+        let parent = parents.len();
+        parents.push(usize::MAX);
+        parents[a] = parent;
+        parents[b] = parent;
+        queue.push((a_count + b_count, parent));
+    }
+
+    let mut depths = vec![0usize; used.len()];
+    for (node, depth) in depths.iter_mut().enumerate() {
+        let mut parent = parents[node];
+        while parent != usize::MAX {
+            *depth += 1;
+            parent = parents[parent];
+        }
+    }
+
+    // Lengths over `max` are cut down, then others lengthened until the code is complete again
+    let max = max as usize;
+    let mut per_length = vec![0u32; max + 1];
+    for &depth in &depths {
+        per_length[depth.min(max)] += 1;
+    }
+    let kraft =
+        |per_length: &[u32]| -> u32 { (1..=max).map(|len| per_length[len] << (max - len)).sum() };
+    while kraft(&per_length) > 1 << max {
+        per_length[max] -= 1;
+        if let Some(len) = (1..max).rev().find(|&len| per_length[len] > 0) {
+            per_length[len] -= 1;
+            per_length[len + 1] += 2;
+        }
+    }
+
+    // The rarest symbols get the longest codes
+    used.sort_by_key(|&i| counts[i]);
+    let mut symbols = used.into_iter();
+    for len in (1..=max).rev() {
+        for _ in 0..per_length[len] {
+            if let Some(symbol) = symbols.next() {
+                lengths[symbol] = len as u8;
+            }
+        }
+    }
+
+    lengths
+}
+
+/// The canonical Huffman codes for symbols with code `lengths`.
+fn canonical(lengths: &[u8]) -> Vec<u16> {
+    let mut per_length = [0u16; 16];
+    for &len in lengths {
+        per_length[len as usize] += 1;
+    }
+    per_length[0] = 0;
+
+    let mut next = [0u16; 16];
+    let mut code = 0;
+    for len in 1..16 {
+        code = (code + per_length[len - 1]) << 1;
+        next[len] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|&len| {
+            let code = next[len as usize];
+            next[len as usize] += 1;
+            code
+        })
+        .collect()
+}
+
+/// Packs bits least significant first, as DEFLATE does.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    pending: u64,
+    pending_bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, bits: u8) {
+        self.pending |= (value as u64) << self.pending_bits;
+        self.pending_bits += bits as u32;
+        while self.pending_bits >= 8 {
+            self.bytes.push(self.pending as u8);
+            self.pending >>= 8;
+            self.pending_bits -= 8;
+        }
+    }
+
+    /// Writes a Huffman code, which goes most significant bit first.
+    fn code(&mut self, code: u16, len: u8) {
+        let reversed = code.reverse_bits() >> (16 - len as u32);
+        self.write(reversed as u32, len);
+    }
+
+    fn align(&mut self) {
+        if self.pending_bits > 0 {
+            self.write(0, 8 - self.pending_bits as u8);
+        }
+    }
+
+    /// How many bytes it's written, counting a partial one.
+    fn len(&self) -> usize {
+        self.bytes.len() + self.pending_bits.div_ceil(8) as usize
+    }
+
+    fn append(&mut self, other: &BitWriter) {
+        for &byte in &other.bytes {
+            self.write(byte as u32, 8);
+        }
+        self.write(other.pending as u32, other.pending_bits as u8);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+/// The CRC-32 gzip ends members with.
+fn crc32(data: &[u8]) -> u32 {
+    let table: [u32; 256] = std::array::from_fn(|n| {
+        (0..8).fold(n as u32, |c, _| match c & 1 {
+            1 => 0xedb8_8320 ^ (c >> 1),
+            _ => c >> 1,
+        })
+    });
+
+    !data.iter().fold(!0u32, |crc, &byte| {
+        table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+    use crate::compress::testing::samples;
+
+    /// Decompresses `compressed` as zlib does, checking its CRC and length.
+    fn decompress(compressed: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut data = vec![];
+        GzDecoder::new(compressed).read_to_end(&mut data)?;
+
+        Ok(data)
+    }
+
+    #[test]
+    fn compresses_what_zlib_decompresses() {
+        for (sample, data) in samples() {
+            for level in 0..=9 {
+                let compressed = compress(&data, level);
+
+                assert_eq!(
+                    decompress(&compressed).unwrap(),
+                    data,
+                    "{sample} at level {level}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn makes_what_repeats_smaller() {
+        for (sample, data) in samples() {
+            let stored = compress(&data, 0).len();
+            let compressed = compress(&data, 6).len();
+
+            // Blocks that don't compress are stored, at the cost of a header each
+            let headers = 5 * data.len().div_ceil(BLOCK_SYMBOLS);
+            assert!(
+                compressed <= stored + headers,
+                "{sample}: {compressed} > {stored}"
+            );
+            if sample == "repeated text" || sample == "a run" {
+                assert!(compressed < data.len() / 100, "{sample}");
+            }
+        }
+    }
+
+    #[test]
+    fn checksums_as_crc_32_does() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+}
@@ -0,0 +1,199 @@
+use std::panic::{PanicHookInfo, RefUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::{incremental, isolate, thread, RegisterError};
+
+/// The type of panic hooks as std stores them.
+pub(crate) type Hook = Box<dyn Fn(&PanicHookInfo<'_>) + Send + Sync + 'static>;
+
+/// An assembled pipeline, given the hook that was installed before it.
+pub(crate) type Pipeline = Box<dyn Fn(&PanicHookInfo<'_>, &Hook) + Send + Sync + 'static>;
+
+/// The most recent evac installation, which is what std's hook is expected to be.
+static TOP: Mutex<Option<Arc<Installation>>> = Mutex::new(None);
+
+/// Bookkeeping for a single installed pipeline.
+struct Installation {
+    /// The hook that was installed before this one.
+    previous: Arc<Hook>,
+    /// The installation `previous` belongs to, if it was one of ours.
+    parent: Option<Arc<Installation>>,
+    /// Set once the installation has been uninstalled but couldn't be taken out of the hook chain.
+    /// From then on, it only forwards to `previous`.
+    retired: AtomicBool,
+}
+
+// Hooks are never called while being mutated, and being retired is a single atomic flag, so there's
+// no broken state to observe after a panic. This lets guards be held across `catch_unwind`.
+impl RefUnwindSafe for Installation {}
+
+/// Installs `pipeline` as the process's panic hook. Unless `force` is set, this fails if another
+/// evac pipeline is still installed.
+pub(crate) fn install(pipeline: Pipeline, force: bool) -> Result<EvacHandle, RegisterError> {
+    let mut top = TOP.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let registered = top
+        .as_ref()
+        .is_some_and(|top| !top.retired.load(Ordering::Acquire));
+    if registered && !force {
+        return Err(RegisterError::AlreadyRegistered);
+    }
+
+    let installation = Arc::new(Installation {
+        previous: Arc::new(std::panic::take_hook()),
+        parent: top.take(),
+        retired: AtomicBool::new(false),
+    });
+
+    let hooked = Arc::clone(&installation);
+    std::panic::set_hook(Box::new(move |info| {
+        // std aborts the process on a panic inside the hook itself, so the only panics that get
+        // here while one is being handled are those of threads evac runs handlers on. Each is
+        // reported by the pipeline that ran it, once caught, so it's only noted where it happened
+        if isolate::is_isolated() {
+            isolate::witness(info);
+            return;
+        }
+
+        match hooked.retired.load(Ordering::Acquire) {
+            true => (hooked.previous)(info),
+            false => thread::per_panic(|| pipeline(info, &hooked.previous)),
+        }
+    }));
+
+    *top = Some(Arc::clone(&installation));
+
+    Ok(EvacHandle {
+        registration: Registration::Hook(installation),
+    })
+}
+
+/// Returned by [`EvacBuilder::register`](crate::EvacBuilder::register), for backing out of a
+/// registration. Dropping the handle leaves evac's hook installed.
+///
+/// ## Example
+/// ```
+/// # use evac::EvacBuilder;
+/// let handle = EvacBuilder::new()
+///   .with_handler(|_, _: &mut ()| Ok(()))
+///   .register(())?;
+///
+/// // Later, when tearing down
+/// handle.uninstall();
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+pub struct EvacHandle {
+    registration: Registration,
+}
+
+/// What an [`EvacHandle`] has to undo.
+enum Registration {
+    /// A pipeline installed as its own hook.
+    Hook(Arc<Installation>),
+    /// An entry in the incremental dispatch table.
+    Incremental(u64),
+}
+
+impl EvacHandle {
+    pub(crate) fn incremental(id: u64) -> Self {
+        Self {
+            registration: Registration::Incremental(id),
+        }
+    }
+
+    /// Whether the registration is still part of the hook chain.
+    pub(crate) fn is_active(&self) -> bool {
+        match &self.registration {
+            Registration::Hook(installation) => !installation.retired.load(Ordering::Acquire),
+            Registration::Incremental(id) => incremental::contains(*id),
+        }
+    }
+
+    /// Removes evac's hook, dropping its handlers and context, and restores the hook that was
+    /// installed before [`EvacBuilder::register`](crate::EvacBuilder::register) was called.
+    ///
+    /// The hook can only be swapped out if it's still the most recent evac registration and the
+    /// current thread isn't panicking. Otherwise it stays in place, but passes every panic straight
+    /// through to the previous hook, and is skipped over when the registration made on top of it
+    /// is uninstalled. A hook set outside of evac since registering is replaced as well.
+    ///
+    /// Handles from [`EvacBuilder::register_incremental`](crate::EvacBuilder::register_incremental)
+    /// only remove their own pipeline; the shared hook is uninstalled along with the last one.
+    pub fn uninstall(self) {
+        self.release();
+    }
+
+    pub(crate) fn release(&self) {
+        let installation = match &self.registration {
+            Registration::Hook(installation) => installation,
+            Registration::Incremental(id) => return incremental::remove(*id),
+        };
+
+        let mut top = TOP.lock().unwrap_or_else(PoisonError::into_inner);
+
+        installation.retired.store(true, Ordering::Release);
+
+        let is_top = top
+            .as_ref()
+            .is_some_and(|top| Arc::ptr_eq(top, installation));
+
+        // std refuses to swap hooks mid-panic, so being retired will have to do
+        if !is_top || std::thread::panicking() {
+            return;
+        }
+
+        // Skip past any retired installations underneath us so they don't linger
+        let mut target = installation;
+        while let Some(parent) = target.parent.as_ref() {
+            if !parent.retired.load(Ordering::Acquire) {
+                break;
+            }
+            target = parent;
+        }
+
+        let previous = Arc::clone(&target.previous);
+        *top = target.parent.clone();
+        std::panic::set_hook(Box::new(move |info| previous(info)));
+    }
+}
+
+/// Returned by [`EvacBuilder::register_scoped`](crate::EvacBuilder::register_scoped). Uninstalls
+/// evac's hook when dropped, as per [`EvacHandle::uninstall`].
+///
+/// Guards made with [`EvacBuilder::force_register_scoped`](crate::EvacBuilder::force_register_scoped)
+/// can be nested, and may be dropped in any order; each one restores the hook chain as if its own
+/// registration had never been made.
+///
+/// ## Example
+/// ```
+/// # use evac::EvacBuilder;
+/// {
+///   let _guard = EvacBuilder::new()
+///     .with_handler(|_, _: &mut ()| {
+///       eprintln!("panicked within the scope");
+///       Ok(())
+///     })
+///     .register_scoped(())?;
+///
+///   // ...
+/// }
+/// // The previous hook is back in place here
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+#[must_use = "the hook is uninstalled as soon as the guard is dropped"]
+pub struct EvacGuard {
+    handle: EvacHandle,
+}
+
+impl EvacGuard {
+    pub(crate) fn new(handle: EvacHandle) -> Self {
+        Self { handle }
+    }
+}
+
+impl Drop for EvacGuard {
+    fn drop(&mut self) {
+        self.handle.release();
+    }
+}
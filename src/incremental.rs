@@ -0,0 +1,84 @@
+use std::panic::PanicHookInfo;
+use std::sync::{Mutex, PoisonError, RwLock};
+
+use crate::handle::{self, EvacHandle, Hook, Pipeline};
+
+/// Every incremental registration, read by the dispatcher as it handles a panic.
+static TABLE: RwLock<Table> = RwLock::new(Table {
+    next_id: 0,
+    pipelines: Vec::new(),
+});
+
+/// The one hook that dispatches to the table, while it's installed. Only ever locked to register
+/// or remove pipelines, never while handling a panic, so that it can be held while the hook is
+/// swapped, rather than the table: std holds its own lock on the hook as a panic runs it, and that
+/// panic may well be waiting on the table.
+static DISPATCHER: Mutex<Option<EvacHandle>> = Mutex::new(None);
+
+struct Table {
+    next_id: u64,
+    pipelines: Vec<(u64, Pipeline)>,
+}
+
+/// Adds `pipeline` to the dispatch table, wrapping the current hook with the dispatcher if it
+/// isn't already in place. This is what `std::panic::update_hook` would do, if it were stable.
+pub(crate) fn register(pipeline: Pipeline) -> EvacHandle {
+    let mut dispatcher = DISPATCHER.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let id = {
+        let mut table = TABLE.write().unwrap_or_else(PoisonError::into_inner);
+        let id = table.next_id;
+        table.next_id += 1;
+        table.pipelines.push((id, pipeline));
+
+        id
+    };
+
+    // Only the first registration, or the first since the dispatcher was uninstalled, touches
+    // the hook itself. The rest just join the table, so the hook doesn't nest any deeper.
+    if !dispatcher.as_ref().is_some_and(EvacHandle::is_active) {
+        let installed = handle::install(Box::new(dispatch), true)
+            .expect("forced installs don't check for earlier ones");
+        *dispatcher = Some(installed);
+    }
+
+    EvacHandle::incremental(id)
+}
+
+/// Removes the pipeline registered under `id`, uninstalling the dispatcher if it was the last one.
+pub(crate) fn remove(id: u64) {
+    let mut dispatcher = DISPATCHER.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let empty = {
+        let mut table = TABLE.write().unwrap_or_else(PoisonError::into_inner);
+        table.pipelines.retain(|(entry, _)| *entry != id);
+
+        table.pipelines.is_empty()
+    };
+
+    if empty {
+        if let Some(dispatcher) = dispatcher.take() {
+            dispatcher.release();
+        }
+    }
+}
+
+/// Whether a pipeline is registered under `id`.
+pub(crate) fn contains(id: u64) -> bool {
+    let table = TABLE.read().unwrap_or_else(PoisonError::into_inner);
+
+    table.pipelines.iter().any(|(entry, _)| *entry == id)
+}
+
+/// The dispatcher's hook. Runs the wrapped hook, then every pipeline in registration order.
+fn dispatch(info: &PanicHookInfo<'_>, previous: &Hook) {
+    previous(info);
+
+    // The wrapped hook has already run, so the pipelines don't get to run it again
+    let nothing: Hook = Box::new(|_| {});
+
+    let table = TABLE.read().unwrap_or_else(PoisonError::into_inner);
+    for (_, pipeline) in &table.pipelines {
+        pipeline(info, &nothing);
+    }
+}
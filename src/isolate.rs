@@ -0,0 +1,102 @@
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe, PanicHookInfo};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+thread_local! {
+    /// Set on the threads isolated handlers run on.
+    static ISOLATED: Cell<bool> = const { Cell::new(false) };
+    /// Where the last panic on an isolated thread happened, as the hook saw it, until it's caught.
+    static LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Whether the current thread is running an isolated handler. A panic here is the handler's own,
+/// and is reported by the thread that spawned it rather than being handled again.
+pub(crate) fn is_isolated() -> bool {
+    ISOLATED.with(Cell::get)
+}
+
+/// Notes where a panic on an isolated thread happened, for [`catch`] to report along with its
+/// message, as the hook is the only place that's known.
+pub(crate) fn witness(info: &PanicHookInfo<'_>) {
+    let location = info.location().map(ToString::to_string);
+    let _ = LOCATION.try_with(|last| *last.borrow_mut() = location);
+}
+
+/// Runs `f`, catching a panic inside it. Returns the panic's message if it did, along with where it
+/// happened, if this is an isolated thread.
+pub(crate) fn catch<R>(f: impl FnOnce() -> R) -> Result<R, String> {
+    let _ = LOCATION.try_with(|last| last.borrow_mut().take());
+
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let message = message(payload);
+        match LOCATION
+            .try_with(|last| last.borrow_mut().take())
+            .ok()
+            .flatten()
+        {
+            Some(location) => format!("{message}, at {location}"),
+            None => message,
+        }
+    })
+}
+
+/// Marks the current thread as one that only runs handlers, see [`is_isolated`].
+pub(crate) fn mark_isolated() {
+    ISOLATED.with(|isolated| isolated.set(true));
+}
+
+/// Lends the panic info to a helper thread.
+struct SharedInfo<'a, 'b>(&'a PanicHookInfo<'b>);
+
+// The info is only `!Send` because its payload may not be `Sync`. The panicking thread is blocked
+// until the helper is done with it, so the payload is never accessed from two threads at once.
+unsafe impl Send for SharedInfo<'_, '_> {}
+
+/// Runs `f` on a helper thread, so that a panic inside it can be caught; a panic inside a panic
+/// hook would abort the process instead. Returns the panic's message if `f` panicked. If no thread
+/// can be spawned, `f` is run on the current thread without isolation.
+pub(crate) fn run<R, F>(info: &PanicHookInfo<'_>, f: F) -> Result<R, String>
+where
+    R: Send,
+    F: FnOnce(&PanicHookInfo<'_>) -> R + Send,
+{
+    let shared = SharedInfo(info);
+    // Kept out here so that it can still be run if the helper fails to spawn
+    let f = Mutex::new(Some(f));
+    let take = || {
+        f.lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .expect("handlers only run once")
+    };
+
+    thread::scope(|scope| {
+        let spawned = thread::Builder::new()
+            .name("evac-handler".into())
+            .spawn_scoped(scope, || {
+                mark_isolated();
+
+                // Moved as a whole, as capturing just the reference would leave it `!Send`
+                let shared = shared;
+                catch(|| take()(shared.0))
+            });
+
+        match spawned {
+            Ok(helper) => helper.join().map_err(message).and_then(|result| result),
+            Err(_) => Ok(take()(info)),
+        }
+    })
+}
+
+/// Gets the message out of a panic payload, which is a string unless `panic_any` was used.
+pub(crate) fn message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "Box<dyn Any>".to_string(),
+        },
+    }
+}
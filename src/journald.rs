@@ -0,0 +1,131 @@
+//! Writes reports to the systemd journal, with its native protocol, see
+//! [`handlers::journald`](crate::handlers::journald).
+
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::fs::FileExt;
+use std::os::unix::net::UnixDatagram;
+use std::{mem, ptr};
+
+use crate::{format, PanicReport};
+
+/// Where journald listens.
+const SOCKET: &str = "/run/systemd/journal/socket";
+
+/// The priority entries are written with, `crit`.
+const CRITICAL: u8 = 2;
+
+/// Writes `report` to the journal as an entry of its own, from `identifier`, if it's known.
+pub(crate) fn send(report: &PanicReport<'_>, identifier: Option<&str>) -> io::Result<()> {
+    let mut entry = Vec::new();
+
+    let thread = report.thread_name().unwrap_or("<unnamed>");
+    let message = report.message().unwrap_or("Box<dyn Any>");
+    field(&mut entry, "MESSAGE", &format::summary(report));
+    field(&mut entry, "PRIORITY", &CRITICAL.to_string());
+    if let Some(identifier) = identifier {
+        field(&mut entry, "SYSLOG_IDENTIFIER", identifier);
+    }
+    if let Some(location) = report.location() {
+        field(&mut entry, "CODE_FILE", location.file());
+        field(&mut entry, "CODE_LINE", &location.line().to_string());
+        field(&mut entry, "CODE_COLUMN", &location.column().to_string());
+    }
+    field(&mut entry, "EVAC_FINGERPRINT", report.fingerprint());
+    field(&mut entry, "EVAC_PANIC_MESSAGE", message);
+    field(&mut entry, "EVAC_THREAD", thread);
+    if let Some(backtrace) = report.backtrace_text() {
+        field(&mut entry, "EVAC_BACKTRACE", &backtrace);
+    }
+    if let Some(app) = report.app_metadata() {
+        field(&mut entry, "EVAC_APP_VERSION", app.version);
+        if let Some(commit) = app.git_commit {
+            field(&mut entry, "EVAC_APP_GIT_COMMIT", commit);
+        }
+    }
+    for (key, value) in report.annotations() {
+        field(&mut entry, &format!("EVAC_ANNOTATION_{}", name(key)), value);
+    }
+
+    let socket = UnixDatagram::unbound()?;
+    match socket.send_to(&entry, SOCKET) {
+        Ok(_) => Ok(()),
+        // Too big for a datagram, so handed over in a file instead, as systemd's own client does
+        Err(e) if matches!(e.raw_os_error(), Some(libc::EMSGSIZE | libc::ENOBUFS)) => {
+            send_in_memfd(&socket, &entry)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Adds `name=value` to `entry`, or `name`, the length of `value`, then `value`, if it spans
+/// lines.
+fn field(entry: &mut Vec<u8>, name: &str, value: &str) {
+    entry.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value.as_bytes());
+    entry.push(b'\n');
+}
+
+/// `key` as a field name, which can only hold uppercase letters, digits and underscores.
+fn name(key: &str) -> String {
+    key.chars()
+        .map(|c| match c {
+            'a'..='z' => c.to_ascii_uppercase(),
+            'A'..='Z' | '0'..='9' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+fn send_in_memfd(socket: &UnixDatagram, entry: &[u8]) -> io::Result<()> {
+    // SAFETY: The name is NUL-terminated
+    let fd = unsafe { libc::memfd_create(c"evac-journal".as_ptr(), libc::MFD_ALLOW_SEALING) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `fd` was just opened, and nothing else owns it
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    std::fs::File::from(fd.try_clone()?).write_all_at(entry, 0)?;
+    // journald only takes files that can't change under it
+    let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+    // SAFETY: `fd` is open
+    if unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_ADD_SEALS, seals) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // SAFETY: Everything the message points to outlives the call, and the control buffer is big
+    // enough for the one descriptor, as `CMSG_SPACE` says
+    unsafe {
+        let mut address: libc::sockaddr_un = mem::zeroed();
+        address.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        for (to, from) in address.sun_path.iter_mut().zip(SOCKET.bytes()) {
+            *to = from as libc::c_char;
+        }
+
+        let space = libc::CMSG_SPACE(mem::size_of::<libc::c_int>() as u32) as usize;
+        let mut control = vec![0u8; space];
+        let mut message: libc::msghdr = mem::zeroed();
+        message.msg_name = ptr::addr_of_mut!(address).cast();
+        message.msg_namelen = mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+        message.msg_control = control.as_mut_ptr().cast();
+        message.msg_controllen = space as _;
+
+        let header = libc::CMSG_FIRSTHDR(&message);
+        (*header).cmsg_level = libc::SOL_SOCKET;
+        (*header).cmsg_type = libc::SCM_RIGHTS;
+        (*header).cmsg_len = libc::CMSG_LEN(mem::size_of::<libc::c_int>() as u32) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(header).cast(), fd.as_raw_fd());
+
+        if libc::sendmsg(socket.as_raw_fd(), &message, 0) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
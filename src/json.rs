@@ -0,0 +1,639 @@
+//! Just enough of a JSON serializer for reports, so that writing them doesn't need another
+//! dependency. Output is compact, on a single line.
+
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+
+use serde::ser::{self, Serialize};
+
+/// Writes `value` as JSON.
+pub(crate) fn to_writer<W, T>(writer: &mut W, value: &T) -> io::Result<()>
+where
+    W: Write + ?Sized,
+    T: Serialize + ?Sized,
+{
+    value.serialize(&mut Json { writer }).map_err(|Error(e)| e)
+}
+
+/// Writes `value` as JSON, into memory.
+pub(crate) fn to_vec<T: Serialize + ?Sized>(value: &T) -> io::Result<Vec<u8>> {
+    let mut json = vec![];
+    to_writer(&mut json, value)?;
+
+    Ok(json)
+}
+
+#[derive(Debug)]
+pub(crate) struct Error(pub(crate) io::Error);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(io::Error::other(msg.to_string()))
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error(e)
+    }
+}
+
+struct Json<'w, W: ?Sized> {
+    writer: &'w mut W,
+}
+
+impl<W: Write + ?Sized> Json<'_, W> {
+    fn raw(&mut self, s: &str) -> Result<(), Error> {
+        Ok(self.writer.write_all(s.as_bytes())?)
+    }
+
+    fn string(&mut self, s: &str) -> Result<(), Error> {
+        self.raw("\"")?;
+
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            let escaped = match c {
+                '"' => "\\\"",
+                '\\' => "\\\\",
+                '\n' => "\\n",
+                '\r' => "\\r",
+                '\t' => "\\t",
+                c if c < ' ' => "",
+                _ => continue,
+            };
+
+            self.raw(&s[start..i])?;
+            match escaped {
+                "" => write!(self.writer, "\\u{:04x}", c as u32)?,
+                escaped => self.raw(escaped)?,
+            }
+            start = i + c.len_utf8();
+        }
+        self.raw(&s[start..])?;
+
+        self.raw("\"")
+    }
+
+    fn display(&mut self, value: impl Display) -> Result<(), Error> {
+        Ok(write!(self.writer, "{value}")?)
+    }
+}
+
+/// Encodes `bytes` as standard, padded base64, as JSON has no bytes of its own.
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[((n >> (18 - 6 * i)) & 63) as usize] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
+
+/// The string `name` is set to in the JSON object `json`, wherever it is in it, for the few
+/// fields read from answers to requests, without a parser for them.
+#[cfg(any(feature = "gcp", feature = "cloudwatch"))]
+pub(crate) fn string_field(json: &str, name: &str) -> Option<String> {
+    let (_, rest) = json.split_once(&format!("\"{name}\""))?;
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let mut chars = rest.strip_prefix('"')?.chars();
+
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                // Such as `\"`, `\\` and `\/`
+                escaped => value.push(escaped),
+            },
+            c => value.push(c),
+        }
+    }
+}
+
+/// Decodes standard base64, as written by [`base64`], padded or not.
+pub(crate) fn from_base64(encoded: &str) -> Option<Vec<u8>> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+
+    let encoded = encoded.trim_end_matches('=').as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.chunks(4) {
+        // A single character left over can't hold a whole byte
+        if chunk.len() == 1 {
+            return None;
+        }
+
+        let mut n = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            n |= (sextet(c)? as u32) << (18 - 6 * i);
+        }
+        bytes.extend(&n.to_be_bytes()[1..chunk.len()]);
+    }
+
+    Some(bytes)
+}
+
+impl<'a, 'w, W: Write + ?Sized> ser::Serializer for &'a mut Json<'w, W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a, 'w, W>;
+    type SerializeTuple = Compound<'a, 'w, W>;
+    type SerializeTupleStruct = Compound<'a, 'w, W>;
+    type SerializeTupleVariant = Compound<'a, 'w, W>;
+    type SerializeMap = Compound<'a, 'w, W>;
+    type SerializeStruct = Compound<'a, 'w, W>;
+    type SerializeStructVariant = Compound<'a, 'w, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.display(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.display(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.display(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.display(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.display(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), Error> {
+        self.display(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.display(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.display(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.display(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.display(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), Error> {
+        self.display(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        // JSON has no infinities or NaN
+        match v.is_finite() {
+            true => self.display(v),
+            false => self.raw("null"),
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.string(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.string(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.string(&base64(v))
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        self.raw("null")
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.raw("null")
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), Error> {
+        self.raw("null")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.string(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.raw("{")?;
+        self.string(variant)?;
+        self.raw(":")?;
+        value.serialize(&mut *self)?;
+        self.raw("}")
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        self.raw("[")?;
+        Ok(Compound::new(self, "]"))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        self.raw("{")?;
+        self.string(variant)?;
+        self.raw(":[")?;
+        Ok(Compound::new(self, "]}"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        self.raw("{")?;
+        Ok(Compound::new(self, "}"))
+    }
+
+    fn serialize_struct(self, _: &'static str, len: usize) -> Result<Self::SerializeStruct, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        self.raw("{")?;
+        self.string(variant)?;
+        self.raw(":{")?;
+        Ok(Compound::new(self, "}}"))
+    }
+}
+
+/// An array or object being written, as far as it's got.
+pub(crate) struct Compound<'a, 'w, W: ?Sized> {
+    json: &'a mut Json<'w, W>,
+    first: bool,
+    /// What closes it.
+    end: &'static str,
+}
+
+impl<'a, 'w, W: Write + ?Sized> Compound<'a, 'w, W> {
+    fn new(json: &'a mut Json<'w, W>, end: &'static str) -> Self {
+        Self {
+            json,
+            first: true,
+            end,
+        }
+    }
+
+    fn comma(&mut self) -> Result<(), Error> {
+        match std::mem::replace(&mut self.first, false) {
+            true => Ok(()),
+            false => self.json.raw(","),
+        }
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.comma()?;
+        value.serialize(&mut *self.json)
+    }
+
+    fn key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.comma()?;
+
+        // Keys have to be strings, so anything else is written out as one
+        let key = to_vec(key)?;
+        match key.first() {
+            Some(b'"') => self.json.writer.write_all(&key)?,
+            _ => self.json.string(&String::from_utf8_lossy(&key))?,
+        }
+
+        self.json.raw(":")
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.comma()?;
+        self.json.string(key)?;
+        self.json.raw(":")?;
+        value.serialize(&mut *self.json)
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        self.json.raw(self.end)
+    }
+}
+
+impl<W: Write + ?Sized> ser::SerializeSeq for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write + ?Sized> ser::SerializeTuple for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write + ?Sized> ser::SerializeTupleStruct for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write + ?Sized> ser::SerializeTupleVariant for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write + ?Sized> ser::SerializeMap for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.json)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write + ?Sized> ser::SerializeStruct for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl<W: Write + ?Sized> ser::SerializeStructVariant for Compound<'_, '_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+
+    use super::*;
+    use crate::report::testing::with_report;
+
+    #[derive(Serialize)]
+    enum Variant {
+        Unit,
+        Newtype(u8),
+        Tuple(u8, &'static str),
+        Struct { field: bool },
+    }
+
+    #[derive(Serialize)]
+    struct Sample {
+        bools: (bool, bool),
+        integers: (i8, i64, i64, u64, i128),
+        floats: (f32, f64, f64, f64),
+        non_finite: (f64, f64),
+        text: String,
+        character: char,
+        missing: Option<u8>,
+        present: Option<u8>,
+        unit: (),
+        list: Vec<u16>,
+        empty: Vec<u16>,
+        map: BTreeMap<&'static str, i32>,
+        numeric_keys: BTreeMap<u32, &'static str>,
+        variants: Vec<Variant>,
+    }
+
+    fn sample() -> Sample {
+        Sample {
+            bools: (true, false),
+            integers: (-128, i64::MIN, i64::MAX, u64::MAX, -1),
+            floats: (1.5, 0.1, -0.0, 1e300),
+            non_finite: (f64::NAN, f64::INFINITY),
+            text: (0..0x80u8)
+                .map(char::from)
+                .chain("é\u{2028}😀".chars())
+                .collect(),
+            character: '"',
+            missing: None,
+            present: Some(7),
+            unit: (),
+            list: vec![1, 2, 3],
+            empty: vec![],
+            map: [("a", 1), ("b", -2)].into(),
+            numeric_keys: [(1, "one"), (20, "twenty")].into(),
+            variants: vec![
+                Variant::Unit,
+                Variant::Newtype(1),
+                Variant::Tuple(2, "two"),
+                Variant::Struct { field: true },
+            ],
+        }
+    }
+
+    #[test]
+    fn writes_what_serde_json_reads_back() {
+        let written = to_vec(&sample()).unwrap();
+        let read: serde_json::Value = serde_json::from_slice(&written).unwrap();
+
+        assert_eq!(read, serde_json::to_value(sample()).unwrap());
+        assert!(!written.contains(&b'\n'));
+    }
+
+    #[test]
+    fn writes_reports_as_serde_json_does() {
+        let (ours, theirs) = with_report("a \"quoted\"\tmessage\nover two lines", |report| {
+            report.annotate("user.id", "42");
+            (
+                to_vec(report).unwrap(),
+                serde_json::to_value(&*report).unwrap(),
+            )
+        });
+
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&ours).unwrap(),
+            theirs
+        );
+    }
+
+    #[test]
+    fn encodes_base64_as_rfc_4648_does() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+
+        for (decoded, encoded) in vectors {
+            assert_eq!(base64(decoded.as_bytes()), encoded);
+            assert_eq!(from_base64(encoded).unwrap(), decoded.as_bytes());
+            assert_eq!(
+                from_base64(encoded.trim_end_matches('=')).unwrap(),
+                decoded.as_bytes()
+            );
+        }
+        assert_eq!(from_base64("Zm9vY"), None);
+        assert_eq!(from_base64("Zm9v!A=="), None);
+    }
+
+    /// Serializes as bytes, rather than a sequence of numbers.
+    struct Bytes(&'static [u8]);
+
+    impl Serialize for Bytes {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    #[test]
+    fn writes_bytes_as_base64() {
+        assert_eq!(to_vec(&Bytes(b"foobar")).unwrap(), b"\"Zm9vYmFy\"");
+    }
+
+    #[cfg(any(feature = "gcp", feature = "cloudwatch"))]
+    #[test]
+    fn finds_string_fields_in_answers() {
+        let json = r#"{"outer": {"token" : "a\"b\\cé\n"}, "count": 3}"#;
+
+        assert_eq!(string_field(json, "token").as_deref(), Some("a\"b\\cé\n"));
+        assert_eq!(string_field(json, "count"), None);
+        assert_eq!(string_field(json, "missing"), None);
+    }
+}
@@ -0,0 +1,375 @@
+//! Telling, on startup, whether the last run crashed, such as to offer to send a report or
+//! restore the session, see [`EvacBuilder::crash_marker`](crate::EvacBuilder::crash_marker).
+
+use std::fs;
+use std::io;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::filter;
+
+/// What the marker said when the pipeline was registered.
+static LAST_CRASH: Mutex<Option<LastCrashInfo>> = Mutex::new(None);
+/// Where the marker is, once a pipeline with one has been registered.
+static MARKER: Mutex<Option<PathBuf>> = Mutex::new(None);
+/// How the last run ended, as of the pipeline being registered.
+static LAST_RUN: Mutex<Option<LastRun>> = Mutex::new(None);
+/// Where the heartbeat is, and what it last said, once a pipeline with one has been registered.
+/// Held while writing to it, so that the heartbeat can't write over how the process ended.
+static HEARTBEAT: Mutex<Option<(PathBuf, State)>> = Mutex::new(None);
+
+/// The crash an earlier run left a marker for, if it did, as of the pipeline being registered.
+/// Gives `None` until a builder with
+/// [`EvacBuilder::crash_marker`](crate::EvacBuilder::crash_marker) has been registered, and
+/// after [`clear`].
+///
+/// ## Example
+/// ```
+/// # use evac::EvacBuilder;
+/// # let marker = std::env::temp_dir().join("evac-did-crash-example");
+/// EvacBuilder::new()
+///   .with_handler(|_, _: &mut ()| Ok(()))
+///   .crash_marker(marker)
+///   .register(())?;
+///
+/// if let Some(crash) = evac::last_run::did_crash() {
+///   eprintln!("crashed last time: {}", crash.message.as_deref().unwrap_or("no message"));
+///   // Once the user has been asked about it
+///   evac::last_run::clear()?;
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn did_crash() -> Option<LastCrashInfo> {
+    LAST_CRASH
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Deletes the marker, so that the crash isn't brought up again on the next launch, and
+/// [`did_crash`] gives `None` from then on. Does nothing if there's no marker.
+pub fn clear() -> io::Result<()> {
+    LAST_CRASH
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+
+    let marker = MARKER.lock().unwrap_or_else(PoisonError::into_inner);
+    match marker.as_ref().map(fs::remove_file) {
+        Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// How the last run ended, as far as its heartbeat tells, or `None` until a builder with
+/// [`EvacBuilder::heartbeat`](crate::EvacBuilder::heartbeat) has been registered, or on the first
+/// run with one.
+///
+/// Unlike [`did_crash`], this tells apart runs that were killed before they could panic, or
+/// say anything at all, such as by `SIGKILL` or the OOM killer, so that the app can report those
+/// silent deaths too.
+///
+/// ## Example
+/// ```
+/// # use evac::last_run::{Heartbeat, LastRun};
+/// # use evac::EvacBuilder;
+/// # let heartbeat = std::env::temp_dir().join("evac-classify-example");
+/// EvacBuilder::new()
+///   .with_handler(|_, _: &mut ()| Ok(()))
+///   .heartbeat(Heartbeat::new(heartbeat))
+///   .register(())?;
+///
+/// match evac::last_run::classify() {
+///   Some(LastRun::Killed { last_seen }) => eprintln!("killed without warning after {last_seen:?}"),
+///   Some(LastRun::Panicked) => eprintln!("we crashed last time"),
+///   _ => {}
+/// }
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+pub fn classify() -> Option<LastRun> {
+    *LAST_RUN.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Marks that the process is exiting cleanly, for [`classify`] to say so on the next run. On
+/// Unix, it's done by itself as the process exits, by returning from `main` or calling
+/// [`std::process::exit`], so this is only needed elsewhere, in which case it should be the
+/// last thing the process does. Does nothing if the process has already panicked, as that's how
+/// it ends.
+pub fn clean_exit() {
+    let mut heartbeat = HEARTBEAT.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some((path, state)) = heartbeat.as_mut() {
+        if *state == State::Alive {
+            *state = State::Exited;
+            beat(path, *state);
+        }
+    }
+}
+
+/// How the last run ended, see [`classify`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LastRun {
+    /// It exited without panicking.
+    Clean,
+    /// It panicked, and the hook saw it. A panic that was caught, after which the process went on
+    /// to exit cleanly, still counts.
+    Panicked,
+    /// It died without exiting or panicking, such as by being killed, some time after its last
+    /// heartbeat, at `last_seen`, and within an [interval](Heartbeat::interval) of it.
+    Killed {
+        /// When the heartbeat was last written to.
+        last_seen: SystemTime,
+    },
+}
+
+/// Where the heartbeat is written, and how often, see
+/// [`EvacBuilder::heartbeat`](crate::EvacBuilder::heartbeat).
+///
+/// ## Example
+/// ```
+/// # use std::time::Duration;
+/// # use evac::last_run::Heartbeat;
+/// let heartbeat = Heartbeat::new("/var/lib/my-app/heartbeat").interval(Duration::from_secs(30));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Heartbeat {
+    path: PathBuf,
+    interval: Duration,
+}
+
+impl Heartbeat {
+    /// Writes the heartbeat to the file at `path` every 10 seconds.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            interval: Duration::from_secs(10),
+        }
+    }
+
+    /// Writes the heartbeat every `interval`, which is as close as [`classify`] can tell when a
+    /// run that was killed died.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+
+        self
+    }
+
+    /// Reads back how the last run ended, then starts writing this one's heartbeat, on a thread
+    /// of its own.
+    pub(crate) fn start(self) -> Beating {
+        let last = fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|heartbeat| read(&heartbeat));
+        *LAST_RUN.lock().unwrap_or_else(PoisonError::into_inner) = last;
+
+        *HEARTBEAT.lock().unwrap_or_else(PoisonError::into_inner) =
+            Some((self.path.clone(), State::Alive));
+        beat(&self.path, State::Alive);
+        #[cfg(unix)]
+        on_exit();
+
+        let interval = self.interval;
+        // Without a thread, the heartbeat is only as of startup, which still tells how it ends
+        let _ = std::thread::Builder::new()
+            .name("evac-heartbeat".to_string())
+            .spawn(move || loop {
+                std::thread::sleep(interval);
+
+                let heartbeat = HEARTBEAT.lock().unwrap_or_else(PoisonError::into_inner);
+                match heartbeat.as_ref() {
+                    Some((path, State::Alive)) => beat(path, State::Alive),
+                    // Whatever ended the process is what the next run should see
+                    _ => return,
+                }
+            });
+
+        Beating
+    }
+}
+
+/// Marks that the process panicked, in place of its heartbeat, see [`Heartbeat`].
+pub(crate) struct Beating;
+
+impl Beating {
+    pub(crate) fn panicked(&self) {
+        let mut heartbeat = HEARTBEAT.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((path, state)) = heartbeat.as_mut() {
+            *state = State::Panicked;
+            beat(path, *state);
+        }
+    }
+}
+
+/// What the heartbeat says about the process.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Alive,
+    Panicked,
+    Exited,
+}
+
+/// Writes `state` to the heartbeat at `path`, as of now.
+fn beat(path: &Path, state: State) {
+    let state = match state {
+        State::Alive => "alive",
+        State::Panicked => "panicked",
+        State::Exited => "exited",
+    };
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    // There's nowhere to report this to, and it mustn't hold up the handlers
+    let _ = fs::write(path, format!("{state} {millis} {}\n", std::process::id()));
+}
+
+/// How the run that wrote `heartbeat` ended, if it can be told.
+fn read(heartbeat: &str) -> Option<LastRun> {
+    let mut fields = heartbeat.split_whitespace();
+    let state = fields.next()?;
+    let millis: u64 = fields.next()?.parse().ok()?;
+
+    match state {
+        "alive" => Some(LastRun::Killed {
+            last_seen: UNIX_EPOCH + Duration::from_millis(millis),
+        }),
+        "panicked" => Some(LastRun::Panicked),
+        "exited" => Some(LastRun::Clean),
+        _ => None,
+    }
+}
+
+/// Has [`clean_exit`] called as the process exits.
+#[cfg(unix)]
+fn on_exit() {
+    extern "C" fn exiting() {
+        clean_exit();
+    }
+
+    static REGISTERED: std::sync::Once = std::sync::Once::new();
+    REGISTERED.call_once(|| {
+        // SAFETY: `exiting` is a plain function that only locks a static and writes a file
+        unsafe {
+            libc::atexit(exiting);
+        }
+    });
+}
+
+/// What's known about a crash from its marker, see [`did_crash`].
+///
+/// Only the time is sure to be known, as the rest is written after it, and the process may not
+/// have lived long enough to finish.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct LastCrashInfo {
+    /// When the process panicked, or, if it didn't get as far as saying so, when the marker was
+    /// last written to.
+    pub timestamp: SystemTime,
+    /// The ID of the process that panicked.
+    pub pid: Option<u32>,
+    /// The name of the thread that panicked, if it had one.
+    pub thread: Option<String>,
+    /// Where it panicked, such as `src/main.rs:10:5`.
+    pub location: Option<String>,
+    /// The panic's message, if it has one.
+    pub message: Option<String>,
+}
+
+/// Writes the marker for each panic, see
+/// [`EvacBuilder::crash_marker`](crate::EvacBuilder::crash_marker).
+pub(crate) struct Marker {
+    path: PathBuf,
+}
+
+impl Marker {
+    /// Reads back the marker an earlier run left at `path`, if any, for [`did_crash`].
+    pub(crate) fn start(path: PathBuf) -> Self {
+        let last = match fs::read_to_string(&path) {
+            Ok(marker) => Some(parse(&marker, || modified(&path))),
+            Err(_) => None,
+        };
+        *LAST_CRASH.lock().unwrap_or_else(PoisonError::into_inner) = last;
+        *MARKER.lock().unwrap_or_else(PoisonError::into_inner) = Some(path.clone());
+
+        Self { path }
+    }
+
+    /// Marks that the process is crashing, as it panicked as per `info`.
+    pub(crate) fn mark(&self, info: &PanicHookInfo<'_>) {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        let mut marker = format!("timestamp={millis}\npid={}\n", std::process::id());
+        if let Some(thread) = std::thread::current().name() {
+            marker += &format!("thread={}\n", escape(thread));
+        }
+        if let Some(location) = info.location() {
+            marker += &format!("location={}\n", escape(&location.to_string()));
+        }
+        if let Some(message) = filter::message(info) {
+            marker += &format!("message={}\n", escape(message));
+        }
+
+        // There's nowhere to report this to, and it mustn't hold up the handlers
+        let _ = fs::write(&self.path, marker);
+    }
+}
+
+/// The crash `marker` tells of, which was last written to at `modified`, should it not say when.
+fn parse(marker: &str, modified: impl FnOnce() -> SystemTime) -> LastCrashInfo {
+    let field = |name: &str| {
+        marker.lines().find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key == name).then(|| unescape(value))
+        })
+    };
+
+    let timestamp = field("timestamp")
+        .and_then(|millis| millis.parse().ok())
+        .map(|millis| UNIX_EPOCH + Duration::from_millis(millis));
+
+    LastCrashInfo {
+        timestamp: timestamp.unwrap_or_else(modified),
+        pid: field("pid").and_then(|pid| pid.parse().ok()),
+        thread: field("thread"),
+        location: field("location"),
+        message: field("message"),
+    }
+}
+
+fn modified(path: &Path) -> SystemTime {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or_else(|_| SystemTime::now())
+}
+
+/// `value` on a line of its own, with `\` and line breaks escaped.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => unescaped.push('\n'),
+                Some('r') => unescaped.push('\r'),
+                Some(other) => unescaped.push(other),
+                None => unescaped.push('\\'),
+            },
+            c => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
@@ -1,9 +1,194 @@
 use std::error::Error;
-use std::{panic::PanicInfo, sync::Arc};
+use std::fmt::Display;
+use std::io::Write;
+use std::panic::PanicHookInfo;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::{Duration, Instant};
 
-/// The type of closures accepted in `evac`. Errors are printed to `stderr`.
-pub type PanicHandler<T: 'static> =
-    Box<dyn Fn(&PanicInfo<'_>, &mut T) -> Result<(), Box<dyn Error>> + 'static + Send + Sync>;
+#[cfg(feature = "age")]
+mod age;
+#[cfg(feature = "all-threads")]
+mod all_threads;
+mod app;
+#[cfg(feature = "app-insights")]
+mod app_insights;
+#[cfg(any(feature = "s3", feature = "cloudwatch"))]
+mod aws;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+mod binary;
+pub mod breadcrumbs;
+pub mod buffer;
+pub mod build;
+mod capture;
+#[cfg(feature = "cloudwatch")]
+mod cloudwatch;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compress;
+#[cfg(feature = "config")]
+pub mod config;
+mod console;
+mod context;
+mod core_dump;
+pub mod crash_loop;
+mod crash_thread;
+#[cfg(any(feature = "age", feature = "s3", feature = "cloudwatch"))]
+mod crypto;
+mod dedup;
+mod env;
+mod error;
+#[cfg(all(windows, feature = "event-log"))]
+mod event_log;
+mod exec;
+mod executor;
+mod extensions;
+mod filter;
+mod fingerprint;
+mod format;
+#[cfg(feature = "gcp")]
+mod gcp;
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(feature = "gzip")]
+mod gzip;
+mod handle;
+pub mod handlers;
+#[cfg(feature = "http")]
+mod http;
+mod incremental;
+mod isolate;
+#[cfg(all(target_os = "linux", feature = "journald"))]
+mod journald;
+#[cfg(feature = "serde")]
+mod json;
+pub mod last_run;
+mod limit;
+mod local;
+#[cfg(feature = "log")]
+mod log;
+#[cfg(all(target_os = "android", feature = "android"))]
+mod logcat;
+#[cfg(feature = "otlp")]
+mod otlp;
+#[cfg(feature = "http")]
+pub mod outbox;
+mod parallel;
+mod pipeline;
+mod process;
+#[cfg(feature = "http")]
+mod pushgateway;
+mod regex;
+pub mod report;
+mod reserve;
+mod retry;
+#[cfg(feature = "s3")]
+mod s3;
+mod scrub;
+#[cfg(all(unix, feature = "sd-notify"))]
+mod sd_notify;
+#[cfg(feature = "sentry")]
+mod sentry;
+#[cfg(feature = "slog")]
+mod slog;
+#[cfg(feature = "smtp")]
+mod smtp;
+pub mod stats;
+mod statsd;
+mod stderr;
+mod summary;
+#[cfg(feature = "sysinfo")]
+pub mod sysinfo;
+#[cfg(all(unix, feature = "syslog"))]
+mod syslog;
+mod termination_log;
+pub mod thread;
+mod timeout;
+#[cfg(any(feature = "http", feature = "smtp"))]
+mod tls;
+mod toml;
+#[cfg(feature = "tracing")]
+mod tracing;
+mod truncate;
+mod watchdog;
+#[cfg(feature = "http")]
+mod webhook;
+#[cfg(feature = "zstd")]
+mod zstd;
+
+#[cfg(feature = "age")]
+pub use age::{AgeRecipient, Encrypted};
+#[cfg(feature = "all-threads")]
+pub use all_threads::{StackFrame, ThreadTrace};
+pub use app::AppMetadata;
+pub use capture::{ArgsCapture, EnvCapture};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub use compress::Compressed;
+pub use context::{Contention, ContextHandle, ContextProvider};
+pub use crash_thread::CrashThread;
+pub use dedup::occurrences;
+#[cfg(feature = "age")]
+pub use error::RecipientError;
+pub use error::{
+    FilterError, HandlerError, MissingExtension, PatternError, RegisterError, Skipped,
+};
+pub use extensions::Extensions;
+pub use fingerprint::Grouping;
+#[cfg(feature = "cbor")]
+pub use format::CborFormatter;
+#[cfg(feature = "serde")]
+pub use format::JsonFormatter;
+#[cfg(feature = "msgpack")]
+pub use format::MessagePackFormatter;
+pub use format::{ReportFormatter, TextFormatter};
+#[cfg(feature = "gcp")]
+pub use gcp::ErrorReportingFormatter;
+pub use handle::{EvacGuard, EvacHandle};
+pub use local::{LocalErrorSink, LocalEvacBuilder, LocalHandle, LocalPanicHandler};
+pub use parallel::{ParallelGroup, SharedHandler};
+pub use report::{Attachment, BacktraceMode, PanicReport, PayloadType, ReportHandler};
+pub use retry::Retry;
+pub use scrub::{ReportField, Scrubber};
+pub use summary::{HandlerOutcome, PipelineSummary};
+pub use timeout::TimeoutError;
+pub use truncate::ReportLimits;
+pub use watchdog::DeadlineAction;
+
+use context::{ContextLock, Lazy, Provided, Source, WeakSource};
+use crash_loop::CrashLoop;
+use env::Environment;
+use handle::Pipeline;
+use last_run::Heartbeat;
+use parallel::Group;
+use pipeline::Runner;
+use regex::Regex;
+use stats::CrashStats;
+use thread::Suppressed;
+
+/// The type of closures accepted in `evac`. Errors are sent to the [`ErrorSink`], which prints them
+/// to `stderr` by default.
+pub type PanicHandler<T, E = Box<dyn Error>> =
+    Box<dyn Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + 'static + Send + Sync>;
+
+/// The type of closures that errors encountered while handling a panic are sent to.
+pub type ErrorSink<E = Box<dyn Error>> = Box<dyn Fn(&HandlerError<'_, E>) + 'static + Send + Sync>;
+
+/// The type of closures told about each failed handler, see [`EvacBuilder::on_handler_error`].
+pub type HandlerErrorCallback<E = Box<dyn Error>> =
+    Box<dyn Fn(Option<&str>, usize, &E) + 'static + Send + Sync>;
+
+/// The type of closures given the [`PipelineSummary`] at the very end, see
+/// [`EvacBuilder::on_complete`].
+pub type CompletionCallback = Box<dyn Fn(&PipelineSummary<'_>) + 'static + Send + Sync>;
+
+/// The type of closures told about each panic that isn't handled, see
+/// [`EvacBuilder::on_suppressed`].
+pub type SuppressedCallback<T> = Box<dyn Fn(&PanicHookInfo<'_>, &mut T) + 'static + Send + Sync>;
+
+/// The type of closures run once all of the handlers are done. Errors are handled the same as a
+/// handler's.
+pub type Finalizer<T, E = Box<dyn Error>> =
+    Box<dyn Fn(&mut T, &PipelineSummary<'_>) -> Result<(), E> + 'static + Send + Sync>;
 
 /// Builder for assembling a series of panic handlers. Because Rust only allows for a single panic
 /// hook, this builder enables composing multiple panic handlers into a single hook.
@@ -12,84 +197,2317 @@ pub type PanicHandler<T: 'static> =
 /// ```
 /// # use std::fs::OpenOptions;
 /// # use std::io::Write;
-/// # use std::panic::PanicInfo;
+/// # use std::panic::PanicHookInfo;
 /// # use std::path::PathBuf;
-/// # fn get_dump(_: &PanicInfo) -> Vec<u8> { vec![] }
-/// # let dump_path = PathBuf::from("")?;
+/// # use evac::EvacBuilder;
+/// # fn get_dump(_: &PanicHookInfo) -> Vec<u8> { vec![] }
+/// # let dump_path = PathBuf::from("crash.dump");
 /// EvacBuilder::new()
-///   .with_handler(|panic_info, path| {
+///   .with_handler(|panic_info, path: &mut PathBuf| {
 ///     // Build the dump file
-///     let mut dump: Vec<u8> = get_dump(panic_info);
+///     let dump: Vec<u8> = get_dump(panic_info);
 ///     // Write the dump to disk
-///     let mut file = OpenOptions::new().write(true).open(path)?;
-///     file.write_all(&mut dump)?;
+///     let mut file = OpenOptions::new().create(true).write(true).open(path)?;
+///     file.write_all(&dump)?;
+///     Ok(())
+///   })
+///   .register(dump_path)?; // Register Evac with the path as the context
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+///
+/// Handlers return `Box<dyn Error>` by default. Any other error type that can be displayed can be
+/// used instead, by naming it when constructing the builder:
+/// ```
+/// # use std::fmt::{self, Display, Formatter};
+/// # use evac::{EvacBuilder, HandlerError};
+/// #[derive(Debug)]
+/// enum ReportError {
+///   Offline,
+/// }
+///
+/// impl Display for ReportError {
+///   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+///     write!(f, "the report server is offline")
+///   }
+/// }
+///
+/// EvacBuilder::<(), ReportError>::default()
+///   .with_handler(|_, _| Err(ReportError::Offline))
+///   .error_sink(|err| {
+///     if let HandlerError::Handler { error: ReportError::Offline, .. } = err {
+///       eprintln!("will retry on the next start");
+///     }
 ///   })
-///   .register(dump_path); // Register Evac with the path as the context
+///   .register(())?;
+/// # Ok::<(), evac::RegisterError>(())
 /// ```
-pub struct EvacBuilder<T: 'static> {
-    handlers: Vec<PanicHandler<T>>,
-    preserve_default: bool,
+pub struct EvacBuilder<T: 'static, E: 'static = Box<dyn Error>> {
+    handlers: Vec<HandlerEntry<T, E>>,
+    existing_hook: Option<Position>,
+    contention: Contention,
+    snapshot: Option<fn(&T) -> T>,
+    finalizers: Vec<Finalizer<T, E>>,
+    error_sink: Option<ErrorSink<E>>,
+    on_handler_error: Vec<HandlerErrorCallback<E>>,
+    on_complete: Vec<CompletionCallback>,
+    error_policy: Option<ErrorPolicy>,
+    critical_policy: Option<ErrorPolicy>,
+    isolate: bool,
+    deadline: Option<(Duration, DeadlineAction)>,
+    after_panic: Option<AfterPanic>,
+    crash_thread: Option<CrashThread>,
+    memory_reserve: Option<usize>,
+    sample_rate: Option<f64>,
+    max_reports: Option<(u32, Duration)>,
+    on_suppressed: Vec<SuppressedCallback<T>>,
+    dedup_window: Option<Duration>,
+    crash_loop: Option<CrashLoop>,
+    crash_marker: Option<PathBuf>,
+    crash_stats: Option<CrashStats>,
+    heartbeat: Option<Heartbeat>,
+    ignore_environment: bool,
+    backtrace: Option<BacktraceMode>,
+    app_metadata: Option<AppMetadata>,
+    env_capture: Option<EnvCapture>,
+    args_capture: Option<ArgsCapture>,
+    #[cfg(feature = "all-threads")]
+    all_threads: bool,
+    #[cfg(feature = "sysinfo")]
+    system_info: Option<Duration>,
+    grouping: Option<Grouping>,
+    scrubber: Option<Scrubber>,
+    limits: Option<ReportLimits>,
 }
 
-impl<T: 'static> EvacBuilder<T> {
-    /// Constructs a new [`EvalBuilder`]. Empty of handlers and does not preserve the default panic
-    /// hook.
-    pub fn new() -> Self {
+/// A registered handler, along with the name it can be looked up by and where it runs.
+struct HandlerEntry<T: 'static, E: 'static> {
+    name: Option<String>,
+    priority: Priority,
+    on_error: Option<ErrorPolicy>,
+    criticality: Criticality,
+    retry: Retry,
+    /// Checked before each panic, the handler only runs if they all pass.
+    conditions: Vec<Condition<T>>,
+    /// Set for handlers that only run once, to whether they've run yet.
+    once: Option<AtomicBool>,
+    handler: HandlerKind<T, E>,
+}
+
+/// Decides whether a handler runs for a panic, see [`EvacBuilder::with_handler_if`].
+type Condition<T> = Box<dyn Fn(&PanicHookInfo<'_>, &T) -> bool + Send + Sync>;
+
+/// Decides whether a group of handlers runs for a panic, see [`EvacBuilder::only_from`].
+type GroupCondition = dyn Fn(&PanicHookInfo<'_>) -> bool + Send + Sync;
+
+/// What a [`HandlerEntry`] runs.
+enum HandlerKind<T: 'static, E: 'static> {
+    Single(PanicHandler<T, E>),
+    Report(ReportHandler<T, E>),
+    Group(Group<T, E>),
+}
+
+impl<T: 'static, E: 'static> HandlerKind<T, E> {
+    /// How many handlers this is made up of.
+    fn len(&self) -> usize {
+        match self {
+            HandlerKind::Single(_) | HandlerKind::Report(_) => 1,
+            HandlerKind::Group(group) => group.names.len(),
+        }
+    }
+}
+
+impl<T: 'static, E: 'static> HandlerEntry<T, E> {
+    fn new(name: Option<String>, priority: Priority, handler: HandlerKind<T, E>) -> Self {
         Self {
-            handlers: vec![],
-            preserve_default: false,
+            name,
+            priority,
+            on_error: None,
+            criticality: Criticality::BestEffort,
+            retry: Retry::default(),
+            conditions: vec![],
+            once: None,
+            handler,
+        }
+    }
+
+    /// Whether this handler sits out the panic.
+    fn skips(
+        &self,
+        info: &PanicHookInfo<'_>,
+        ctx: &T,
+        env: &Environment,
+        suppressed: Option<&Suppressed<'_>>,
+    ) -> bool {
+        !env.allows(self.name.as_deref())
+            || suppressed.is_some_and(|suppressed| suppressed.suppresses(self.name.as_deref()))
+            || !self.conditions.iter().all(|condition| condition(info, ctx))
+            // Checked last, so that it's only used up by the handler running
+            || self
+                .once
+                .as_ref()
+                .is_some_and(|ran| ran.swap(true, Ordering::AcqRel))
+    }
+
+    /// What to do if this handler fails, given the builder's policies.
+    fn policy(
+        &self,
+        error_policy: Option<ErrorPolicy>,
+        critical_policy: Option<ErrorPolicy>,
+    ) -> ErrorPolicy {
+        match self.criticality {
+            Criticality::BestEffort => self.on_error.or(error_policy).unwrap_or_default(),
+            Criticality::Critical => self
+                .on_error
+                .or(critical_policy)
+                .unwrap_or(ErrorPolicy::AbortProcess),
+        }
+    }
+}
+
+/// When a handler runs relative to the others. Handlers run from [`Priority::Critical`] down to
+/// [`Priority::Low`], and handlers of the same priority run in the order they were added.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Priority {
+    /// For handlers that must get a chance to run, such as flushing logs.
+    Critical,
+    /// For handlers that should run ahead of most others.
+    High,
+    /// The priority of handlers added without one.
+    #[default]
+    Normal,
+    /// For handlers that can live without, such as network uploads.
+    Low,
+}
+
+/// Where a preserved hook runs relative to evac's handlers, see
+/// [`EvacBuilder::preserve_existing_hook`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Position {
+    /// Run the hook before any of the handlers.
+    Before,
+    /// Run the hook once all of the handlers are done.
+    After,
+}
+
+/// What happens after a handler fails, see [`EvacBuilder::error_policy`]. The error is reported
+/// either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ErrorPolicy {
+    /// Carry on with the next handler.
+    #[default]
+    Continue,
+    /// Skip the remaining handlers. Finalizers still run, and so does a preserved hook.
+    StopChain,
+    /// Abort the process straight away, without running anything else.
+    AbortProcess,
+    /// Carry on with the next handler, but exit the process with the given code once the pipeline
+    /// is done, rather than letting the panic unwind. Nothing else gets to run, including
+    /// destructors, as per [`std::process::exit`].
+    Exit(i32),
+}
+
+/// What the process does once a panic has been handled, see [`EvacBuilder::after_panic`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum AfterPanic {
+    /// Carry on as if there were no hook, unwinding or aborting as per the panic strategy.
+    #[default]
+    Continue,
+    /// Exit the process with the given code, as per [`std::process::exit`].
+    Exit(i32),
+    /// Abort the process, as per [`std::process::abort`].
+    Abort,
+    /// End the process such that the OS records a core dump of it, for debugging after the fact.
+    /// On Unix, this restores the default handling of `SIGABRT`, raises the core size limit as
+    /// far as it's allowed to go, and raises `SIGABRT`. Whether a core is actually written still
+    /// depends on how the system is set up, such as its `core_pattern` on Linux. Elsewhere, this
+    /// is the same as [`AfterPanic::Abort`], which already produces the platform's crash dump.
+    CoreDump,
+}
+
+/// Whether a handler's failure can be lived with, see [`EvacBuilder::handler_criticality`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Criticality {
+    /// For handlers that can fail without consequence, such as pushing metrics. Failures are
+    /// handled as per [`EvacBuilder::error_policy`].
+    #[default]
+    BestEffort,
+    /// For handlers that mustn't fail quietly, such as flushing a write-ahead log. Failures are
+    /// handled as per [`EvacBuilder::critical_error_policy`].
+    Critical,
+}
+
+impl<T: Send + 'static> EvacBuilder<T> {
+    /// Constructs a new [`EvacBuilder`]. Empty of handlers and does not preserve the existing panic
+    /// hook. Handlers return `Box<dyn Error>`; use [`Default`] to pick another error type.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: Send + 'static, E: Display + 'static> EvacBuilder<T, E> {
+    /// Turns back on the default panic hook that ships with Rust, running it before the handlers.
+    /// Shorthand for `preserve_existing_hook(Position::Before)`; if another hook has replaced the
+    /// default by the time evac is registered, that hook is the one that runs.
+    pub fn preserve_default_panic(self) -> Self {
+        self.preserve_existing_hook(Position::Before)
+    }
+
+    /// Keeps whichever panic hook is installed when evac is registered, such as one set by a
+    /// logging or error reporting crate, and runs it at `position` relative to evac's handlers.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{EvacBuilder, Position};
+    /// std::panic::set_hook(Box::new(|_| eprintln!("some other crate's hook")));
+    ///
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .preserve_existing_hook(Position::After)
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn preserve_existing_hook(mut self, position: Position) -> Self {
+        self.existing_hook = Some(position);
+
+        self
+    }
+
+    /// Sets what happens when several threads panic at once, see [`Contention`]. By default, each
+    /// pipeline waits its turn.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::{Contention, EvacBuilder};
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .on_contention(Contention::Timeout(Duration::from_secs(5)))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn on_contention(mut self, contention: Contention) -> Self {
+        self.contention = contention;
+
+        self
+    }
+
+    /// Caps how long handling a panic may take, all told, after which the process is ended as per
+    /// `action`. This guards against the crash handling itself wedging the process, such as on a
+    /// handler deadlocking. The deadline is kept by a watchdog thread spawned on registration; if
+    /// it can't be spawned, there's no deadline.
+    ///
+    /// Each panic has a clock of its own, which starts when the panic starts being handled and
+    /// stops once it's done, so it covers the preserved hook and time spent waiting on the context
+    /// as well. Panics handled at the same time don't add to each other's.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::{DeadlineAction, EvacBuilder};
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .deadline(Duration::from_secs(10), DeadlineAction::Exit(70))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn deadline(mut self, deadline: Duration, action: DeadlineAction) -> Self {
+        self.deadline = Some((deadline, action));
+
+        self
+    }
+
+    /// Sets what the process does once a panic has been handled, whether or not the handlers
+    /// succeeded. Use it to tell a supervisor that the process crashed, rather than shut down
+    /// cleanly. By default, the panic carries on unwinding. An [`ErrorPolicy::Exit`] that was
+    /// triggered takes precedence.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{AfterPanic, EvacBuilder};
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .after_panic(AfterPanic::Exit(70))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn after_panic(mut self, after_panic: AfterPanic) -> Self {
+        self.after_panic = Some(after_panic);
+
+        self
+    }
+
+    /// Runs the handlers on a thread of their own, spawned on registration, rather than on the
+    /// panicking thread. This keeps them clear of whatever state the panicking thread is in,
+    /// such as being low on stack. The panicking thread hands the panic over without allocating,
+    /// and waits for the handlers to finish. If the thread can't be spawned, the handlers run on
+    /// the panicking thread as usual.
+    ///
+    /// Only one panic is handled on the crash thread at a time. A handler that panics there is
+    /// reported as a [`HandlerError::Panicked`], as if the handlers were
+    /// [isolated](EvacBuilder::isolate_handlers), and the rest still run. Anything a handler finds
+    /// out about the current thread, such as its name or a backtrace, is about the crash thread,
+    /// though [routing by thread](EvacBuilder::only_on_threads) still goes by the panicking
+    /// thread. Handlers a thread adds for itself, with [`thread::with_handlers`], aren't `Send`,
+    /// so they run on the panicking thread, once the crash thread is done.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::{CrashThread, DeadlineAction, EvacBuilder};
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .crash_thread(CrashThread::new().timeout(Duration::from_secs(10), DeadlineAction::Abort))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn crash_thread(mut self, crash_thread: CrashThread) -> Self {
+        self.crash_thread = Some(crash_thread);
+
+        self
+    }
+
+    /// Sets aside `size` bytes on registration, which are freed as soon as a panic starts being
+    /// handled. If the panic came from running out of memory, this leaves the hook and handlers
+    /// room to allocate in while they build their reports. The memory is taken back once the
+    /// panic has been handled, if there's enough to spare by then.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .memory_reserve(512 * 1024)
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn memory_reserve(mut self, size: usize) -> Self {
+        self.memory_reserve = Some(size);
+
+        self
+    }
+
+    /// Only handles a random `rate` of panics, from `0.0` for none of them to `1.0` for all of
+    /// them. Panics that aren't handled still go to the preserved hook, if there is one, and to
+    /// [`EvacBuilder::on_suppressed`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   // Handle one in ten panics
+    ///   .sample_rate(0.1)
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = Some(rate);
+
+        self
+    }
+
+    /// Handles at most `max` panics within each `per`, such as for a service panicking in a hot
+    /// loop, which would otherwise send a report for every time around. Panics over the limit
+    /// are treated the same as ones [sampled out](EvacBuilder::sample_rate), which don't count
+    /// towards it.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .max_reports_per(5, Duration::from_secs(60))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn max_reports_per(mut self, max: u32, per: Duration) -> Self {
+        self.max_reports = Some((max, per));
+
+        self
+    }
+
+    /// Adds a callback for panics that aren't handled, due to
+    /// [sampling](EvacBuilder::sample_rate) or [rate limiting](EvacBuilder::max_reports_per).
+    /// It's given the context, so the panics can still be counted towards the next report.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::EvacBuilder;
+    /// #[derive(Default)]
+    /// struct Stats {
+    ///   suppressed: u64,
+    /// }
+    ///
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, stats: &mut Stats| {
+    ///     eprintln!("{} panic(s) weren't reported", stats.suppressed);
+    ///     stats.suppressed = 0;
+    ///     Ok(())
+    ///   })
+    ///   .max_reports_per(1, Duration::from_secs(60))
+    ///   .on_suppressed(|_, stats| stats.suppressed += 1)
+    ///   .register(Stats::default())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn on_suppressed<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&PanicHookInfo<'_>, &mut T) + Send + Sync + 'static,
+    {
+        self.on_suppressed.push(Box::new(callback));
+
+        self
+    }
+
+    /// Only handles a panic once within each `window`, going by its message and location. The
+    /// panics in between are treated the same as ones [sampled out](EvacBuilder::sample_rate).
+    /// Once the window is up, the panic is handled again the next time it happens, and
+    /// [`occurrences`] tells the handlers how many times it happened in the meantime. Up to 1024
+    /// different panics are kept track of at once, past which the one that's gone longest without
+    /// happening is forgotten.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| {
+    ///     eprintln!("uploading report, seen {} times", evac::occurrences().unwrap_or(1));
+    ///     Ok(())
+    ///   })
+    ///   .deduplicate(Duration::from_secs(300))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn deduplicate(mut self, window: Duration) -> Self {
+        self.dedup_window = Some(window);
+
+        self
+    }
+
+    /// Records each panic to a small state file, so that the process can tell when it keeps
+    /// crashing soon after being restarted. The file is read back on registration, after which
+    /// [`crash_loop::status`] says whether the process is in a crash loop, both at startup and
+    /// while handling a panic, which counts towards it.
+    ///
+    /// Pair it with [`EvacBuilder::in_crash_loop`] and [`EvacBuilder::outside_crash_loop`] to
+    /// switch to a degraded pipeline while crash looping.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::crash_loop::CrashLoop;
+    /// # use evac::EvacBuilder;
+    /// # let state = std::env::temp_dir().join("evac-crash-loop-example");
+    /// EvacBuilder::new()
+    ///   .crash_loop(CrashLoop::new(state).threshold(3, Duration::from_secs(30)))
+    ///   .outside_crash_loop(|normal| {
+    ///     normal.with_handler(|_, _: &mut ()| {
+    ///       eprintln!("uploading report");
+    ///       Ok(())
+    ///     })
+    ///   })
+    ///   .in_crash_loop(|degraded| {
+    ///     degraded.with_handler(|_, _: &mut ()| {
+    ///       eprintln!("crash looping, only writing the report locally");
+    ///       Ok(())
+    ///     })
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn crash_loop(mut self, crash_loop: CrashLoop) -> Self {
+        self.crash_loop = Some(crash_loop);
+
+        self
+    }
+
+    /// Writes a marker to the file at `path` as the very first thing on each panic, so that the
+    /// next run can tell the last one crashed, such as to offer to send a report, or restore the
+    /// session. The marker is read back on registration, after which
+    /// [`last_run::did_crash`] says what's known about the crash, until [`last_run::clear`]
+    /// deletes it, once the user has been asked.
+    ///
+    /// Every panic that reaches the hook is marked, whether or not it's handled, or caught
+    /// further up.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// # let marker = std::env::temp_dir().join("evac-crash-marker-example");
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .crash_marker(marker)
+    ///   .register(())?;
+    ///
+    /// if evac::last_run::did_crash().is_some() {
+    ///   eprintln!("Sorry, we crashed last time. Restoring your session...");
+    /// }
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn crash_marker(mut self, path: impl Into<PathBuf>) -> Self {
+        self.crash_marker = Some(path.into());
+
+        self
+    }
+
+    /// Counts each panic in a small store on disk, by the app's version and the report's
+    /// [fingerprint](PanicReport::fingerprint), so that [`stats::summary`] can say how often the
+    /// app has crashed lately, across runs, such as to turn off a feature that keeps crashing it.
+    ///
+    /// Panics are counted once they're fingerprinted, before the handlers run, so, unlike for
+    /// [crash loops](EvacBuilder::crash_loop), panics that are [sampled](EvacBuilder::sample_rate)
+    /// out, rate limited, or [duplicates](EvacBuilder::deduplicate) aren't.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::stats::CrashStats;
+    /// # use evac::EvacBuilder;
+    /// # let store = std::env::temp_dir().join("evac-crash-stats-example");
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .app_metadata(evac::app_metadata!())
+    ///   .crash_stats(CrashStats::new(store))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn crash_stats(mut self, stats: CrashStats) -> Self {
+        self.crash_stats = Some(stats);
+
+        self
+    }
+
+    /// Writes a heartbeat to a file every so often, from a thread of its own, and how the process
+    /// ended, once it panics or exits, so that the next run can tell, with
+    /// [`last_run::classify`], whether the last one exited cleanly, panicked, or was killed
+    /// without a word, such as by `SIGKILL` or the OOM killer, which no panic hook ever sees.
+    ///
+    /// How the last run ended is read back on registration. Exiting is marked by itself on Unix,
+    /// elsewhere it takes [`last_run::clean_exit`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::last_run::{Heartbeat, LastRun};
+    /// # use evac::EvacBuilder;
+    /// # let heartbeat = std::env::temp_dir().join("evac-heartbeat-example");
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .heartbeat(Heartbeat::new(heartbeat).interval(Duration::from_secs(5)))
+    ///   .register(())?;
+    ///
+    /// if let Some(LastRun::Killed { .. }) = evac::last_run::classify() {
+    ///   eprintln!("the last run was killed, reporting it");
+    /// }
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = Some(heartbeat);
+
+        self
+    }
+
+    /// Adds the handlers added by `group`, which only run while the process is in a
+    /// [crash loop](EvacBuilder::crash_loop). Everything else `group` sets is merged in, as per
+    /// [`EvacBuilder::merge`].
+    pub fn in_crash_loop<F>(self, group: F) -> Self
+    where
+        F: FnOnce(EvacBuilder<T, E>) -> EvacBuilder<T, E>,
+    {
+        self.grouped(Arc::new(|_| in_crash_loop()), group)
+    }
+
+    /// Adds the handlers added by `group`, which only run while the process isn't in a
+    /// [crash loop](EvacBuilder::crash_loop), as per [`EvacBuilder::in_crash_loop`].
+    pub fn outside_crash_loop<F>(self, group: F) -> Self
+    where
+        F: FnOnce(EvacBuilder<T, E>) -> EvacBuilder<T, E>,
+    {
+        self.grouped(Arc::new(|_| !in_crash_loop()), group)
+    }
+
+    /// Adds a panic handler. Handlers are executed in the order they are registered in, within
+    /// their [`Priority`]. They take a mutable reference to the context value so handlers can add
+    /// to the context as they execute, enabling efficient reuse of values.
+    ///
+    /// A context made up of several values can be a tuple, which handlers destructure in their
+    /// arguments. Each binding is then a mutable reference to its part of the context. Naming the
+    /// context type once, on the builder or the first handler, is enough for the rest to infer it.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use evac::EvacBuilder;
+    /// #[derive(Default)]
+    /// struct Metrics {
+    ///   panics: u64,
+    /// }
+    ///
+    /// struct Config {
+    ///   upload: bool,
+    /// }
+    ///
+    /// EvacBuilder::<(PathBuf, Metrics, Config)>::new()
+    ///   .with_handler(|_, (path, metrics, _)| {
+    ///     metrics.panics += 1;
+    ///     eprintln!("writing dump #{} to {}", metrics.panics, path.display());
+    ///     Ok(())
+    ///   })
+    ///   .with_handler(|_, (_, _, config)| {
+    ///     if config.upload {
+    ///       eprintln!("uploading dump");
+    ///     }
+    ///     Ok(())
+    ///   })
+    ///   .register((PathBuf::from("crash.dump"), Metrics::default(), Config { upload: false }))?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn with_handler<F>(self, handler: F) -> Self
+    where
+        F: Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        self.with_boxed_handler(Box::new(handler))
+    }
+
+    /// Adds a stateful panic handler. The handler keeps its captured state across panics, and is
+    /// locked while it runs so that panics on different threads take turns with it.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// let mut panics_seen = 0;
+    /// EvacBuilder::new()
+    ///   .with_mut_handler(move |_, _: &mut ()| {
+    ///     panics_seen += 1;
+    ///     eprintln!("{panics_seen} panic(s) so far");
+    ///     Ok(())
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn with_mut_handler<F>(self, handler: F) -> Self
+    where
+        F: FnMut(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + Send + 'static,
+    {
+        let handler = Mutex::new(handler);
+
+        self.with_handler(move |info, ctx| {
+            // A poisoned handler was interrupted by a panic on another thread, its state is still
+            // the best we have
+            let mut handler = handler.lock().unwrap_or_else(PoisonError::into_inner);
+
+            handler(info, ctx)
+        })
+    }
+
+    /// Adds a panic handler made of two, where `fallback` only runs if `primary` returns an error.
+    /// Both are given the same context, so anything `primary` got done is there for `fallback` to
+    /// pick up. If `fallback` succeeds, `primary`'s error is dropped; otherwise `fallback`'s error
+    /// is the one reported.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::fs;
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_handler(|info, report: &mut String| {
+    ///     *report = info.to_string();
+    ///     Ok(())
+    ///   })
+    ///   .with_handler_or(
+    ///     |_, _| {
+    ///       // Stand-in for an HTTP request
+    ///       Err("no network".into())
+    ///     },
+    ///     |_, report| {
+    ///       fs::write(std::env::temp_dir().join("crash-report.txt"), report)?;
+    ///       Ok(())
+    ///     },
+    ///   )
+    ///   .register(String::new())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn with_handler_or<F, G>(self, primary: F, fallback: G) -> Self
+    where
+        F: Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + Send + Sync + 'static,
+        G: Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        self.with_handler(move |info, ctx| primary(info, ctx).or_else(|_| fallback(info, ctx)))
+    }
+
+    /// Adds a panic handler that only runs if `predicate` returns `true` for the panic. Use it to
+    /// leave out an expensive handler for panics that are expected now and then, while the other
+    /// handlers still run. A handler that's left out isn't part of the [`PipelineSummary`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_handler(|info, _: &mut ()| {
+    ///     eprintln!("logging {info}");
+    ///     Ok(())
+    ///   })
+    ///   .with_handler_if(
+    ///     |info, _| !info.to_string().contains("assertion"),
+    ///     |_, _| {
+    ///       eprintln!("writing minidump");
+    ///       Ok(())
+    ///     },
+    ///   )
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn with_handler_if<P, F>(mut self, predicate: P, handler: F) -> Self
+    where
+        P: Fn(&PanicHookInfo<'_>, &T) -> bool + Send + Sync + 'static,
+        F: Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        let mut entry = HandlerEntry::new(
+            None,
+            Priority::Normal,
+            HandlerKind::Single(Box::new(handler)),
+        );
+        entry.conditions.push(Box::new(predicate));
+        self.handlers.push(entry);
+
+        self
+    }
+
+    /// Adds a panic handler that only ever runs once, for the first panic it's given, such as for
+    /// triggering a failover. It's skipped for every panic after that, even if several threads
+    /// panic at the same time, or panics keep coming after being caught.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_handler_once(|_, _: &mut ()| {
+    ///     eprintln!("failing over to the standby");
+    ///     Ok(())
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn with_handler_once<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        let mut entry = HandlerEntry::new(
+            None,
+            Priority::Normal,
+            HandlerKind::Single(Box::new(handler)),
+        );
+        entry.once = Some(AtomicBool::new(false));
+        self.handlers.push(entry);
+
+        self
+    }
+
+    /// Adds a panic handler that's given a [`PanicReport`] instead of the bare panic info. The
+    /// report is put together once per panic, and handed from one of these handlers to the next,
+    /// so that notes added by one can be picked up by the ones after it.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_report_handler(|report, _: &mut ()| {
+    ///     report.annotate("build", env!("CARGO_PKG_VERSION"));
+    ///     Ok(())
+    ///   })
+    ///   .with_report_handler(|report, _| {
+    ///     let thread = report.thread_name().unwrap_or("<unnamed>");
+    ///     eprintln!("thread '{thread}' panicked: {:?}", report.message());
+    ///     for (key, value) in report.annotations() {
+    ///       eprintln!("  {key}: {value}");
+    ///     }
+    ///     Ok(())
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn with_report_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&mut PanicReport<'_>, &mut T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        self.handlers.push(HandlerEntry::new(
+            None,
+            Priority::Normal,
+            HandlerKind::Report(Box::new(handler)),
+        ));
+
+        self
+    }
+
+    /// Adds an already boxed [`PanicHandler`]. Behaves the same as [`EvacBuilder::with_handler`].
+    pub fn with_boxed_handler(mut self, handler: PanicHandler<T, E>) -> Self {
+        self.handlers.push(HandlerEntry::new(
+            None,
+            Priority::Normal,
+            HandlerKind::Single(handler),
+        ));
+
+        self
+    }
+
+    /// Adds a panic handler that runs at the given [`Priority`] rather than
+    /// [`Priority::Normal`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{EvacBuilder, Priority};
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| {
+    ///     eprintln!("runs second");
+    ///     Ok(())
+    ///   })
+    ///   .with_handler_at(Priority::Critical, |_, _| {
+    ///     eprintln!("runs first");
+    ///     Ok(())
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn with_handler_at<F>(mut self, priority: Priority, handler: F) -> Self
+    where
+        F: Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        self.handlers.push(HandlerEntry::new(
+            None,
+            priority,
+            HandlerKind::Single(Box::new(handler)),
+        ));
+
+        self
+    }
+
+    /// Adds a panic handler that can later be referred to by name, see
+    /// [`EvacBuilder::remove_handler`] and [`EvacBuilder::replace_handler`]. Names are unique; if a
+    /// handler by this name already exists, it is replaced in place.
+    pub fn with_named_handler<F>(mut self, name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        let name = name.into();
+
+        match self.position(&name) {
+            Some(idx) => self.handlers[idx].handler = HandlerKind::Single(Box::new(handler)),
+            None => self.handlers.push(HandlerEntry::new(
+                Some(name),
+                Priority::Normal,
+                HandlerKind::Single(Box::new(handler)),
+            )),
         }
+
+        self
     }
 
-    /// Turns back on the default panic hook that ships with Rust.
-    pub fn preserve_default_panic(mut self) -> Self {
-        self.preserve_default = true;
+    /// Adds a report handler that can later be referred to by name, as with
+    /// [`EvacBuilder::with_named_handler`]. Behaves the same as
+    /// [`EvacBuilder::with_report_handler`] otherwise.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{handlers, EvacBuilder, TextFormatter};
+    /// EvacBuilder::new()
+    ///   .with_named_report_handler("stderr", handlers::stderr(TextFormatter::compact()))
+    ///   .skip_if_message_matches("stderr", ["broken pipe"])?
+    ///   .register(())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn with_named_report_handler<F>(mut self, name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&mut PanicReport<'_>, &mut T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        let name = name.into();
+
+        match self.position(&name) {
+            Some(idx) => self.handlers[idx].handler = HandlerKind::Report(Box::new(handler)),
+            None => self.handlers.push(HandlerEntry::new(
+                Some(name),
+                Priority::Normal,
+                HandlerKind::Report(Box::new(handler)),
+            )),
+        }
 
         self
     }
 
-    /// Adds a panic handler. Handlers are executed in the order they are registered in. They take
-    /// a mutable reference to the context value so handlers can add to the context as they execute,
-    /// enabling efficient reuse of values.
-    pub fn with_handler(mut self, handler: PanicHandler<T>) -> Self {
-        self.handlers.push(handler);
+    /// Removes the handler registered under `name`, if there is one.
+    pub fn remove_handler(mut self, name: &str) -> Self {
+        if let Some(idx) = self.position(name) {
+            self.handlers.remove(idx);
+        }
 
         self
     }
 
-    /// Assembles and registers the supplied panic handlers as a serial panic handler.
-    pub fn register(self, ctx: T) {
-        let Self {
-            handlers,
-            preserve_default,
-        } = self;
+    /// Swaps out the handler registered under `name`, keeping its place and priority. If
+    /// no handler has that name, nothing is changed.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// // Set up by a library
+    /// let builder = EvacBuilder::new()
+    ///   .with_named_handler("report", |_, _: &mut ()| {
+    ///     eprintln!("uploading report");
+    ///     Ok(())
+    ///   });
+    ///
+    /// // Overridden by the application
+    /// builder
+    ///   .replace_handler("report", |_, _| {
+    ///     eprintln!("writing report to disk");
+    ///     Ok(())
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn replace_handler<F>(mut self, name: &str, handler: F) -> Self
+    where
+        F: Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        if let Some(idx) = self.position(name) {
+            self.handlers[idx].handler = HandlerKind::Single(Box::new(handler));
+        }
+
+        self
+    }
+
+    /// Sets what happens once a handler fails, see [`ErrorPolicy`]. By default, the remaining
+    /// handlers run regardless.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{ErrorPolicy, EvacBuilder};
+    /// EvacBuilder::new()
+    ///   .with_named_handler("write-minidump", |_, _: &mut ()| Err("disk full".into()))
+    ///   .with_named_handler("upload-minidump", |_, _| {
+    ///     eprintln!("never runs, there's nothing to upload");
+    ///     Ok(())
+    ///   })
+    ///   .error_policy(ErrorPolicy::StopChain)
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = Some(policy);
+
+        self
+    }
+
+    /// Overrides the [`ErrorPolicy`] for the handler registered under `name`, for when only some
+    /// failures should stop the others. If no handler has that name, nothing is changed.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{ErrorPolicy, EvacBuilder};
+    /// EvacBuilder::new()
+    ///   .with_named_handler("flush-logs", |_, _: &mut ()| Err("log server is down".into()))
+    ///   .with_named_handler("write-minidump", |_, _| Ok(()))
+    ///   .with_named_handler("upload-minidump", |_, _| Ok(()))
+    ///   // Uploading a broken minidump would be worse than not uploading one
+    ///   .handler_error_policy("write-minidump", ErrorPolicy::StopChain)
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn handler_error_policy(mut self, name: &str, policy: ErrorPolicy) -> Self {
+        if let Some(idx) = self.position(name) {
+            self.handlers[idx].on_error = Some(policy);
+        }
+
+        self
+    }
+
+    /// Marks the handler registered under `name` as [`Criticality::Critical`] or otherwise. If no
+    /// handler has that name, nothing is changed.
+    ///
+    /// This is separate from [`Priority`], which only decides when handlers run; a
+    /// [`Priority::Low`] handler can still be critical.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{Criticality, ErrorPolicy, EvacBuilder};
+    /// EvacBuilder::new()
+    ///   .with_named_handler("flush-wal", |_, _: &mut ()| Ok(()))
+    ///   .with_named_handler("push-metrics", |_, _| Err("no network".into()))
+    ///   .handler_criticality("flush-wal", Criticality::Critical)
+    ///   // Let the supervisor know something was lost
+    ///   .critical_error_policy(ErrorPolicy::Exit(70))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn handler_criticality(mut self, name: &str, criticality: Criticality) -> Self {
+        if let Some(idx) = self.position(name) {
+            self.handlers[idx].criticality = criticality;
+        }
+
+        self
+    }
+
+    /// Runs the handler registered under `name` at the given [`Priority`], keeping its place
+    /// among the handlers of that priority. If no handler has that name, nothing is changed.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{EvacBuilder, Priority};
+    /// EvacBuilder::new()
+    ///   .with_named_handler("upload", |_, _: &mut ()| Ok(()))
+    ///   .with_named_handler("flush-wal", |_, _| Ok(()))
+    ///   .handler_priority("flush-wal", Priority::Critical)
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn handler_priority(mut self, name: &str, priority: Priority) -> Self {
+        if let Some(idx) = self.position(name) {
+            self.handlers[idx].priority = priority;
+        }
+
+        self
+    }
+
+    /// Sets what happens once a [`Criticality::Critical`] handler fails, in place of
+    /// [`EvacBuilder::error_policy`]. By default, the process is aborted. Handlers with their own
+    /// [`EvacBuilder::handler_error_policy`] keep it regardless.
+    pub fn critical_error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.critical_policy = Some(policy);
+
+        self
+    }
 
-        // If we're preserving the default, pop it for use in our hook
-        let default_hook = match preserve_default {
-            true => Some(std::panic::take_hook()),
-            false => None,
+    /// Runs each handler on a helper thread, so that a handler that panics is reported as a
+    /// [`HandlerError::Panicked`] rather than aborting the process, and the rest of the handlers
+    /// still run. A panicking handler counts as a failed one for its [`ErrorPolicy`], but isn't
+    /// passed to [`EvacBuilder::on_handler_error`] callbacks, as there's no error to give them.
+    ///
+    /// Handlers are still run one at a time, with the panicking thread waiting on each. Anything
+    /// tied to the current thread, such as [`std::thread::current`], thread locals, or a captured
+    /// [`Backtrace`](std::backtrace::Backtrace), refers to the helper thread instead. If a helper
+    /// can't be spawned, the handler is run on the panicking thread as usual.
+    ///
+    /// A handler that panics may leave the context half updated. Later handlers, and later
+    /// panics, are still given it as is.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, reports: &mut Vec<String>| {
+    ///     let _ = &reports[3];
+    ///     Ok(())
+    ///   })
+    ///   .with_handler(|_, _| {
+    ///     eprintln!("still runs");
+    ///     Ok(())
+    ///   })
+    ///   .isolate_handlers()
+    ///   .register(vec![])?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn isolate_handlers(mut self) -> Self {
+        self.isolate = true;
+
+        self
+    }
+
+    /// Stops the hook from honoring evac's environment variables, for deployments where whoever
+    /// sets the process's environment mustn't be able to turn crash reporting off. Otherwise,
+    /// they're read each time a panic is handled:
+    ///
+    /// - `EVAC_DISABLE=1` skips the handlers, finalizers and callbacks. The preserved hook still
+    ///   runs, and the process still ends as per [`EvacBuilder::after_panic`].
+    /// - `EVAC_ONLY=file,stderr` only runs the handlers with those names.
+    /// - `EVAC_VERBOSE=1` prints how each handler went to `stderr`.
+    ///
+    /// Flags count as set unless they're empty, `0` or `false`.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .ignore_environment()
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn ignore_environment(mut self) -> Self {
+        self.ignore_environment = true;
+
+        self
+    }
+
+    /// Captures a backtrace as soon as the hook is entered, for the handlers to find on the
+    /// [`PanicReport`], see [`PanicReport::backtrace`]. It's only captured for pipelines with
+    /// [report handlers](EvacBuilder::with_report_handler), and, unless `mode` is
+    /// [`BacktraceMode::Forced`], only if `RUST_BACKTRACE` asks for one.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{BacktraceMode, EvacBuilder};
+    /// EvacBuilder::new()
+    ///   .with_report_handler(|report, _: &mut ()| {
+    ///     if let Some(backtrace) = report.backtrace() {
+    ///       eprintln!("{backtrace}");
+    ///     }
+    ///     Ok(())
+    ///   })
+    ///   .capture_backtrace(BacktraceMode::Forced)
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn capture_backtrace(mut self, mode: BacktraceMode) -> Self {
+        self.backtrace = Some(mode);
+
+        self
+    }
+
+    /// Attaches what was built, and how, to every [`PanicReport`], see
+    /// [`PanicReport::app_metadata`]. Usually made with [`app_metadata!`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_report_handler(|report, _: &mut ()| {
+    ///     let app = report.app_metadata().expect("attached below");
+    ///     eprintln!("{} {} crashed", app.name, app.version);
+    ///     Ok(())
+    ///   })
+    ///   .app_metadata(evac::app_metadata!())
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn app_metadata(mut self, metadata: AppMetadata) -> Self {
+        self.app_metadata = Some(metadata);
+
+        self
+    }
+
+    /// Captures the process's environment variables once a panic is being handled, for the
+    /// handlers to find on the [`PanicReport`], see [`PanicReport::env_vars`]. Which are captured,
+    /// and which have their values hidden, is up to `capture`. It's only done for pipelines with
+    /// [report handlers](EvacBuilder::with_report_handler).
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{EnvCapture, EvacBuilder};
+    /// EvacBuilder::new()
+    ///   .with_report_handler(|report, _: &mut ()| {
+    ///     for (name, value) in report.env_vars() {
+    ///       eprintln!("{name}={value}");
+    ///     }
+    ///     Ok(())
+    ///   })
+    ///   .capture_env(EnvCapture::new().allow("APP_*"))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn capture_env(mut self, capture: EnvCapture) -> Self {
+        self.env_capture = Some(capture);
+
+        self
+    }
+
+    /// Captures the process's command-line arguments once a panic is being handled, for the
+    /// handlers to find on the [`PanicReport`], see [`PanicReport::args`]. Which have their values
+    /// hidden is up to `capture`. It's only done for pipelines with
+    /// [report handlers](EvacBuilder::with_report_handler).
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{ArgsCapture, EvacBuilder};
+    /// EvacBuilder::new()
+    ///   .with_report_handler(|report, _: &mut ()| {
+    ///     eprintln!("invoked as: {}", report.args().join(" "));
+    ///     Ok(())
+    ///   })
+    ///   .capture_args(ArgsCapture::new().redact_after("--db-url"))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn capture_args(mut self, capture: ArgsCapture) -> Self {
+        self.args_capture = Some(capture);
+
+        self
+    }
+
+    /// Traces every thread in the process as soon as the hook is entered, for the handlers to find
+    /// on the [`PanicReport`], see [`PanicReport::threads`]. Handy when the panic is only the end
+    /// of the story, such as after a deadlock was given up on. It's only done for pipelines with
+    /// [report handlers](EvacBuilder::with_report_handler).
+    ///
+    /// Each thread is interrupted with a signal, `SIGRTMAX - 1`, to trace itself, and is given up
+    /// on if it doesn't within 50ms, even part way through. If the application already handles
+    /// that signal, its handler is left alone, and no threads are traced. Only supported on Linux
+    /// with glibc; elsewhere, no threads are traced.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_report_handler(|report, _: &mut ()| {
+    ///     for thread in report.threads() {
+    ///       eprintln!("{thread}");
+    ///     }
+    ///     Ok(())
+    ///   })
+    ///   .capture_all_threads()
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    #[cfg(feature = "all-threads")]
+    pub fn capture_all_threads(mut self) -> Self {
+        self.all_threads = true;
+
+        self
+    }
+
+    /// Looks up facts about the host once a panic is being handled, for the handlers to find on the
+    /// [`PanicReport`], see [`PanicReport::system_info`]. The lookups stop once `budget` is up,
+    /// leaving out whatever wasn't got to, see [`SystemInfo::gather`](sysinfo::SystemInfo::gather).
+    /// It's only done for pipelines with [report handlers](EvacBuilder::with_report_handler).
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_report_handler(|report, _: &mut ()| {
+    ///     if let Some(host) = report.system_info() {
+    ///       eprintln!("running on {:?} {:?}", host.os_name, host.os_version);
+    ///     }
+    ///     Ok(())
+    ///   })
+    ///   .capture_system_info(Duration::from_millis(20))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    #[cfg(feature = "sysinfo")]
+    pub fn capture_system_info(mut self, budget: Duration) -> Self {
+        self.system_info = Some(budget);
+
+        self
+    }
+
+    /// Gives each [`PanicReport`] a [fingerprint](PanicReport::fingerprint) as per `grouping`,
+    /// once it's been put together, and before it's [scrubbed](EvacBuilder::scrub) or
+    /// [cut down](EvacBuilder::report_limits), so that neither changes which panics are grouped
+    /// together. Without this, they're grouped as per [`Grouping::new`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{handlers, EvacBuilder, Grouping, TextFormatter};
+    /// EvacBuilder::new()
+    ///   .with_report_handler(handlers::stderr(TextFormatter::new()))
+    ///   .grouping(Grouping::new().in_app("my_app::").ignore_location())
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn grouping(mut self, grouping: Grouping) -> Self {
+        self.grouping = Some(grouping);
+
+        self
+    }
+
+    /// Scrubs each [`PanicReport`] with `scrubber` once it's been put together, before any of the
+    /// [report handlers](EvacBuilder::with_report_handler) see it. What the handlers add to it
+    /// themselves, such as annotations, isn't scrubbed, so handlers that send reports anywhere
+    /// should come after the ones that add to them, and scrub it themselves.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{handlers, EvacBuilder, Scrubber, TextFormatter};
+    /// EvacBuilder::new()
+    ///   .with_report_handler(handlers::stderr(TextFormatter::new()))
+    ///   .capture_env(evac::EnvCapture::new())
+    ///   .scrub(Scrubber::new().rule(r"\b\d{13,16}\b", "[card]")?)
+    ///   .register(())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn scrub(mut self, scrubber: Scrubber) -> Self {
+        self.scrubber = Some(scrubber);
+
+        self
+    }
+
+    /// Cuts each [`PanicReport`] down to `limits` once it's been put together, and
+    /// [scrubbed](EvacBuilder::scrub), before any of the
+    /// [report handlers](EvacBuilder::with_report_handler) see it, so that none of them can be
+    /// handed something unreasonably big.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{handlers, EvacBuilder, ReportLimits, TextFormatter};
+    /// EvacBuilder::new()
+    ///   .with_report_handler(handlers::stderr(TextFormatter::new()))
+    ///   .report_limits(ReportLimits::new().max_message_len(64 * 1024).max_frames(200))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn report_limits(mut self, limits: ReportLimits) -> Self {
+        self.limits = Some(limits);
+
+        self
+    }
+
+    /// Retries the handler registered under `name` when it fails, as per `retry`. Only its last
+    /// error is reported, and it only counts as failed, for its [`ErrorPolicy`] and in the
+    /// [`PipelineSummary`], once it's out of retries. Panics aren't retried. If no handler has that
+    /// name, nothing is changed.
+    ///
+    /// The panicking thread waits out each backoff, and other panics wait on it as per
+    /// [`EvacBuilder::on_contention`], so keep them short.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::{EvacBuilder, Retry};
+    /// EvacBuilder::new()
+    ///   .with_named_handler("upload", |_, _: &mut ()| {
+    ///     // Stand-in for an HTTP request
+    ///     Err("connection reset".into())
+    ///   })
+    ///   .handler_retry("upload", Retry::retries(2).backoff(Duration::from_millis(250)))
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn handler_retry(mut self, name: &str, retry: Retry) -> Self {
+        if let Some(idx) = self.position(name) {
+            self.handlers[idx].retry = retry;
+        }
+
+        self
+    }
+
+    /// Only runs the handler registered under `name` for panics whose message has a match for one
+    /// of `patterns` in it. Panics without a string message never match.
+    ///
+    /// The patterns are regular expressions, of the same syntax as
+    /// [`Scrubber::rule`](crate::Scrubber::rule)'s, that can match anywhere in the message, so
+    /// plain text matches as a substring. Characters such as `.` and `(` have to be escaped with
+    /// a `\` to be taken as they are.
+    ///
+    /// ## Errors
+    /// Fails if there's no such handler yet, or one of the patterns isn't supported.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_named_handler("page-on-call", |_, _: &mut ()| Ok(()))
+    ///   .only_if_message_matches("page-on-call", ["data corruption", r"invariant #\d+"])?
+    ///   .register(())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn only_if_message_matches<I>(self, name: &str, patterns: I) -> Result<Self, FilterError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.message_filter(name, patterns, true)
+    }
+
+    /// Skips the handler registered under `name` for panics whose message has a match for one of
+    /// `patterns` in it, such as known-noisy panics from a dependency that aren't worth uploading.
+    /// The patterns are as per [`EvacBuilder::only_if_message_matches`].
+    ///
+    /// ## Errors
+    /// Fails if there's no such handler yet, or one of the patterns isn't supported.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_named_handler("upload", |_, _: &mut ()| Ok(()))
+    ///   .skip_if_message_matches("upload", ["connection reset by peer"])?
+    ///   .register(())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn skip_if_message_matches<I>(self, name: &str, patterns: I) -> Result<Self, FilterError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.message_filter(name, patterns, false)
+    }
+
+    /// Only runs the handler registered under `name` for panics whose message matching one of
+    /// `patterns` is `matching`.
+    fn message_filter<I>(
+        mut self,
+        name: &str,
+        patterns: I,
+        matching: bool,
+    ) -> Result<Self, FilterError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let patterns = patterns
+            .into_iter()
+            .map(|pattern| Regex::new(pattern.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let Some(idx) = self.position(name) else {
+            return Err(FilterError::UnknownHandler {
+                name: name.to_string(),
+            });
         };
 
-        // Make the context Send + Sync; it'll be immutable until a panic occurs anyways
-        let mut ctx = Arc::new(ctx);
+        self.handlers[idx].conditions.push(Box::new(move |info, _| {
+            filter::message_matches(info, &patterns) == matching
+        }));
+
+        Ok(self)
+    }
+
+    /// Adds the handlers added by `group`, which only run for panics in a file matching `pattern`.
+    /// The pattern is a prefix of the path, or a glob over the whole path if it has a `*` or `?`
+    /// in it. Paths are as the compiler saw them, so panics in dependencies are usually under
+    /// Cargo's registry, such as `*/.cargo/registry/*`.
+    ///
+    /// Everything else `group` sets is merged in, as per [`EvacBuilder::merge`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .only_from("src/engine/", |engine| {
+    ///     engine.with_handler(|_, _: &mut ()| {
+    ///       eprintln!("dumping engine state");
+    ///       Ok(())
+    ///     })
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn only_from<F>(self, pattern: impl Into<String>, group: F) -> Self
+    where
+        F: FnOnce(EvacBuilder<T, E>) -> EvacBuilder<T, E>,
+    {
+        let pattern = pattern.into();
+
+        self.grouped(
+            Arc::new(move |info| filter::location_matches(info, &pattern)),
+            group,
+        )
+    }
+
+    /// Adds the handlers added by `group`, which only run for panics in a file that doesn't match
+    /// `pattern`, as per [`EvacBuilder::only_from`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .skip_from("vendor/", |ours| {
+    ///     ours.with_handler(|_, _: &mut ()| {
+    ///       eprintln!("uploading report");
+    ///       Ok(())
+    ///     })
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn skip_from<F>(self, pattern: impl Into<String>, group: F) -> Self
+    where
+        F: FnOnce(EvacBuilder<T, E>) -> EvacBuilder<T, E>,
+    {
+        let pattern = pattern.into();
+
+        self.grouped(
+            Arc::new(move |info| !filter::location_matches(info, &pattern)),
+            group,
+        )
+    }
+
+    /// Adds the handlers added by `group`, which only run for panics on a thread whose name
+    /// matches `pattern`. The pattern is a glob, in which `*` matches any run of characters and
+    /// `?` matches any one. Threads without a name never match.
+    ///
+    /// Everything else `group` sets is merged in, as per [`EvacBuilder::merge`].
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .only_on_threads("main", |main| {
+    ///     main.with_handler(|_, _: &mut ()| {
+    ///       eprintln!("writing full dump and uploading it");
+    ///       Ok(())
+    ///     })
+    ///   })
+    ///   .only_on_threads("worker-*", |workers| {
+    ///     workers.with_handler(|_, _: &mut ()| {
+    ///       eprintln!("writing compact record");
+    ///       Ok(())
+    ///     })
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn only_on_threads<F>(self, pattern: impl Into<String>, group: F) -> Self
+    where
+        F: FnOnce(EvacBuilder<T, E>) -> EvacBuilder<T, E>,
+    {
+        let pattern = pattern.into();
+
+        self.grouped(Arc::new(move |_| filter::thread_matches(&pattern)), group)
+    }
 
-        // Register our hook
-        std::panic::set_hook(Box::new(move |info: &PanicInfo| {
-            // Turn our Arc Context into a mutable reference.
-            let ctx = Arc::get_mut(&mut ctx).unwrap();
+    /// Adds the handlers added by `group`, which only run for panics on a thread whose name
+    /// doesn't match `pattern`, as per [`EvacBuilder::only_on_threads`]. Threads without a name
+    /// never match, so their panics are handled.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .skip_on_threads("worker-*", |control| {
+    ///     control.with_handler(|_, _: &mut ()| {
+    ///       eprintln!("uploading report");
+    ///       Ok(())
+    ///     })
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn skip_on_threads<F>(self, pattern: impl Into<String>, group: F) -> Self
+    where
+        F: FnOnce(EvacBuilder<T, E>) -> EvacBuilder<T, E>,
+    {
+        let pattern = pattern.into();
 
-            // If we popped the default hook, run it now
-            if let Some(hook) = default_hook {
-                hook(info);
+        self.grouped(Arc::new(move |_| !filter::thread_matches(&pattern)), group)
+    }
+
+    /// Merges in the builder made by `group`, with each of its handlers only running if
+    /// `condition` passes.
+    fn grouped<F>(mut self, condition: Arc<GroupCondition>, group: F) -> Self
+    where
+        F: FnOnce(EvacBuilder<T, E>) -> EvacBuilder<T, E>,
+    {
+        let mut grouped = group(EvacBuilder::default());
+        for entry in &mut grouped.handlers {
+            let condition = Arc::clone(&condition);
+            entry
+                .conditions
+                .push(Box::new(move |info, _| condition(info)));
+        }
+        self.absorb(grouped);
+
+        self
+    }
+
+    /// Adds a finalizer, which runs once the handlers are done, whether or not they succeeded. Use
+    /// it to wrap up after them, such as closing a dump file or sending a "report complete"
+    /// marker. Finalizers are given the context, and a [`PipelineSummary`] of how the handlers
+    /// fared, and run in the order they were added.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_named_handler("upload", |_, _: &mut ()| Err("no network".into()))
+    ///   .with_finalizer(|_, summary| {
+    ///     for failed in summary.failed() {
+    ///       eprintln!("{} failed", failed.name.unwrap_or("a handler"));
+    ///     }
+    ///     Ok(())
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn with_finalizer<F>(mut self, finalizer: F) -> Self
+    where
+        F: Fn(&mut T, &PipelineSummary<'_>) -> Result<(), E> + Send + Sync + 'static,
+    {
+        self.finalizers.push(Box::new(finalizer));
+
+        self
+    }
+
+    /// Sends errors encountered while handling a panic to `sink`, instead of printing them to
+    /// `stderr`. Use it to route them to a logger, or `|_| {}` to deliberately ignore them.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{EvacBuilder, HandlerError};
+    /// EvacBuilder::new()
+    ///   .with_named_handler("upload", |_, _: &mut ()| Err("no network".into()))
+    ///   .error_sink(|err: &HandlerError| {
+    ///     // Stand-in for a logging call
+    ///     println!("[crash-reporting] {err}");
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn error_sink<F>(mut self, sink: F) -> Self
+    where
+        F: Fn(&HandlerError<'_, E>) + Send + Sync + 'static,
+    {
+        self.error_sink = Some(Box::new(sink));
+
+        self
+    }
+
+    /// Writes errors encountered while handling a panic to `writer`, one per line, instead of
+    /// printing them to `stderr`. Failures to write are ignored.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::fs::File;
+    /// # use evac::EvacBuilder;
+    /// let log = File::create(std::env::temp_dir().join("evac-errors.log"))?;
+    ///
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .error_writer(log)
+    ///   .register(())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn error_writer<W>(self, writer: W) -> Self
+    where
+        W: Write + Send + 'static,
+    {
+        let writer = Mutex::new(writer);
+
+        self.error_sink(move |err| {
+            let mut writer = writer.lock().unwrap_or_else(PoisonError::into_inner);
+            let _ = writeln!(writer, "{err}");
+            let _ = writer.flush();
+        })
+    }
+
+    /// Adds a callback that's told about each handler that fails, by its name (if it was added as
+    /// a named handler), its position in the order the handlers ran in, and the error it returned.
+    /// Use it to react programmatically, such as by incrementing a metric. The error is still sent
+    /// to the error sink as well; set a silent [`EvacBuilder::error_sink`] to only use callbacks.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::sync::atomic::{AtomicU64, Ordering};
+    /// # use evac::EvacBuilder;
+    /// static UPLOAD_FAILURES: AtomicU64 = AtomicU64::new(0);
+    ///
+    /// EvacBuilder::new()
+    ///   .with_named_handler("upload", |_, _: &mut ()| Err("no network".into()))
+    ///   .on_handler_error(|name, _, _| {
+    ///     if name == Some("upload") {
+    ///       UPLOAD_FAILURES.fetch_add(1, Ordering::Relaxed);
+    ///     }
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn on_handler_error<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(Option<&str>, usize, &E) + Send + Sync + 'static,
+    {
+        self.on_handler_error.push(Box::new(callback));
+
+        self
+    }
+
+    /// Adds a callback that's given the [`PipelineSummary`] once the handlers and finalizers are
+    /// done, for reporting on the whole pipeline at once. Callbacks run in the order they were
+    /// added, and don't run if the handlers were skipped.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{EvacBuilder, HandlerError};
+    /// EvacBuilder::new()
+    ///   .with_named_handler("write-report", |_, _: &mut ()| Ok(()))
+    ///   .with_named_handler("upload", |_, _| Err("no network".into()))
+    ///   // Leave the reporting to the summary
+    ///   .error_sink(|_: &HandlerError| {})
+    ///   .on_complete(|summary| {
+    ///     let failures: Vec<_> = summary
+    ///       .failed()
+    ///       .map(|failed| {
+    ///         let name = failed.name.unwrap_or("a handler");
+    ///         format!("{name} failed: {}", failed.error.as_deref().unwrap_or_default())
+    ///       })
+    ///       .collect();
+    ///
+    ///     eprintln!(
+    ///       "{} of {} handlers succeeded in {:?}; {}",
+    ///       summary.succeeded().count(),
+    ///       summary.ran(),
+    ///       summary.total_duration(),
+    ///       failures.join(", "),
+    ///     );
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn on_complete<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&PipelineSummary<'_>) + Send + Sync + 'static,
+    {
+        self.on_complete.push(Box::new(callback));
+
+        self
+    }
+
+    /// Combines the handlers of two builders. `other`'s handlers run after this builder's within
+    /// each [`Priority`], except for named handlers that this builder already has, which are
+    /// replaced in place as per [`EvacBuilder::with_named_handler`]. The existing panic hook is
+    /// preserved if either builder preserves it, at this builder's [`Position`] if both do, and
+    /// likewise for [snapshotting](EvacBuilder::snapshot_context) the context and
+    /// [isolating](EvacBuilder::isolate_handlers) the handlers. `other`'s finalizers and
+    /// callbacks run after this builder's. This builder's error sink,
+    /// [deadline](EvacBuilder::deadline) and [`AfterPanic`] are kept if it has them. `other`'s
+    /// [`ErrorPolicy`]s still apply to its own handlers, unless they override them.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// fn from_logging_crate() -> EvacBuilder<()> {
+    ///   EvacBuilder::new().with_named_handler("flush-logs", |_, _| Ok(()))
+    /// }
+    ///
+    /// fn from_metrics_crate() -> EvacBuilder<()> {
+    ///   EvacBuilder::new().with_named_handler("push-metrics", |_, _| Ok(()))
+    /// }
+    ///
+    /// from_logging_crate()
+    ///   .merge(from_metrics_crate())
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn merge(mut self, other: EvacBuilder<T, E>) -> Self {
+        self.absorb(other);
+
+        self
+    }
+
+    fn absorb(&mut self, other: EvacBuilder<T, E>) {
+        self.existing_hook = self.existing_hook.or(other.existing_hook);
+        self.snapshot = self.snapshot.or(other.snapshot);
+        self.deadline = self.deadline.or(other.deadline);
+        self.after_panic = self.after_panic.or(other.after_panic);
+        self.crash_thread = self.crash_thread.or(other.crash_thread);
+        self.memory_reserve = self.memory_reserve.or(other.memory_reserve);
+        self.sample_rate = self.sample_rate.or(other.sample_rate);
+        self.max_reports = self.max_reports.or(other.max_reports);
+        self.on_suppressed.extend(other.on_suppressed);
+        self.dedup_window = self.dedup_window.or(other.dedup_window);
+        self.crash_loop = self.crash_loop.take().or(other.crash_loop);
+        self.crash_marker = self.crash_marker.take().or(other.crash_marker);
+        self.crash_stats = self.crash_stats.take().or(other.crash_stats);
+        self.heartbeat = self.heartbeat.take().or(other.heartbeat);
+        self.isolate |= other.isolate;
+        self.ignore_environment |= other.ignore_environment;
+        self.backtrace = self.backtrace.or(other.backtrace);
+        self.app_metadata = self.app_metadata.or(other.app_metadata);
+        self.env_capture = self.env_capture.take().or(other.env_capture);
+        self.args_capture = self.args_capture.take().or(other.args_capture);
+        #[cfg(feature = "all-threads")]
+        {
+            self.all_threads |= other.all_threads;
+        }
+        #[cfg(feature = "sysinfo")]
+        {
+            self.system_info = self.system_info.or(other.system_info);
+        }
+        self.grouping = self.grouping.take().or(other.grouping);
+        self.scrubber = self.scrubber.take().or(other.scrubber);
+        self.limits = self.limits.or(other.limits);
+        self.finalizers.extend(other.finalizers);
+        self.error_sink = self.error_sink.take().or(other.error_sink);
+        self.on_handler_error.extend(other.on_handler_error);
+        self.on_complete.extend(other.on_complete);
+
+        for mut entry in other.handlers {
+            let inherited = match entry.criticality {
+                Criticality::BestEffort => other.error_policy,
+                Criticality::Critical => other.critical_policy,
+            };
+            entry.on_error = entry.on_error.or(inherited);
+
+            match entry.name.as_deref().and_then(|name| self.position(name)) {
+                Some(idx) => self.handlers[idx] = entry,
+                None => self.handlers.push(entry),
             }
+        }
+    }
+
+    /// Finds the index of the handler registered under `name`.
+    fn position(&self, name: &str) -> Option<usize> {
+        self.handlers
+            .iter()
+            .position(|entry| entry.name.as_deref() == Some(name))
+    }
 
-            // Run each registered handler, logging errors to stderr
-            for hook in handlers {
-                if let Err(e) = hook(info, ctx) {
-                    eprintln!("Error encountered in panic handler:");
-                    eprintln!("{e}");
-                }
+    /// Assembles and registers the supplied panic handlers as a serial panic handler. The returned
+    /// [`EvacHandle`] can be used to uninstall it again.
+    ///
+    /// Everything the hook needs is set up here, so that dispatching a panic to the handlers
+    /// doesn't allocate by itself, in case the panic came from running out of memory. The
+    /// [`PipelineSummary`] is the exception, which is only kept if there are finalizers or
+    /// completion callbacks to read it, as are features that run handlers on other threads.
+    ///
+    /// ## Errors
+    /// Fails with [`RegisterError::AlreadyRegistered`] if an earlier registration is still
+    /// installed, rather than silently discarding its handlers.
+    pub fn register(self, ctx: T) -> Result<EvacHandle, RegisterError> {
+        self.install(ctx, false)
+    }
+
+    /// Like [`EvacBuilder::register`], but installs the hook even if another evac registration is
+    /// installed. The earlier hook is only kept if this builder preserves the existing hook.
+    pub fn force_register(self, ctx: T) -> EvacHandle {
+        self.install(ctx, true)
+            .expect("forced registrations don't check for earlier ones")
+    }
+
+    /// Like [`EvacBuilder::register`], but the hook only stays installed for as long as the
+    /// returned [`EvacGuard`] is alive.
+    pub fn register_scoped(self, ctx: T) -> Result<EvacGuard, RegisterError> {
+        self.register(ctx).map(EvacGuard::new)
+    }
+
+    /// Like [`EvacBuilder::register_scoped`], but installs the hook even if another evac
+    /// registration is installed, as per [`EvacBuilder::force_register`].
+    pub fn force_register_scoped(self, ctx: T) -> EvacGuard {
+        EvacGuard::new(self.force_register(ctx))
+    }
+
+    /// Registers the handlers alongside whatever hook is currently installed, rather than in place
+    /// of it. Independent components can each call this without coordinating on a single builder:
+    /// the first call wraps the current hook with a dispatcher, and later calls join it. The
+    /// wrapped hook runs first, then each incremental registration in the order they were made.
+    ///
+    /// The current hook is always kept, and always runs first, so
+    /// [`EvacBuilder::preserve_existing_hook`] is only taken as `Position::Before`, which is what
+    /// happens anyway. Fails with [`RegisterError::HookAfter`] if it was given `Position::After`.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// // In one component
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .register_incremental(())?;
+    ///
+    /// // In another, which needn't know about the first
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Ok(()))
+    ///   .register_incremental(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn register_incremental(mut self, ctx: T) -> Result<EvacHandle, RegisterError> {
+        // The dispatcher runs it, once, ahead of all of the pipelines
+        match self.existing_hook.take() {
+            Some(Position::After) => Err(RegisterError::HookAfter),
+            _ => Ok(incremental::register(self.assemble(ContextLock::new(ctx)))),
+        }
+    }
+
+    /// Like [`EvacBuilder::register`], but the context is only built once a panic actually occurs,
+    /// by calling `init` inside the hook. If `init` fails, its error is reported the same way as a
+    /// handler's, and the handlers are skipped; they're skipped for any later panics too, as `init`
+    /// only gets the one chance.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::fs::{self, File};
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _dump: &mut File| Ok(()))
+    ///   .register_lazy(|| {
+    ///     let dir = std::env::temp_dir().join("crashes");
+    ///     fs::create_dir_all(&dir)?;
+    ///     Ok(File::create(dir.join("crash.dump"))?)
+    ///   })?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn register_lazy<F>(self, init: F) -> Result<EvacHandle, RegisterError>
+    where
+        F: FnOnce() -> Result<T, Box<dyn Error>> + Send + 'static,
+    {
+        handle::install(self.assemble(Lazy::new(init)), false)
+    }
+
+    /// Like [`EvacBuilder::register`], but the context is supplied by a [`ContextProvider`] each
+    /// time a panic occurs, rather than being owned by evac.
+    pub fn register_provider<P>(self, provider: P) -> Result<EvacHandle, RegisterError>
+    where
+        P: ContextProvider<T>,
+    {
+        handle::install(self.assemble(Provided(provider)), false)
+    }
+
+    /// Like [`EvacBuilder::register`], but also returns a [`ContextHandle`] for updating the
+    /// context after registration.
+    pub fn register_with_handle(
+        self,
+        ctx: T,
+    ) -> Result<(EvacHandle, ContextHandle<T>), RegisterError> {
+        let ctx = Arc::new(ContextLock::new(ctx));
+        let handle = ContextHandle::new(Arc::clone(&ctx));
+
+        Ok((handle::install(self.assemble(ctx), false)?, handle))
+    }
+
+    fn install(self, ctx: T, force: bool) -> Result<EvacHandle, RegisterError> {
+        handle::install(self.assemble(ContextLock::new(ctx)), force)
+    }
+
+    fn assemble(self, ctx: impl Source<T>) -> Pipeline {
+        let runner = Runner::new(self, ctx);
+
+        Box::new(move |info, previous| runner.hook(info, previous))
+    }
+}
+
+impl<T: Clone + Send + 'static, E: Display + 'static> EvacBuilder<T, E> {
+    /// Gives each panic its own clone of the context, so that what one panic's handlers do to it
+    /// isn't seen by the handlers of the next. The context is only locked for as long as it takes
+    /// to clone it, so panics on different threads don't wait on each other's handlers either.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, fields: &mut Vec<String>| {
+    ///     fields.push(format!("thread = {:?}", std::thread::current().name()));
+    ///     Ok(())
+    ///   })
+    ///   .with_handler(|_, fields| {
+    ///     // Only ever sees this panic's thread
+    ///     eprintln!("{}", fields.join(", "));
+    ///     Ok(())
+    ///   })
+    ///   .snapshot_context()
+    ///   .register(vec!["app = example".to_string()])?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn snapshot_context(mut self) -> Self {
+        self.snapshot = Some(T::clone);
+
+        self
+    }
+}
+
+impl<T: Send + Sync + 'static, E: Display + 'static> EvacBuilder<T, E> {
+    /// Adds a group of handlers that don't depend on each other, to be run at the same time rather
+    /// than one after the other. Handlers before the group have finished by the time it starts, and
+    /// the group has finished by the time the handlers after it start.
+    ///
+    /// Each handler in the group runs on a thread of its own, which is spawned up front, so that
+    /// spawning can't fail once a panic occurs. Handlers that fail, or panic, are reported
+    /// individually, and the group as a whole counts as failed for its [`ErrorPolicy`] if any of
+    /// them does. A panicking handler doesn't take the others with it.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use evac::{EvacBuilder, ParallelGroup};
+    /// EvacBuilder::new()
+    ///   .with_handler(|_, _: &mut PathBuf| {
+    ///     eprintln!("runs first");
+    ///     Ok(())
+    ///   })
+    ///   .parallel_group(
+    ///     ParallelGroup::new()
+    ///       .with_named_handler("write-dump", |_, dir: &PathBuf| {
+    ///         eprintln!("writing dump to {}", dir.display());
+    ///         Ok(())
+    ///       })
+    ///       .with_named_handler("sentry", |message, _| {
+    ///         eprintln!("posting `{message}` to Sentry");
+    ///         Ok(())
+    ///       }),
+    ///   )
+    ///   .register(PathBuf::from("/var/crash"))?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn parallel_group(mut self, group: ParallelGroup<T, E>) -> Self {
+        let group = HandlerKind::Group(group.into_group());
+        self.handlers
+            .push(HandlerEntry::new(None, Priority::Normal, group));
+
+        self
+    }
+}
+
+impl<T: Send + 'static, E: Display + From<TimeoutError> + 'static> EvacBuilder<T, E> {
+    /// Adds an async panic handler, which is run to completion before the next handler starts. If
+    /// it takes longer than `timeout`, it's dropped, and a [`TimeoutError`] is reported in its
+    /// place, converted into the handlers' error type.
+    ///
+    /// By default, it's run on a minimal executor, which is enough for futures that don't depend
+    /// on a particular runtime. With the `tokio` feature, it's run on the panicking thread's tokio
+    /// runtime if it has one, and on a fresh single-threaded runtime if not. A panicking thread's
+    /// runtime can't be blocked on as usual, so the handler's IO and timers are only driven if the
+    /// runtime has other threads to do it.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::EvacBuilder;
+    /// # async fn upload(_: &str, _: String) -> Result<(), Box<dyn std::error::Error>> { Ok(()) }
+    /// EvacBuilder::new()
+    ///   .with_async_handler(Duration::from_secs(5), async |info, endpoint: &mut String| {
+    ///     upload(endpoint, info.to_string()).await
+    ///   })
+    ///   .register("https://crashes.example.com".to_string())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn with_async_handler<F>(self, timeout: Duration, handler: F) -> Self
+    where
+        F: AsyncFn(&PanicHookInfo<'_>, &mut T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        self.with_handler(
+            move |info, ctx| match executor::block_on(handler(info, ctx), timeout) {
+                Some(result) => result,
+                None => Err(E::from(TimeoutError::Elapsed { timeout })),
+            },
+        )
+    }
+}
+
+impl<T: Clone + Send + 'static, E: Display + From<TimeoutError> + 'static> EvacBuilder<T, E> {
+    /// Adds a panic handler that's given up on if it runs for longer than `timeout`, so that one
+    /// that hangs, such as on a DNS lookup that never resolves, can't keep the process from
+    /// exiting. Giving up is reported as a [`TimeoutError`], converted into the handlers' error
+    /// type, and the remaining handlers carry on.
+    ///
+    /// The handler runs on a thread of its own, which is left behind if it times out. That means
+    /// it can't borrow anything from the panic: it's given the panic rendered as a message, and a
+    /// clone of the context, which replaces the context only if the handler finishes in time. Its
+    /// errors are passed on as their message.
+    ///
+    /// `on_timeout` is called on the panicking thread once the handler is given up on, with the
+    /// panic's [`PanicReport`] and how long the handler was waited on, such as for recording what
+    /// the handler couldn't.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use evac::EvacBuilder;
+    /// # fn upload(_: &str, _: &str) -> Result<(), Box<dyn std::error::Error>> { Ok(()) }
+    /// EvacBuilder::new()
+    ///   .with_handler_timeout(
+    ///     Duration::from_secs(5),
+    ///     |message, endpoint: &mut String| upload(endpoint, message),
+    ///     |report, elapsed| {
+    ///       eprintln!("gave up uploading {} after {elapsed:?}", report.fingerprint());
+    ///     },
+    ///   )
+    ///   .register("https://crashes.example.com".to_string())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn with_handler_timeout<F, C, HE>(
+        self,
+        timeout: Duration,
+        handler: F,
+        on_timeout: C,
+    ) -> Self
+    where
+        F: Fn(&str, &mut T) -> Result<(), HE> + Send + Sync + 'static,
+        C: Fn(&PanicReport<'_>, Duration) + Send + Sync + 'static,
+        HE: Display,
+    {
+        let handler = Arc::new(handler);
+
+        self.with_report_handler(move |report, ctx| {
+            let started = Instant::now();
+            let result = timeout::run(timeout, &handler, &report.info().to_string(), ctx);
+            if let Err(TimeoutError::Elapsed { .. }) = result {
+                on_timeout(report, started.elapsed());
             }
-        }));
+
+            Ok(result?)
+        })
+    }
+}
+
+impl<S: Send + Sync + 'static, E: Display + 'static> EvacBuilder<Option<Arc<S>>, E> {
+    /// Adds a panic handler for use with [`EvacBuilder::register_weak`]. The handler is given the
+    /// state if it's still alive when the panic occurs, or `None` if it has been dropped.
+    pub fn with_weak_handler<F>(self, handler: F) -> Self
+    where
+        F: Fn(&PanicHookInfo<'_>, Option<&S>) -> Result<(), E> + Send + Sync + 'static,
+    {
+        self.with_handler(move |info, state| handler(info, state.as_deref()))
+    }
+
+    /// Like [`EvacBuilder::register`], but observes state owned elsewhere without keeping it
+    /// alive. Each panic upgrades `state` for the duration of its handlers, which are given `None`
+    /// if it has already been dropped.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use evac::EvacBuilder;
+    /// struct AppState {
+    ///   name: String,
+    /// }
+    ///
+    /// let state = Arc::new(AppState { name: "example".into() });
+    ///
+    /// EvacBuilder::new()
+    ///   .with_weak_handler(|_, state: Option<&AppState>| {
+    ///     match state {
+    ///       Some(state) => eprintln!("{} panicked", state.name),
+    ///       None => eprintln!("panicked during shutdown"),
+    ///     }
+    ///     Ok(())
+    ///   })
+    ///   .register_weak(Arc::downgrade(&state))?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn register_weak(self, state: Weak<S>) -> Result<EvacHandle, RegisterError> {
+        handle::install(self.assemble(WeakSource(state)), false)
+    }
+}
+
+impl<E: Display + From<MissingExtension> + 'static> EvacBuilder<Extensions, E> {
+    /// Adds a panic handler that only needs the `C` part of an [`Extensions`] context. If the
+    /// context has no `C` when a panic occurs, the handler is skipped and reports a
+    /// [`MissingExtension`] error instead, converted into the handlers' error type.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use evac::{EvacBuilder, Extensions};
+    /// struct RequestCount(u64);
+    ///
+    /// EvacBuilder::new()
+    ///   .with_extension_handler(|_, dir: &mut PathBuf| {
+    ///     eprintln!("writing dump to {}", dir.display());
+    ///     Ok(())
+    ///   })
+    ///   .with_extension_handler(|_, count: &mut RequestCount| {
+    ///     eprintln!("{} requests served", count.0);
+    ///     Ok(())
+    ///   })
+    ///   .register(
+    ///     Extensions::new()
+    ///       .with(PathBuf::from("/var/crash"))
+    ///       .with(RequestCount(0)),
+    ///   )?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn with_extension_handler<C, F>(self, handler: F) -> Self
+    where
+        C: Send + 'static,
+        F: Fn(&PanicHookInfo<'_>, &mut C) -> Result<(), E> + Send + Sync + 'static,
+    {
+        self.with_handler(move |info, extensions| match extensions.get_mut::<C>() {
+            Some(ctx) => handler(info, ctx),
+            None => Err(E::from(MissingExtension {
+                type_name: std::any::type_name::<C>(),
+            })),
+        })
+    }
+}
+
+impl<T: Send + 'static, E: Display + 'static> Default for EvacBuilder<T, E> {
+    fn default() -> Self {
+        Self {
+            handlers: vec![],
+            existing_hook: None,
+            contention: Contention::Wait,
+            snapshot: None,
+            finalizers: vec![],
+            error_sink: None,
+            on_handler_error: vec![],
+            on_complete: vec![],
+            error_policy: None,
+            critical_policy: None,
+            isolate: false,
+            deadline: None,
+            after_panic: None,
+            crash_thread: None,
+            memory_reserve: None,
+            sample_rate: None,
+            max_reports: None,
+            on_suppressed: vec![],
+            dedup_window: None,
+            crash_loop: None,
+            crash_marker: None,
+            crash_stats: None,
+            heartbeat: None,
+            ignore_environment: false,
+            backtrace: None,
+            app_metadata: None,
+            env_capture: None,
+            args_capture: None,
+            #[cfg(feature = "all-threads")]
+            all_threads: false,
+            #[cfg(feature = "sysinfo")]
+            system_info: None,
+            grouping: None,
+            scrubber: None,
+            limits: None,
+        }
+    }
+}
+
+/// Merges each builder in turn, as per [`EvacBuilder::merge`].
+impl<T: Send + 'static, E: Display + 'static> Extend<EvacBuilder<T, E>> for EvacBuilder<T, E> {
+    fn extend<I: IntoIterator<Item = EvacBuilder<T, E>>>(&mut self, iter: I) {
+        for other in iter {
+            self.absorb(other);
+        }
     }
 }
+
+/// Adds each handler in turn, as per [`EvacBuilder::with_boxed_handler`].
+impl<T: Send + 'static, E: Display + 'static> Extend<PanicHandler<T, E>> for EvacBuilder<T, E> {
+    fn extend<I: IntoIterator<Item = PanicHandler<T, E>>>(&mut self, iter: I) {
+        for handler in iter {
+            self.handlers.push(HandlerEntry::new(
+                None,
+                Priority::Normal,
+                HandlerKind::Single(handler),
+            ));
+        }
+    }
+}
+
+/// Merges all of the builders into one, as per [`EvacBuilder::merge`].
+impl<T: Send + 'static, E: Display + 'static> FromIterator<EvacBuilder<T, E>>
+    for EvacBuilder<T, E>
+{
+    fn from_iter<I: IntoIterator<Item = EvacBuilder<T, E>>>(iter: I) -> Self {
+        let mut builder = Self::default();
+        builder.extend(iter);
+
+        builder
+    }
+}
+
+fn in_crash_loop() -> bool {
+    crash_loop::status().is_some_and(|status| status.in_crash_loop)
+}
@@ -0,0 +1,117 @@
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::retry;
+
+/// Decides which panics are handled, as per
+/// [`EvacBuilder::sample_rate`](crate::EvacBuilder::sample_rate) and
+/// [`EvacBuilder::max_reports_per`](crate::EvacBuilder::max_reports_per).
+pub(crate) struct Limiter {
+    sample_rate: Option<f64>,
+    max_reports: Option<(u32, Duration)>,
+    window: Mutex<Window>,
+}
+
+/// The reports made since the current window started.
+#[derive(Default)]
+struct Window {
+    started: Option<Instant>,
+    reports: u32,
+}
+
+impl Limiter {
+    pub(crate) fn new(sample_rate: Option<f64>, max_reports: Option<(u32, Duration)>) -> Self {
+        Self {
+            sample_rate,
+            max_reports,
+            window: Mutex::new(Window::default()),
+        }
+    }
+
+    /// Whether the panic happening now is to be handled.
+    pub(crate) fn admit(&self) -> bool {
+        // Sampled first, so that panics sampled out don't use up the window
+        if let Some(rate) = self.sample_rate {
+            if retry::random_fraction() >= rate {
+                return false;
+            }
+        }
+
+        let Some((max, per)) = self.max_reports else {
+            return true;
+        };
+
+        let now = Instant::now();
+        let mut window = self.window.lock().unwrap_or_else(PoisonError::into_inner);
+        let expired = window
+            .started
+            .is_none_or(|started| now.duration_since(started) >= per);
+        if expired {
+            *window = Window {
+                started: Some(now),
+                reports: 0,
+            };
+        }
+
+        if window.reports >= max {
+            return false;
+        }
+        window.reports += 1;
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn admitted(limiter: &Limiter, panics: usize) -> usize {
+        (0..panics).filter(|_| limiter.admit()).count()
+    }
+
+    #[test]
+    fn admits_everything_without_limits() {
+        assert_eq!(admitted(&Limiter::new(None, None), 1000), 1000);
+        assert_eq!(admitted(&Limiter::new(Some(1.0), None), 1000), 1000);
+    }
+
+    #[test]
+    fn samples_at_the_rate_given() {
+        assert_eq!(admitted(&Limiter::new(Some(0.0), None), 1000), 0);
+
+        // Far enough either side of half that it's never off by chance
+        let half = admitted(&Limiter::new(Some(0.5), None), 10_000);
+        assert!((4_000..=6_000).contains(&half), "{half}");
+    }
+
+    #[test]
+    fn admits_at_most_max_per_window() {
+        let limiter = Limiter::new(None, Some((3, Duration::from_secs(3600))));
+
+        assert_eq!(admitted(&limiter, 10), 3);
+        assert_eq!(admitted(&limiter, 10), 0);
+    }
+
+    #[test]
+    fn starts_a_new_window_once_the_last_is_over() {
+        let limiter = Limiter::new(None, Some((2, Duration::from_millis(50))));
+        assert_eq!(admitted(&limiter, 5), 2);
+
+        thread::sleep(Duration::from_millis(60));
+
+        assert_eq!(admitted(&limiter, 5), 2);
+    }
+
+    #[test]
+    fn doesnt_count_panics_sampled_out() {
+        let limiter = Limiter::new(Some(0.5), Some((100, Duration::from_secs(3600))));
+
+        // Half are sampled out, so there's still room in the window after as many as its max
+        let first = admitted(&limiter, 100);
+        assert!(first < 100, "{first}");
+        assert_eq!(admitted(&limiter, 10_000), 100 - first);
+    }
+}
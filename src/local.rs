@@ -0,0 +1,219 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::marker::PhantomData;
+use std::panic::PanicHookInfo;
+use std::sync::{Mutex, PoisonError};
+
+use crate::handle::{EvacHandle, Hook};
+use crate::{incremental, stderr, HandlerError};
+
+/// The type of closures accepted by [`LocalEvacBuilder`]. Unlike
+/// [`PanicHandler`](crate::PanicHandler)s, these never leave the thread they were registered on,
+/// so needn't be `Send` or `Sync`.
+pub type LocalPanicHandler<T> =
+    Box<dyn Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), Box<dyn Error>> + 'static>;
+
+/// The type of closures accepted by [`LocalEvacBuilder::error_sink`], which needn't be `Send` or
+/// `Sync` either.
+pub type LocalErrorSink = Box<dyn Fn(&HandlerError<'_>) + 'static>;
+
+/// The number of live local registrations across all threads, and the dispatcher entry that serves
+/// them while there are any.
+static LOCAL: Mutex<(usize, Option<EvacHandle>)> = Mutex::new((0, None));
+
+thread_local! {
+    /// This thread's local pipelines.
+    static PIPELINES: RefCell<Pipelines> = const { RefCell::new(Pipelines { next_id: 0, list: Vec::new() }) };
+}
+
+/// An assembled local pipeline.
+type LocalPipeline = Box<dyn Fn(&PanicHookInfo<'_>)>;
+
+struct Pipelines {
+    next_id: u64,
+    list: Vec<(u64, LocalPipeline)>,
+}
+
+impl Drop for Pipelines {
+    // A thread exiting takes its registrations with it
+    fn drop(&mut self) {
+        release(self.list.len());
+    }
+}
+
+/// Builder for panic handlers whose context is confined to a single thread, such as `Rc`-based GUI
+/// state that can't be moved into the global hook. The handlers only run when the thread that
+/// registered them panics.
+///
+/// Panic hooks run on the thread that panicked, so the context is simply kept in thread-local
+/// storage, and a shared entry in the incremental dispatch table (see
+/// [`EvacBuilder::register_incremental`](crate::EvacBuilder::register_incremental)) looks it up.
+///
+/// ## Example
+/// ```
+/// # use std::rc::Rc;
+/// # use evac::LocalEvacBuilder;
+/// struct Window {
+///   title: Rc<str>,
+/// }
+///
+/// LocalEvacBuilder::new()
+///   .with_handler(|_, window: &mut Window| {
+///     eprintln!("the \"{}\" window panicked", window.title);
+///     Ok(())
+///   })
+///   .register(Window { title: "Main".into() });
+/// ```
+pub struct LocalEvacBuilder<T: 'static> {
+    handlers: Vec<LocalPanicHandler<T>>,
+    error_sink: Option<LocalErrorSink>,
+}
+
+impl<T: 'static> LocalEvacBuilder<T> {
+    /// Constructs a new [`LocalEvacBuilder`], empty of handlers.
+    pub fn new() -> Self {
+        Self {
+            handlers: vec![],
+            error_sink: None,
+        }
+    }
+
+    /// Adds a panic handler. Handlers are executed in the order they are registered in.
+    pub fn with_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&PanicHookInfo<'_>, &mut T) -> Result<(), Box<dyn Error>> + 'static,
+    {
+        self.handlers.push(Box::new(handler));
+
+        self
+    }
+
+    /// Sends the handlers' errors to `sink`, instead of printing them to `stderr`, as
+    /// [`EvacBuilder::error_sink`](crate::EvacBuilder::error_sink) does.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::{HandlerError, LocalEvacBuilder};
+    /// LocalEvacBuilder::new()
+    ///   .with_handler(|_, _: &mut ()| Err("no window to show it in".into()))
+    ///   .error_sink(|err: &HandlerError| {
+    ///     // Stand-in for a logging call
+    ///     println!("[ui] {err}");
+    ///   })
+    ///   .register(());
+    /// ```
+    pub fn error_sink<F>(mut self, sink: F) -> Self
+    where
+        F: Fn(&HandlerError<'_>) + 'static,
+    {
+        self.error_sink = Some(Box::new(sink));
+
+        self
+    }
+
+    /// Registers the handlers for the current thread. Local registrations are served through the
+    /// incremental dispatch table, so combine with other incremental registrations, and keep the
+    /// hook that was installed before them.
+    pub fn register(self, ctx: T) -> LocalHandle {
+        let Self {
+            handlers,
+            error_sink,
+        } = self;
+        let ctx = RefCell::new(ctx);
+
+        let pipeline: LocalPipeline = Box::new(move |info| {
+            // The context is only borrowed here, and the hook can't run twice on one thread
+            let mut ctx = ctx.borrow_mut();
+
+            for (index, handler) in handlers.iter().enumerate() {
+                if let Err(error) = handler(info, &mut ctx) {
+                    let err = HandlerError::Handler {
+                        name: None,
+                        index,
+                        error: &error,
+                    };
+                    match &error_sink {
+                        Some(sink) => sink(&err),
+                        None => stderr::print_line(format_args!("{err}")),
+                    }
+                }
+            }
+        });
+
+        let id = PIPELINES.with(|pipelines| {
+            let mut pipelines = pipelines.borrow_mut();
+            let id = pipelines.next_id;
+            pipelines.next_id += 1;
+            pipelines.list.push((id, pipeline));
+
+            id
+        });
+
+        let mut local = LOCAL.lock().unwrap_or_else(PoisonError::into_inner);
+        local.0 += 1;
+        if local.1.is_none() {
+            local.1 = Some(incremental::register(Box::new(dispatch)));
+        }
+
+        LocalHandle {
+            id,
+            _thread: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static> Default for LocalEvacBuilder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returned by [`LocalEvacBuilder::register`]. Tied to the thread it was registered on. Dropping
+/// the handle leaves the handlers registered.
+pub struct LocalHandle {
+    id: u64,
+    _thread: PhantomData<*const ()>,
+}
+
+impl LocalHandle {
+    /// Removes the handlers, dropping them along with their context.
+    pub fn uninstall(self) {
+        let removed = PIPELINES.with(|pipelines| {
+            let mut pipelines = pipelines.borrow_mut();
+            let before = pipelines.list.len();
+            pipelines.list.retain(|(id, _)| *id != self.id);
+
+            before - pipelines.list.len()
+        });
+
+        release(removed);
+    }
+}
+
+/// Forgets about `count` local registrations, removing the dispatcher entry after the last one.
+fn release(count: usize) {
+    if count == 0 {
+        return;
+    }
+
+    let mut local = LOCAL.lock().unwrap_or_else(PoisonError::into_inner);
+    local.0 -= count;
+    if local.0 == 0 {
+        if let Some(entry) = local.1.take() {
+            entry.release();
+        }
+    }
+}
+
+/// The shared dispatcher entry. Runs the current thread's local pipelines, if it has any.
+fn dispatch(info: &PanicHookInfo<'_>, _: &Hook) {
+    // The thread-local may be mid-registration or already destroyed, in which case there's
+    // nothing we can safely run
+    let _ = PIPELINES.try_with(|pipelines| {
+        if let Ok(pipelines) = pipelines.try_borrow() {
+            for (_, pipeline) in &pipelines.list {
+                pipeline(info);
+            }
+        }
+    });
+}
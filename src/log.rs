@@ -0,0 +1,78 @@
+//! Emits reports as records of the `log` crate, see [`handlers::log`](crate::handlers::log).
+
+use ::log::kv::{self, Key, Source, Value, VisitSource};
+use ::log::{Level, Metadata, Record};
+
+use crate::{format, PanicReport};
+
+/// The target records are emitted for, so that they can be filtered on as any crate's are.
+const TARGET: &str = "evac";
+
+/// Emits `report` to whichever logger the application set, as an error, if it's enabled.
+pub(crate) fn emit(report: &PanicReport<'_>) {
+    let logger = ::log::logger();
+    let metadata = Metadata::builder()
+        .level(Level::Error)
+        .target(TARGET)
+        .build();
+    if Level::Error > ::log::max_level() || !logger.enabled(&metadata) {
+        return;
+    }
+
+    let fields = Fields {
+        report,
+        backtrace: report.backtrace_text(),
+    };
+    let summary = format::summary(report);
+    logger.log(
+        &Record::builder()
+            .metadata(metadata)
+            .args(format_args!("{summary}"))
+            .file(report.location().map(|location| location.file()))
+            .line(report.location().map(|location| location.line()))
+            .key_values(&fields)
+            .build(),
+    );
+    logger.flush();
+}
+
+/// The report's fields, as key-values for loggers that take them.
+struct Fields<'a, 'r> {
+    report: &'a PanicReport<'r>,
+    backtrace: Option<String>,
+}
+
+impl Source for Fields<'_, '_> {
+    fn visit<'kvs>(&'kvs self, visitor: &mut dyn VisitSource<'kvs>) -> Result<(), kv::Error> {
+        let report = self.report;
+        let mut pair =
+            |key: &'kvs str, value: Value<'kvs>| visitor.visit_pair(Key::from(key), value);
+
+        pair("exception.type", Value::from("panic"))?;
+        pair(
+            "exception.message",
+            Value::from(report.message().unwrap_or("Box<dyn Any>")),
+        )?;
+        if let Some(backtrace) = &self.backtrace {
+            pair("exception.stacktrace", Value::from(backtrace.as_str()))?;
+        }
+        if let Some(location) = report.location() {
+            pair("code.filepath", Value::from(location.file()))?;
+            pair("code.lineno", Value::from(location.line()))?;
+            pair("code.column", Value::from(location.column()))?;
+        }
+        pair(
+            "thread.name",
+            Value::from(report.thread_name().unwrap_or("<unnamed>")),
+        )?;
+        pair("evac.fingerprint", Value::from(report.fingerprint()))?;
+        if let Some(app) = report.app_metadata() {
+            pair("service.version", Value::from(app.version))?;
+        }
+        for (key, value) in report.annotations() {
+            pair(key, Value::from(value.as_str()))?;
+        }
+
+        Ok(())
+    }
+}
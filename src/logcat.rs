@@ -0,0 +1,52 @@
+//! Writes reports to Android's log, see [`handlers::logcat`](crate::handlers::logcat).
+
+use std::ffi::{c_char, c_int, CString};
+use std::io;
+
+use crate::{PanicReport, ReportFormatter, TextFormatter};
+
+#[link(name = "log")]
+extern "C" {
+    fn __android_log_write(priority: c_int, tag: *const c_char, text: *const c_char) -> c_int;
+}
+
+const ANDROID_LOG_ERROR: c_int = 6;
+
+/// How much of an entry logcat keeps, less room for its priority, the tag, and their NULs.
+const MAX_PAYLOAD: usize = 4068;
+
+/// Writes `report` to the log with priority `ERROR`, as from `tag`, an entry per chunk of it that
+/// fits, breaking between lines where it can.
+pub(crate) fn write(report: &PanicReport<'_>, tag: &str) -> io::Result<()> {
+    let mut text = Vec::new();
+    TextFormatter::new().format(report, &mut text)?;
+    let text = String::from_utf8_lossy(&text).replace('\0', " ");
+    let tag = CString::new(tag.replace('\0', " ")).map_err(io::Error::other)?;
+
+    let max = MAX_PAYLOAD.saturating_sub(tag.as_bytes().len() + 3).max(64);
+    let mut rest = text.trim_end();
+    while !rest.is_empty() {
+        let mut end = rest.len().min(max);
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        // At the last line break that fits, if there is one
+        if end < rest.len() {
+            if let Some(newline) = rest[..end].rfind('\n') {
+                end = newline + 1;
+            }
+        }
+        let (chunk, after) = rest.split_at(end);
+        rest = after;
+
+        let chunk = CString::new(chunk.trim_end_matches('\n')).map_err(io::Error::other)?;
+        // SAFETY: Both strings are NUL-terminated, and outlive the call
+        let written =
+            unsafe { __android_log_write(ANDROID_LOG_ERROR, tag.as_ptr(), chunk.as_ptr()) };
+        if written < 0 {
+            return Err(io::Error::from_raw_os_error(-written));
+        }
+    }
+
+    Ok(())
+}
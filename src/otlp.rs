@@ -0,0 +1,237 @@
+//! Exports panics as OpenTelemetry log records, over OTLP/HTTP, see
+//! [`handlers::otlp`](crate::handlers::otlp).
+
+use std::borrow::Cow;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::{json, PanicReport};
+
+/// The severity number for `FATAL`.
+const SEVERITY_FATAL: u8 = 21;
+
+/// The span that was active when the panic happened, as a W3C `traceparent` gives it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SpanContext {
+    trace_id: String,
+    span_id: String,
+    flags: u32,
+}
+
+impl SpanContext {
+    /// Parses `traceparent`, such as `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`,
+    /// giving `None` if it isn't valid, or doesn't name a span.
+    pub(crate) fn parse(traceparent: &str) -> Option<Self> {
+        let mut fields = traceparent.trim().split('-');
+        let version = fields.next()?;
+        let trace_id = fields.next()?;
+        let span_id = fields.next()?;
+        let flags = fields.next()?;
+        // Later versions may add fields after these, but the first can't
+        if !is_hex(version, 2) || version == "ff" || (version == "00" && fields.next().is_some()) {
+            return None;
+        }
+        if !is_hex(trace_id, 32) || !is_hex(span_id, 16) || !is_hex(flags, 2) {
+            return None;
+        }
+        if trace_id.bytes().all(|b| b == b'0') || span_id.bytes().all(|b| b == b'0') {
+            return None;
+        }
+
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+            flags: u32::from_str_radix(flags, 16).ok()?,
+        })
+    }
+}
+
+/// Whether `value` is `len` lowercase hex digits.
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportLogsServiceRequest<'a> {
+    resource_logs: [ResourceLogs<'a>; 1],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceLogs<'a> {
+    resource: Resource<'a>,
+    scope_logs: [ScopeLogs<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct Resource<'a> {
+    attributes: Vec<KeyValue<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScopeLogs<'a> {
+    scope: Scope,
+    log_records: [LogRecord<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct Scope {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LogRecord<'a> {
+    time_unix_nano: String,
+    observed_time_unix_nano: String,
+    severity_number: u8,
+    severity_text: &'static str,
+    body: Value<'a>,
+    attributes: Vec<KeyValue<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    span_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct KeyValue<'a> {
+    key: Cow<'a, str>,
+    value: Value<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Value<'a> {
+    StringValue(Cow<'a, str>),
+    // As a string, since it's 64 bits in the JSON encoding
+    IntValue(String),
+}
+
+impl<'a> KeyValue<'a> {
+    fn string(key: impl Into<Cow<'a, str>>, value: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            key: key.into(),
+            value: Value::StringValue(value.into()),
+        }
+    }
+
+    fn int(key: &'static str, value: impl ToString) -> Self {
+        Self {
+            key: key.into(),
+            value: Value::IntValue(value.to_string()),
+        }
+    }
+}
+
+/// `report` as a request to export a log record of it, with severity `FATAL`, in `span`, if it
+/// panicked in one, for the service `service`, such as `my-app`, if it's given, and with the rest
+/// of the `resource` attributes on top of the ones evac knows.
+pub(crate) fn logs(
+    report: &PanicReport<'_>,
+    service: Option<&str>,
+    resource: &[(String, String)],
+    span: Option<&SpanContext>,
+) -> io::Result<Vec<u8>> {
+    let app = report.app_metadata();
+    let env = |name| {
+        std::env::var(name)
+            .ok()
+            .filter(|value: &String| !value.is_empty())
+    };
+
+    // As the SDKs name services with nothing else to go on
+    let service = service
+        .map(str::to_string)
+        .or_else(|| env("OTEL_SERVICE_NAME"))
+        .or_else(|| app.map(|app| app.name.to_string()))
+        .unwrap_or_else(|| match crate::handlers::executable_name() {
+            Some(name) => format!("unknown_service:{name}"),
+            None => "unknown_service".to_string(),
+        });
+    let mut resource_attributes = vec![KeyValue::string("service.name", service)];
+    if let Some(app) = app {
+        resource_attributes.push(KeyValue::string("service.version", app.version));
+    }
+    resource_attributes.push(KeyValue::int("process.pid", report.pid()));
+    #[cfg(feature = "sysinfo")]
+    if let Some(hostname) = report
+        .system_info()
+        .and_then(|host| host.hostname.as_deref())
+    {
+        resource_attributes.push(KeyValue::string("host.name", hostname));
+    }
+    for (key, value) in resource {
+        resource_attributes.retain(|attribute| attribute.key != key.as_str());
+        resource_attributes.push(KeyValue::string(key.as_str(), value.as_str()));
+    }
+
+    let mut attributes = vec![
+        KeyValue::string("exception.type", "panic"),
+        KeyValue::string(
+            "exception.message",
+            report.message().unwrap_or("Box<dyn Any>"),
+        ),
+    ];
+    if let Some(backtrace) = report.backtrace_text() {
+        attributes.push(KeyValue::string("exception.stacktrace", backtrace));
+    }
+    if let Some(location) = report.location() {
+        attributes.push(KeyValue::string("code.filepath", location.file()));
+        attributes.push(KeyValue::int("code.lineno", location.line()));
+        attributes.push(KeyValue::int("code.column", location.column()));
+    }
+    attributes.push(KeyValue::string(
+        "thread.name",
+        report.thread_name().unwrap_or("<unnamed>"),
+    ));
+    attributes.push(KeyValue::string("evac.fingerprint", report.fingerprint()));
+    for (key, value) in report.annotations() {
+        attributes.push(KeyValue::string(key.as_str(), value.as_str()));
+    }
+
+    let request = ExportLogsServiceRequest {
+        resource_logs: [ResourceLogs {
+            resource: Resource {
+                attributes: resource_attributes,
+            },
+            scope_logs: [ScopeLogs {
+                scope: Scope {
+                    name: "evac",
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                log_records: [LogRecord {
+                    time_unix_nano: nanos(report.timestamp()),
+                    observed_time_unix_nano: nanos(SystemTime::now()),
+                    severity_number: SEVERITY_FATAL,
+                    severity_text: "FATAL",
+                    body: Value::StringValue(crate::format::summary(report).into()),
+                    attributes,
+                    trace_id: span.map(|span| span.trace_id.as_str()),
+                    span_id: span.map(|span| span.span_id.as_str()),
+                    flags: span.map(|span| span.flags),
+                }],
+            }],
+        }],
+    };
+
+    json::to_vec(&request)
+}
+
+/// `time` in nanoseconds since the epoch, as a string, since it's 64 bits in the JSON encoding.
+fn nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
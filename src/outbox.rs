@@ -0,0 +1,286 @@
+//! Holding onto uploads that fail as a panic is handled, as they often do when the network is
+//! what broke, to deliver them later, see
+//! [`UploadConfig::outbox`](crate::handlers::UploadConfig::outbox).
+//!
+//! Each upload is saved to a file of its own in the outbox's directory, along with where it was
+//! going. Once the program is running again, [`flush`] sends them on with the uploaders that
+//! saved them, as built again as the pipeline is, so that nothing but what's uploaded and where
+//! it goes ends up on disk, and not the headers, which tend to hold credentials.
+//!
+//! ## Example
+//! ```
+//! # use evac::handlers::{self, UploadConfig};
+//! # use evac::outbox::Outbox;
+//! # use evac::EvacBuilder;
+//! let outbox = Outbox::new(std::env::temp_dir().join("my-app-outbox"));
+//! let upload = handlers::http_upload(
+//!   "http://127.0.0.1:8125/crashes",
+//!   UploadConfig::new().outbox(outbox),
+//! )?;
+//!
+//! EvacBuilder::new()
+//!   .with_report_handler(upload)
+//!   .register(())?;
+//!
+//! // Without holding up startup, in case the collector is still down
+//! std::thread::spawn(evac::outbox::flush);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::handlers::Retention;
+
+/// What outboxes' files end with.
+const EXTENSION: &str = "upload";
+
+/// Sends an upload's body, giving the status the server answered with, if it did, on failure.
+pub(crate) type Deliver = Arc<dyn Fn(&[u8]) -> Result<(), (Option<u16>, io::Error)> + Send + Sync>;
+
+/// Whether a failed delivery may succeed if tried again, going by the status, as the uploader
+/// understands it.
+pub(crate) type Retryable = fn(&(Option<u16>, io::Error)) -> bool;
+
+/// Every uploader with an outbox that's been built, for [`flush`] to deliver with.
+static UPLOADERS: Mutex<Vec<Uploader>> = Mutex::new(vec![]);
+
+#[derive(Clone)]
+struct Uploader {
+    outbox: Outbox,
+    url: String,
+    deliver: Deliver,
+    retryable: Retryable,
+}
+
+/// Where failed uploads are kept, and for how long.
+///
+/// ## Example
+/// ```
+/// # use std::time::Duration;
+/// # use evac::outbox::Outbox;
+/// let outbox = Outbox::new("/var/lib/my-app/outbox")
+///   .max_count(20)
+///   .max_age(Duration::from_secs(3 * 24 * 60 * 60));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Outbox {
+    directory: PathBuf,
+    max_count: usize,
+    max_age: Duration,
+}
+
+impl Outbox {
+    /// Keeps failed uploads in `directory`, creating it if need be, up to 100 of them, for up to
+    /// a week each.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+            max_count: 100,
+            max_age: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+
+    /// Keeps the newest `count` uploads, deleting the rest.
+    pub fn max_count(mut self, count: usize) -> Self {
+        self.max_count = count;
+
+        self
+    }
+
+    /// Deletes uploads more than `age` old, undelivered.
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = age;
+
+        self
+    }
+
+    /// Deletes the uploads that aren't to be kept, returning how many were.
+    fn prune(&self) -> io::Result<usize> {
+        Retention::new()
+            .max_files(self.max_count)
+            .max_age(self.max_age)
+            .matching(format!("*.{EXTENSION}"))
+            .prune(&self.directory)
+    }
+
+    /// Saves `body`, which failed to upload to `url`, returning where.
+    pub(crate) fn save(&self, url: &str, body: &[u8]) -> io::Result<PathBuf> {
+        // Shared by every outbox, so that none of them can pick the same name
+        static SAVED: AtomicU64 = AtomicU64::new(0);
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let count = SAVED.fetch_add(1, Ordering::Relaxed);
+        let name = format!("{millis}-{}-{count}.{EXTENSION}", std::process::id());
+
+        let mut contents = Vec::with_capacity(url.len() + 1 + body.len());
+        contents.extend_from_slice(url.as_bytes());
+        contents.push(b'\n');
+        contents.extend_from_slice(body);
+
+        // Written under a hidden name first, so that flushing never sees it half-written
+        fs::create_dir_all(&self.directory)?;
+        let path = self.directory.join(&name);
+        let temp = self.directory.join(format!(".{name}.tmp"));
+        let written =
+            crate::handlers::write_new(&temp, &contents).and_then(|()| fs::rename(&temp, &path));
+        if written.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        written?;
+
+        self.prune()?;
+
+        Ok(path)
+    }
+}
+
+/// Makes `deliver`, which uploads to `url`, what delivers the uploads in `outbox` that were
+/// going there, taking over from any uploader built before for the same. Failed deliveries are
+/// kept for next time if they're `retryable`.
+pub(crate) fn register(outbox: &Outbox, url: &str, deliver: Deliver, retryable: Retryable) {
+    let mut uploaders = UPLOADERS.lock().unwrap_or_else(PoisonError::into_inner);
+    uploaders
+        .retain(|uploader| uploader.outbox.directory != outbox.directory || uploader.url != url);
+    uploaders.push(Uploader {
+        outbox: outbox.clone(),
+        url: url.to_string(),
+        deliver,
+        retryable,
+    });
+}
+
+/// What [`flush`] made of the uploads in the outboxes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Flushed {
+    /// How many were delivered, and so deleted.
+    pub delivered: usize,
+    /// How many were deleted undelivered, as too old, one too many, or turned away by the
+    /// server for good, such as with `400 Bad Request`.
+    pub dropped: usize,
+    /// How many are left for next time, as the server still couldn't be reached, or answered
+    /// with an error that may pass.
+    pub remaining: usize,
+}
+
+/// Delivers the uploads in the outboxes of every uploader built so far, oldest first, deleting
+/// each once it's delivered. Call it once the pipeline is built, such as at startup, on a thread
+/// of its own, as uploads are retried as the uploader's configuration says.
+///
+/// Each outbox is pruned first. Once an upload in one fails in a way that may pass, the rest of
+/// it is left for next time, rather than waiting on each of them in turn. Uploads going
+/// somewhere no uploader built goes anymore are left to be pruned.
+///
+/// An upload may be delivered twice, if the process exits, or another one flushes the same
+/// outbox, partway through, so whatever takes them in should tell repeats apart, such as by the
+/// report's [fingerprint](crate::PanicReport::fingerprint) and timestamp.
+///
+/// ## Example
+/// ```
+/// # use evac::handlers::{self, UploadConfig};
+/// # use evac::outbox::Outbox;
+/// # let directory = std::env::temp_dir().join("my-app-outbox-flush");
+/// let upload = handlers::http_upload::<(), std::io::Error>(
+///   "http://127.0.0.1:8125/crashes",
+///   UploadConfig::new().outbox(Outbox::new(directory)),
+/// )?;
+///
+/// let flushed = evac::outbox::flush()?;
+/// if flushed.remaining > 0 {
+///   eprintln!("{} crash reports are still waiting to be sent", flushed.remaining);
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn flush() -> io::Result<Flushed> {
+    let uploaders = UPLOADERS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+
+    let mut flushed = Flushed::default();
+    for uploader in uploaders {
+        flushed.dropped += uploader.outbox.prune()?;
+
+        let mut waiting = false;
+        for path in uploads(&uploader.outbox.directory)? {
+            let Some(body) = read(&path, &uploader.url)? else {
+                continue;
+            };
+            // The server isn't taking them yet
+            if waiting {
+                flushed.remaining += 1;
+                continue;
+            }
+
+            match (uploader.deliver)(&body) {
+                Ok(()) => flushed.delivered += 1,
+                Err(failed) if !(uploader.retryable)(&failed) => flushed.dropped += 1,
+                Err(_) => {
+                    flushed.remaining += 1;
+                    waiting = true;
+                    continue;
+                }
+            }
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(flushed)
+}
+
+/// The uploads in `directory`, oldest first.
+fn uploads(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    let mut uploads = vec![];
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') || !name.ends_with(&format!(".{EXTENSION}")) {
+            continue;
+        }
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+
+        uploads.push((entry.metadata()?.modified()?, entry.path()));
+    }
+    uploads.sort_unstable();
+
+    Ok(uploads.into_iter().map(|(_, path)| path).collect())
+}
+
+/// The body of the upload at `path`, if it was going to `url`.
+fn read(path: &Path, url: &str) -> io::Result<Option<Vec<u8>>> {
+    let mut contents = match fs::read(path) {
+        Ok(contents) => contents,
+        // Such as if another process delivered it first
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let is_for_url =
+        contents.starts_with(url.as_bytes()) && contents.get(url.len()) == Some(&b'\n');
+    if !is_for_url {
+        return Ok(None);
+    }
+    contents.drain(..=url.len());
+
+    Ok(Some(contents))
+}
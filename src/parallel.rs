@@ -0,0 +1,188 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::panic::PanicHookInfo;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use crate::isolate;
+
+/// The type of closures accepted by [`ParallelGroup`]. They're only given shared access to the
+/// context, as the other handlers in the group are using it at the same time, and are given the
+/// panic rendered as a message, as the panic's payload can't be shared between threads.
+pub type SharedHandler<T, E = Box<dyn Error>> =
+    Box<dyn Fn(&str, &T) -> Result<(), E> + 'static + Send + Sync>;
+
+/// A set of handlers that don't depend on each other, and so can run at the same time, see
+/// [`EvacBuilder::parallel_group`](crate::EvacBuilder::parallel_group).
+pub struct ParallelGroup<T: 'static, E: 'static = Box<dyn Error>> {
+    handlers: Vec<(Option<String>, SharedHandler<T, E>)>,
+}
+
+impl<T: Send + Sync + 'static, E: Display + 'static> ParallelGroup<T, E> {
+    /// Constructs an empty group.
+    pub fn new() -> Self {
+        Self { handlers: vec![] }
+    }
+
+    /// Adds a handler to the group.
+    pub fn with_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&str, &T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        self.handlers.push((None, Box::new(handler)));
+
+        self
+    }
+
+    /// Adds a handler to the group under a name, which it's reported by.
+    pub fn with_named_handler<F>(mut self, name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&str, &T) -> Result<(), E> + Send + Sync + 'static,
+    {
+        self.handlers.push((Some(name.into()), Box::new(handler)));
+
+        self
+    }
+
+    /// Spawns the group's threads, and wraps it up for the dispatcher, which doesn't know that the
+    /// context can be shared.
+    pub(crate) fn into_group(self) -> Group<T, E> {
+        let (names, handlers): (Vec<_>, Vec<_>) = self.handlers.into_iter().unzip();
+        let pool = Pool::spawn(handlers.len());
+
+        Group {
+            names,
+            run: Box::new(move |info, ctx, fail| {
+                let message = info.to_string();
+                let ctx: &T = ctx;
+
+                let jobs = handlers
+                    .iter()
+                    .enumerate()
+                    .map(|(member, handler)| {
+                        let message = &message;
+                        let job: Job<'_> = Box::new(move || {
+                            handler(message, ctx).map_err(|e| {
+                                fail(member, &e);
+                                e.to_string()
+                            })
+                        });
+
+                        job
+                    })
+                    .collect();
+
+                pool.scope(jobs)
+            }),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static, E: Display + 'static> Default for ParallelGroup<T, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An assembled [`ParallelGroup`].
+pub(crate) struct Group<T: 'static, E: 'static> {
+    /// The names of the group's handlers, in the order they were added.
+    pub(crate) names: Vec<Option<String>>,
+    pub(crate) run: GroupRunner<T, E>,
+}
+
+/// Runs every handler in a group, calling the given closure with each error, and gives back how
+/// each went: the error's message if it failed, or the panic's message if it panicked.
+type GroupRunner<T, E> = Box<
+    dyn Fn(&PanicHookInfo<'_>, &mut T, &(dyn Fn(usize, &E) + Sync)) -> Vec<Outcome> + Send + Sync,
+>;
+
+/// How a single job went, see [`GroupRunner`].
+type Outcome = Result<Result<(), String>, String>;
+
+/// A handler bound to a single panic.
+type Job<'env> = Box<dyn FnOnce() -> Result<(), String> + Send + 'env>;
+
+/// Threads kept around to run jobs on.
+struct Pool {
+    /// Gone if the threads couldn't be spawned.
+    sender: Option<Sender<Box<dyn FnOnce() + Send>>>,
+}
+
+impl Pool {
+    fn spawn(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let spawned = (0..threads).all(|_| {
+            let receiver = Arc::clone(&receiver);
+            thread::Builder::new()
+                .name("evac-parallel".into())
+                .spawn(move || work(&receiver))
+                .is_ok()
+        });
+
+        Self {
+            sender: spawned.then_some(sender),
+        }
+    }
+
+    /// Runs every job on the pool, and waits for all of them to finish. If the pool couldn't be
+    /// spawned, they're run one after the other instead.
+    fn scope(&self, jobs: Vec<Job<'_>>) -> Vec<Outcome> {
+        let Some(sender) = &self.sender else {
+            return jobs.into_iter().map(|job| Ok(job())).collect();
+        };
+
+        let count = jobs.len();
+        let (done, results) = mpsc::channel();
+
+        for (idx, job) in jobs.into_iter().enumerate() {
+            let done = done.clone();
+            let job: Box<dyn FnOnce() + Send + '_> = Box::new(move || {
+                let result = isolate::catch(job);
+                let _ = done.send((idx, result));
+            });
+
+            // SAFETY: Each job is waited on below before returning, either for its result or for it
+            // to be dropped unrun, so nothing it borrows is gone while it's still around.
+            let job: Box<dyn FnOnce() + Send + 'static> = unsafe { std::mem::transmute(job) };
+            if let Err(mpsc::SendError(job)) = sender.send(job) {
+                job();
+            }
+        }
+
+        // Once every job has dropped its sender, none of them can be running
+        drop(done);
+
+        let mut ordered: Vec<Outcome> = (0..count)
+            .map(|_| Ok(Err("the handler was dropped without running".into())))
+            .collect();
+        for _ in 0..count {
+            let Ok((idx, result)) = results.recv() else {
+                break;
+            };
+            ordered[idx] = result;
+        }
+
+        ordered
+    }
+}
+
+/// Runs jobs off the shared queue until the pool is dropped.
+fn work(receiver: &Mutex<Receiver<Box<dyn FnOnce() + Send>>>) {
+    // Panics in here are reported by the group, not handled again
+    isolate::mark_isolated();
+
+    loop {
+        let job = receiver
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .recv();
+        match job {
+            Ok(job) => job(),
+            Err(_) => return,
+        }
+    }
+}
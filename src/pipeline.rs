@@ -0,0 +1,630 @@
+//! Runs an assembled pipeline for each panic, one step at a time, see [`EvacBuilder`].
+
+use std::backtrace::Backtrace;
+use std::fmt::Display;
+use std::panic::PanicHookInfo;
+use std::time::Instant;
+
+use crate::context::{Contended, Source, Unavailable};
+use crate::crash_loop::{CrashLoop, Tracker};
+use crate::crash_thread::CrashWorker;
+use crate::dedup::{self, Dedup};
+use crate::env::Environment;
+use crate::handle::Hook;
+use crate::last_run::{Beating, Heartbeat, Marker};
+use crate::limit::Limiter;
+use crate::parallel::Group;
+use crate::report::SharedReport;
+use crate::reserve::Reserve;
+use crate::stats::CrashStats;
+use crate::thread::{Layer, Suppressed};
+use crate::watchdog::Watchdog;
+use crate::CrashThread;
+#[cfg(feature = "all-threads")]
+use crate::{all_threads, ThreadTrace};
+use crate::{
+    breadcrumbs, core_dump, isolate, process, stderr, AfterPanic, AppMetadata, ArgsCapture,
+    BacktraceMode, CompletionCallback, Contention, EnvCapture, ErrorPolicy, ErrorSink, EvacBuilder,
+    Finalizer, Grouping, HandlerEntry, HandlerError, HandlerErrorCallback, HandlerKind,
+    HandlerOutcome, PanicReport, PipelineSummary, Position, ReportLimits, Scrubber, Skipped,
+    SuppressedCallback,
+};
+
+/// Everything a pipeline needs to handle a panic, set up when it's registered.
+pub(crate) struct Runner<T: 'static, E: 'static, S> {
+    handlers: Box<[HandlerEntry<T, E>]>,
+    existing_hook: Option<Position>,
+    ctx: S,
+    contention: Contention,
+    snapshot: Option<fn(&T) -> T>,
+    finalizers: Vec<Finalizer<T, E>>,
+    error_sink: Option<ErrorSink<E>>,
+    on_handler_error: Vec<HandlerErrorCallback<E>>,
+    on_complete: Vec<CompletionCallback>,
+    error_policy: Option<ErrorPolicy>,
+    critical_policy: Option<ErrorPolicy>,
+    isolate: bool,
+    watchdog: Option<Watchdog>,
+    after_panic: Option<AfterPanic>,
+    crash_thread: Option<CrashWorker>,
+    memory_reserve: Option<Reserve>,
+    limiter: Limiter,
+    on_suppressed: Vec<SuppressedCallback<T>>,
+    dedup: Option<Dedup>,
+    crash_loop: Option<Tracker>,
+    crash_marker: Option<Marker>,
+    crash_stats: Option<CrashStats>,
+    heartbeat: Option<Beating>,
+    ignore_environment: bool,
+    backtrace: Option<BacktraceMode>,
+    app_metadata: Option<AppMetadata>,
+    env_capture: Option<EnvCapture>,
+    args_capture: Option<ArgsCapture>,
+    #[cfg(feature = "all-threads")]
+    all_threads: bool,
+    #[cfg(feature = "sysinfo")]
+    system_info: Option<std::time::Duration>,
+    grouping: Option<Grouping>,
+    scrubber: Option<Scrubber>,
+    limits: Option<ReportLimits>,
+    /// Whether the summary is kept, which is only if something is going to read it, as it
+    /// allocates.
+    summarized: bool,
+    /// How many outcomes the summary has room for.
+    outcomes: usize,
+    /// Whether a report is built, which is likewise only if something takes it.
+    reported: bool,
+}
+
+/// What's known of the panic being handled, taken on the panicking thread, wherever its handlers
+/// end up running.
+struct Panic<'a> {
+    info: &'a PanicHookInfo<'a>,
+    env: Environment,
+    /// As [`Dedup::admit`] counted them, if repeats are being counted.
+    occurrences: Option<Option<u64>>,
+    admitted: bool,
+    backtrace: Option<Backtrace>,
+    #[cfg(feature = "all-threads")]
+    threads: Vec<ThreadTrace>,
+    /// The thread's own handlers, see [`crate::thread`], and which of the pipeline's they skip.
+    overrides: Option<&'a Layer>,
+    suppressed: Option<Suppressed<'a>>,
+    /// Whether the thread's own handlers are still to be run, once the crash thread is done.
+    overrides_due: bool,
+    /// The code to exit with once the panic is handled, if a failed handler asked for an exit.
+    exit: Option<i32>,
+}
+
+impl<T: Send + 'static, E: Display + 'static, S: Source<T>> Runner<T, E, S> {
+    /// Sets up what `builder` describes, handing the handlers `ctx`. Anything that runs alongside
+    /// the pipeline is spawned now, as it can't be relied on to spawn once something has gone
+    /// wrong.
+    pub(crate) fn new(builder: EvacBuilder<T, E>, ctx: S) -> Self {
+        let EvacBuilder {
+            mut handlers,
+            existing_hook,
+            contention,
+            snapshot,
+            finalizers,
+            error_sink,
+            on_handler_error,
+            on_complete,
+            error_policy,
+            critical_policy,
+            isolate,
+            deadline,
+            after_panic,
+            crash_thread,
+            memory_reserve,
+            sample_rate,
+            max_reports,
+            on_suppressed,
+            dedup_window,
+            crash_loop,
+            crash_marker,
+            crash_stats,
+            heartbeat,
+            ignore_environment,
+            backtrace,
+            app_metadata,
+            env_capture,
+            args_capture,
+            #[cfg(feature = "all-threads")]
+            all_threads,
+            #[cfg(feature = "sysinfo")]
+            system_info,
+            grouping,
+            scrubber,
+            limits,
+        } = builder;
+
+        // Stable, so insertion order is kept within a priority
+        handlers.sort_by_key(|entry| entry.priority);
+        let handlers = handlers.into_boxed_slice();
+
+        let summarized = !finalizers.is_empty() || !on_complete.is_empty();
+        let outcomes = match summarized {
+            true => handlers.iter().map(|entry| entry.handler.len()).sum(),
+            false => 0,
+        };
+        let reported = crash_stats.is_some()
+            || handlers
+                .iter()
+                .any(|entry| matches!(entry.handler, HandlerKind::Report(_)));
+
+        // Read ahead of time for the report, rather than from inside the hook
+        if reported {
+            process::started();
+        }
+
+        Self {
+            handlers,
+            existing_hook,
+            ctx,
+            contention,
+            snapshot,
+            finalizers,
+            error_sink,
+            on_handler_error,
+            on_complete,
+            error_policy,
+            critical_policy,
+            isolate,
+            watchdog: deadline.and_then(|(deadline, action)| Watchdog::spawn(deadline, action)),
+            after_panic,
+            crash_thread: crash_thread.and_then(CrashThread::spawn),
+            memory_reserve: memory_reserve.map(Reserve::new),
+            limiter: Limiter::new(sample_rate, max_reports),
+            on_suppressed,
+            dedup: dedup_window.map(Dedup::new),
+            crash_loop: crash_loop.map(CrashLoop::start),
+            crash_marker: crash_marker.map(Marker::start),
+            crash_stats: crash_stats.map(CrashStats::start),
+            heartbeat: heartbeat.map(Heartbeat::start),
+            ignore_environment,
+            backtrace,
+            app_metadata,
+            env_capture,
+            args_capture,
+            #[cfg(feature = "all-threads")]
+            all_threads,
+            #[cfg(feature = "sysinfo")]
+            system_info,
+            grouping,
+            scrubber,
+            limits,
+            summarized,
+            outcomes,
+            reported,
+        }
+    }
+
+    /// Handles the panic `info` describes, `previous` being the hook that was installed before.
+    pub(crate) fn hook(&self, info: &PanicHookInfo<'_>, previous: &Hook) {
+        // Before anything else, in case it's what's needed to get any further
+        let _released = self.memory_reserve.as_ref().map(Reserve::release);
+        // Then straight away, so that the next run knows, even if nothing else gets done
+        if let Some(crash_marker) = &self.crash_marker {
+            crash_marker.mark(info);
+        }
+        if let Some(heartbeat) = &self.heartbeat {
+            heartbeat.panicked();
+        }
+        let _running = self.watchdog.as_ref().map(Watchdog::start);
+
+        let mut panic = self.capture(info);
+
+        // If we're preserving the existing hook, it may go first
+        if self.existing_hook == Some(Position::Before) {
+            previous(info);
+        }
+
+        // Looked up here, as the handlers may be run on the crash thread, where the thread's own
+        // handlers mustn't be touched, as they needn't be `Send`
+        let overrides = Layer::current();
+        panic.overrides = overrides.as_deref();
+        panic.suppressed = overrides.as_deref().map(Layer::suppressed);
+        self.admit(&mut panic);
+
+        let lent = self.lend(&mut panic);
+
+        if panic.overrides_due {
+            self.run_overrides(&panic);
+        }
+        if let Err(unavailable) = lent {
+            self.report_unavailable(unavailable);
+        }
+
+        if self.existing_hook == Some(Position::After) {
+            previous(info);
+        }
+
+        self.after_panic(panic.exit);
+    }
+
+    /// Takes what has to be taken of the panic on the panicking thread, as the handlers may run on
+    /// another, and reads the environment.
+    fn capture<'a>(&self, info: &'a PanicHookInfo<'a>) -> Panic<'a> {
+        let backtrace = self
+            .backtrace
+            .filter(|_| self.reported)
+            .and_then(BacktraceMode::capture);
+        #[cfg(feature = "all-threads")]
+        let threads = match self.all_threads && self.reported {
+            true => all_threads::capture(),
+            false => vec![],
+        };
+        let env = match self.ignore_environment {
+            true => Environment::default(),
+            false => Environment::read(),
+        };
+        if env.disabled && env.verbose {
+            stderr::print_line(format_args!("evac: handlers disabled by EVAC_DISABLE"));
+        }
+
+        Panic {
+            info,
+            env,
+            occurrences: None,
+            admitted: false,
+            backtrace,
+            #[cfg(feature = "all-threads")]
+            threads,
+            overrides: None,
+            suppressed: None,
+            overrides_due: false,
+            exit: None,
+        }
+    }
+
+    /// Decides whether the panic is handled, up front, so that the context isn't waited on for a
+    /// panic that's not.
+    fn admit(&self, panic: &mut Panic<'_>) {
+        // Every panic counts towards a crash loop, whether or not it's handled
+        if let Some(crash_loop) = &self.crash_loop {
+            crash_loop.record();
+        }
+
+        panic.occurrences = self.dedup.as_ref().map(|dedup| dedup.admit(panic.info));
+        // Repeats don't count towards the rate limit
+        panic.admitted = panic.occurrences != Some(None) && self.limiter.admit();
+    }
+
+    /// Hands the context to [`Runner::dispatch`], holding it for the duration of the handlers, as
+    /// per the contention policy.
+    fn lend(&self, panic: &mut Panic<'_>) -> Result<(), Unavailable> {
+        // Unless the handlers are off, or there's nothing to hand it to
+        if panic.env.disabled || (!panic.admitted && self.on_suppressed.is_empty()) {
+            return Ok(());
+        }
+
+        match self.snapshot {
+            // Or just long enough to take a copy for this panic
+            Some(snapshot) => {
+                let mut copy = None;
+                let lent = self
+                    .ctx
+                    .lend(self.contention, &mut |ctx| copy = Some(snapshot(ctx)));
+                if let Some(copy) = &mut copy {
+                    self.dispatch(panic, copy);
+                }
+
+                lent
+            }
+            None => self
+                .ctx
+                .lend(self.contention, &mut |ctx| self.dispatch(panic, ctx)),
+        }
+    }
+
+    /// Runs the handlers wherever they're to run. The context is still lent out on the panicking
+    /// thread, so that it's where providers are called and contention is checked.
+    fn dispatch(&self, panic: &mut Panic<'_>, ctx: &mut T) {
+        match &self.crash_thread {
+            Some(crash_thread) => crash_thread.run(&mut || self.run(panic, ctx)),
+            None => self.run(panic, ctx),
+        }
+    }
+
+    /// Handles the panic with the context, or tells the callbacks it's not being handled.
+    fn run(&self, panic: &mut Panic<'_>, ctx: &mut T) {
+        if !panic.admitted {
+            for callback in &self.on_suppressed {
+                callback(panic.info, ctx);
+            }
+
+            return;
+        }
+
+        let _occurrences = dedup::enter(panic.occurrences.flatten());
+        let mut summary = PipelineSummary::with_capacity(self.outcomes);
+        let mut panic_report = self.build_report(panic);
+
+        self.run_handlers(panic, ctx, panic_report.as_mut(), &mut summary);
+
+        // The thread's own handlers go after the pipeline's, or once the crash thread is done, if
+        // that's where this is
+        match self.crash_thread.is_some() {
+            true => panic.overrides_due = true,
+            false => self.run_overrides(panic),
+        }
+
+        // Then wrap up, regardless of how that went
+        for (index, finalizer) in self.finalizers.iter().enumerate() {
+            if let Err(e) = finalizer(ctx, &summary) {
+                self.report(&HandlerError::Finalizer { index, error: &e });
+            }
+        }
+
+        for callback in &self.on_complete {
+            callback(&summary);
+        }
+    }
+
+    /// The report handed to report handlers, if there are any to take it.
+    fn build_report<'a>(&self, panic: &mut Panic<'a>) -> Option<PanicReport<'a>> {
+        if !self.reported {
+            return None;
+        }
+
+        let mut report = PanicReport::new(panic.info, panic.backtrace.take(), self.app_metadata);
+        report.breadcrumbs = breadcrumbs::drain();
+        if let Some(env_capture) = &self.env_capture {
+            report.env_vars = env_capture.capture();
+        }
+        if let Some(args_capture) = &self.args_capture {
+            report.args = args_capture.capture();
+        }
+        #[cfg(feature = "all-threads")]
+        {
+            report.threads = std::mem::take(&mut panic.threads);
+        }
+        #[cfg(feature = "sysinfo")]
+        if let Some(budget) = self.system_info {
+            report.system_info = Some(crate::sysinfo::SystemInfo::gather(budget));
+        }
+        report.fingerprint = match &self.grouping {
+            Some(grouping) => grouping.fingerprint(&report),
+            None => Grouping::default().fingerprint(&report),
+        };
+        if let Some(crash_stats) = &self.crash_stats {
+            crash_stats.record(&report);
+        }
+        if let Some(scrubber) = &self.scrubber {
+            scrubber.scrub(&mut report);
+        }
+        if let Some(limits) = self.limits {
+            limits.apply(&mut report);
+        }
+
+        Some(report)
+    }
+
+    /// Runs each registered handler, reporting any errors, and stopping or exiting as their
+    /// policies say.
+    fn run_handlers<'s>(
+        &'s self,
+        panic: &mut Panic<'_>,
+        ctx: &mut T,
+        mut panic_report: Option<&mut PanicReport<'_>>,
+        summary: &mut PipelineSummary<'s>,
+    ) {
+        let mut index = 0;
+        for entry in self.handlers.iter() {
+            // Skipped handlers aren't part of the summary
+            if entry.skips(panic.info, ctx, &panic.env, panic.suppressed.as_ref()) {
+                index += entry.handler.len();
+                continue;
+            }
+
+            let started = Instant::now();
+            let succeeded = match &entry.handler {
+                HandlerKind::Single(_) | HandlerKind::Report(_) => {
+                    let panic_report = panic_report.as_deref_mut();
+                    self.run_single(entry, index, panic.info, ctx, panic_report, summary)
+                }
+                HandlerKind::Group(group) => self.run_group(group, index, panic.info, ctx, summary),
+            };
+
+            if panic.env.verbose {
+                let outcome = if succeeded { "succeeded" } else { "failed" };
+                match &entry.name {
+                    Some(name) => stderr::print_line(format_args!(
+                        "evac: handler `{name}` {outcome} in {:?}",
+                        started.elapsed()
+                    )),
+                    None => stderr::print_line(format_args!(
+                        "evac: handler #{index} {outcome} in {:?}",
+                        started.elapsed()
+                    )),
+                }
+            }
+            index += entry.handler.len();
+
+            if !succeeded {
+                match entry.policy(self.error_policy, self.critical_policy) {
+                    ErrorPolicy::Continue => {}
+                    ErrorPolicy::StopChain => break,
+                    ErrorPolicy::AbortProcess => std::process::abort(),
+                    // The first failure to ask for an exit decides the code
+                    ErrorPolicy::Exit(code) => {
+                        panic.exit.get_or_insert(code);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs a single handler, isolated as configured, returning whether it succeeded.
+    fn run_single<'s>(
+        &'s self,
+        entry: &'s HandlerEntry<T, E>,
+        index: usize,
+        info: &PanicHookInfo<'_>,
+        ctx: &mut T,
+        panic_report: Option<&mut PanicReport<'_>>,
+        summary: &mut PipelineSummary<'s>,
+    ) -> bool {
+        let started = Instant::now();
+
+        // Errors are reported from wherever the handler ran, as they needn't be `Send`
+        let attempt = |info: &PanicHookInfo<'_>,
+                       ctx: &mut T,
+                       panic_report: Option<SharedReport<'_, '_>>| {
+            let mut panic_report = panic_report;
+            let call = || match (&entry.handler, &mut panic_report) {
+                (HandlerKind::Report(handler), Some(panic_report)) => handler(panic_report.0, ctx),
+                (HandlerKind::Single(handler), _) => handler(info, ctx),
+                _ => unreachable!("report handlers are always given one"),
+            };
+            let Err(e) = entry.retry.run(call) else {
+                return Ok(());
+            };
+
+            for callback in &self.on_handler_error {
+                callback(entry.name.as_deref(), index, &e);
+            }
+
+            self.report(&HandlerError::Handler {
+                name: entry.name.as_deref(),
+                index,
+                error: &e,
+            });
+
+            match self.summarized {
+                true => Err(e.to_string()),
+                false => Err(String::new()),
+            }
+        };
+
+        let panic_report = panic_report.map(SharedReport);
+        let result = match (self.isolate, self.crash_thread.is_some()) {
+            (true, _) => isolate::run(info, |info| attempt(info, &mut *ctx, panic_report)),
+            // Where a panic can be caught as it is
+            (false, true) => isolate::catch(|| attempt(info, &mut *ctx, panic_report)),
+            (false, false) => Ok(attempt(info, ctx, panic_report)),
+        };
+        let result =
+            result.unwrap_or_else(|message| self.panicked(entry.name.as_deref(), index, message));
+
+        let succeeded = result.is_ok();
+        if self.summarized {
+            summary.record(HandlerOutcome {
+                name: entry.name.as_deref(),
+                index,
+                succeeded,
+                error: result.err(),
+                duration: started.elapsed(),
+            });
+        }
+
+        succeeded
+    }
+
+    /// Runs a group of handlers, returning whether they all succeeded.
+    fn run_group<'s>(
+        &'s self,
+        group: &'s Group<T, E>,
+        index: usize,
+        info: &PanicHookInfo<'_>,
+        ctx: &mut T,
+        summary: &mut PipelineSummary<'s>,
+    ) -> bool {
+        let started = Instant::now();
+
+        let fail = |member: usize, e: &E| {
+            let name = group.names[member].as_deref();
+
+            for callback in &self.on_handler_error {
+                callback(name, index + member, e);
+            }
+
+            self.report(&HandlerError::Handler {
+                name,
+                index: index + member,
+                error: e,
+            });
+        };
+
+        let results = (group.run)(info, ctx, &fail);
+
+        let duration = started.elapsed();
+
+        let mut succeeded = true;
+        for (member, result) in results.into_iter().enumerate() {
+            let name = group.names[member].as_deref();
+            let result =
+                result.unwrap_or_else(|message| self.panicked(name, index + member, message));
+
+            succeeded &= result.is_ok();
+            if self.summarized {
+                summary.record(HandlerOutcome {
+                    name,
+                    index: index + member,
+                    succeeded: result.is_ok(),
+                    error: result.err(),
+                    duration,
+                });
+            }
+        }
+
+        succeeded
+    }
+
+    /// Runs the thread's own handlers, if it has any.
+    fn run_overrides(&self, panic: &Panic<'_>) {
+        if let Some(overrides) = panic.overrides {
+            overrides.run(panic.info, |err| self.report(err));
+        }
+    }
+
+    /// Reports a caught panic, which a handler's outcome then records as a failure.
+    fn panicked(&self, name: Option<&str>, index: usize, message: String) -> Result<(), String> {
+        self.report(&HandlerError::Panicked {
+            name,
+            index,
+            message: &message,
+        });
+
+        Err(message)
+    }
+
+    /// Reports why the handlers couldn't be given the context.
+    fn report_unavailable(&self, unavailable: Unavailable) {
+        match unavailable {
+            Unavailable::Contended(Contended::Busy) => {
+                self.report(&HandlerError::Skipped(Skipped::Busy))
+            }
+            Unavailable::Contended(Contended::Reentrant) => {
+                self.report(&HandlerError::Skipped(Skipped::Reentrant))
+            }
+            Unavailable::Init(e) | Unavailable::Provider(e) => {
+                self.report(&HandlerError::Context { error: &*e })
+            }
+            Unavailable::InitFailed => self.report(&HandlerError::Skipped(Skipped::ContextFailed)),
+        }
+    }
+
+    /// Exits, if a handler asked for it, or otherwise does as [`AfterPanic`] says.
+    fn after_panic(&self, exit: Option<i32>) {
+        if let Some(code) = exit {
+            std::process::exit(code);
+        }
+
+        match self.after_panic.unwrap_or_default() {
+            AfterPanic::Continue => {}
+            AfterPanic::Exit(code) => std::process::exit(code),
+            AfterPanic::Abort => std::process::abort(),
+            AfterPanic::CoreDump => core_dump::raise(),
+        }
+    }
+
+    /// Sends `err` to the error sink, or prints it to `stderr` if there isn't one.
+    fn report(&self, err: &HandlerError<'_, E>) {
+        match &self.error_sink {
+            Some(sink) => sink(err),
+            None => stderr::print_line(format_args!("{err}")),
+        }
+    }
+}
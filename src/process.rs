@@ -0,0 +1,65 @@
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+/// When the process started, as far as it could be told.
+static STARTED: OnceLock<SystemTime> = OnceLock::new();
+
+/// When the process started. Worked out the first time it's asked for, which is when a pipeline
+/// is built, so that the hook needn't. Where the OS doesn't say, it's when first asked for
+/// instead.
+pub(crate) fn started() -> SystemTime {
+    *STARTED.get_or_init(|| read_started().unwrap_or_else(SystemTime::now))
+}
+
+/// The ID of the process that started this one, if it's still known.
+pub(crate) fn parent_id() -> Option<u32> {
+    #[cfg(unix)]
+    return Some(std::os::unix::process::parent_id());
+
+    #[cfg(not(unix))]
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn read_started() -> Option<SystemTime> {
+    use std::fs;
+    use std::time::Duration;
+
+    // How long after boot the process started, in clock ticks
+    let stat = fs::read_to_string("/proc/self/stat").ok()?;
+    // The fields after the name, which is in parentheses and may contain them. The start time is
+    // the 22nd field overall, the 20th after the name
+    let ticks: u64 = stat
+        .rsplit_once(')')?
+        .1
+        .split_whitespace()
+        .nth(19)?
+        .parse()
+        .ok()?;
+    // SAFETY: `sysconf` has no preconditions
+    let per_sec = u64::try_from(unsafe { libc::sysconf(libc::_SC_CLK_TCK) })
+        .ok()?
+        .max(1);
+
+    // How long it's been since boot. Counted back from now, as the boot time the OS gives drifts
+    // from the wall clock
+    // SAFETY: `now` is a valid `timespec` to write to
+    let now = unsafe {
+        let mut now = std::mem::zeroed::<libc::timespec>();
+        if libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut now) != 0 {
+            return None;
+        }
+
+        Duration::new(u64::try_from(now.tv_sec).ok()?, now.tv_nsec as u32)
+    };
+
+    let since_boot = Duration::from_secs(ticks / per_sec)
+        + Duration::from_nanos(ticks % per_sec * 1_000_000_000 / per_sec);
+
+    SystemTime::now().checked_sub(now.checked_sub(since_boot)?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_started() -> Option<SystemTime> {
+    None
+}
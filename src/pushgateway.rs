@@ -0,0 +1,100 @@
+//! Pushes panic metrics to a Prometheus Pushgateway, see
+//! [`handlers::pushgateway`](crate::handlers::pushgateway).
+
+use std::fmt::Write as _;
+use std::io;
+use std::time::UNIX_EPOCH;
+
+use crate::PanicReport;
+
+/// Gives an error if `name` can't be a label's name.
+pub(crate) fn check_label(name: &str) -> io::Result<()> {
+    let mut chars = name.chars();
+    let starts = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    if !starts || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') || name.starts_with("__") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{name}` isn't a valid label name"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// The path to push to for the group of `job` and `labels`, such as `/metrics/job/nightly`.
+pub(crate) fn group_path(job: &str, labels: &[(String, String)]) -> String {
+    let mut path = String::from("/metrics");
+    for (name, value) in [("job", job)].into_iter().chain(
+        labels
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    ) {
+        // Values that are empty, or hold slashes, can only be given in base64, as the path
+        // would otherwise be taken apart wrongly
+        match value.is_empty() || value.contains('/') {
+            true => {
+                let encoded = crate::json::base64(value.as_bytes())
+                    .replace('+', "-")
+                    .replace('/', "_");
+                path += &format!(
+                    "/{name}@base64/{}",
+                    if encoded.is_empty() { "=" } else { &encoded }
+                );
+            }
+            false => path += &format!("/{name}/{}", encode(value)),
+        }
+    }
+
+    path
+}
+
+/// `report` in the text exposition format.
+pub(crate) fn metrics(report: &PanicReport<'_>) -> String {
+    let version = report.app_metadata().map_or("unknown", |app| app.version);
+    let labels = format!(
+        "version=\"{}\",fingerprint=\"{}\"",
+        escape(version),
+        escape(report.fingerprint())
+    );
+    let timestamp = report
+        .timestamp()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+
+    format!(
+        "# HELP app_panics_total Panics, by version and fingerprint.\n\
+         # TYPE app_panics_total counter\n\
+         app_panics_total{{{labels}}} 1\n\
+         # HELP last_panic_timestamp When the last panic happened, in seconds since the epoch.\n\
+         # TYPE last_panic_timestamp gauge\n\
+         last_panic_timestamp{{{labels}}} {timestamp:.3}\n"
+    )
+}
+
+/// `value` as a label's value, between quotes.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// `value` as a segment of a path.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => {
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,560 @@
+//! Just enough of regular expressions for scrubbing reports and filtering panics by their
+//! message, so that it doesn't need another dependency, see
+//! [`Scrubber::rule`](crate::Scrubber::rule) and
+//! [`EvacBuilder::only_if_message_matches`](crate::EvacBuilder::only_if_message_matches).
+//!
+//! Supported are literals, `.`, classes such as `[a-z_]` and `[^0-9]`, the `\d`, `\w` and `\s`
+//! shorthands and their negations, `^`, `$` and `\b`, groups, `|`, and the greedy `*`, `+`, `?`
+//! and `{n,m}` quantifiers. A leading `(?i)` makes ASCII letters match either case.
+
+use std::cell::Cell;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::PatternError;
+
+/// How many steps a single search may take before it's given up on, so that a pattern that
+/// backtracks badly can't hold up the hook.
+const STEP_LIMIT: usize = 1_000_000;
+
+#[derive(Clone)]
+pub(crate) struct Regex {
+    pattern: String,
+    node: Node,
+    ignore_case: bool,
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Start,
+    End,
+    WordBoundary,
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+    },
+}
+
+impl Regex {
+    pub(crate) fn new(pattern: &str) -> Result<Self, PatternError> {
+        let (ignore_case, rest) = match pattern.strip_prefix("(?i)") {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+
+        let mut parser = Parser {
+            pattern,
+            chars: rest.chars().collect(),
+            pos: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unmatched `)`"));
+        }
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            node,
+            ignore_case,
+        })
+    }
+
+    /// Whether there's a match anywhere in `text`. A search that's given up on finds none.
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        let matcher = Matcher {
+            text: &chars,
+            ignore_case: self.ignore_case,
+            steps: Cell::new(0),
+        };
+
+        (0..=chars.len()).any(|pos| matcher.at(&self.node, pos, &mut |_| true))
+    }
+
+    /// Replaces every match in `text` with `replacement`, returning whether there were any.
+    pub(crate) fn replace_all(&self, text: &mut String, replacement: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        let matcher = Matcher {
+            text: &chars,
+            ignore_case: self.ignore_case,
+            steps: Cell::new(0),
+        };
+
+        let mut replaced = String::with_capacity(text.len());
+        let mut found = false;
+        let mut pos = 0;
+        let mut last_end = None;
+        while pos <= chars.len() {
+            let mut end = None;
+            matcher.at(&self.node, pos, &mut |matched| {
+                end = Some(matched);
+                true
+            });
+            if matcher.steps.get() > STEP_LIMIT {
+                break;
+            }
+
+            // An empty match where the last one ended doesn't count, as it'd be replaced twice
+            if end == Some(pos) && last_end == Some(pos) {
+                end = None;
+            }
+
+            match end {
+                Some(end) => {
+                    found = true;
+                    replaced.push_str(replacement);
+                    last_end = Some(end);
+                    // An empty match still moves on, so that it isn't found again
+                    if end == pos {
+                        replaced.extend(chars.get(pos));
+                        pos += 1;
+                    } else {
+                        pos = end;
+                    }
+                }
+                None => {
+                    replaced.extend(chars.get(pos));
+                    pos += 1;
+                }
+            }
+        }
+
+        // Given up on part way, so left as it was
+        if matcher.steps.get() > STEP_LIMIT || !found {
+            return false;
+        }
+
+        *text = replaced;
+        true
+    }
+}
+
+impl Debug for Regex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.pattern, f)
+    }
+}
+
+struct Parser<'a> {
+    pattern: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &'static str) -> PatternError {
+        PatternError {
+            pattern: self.pattern.to_string(),
+            reason,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+
+        Some(c)
+    }
+
+    fn alternation(&mut self) -> Result<Node, PatternError> {
+        let mut branches = vec![self.concat()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            branches.push(self.concat()?);
+        }
+
+        Ok(match branches.len() {
+            1 => branches.remove(0),
+            _ => Node::Alternate(branches),
+        })
+    }
+
+    fn concat(&mut self) -> Result<Node, PatternError> {
+        let mut nodes = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+
+        Ok(Node::Concat(nodes))
+    }
+
+    fn atom(&mut self) -> Result<Node, PatternError> {
+        Ok(match self.next() {
+            Some('.') => Node::Any,
+            Some('^') => Node::Start,
+            Some('$') => Node::End,
+            Some('(') => {
+                // Groups don't capture anything, so all of them work as non-capturing ones
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                }
+
+                let node = self.alternation()?;
+                if self.next() != Some(')') {
+                    return Err(self.error("unclosed `(`"));
+                }
+
+                node
+            }
+            Some('[') => self.class()?,
+            Some('\\') => match self.escape()? {
+                Escaped::Node(node) => node,
+                Escaped::Char(c) => Node::Char(c),
+            },
+            Some('*' | '+' | '?' | '{') => return Err(self.error("nothing to repeat")),
+            Some(c) => Node::Char(c),
+            None => return Err(self.error("unexpected end")),
+        })
+    }
+
+    fn quantified(&mut self, atom: Node) -> Result<Node, PatternError> {
+        let quantifier = match self.peek() {
+            Some(c @ ('*' | '+' | '?' | '{')) => c,
+            _ => return Ok(atom),
+        };
+        self.pos += 1;
+
+        let (min, max) = match quantifier {
+            '*' => (0, None),
+            '+' => (1, None),
+            '?' => (0, Some(1)),
+            _ => {
+                let invalid = |parser: &Self| parser.error("invalid `{`");
+                let min = self.number().ok_or_else(|| invalid(self))?;
+                let max = match self.next() {
+                    Some('}') => Some(min),
+                    Some(',') if self.peek() == Some('}') => {
+                        self.pos += 1;
+                        None
+                    }
+                    Some(',') => {
+                        let max = self.number().ok_or_else(|| invalid(self))?;
+                        if self.next() != Some('}') || max < min {
+                            return Err(invalid(self));
+                        }
+
+                        Some(max)
+                    }
+                    _ => return Err(invalid(self)),
+                };
+
+                (min, max)
+            }
+        };
+
+        if matches!(self.peek(), Some('*' | '+' | '?' | '{')) {
+            return Err(self.error("only greedy, single quantifiers are supported"));
+        }
+
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+        })
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    fn class(&mut self) -> Result<Node, PatternError> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+
+        let mut ranges = vec![];
+        let mut first = true;
+        loop {
+            let start = match self.next() {
+                None => return Err(self.error("unclosed `[`")),
+                // Closes it, unless it's first, which is taken literally
+                Some(']') if !first => break,
+                Some('\\') => match self.escape()? {
+                    Escaped::Node(Node::Class {
+                        ranges: shorthand,
+                        negated: false,
+                    }) => {
+                        ranges.extend(shorthand);
+                        first = false;
+                        continue;
+                    }
+                    Escaped::Char(c) => c,
+                    Escaped::Node(_) => return Err(self.error("unsupported escape in `[`")),
+                },
+                Some(c) => c,
+            };
+            first = false;
+
+            let end = match (self.peek(), self.chars.get(self.pos + 1)) {
+                (Some('-'), Some(&end)) if end != ']' => {
+                    self.pos += 2;
+                    match end {
+                        '\\' => match self.escape()? {
+                            Escaped::Char(c) => c,
+                            Escaped::Node(_) => return Err(self.error("invalid range in `[`")),
+                        },
+                        end => end,
+                    }
+                }
+                _ => start,
+            };
+            if end < start {
+                return Err(self.error("invalid range in `[`"));
+            }
+
+            ranges.push((start, end));
+        }
+
+        Ok(Node::Class { ranges, negated })
+    }
+
+    fn escape(&mut self) -> Result<Escaped, PatternError> {
+        let class = |ranges: &[(char, char)], negated| {
+            Escaped::Node(Node::Class {
+                ranges: ranges.to_vec(),
+                negated,
+            })
+        };
+        const DIGIT: &[(char, char)] = &[('0', '9')];
+        const WORD: &[(char, char)] = &[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+        const SPACE: &[(char, char)] = &[(' ', ' '), ('\t', '\r')];
+
+        Ok(match self.next() {
+            Some('d') => class(DIGIT, false),
+            Some('D') => class(DIGIT, true),
+            Some('w') => class(WORD, false),
+            Some('W') => class(WORD, true),
+            Some('s') => class(SPACE, false),
+            Some('S') => class(SPACE, true),
+            Some('b') => Escaped::Node(Node::WordBoundary),
+            Some('n') => Escaped::Char('\n'),
+            Some('r') => Escaped::Char('\r'),
+            Some('t') => Escaped::Char('\t'),
+            Some(c) if !c.is_ascii_alphanumeric() => Escaped::Char(c),
+            Some(_) => return Err(self.error("unsupported escape")),
+            None => return Err(self.error("unexpected end")),
+        })
+    }
+}
+
+enum Escaped {
+    Node(Node),
+    Char(char),
+}
+
+struct Matcher<'a> {
+    text: &'a [char],
+    ignore_case: bool,
+    /// How many steps the current search has taken, see [`STEP_LIMIT`].
+    steps: Cell<usize>,
+}
+
+impl Matcher<'_> {
+    /// Matches `node` at `pos`, calling `next` with where each way of matching it ends, until
+    /// `next` accepts one.
+    fn at(&self, node: &Node, pos: usize, next: &mut dyn FnMut(usize) -> bool) -> bool {
+        self.steps.set(self.steps.get() + 1);
+        if self.steps.get() > STEP_LIMIT {
+            return false;
+        }
+
+        let current = self.text.get(pos).copied();
+        match node {
+            Node::Char(c) => current.is_some_and(|current| self.eq(current, *c)) && next(pos + 1),
+            Node::Any => current.is_some_and(|c| c != '\n') && next(pos + 1),
+            Node::Class { ranges, negated } => {
+                current.is_some_and(|c| self.in_ranges(c, ranges) != *negated) && next(pos + 1)
+            }
+            Node::Start => pos == 0 && next(pos),
+            Node::End => pos == self.text.len() && next(pos),
+            Node::WordBoundary => {
+                let word = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric() || *c == '_');
+                let before = pos.checked_sub(1).and_then(|i| self.text.get(i));
+                word(before) != word(self.text.get(pos)) && next(pos)
+            }
+            Node::Concat(nodes) => self.sequence(nodes, pos, next),
+            Node::Alternate(branches) => branches
+                .iter()
+                .any(|branch| self.at(branch, pos, &mut *next)),
+            Node::Repeat { node, min, max } => self.repeat(node, *min, *max, 0, pos, next),
+        }
+    }
+
+    fn sequence(&self, nodes: &[Node], pos: usize, next: &mut dyn FnMut(usize) -> bool) -> bool {
+        match nodes.split_first() {
+            None => next(pos),
+            Some((first, rest)) => self.at(first, pos, &mut |end| self.sequence(rest, end, next)),
+        }
+    }
+
+    fn repeat(
+        &self,
+        node: &Node,
+        min: u32,
+        max: Option<u32>,
+        count: u32,
+        pos: usize,
+        next: &mut dyn FnMut(usize) -> bool,
+    ) -> bool {
+        // Greedy, so another repetition is tried before stopping
+        if max.is_none_or(|max| count < max) {
+            let more = self.at(node, pos, &mut |end| {
+                // An empty repetition would repeat forever
+                end != pos && self.repeat(node, min, max, count + 1, end, next)
+            });
+            if more {
+                return true;
+            }
+        }
+
+        count >= min && next(pos)
+    }
+
+    fn eq(&self, a: char, b: char) -> bool {
+        match self.ignore_case {
+            true => a.eq_ignore_ascii_case(&b),
+            false => a == b,
+        }
+    }
+
+    fn in_ranges(&self, c: char, ranges: &[(char, char)]) -> bool {
+        let within = |c: char| {
+            ranges
+                .iter()
+                .any(|&(start, end)| (start..=end).contains(&c))
+        };
+        match self.ignore_case {
+            true => within(c.to_ascii_lowercase()) || within(c.to_ascii_uppercase()),
+            false => within(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What `text` is with every match of `pattern` replaced, by this and by the regex crate.
+    fn replaced(pattern: &str, text: &str) -> (Option<String>, Option<String>) {
+        let mut ours = text.to_string();
+        let ours = Regex::new(pattern)
+            .unwrap()
+            .replace_all(&mut ours, "<>")
+            .then_some(ours);
+
+        let theirs = ::regex::Regex::new(pattern).unwrap();
+        let theirs = theirs.is_match(text).then(|| {
+            theirs
+                .replace_all(text, ::regex::NoExpand("<>"))
+                .into_owned()
+        });
+
+        (ours, theirs)
+    }
+
+    #[test]
+    fn matches_as_the_regex_crate_does() {
+        let texts = [
+            "",
+            "password=hunter2 user=alice",
+            "Bearer eyJhbGciOiJIUzI1NiJ9.e30.ZRrHA1JJJW8opsbCGfG_HACGpVUMN_a9IV7pAx_Zmeo",
+            "card 4111-1111-1111-1111, ssn 078-05-1120",
+            "mail ALICE@Example.com or bob@example.org",
+            "aaa bbb\tccc\nddd",
+            "a_b a-b a.b",
+            "10.0.0.1:8080 and 192.168.1.254",
+        ];
+        let patterns = [
+            "password=\\S+",
+            "(?i)bearer [a-z0-9._-]+",
+            "\\d{4}-\\d{4}-\\d{4}-\\d{4}",
+            "\\d{3}-\\d{2}-\\d{4}",
+            "[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\\.[a-zA-Z]{2,}",
+            "(?i)ALICE|bob",
+            "\\bb\\w*",
+            "\\w+$",
+            "^\\w+",
+            "\\s+",
+            "[^a-z ]",
+            "a.b",
+            "a\\.b",
+            "(\\d{1,3}\\.){3}\\d{1,3}",
+            "(?:10|192)\\.\\d+",
+            ":\\d{2,4}",
+            "b+|c?d",
+            "[\\d.]+",
+            "\\W",
+            "\\D\\d",
+            "x?y{0}z*q",
+            // Matching nothing, as well as something
+            "x*",
+            "a*",
+        ];
+
+        for pattern in patterns {
+            for text in texts {
+                let (ours, theirs) = replaced(pattern, text);
+
+                assert_eq!(ours, theirs, "`{pattern}` in {text:?}");
+                assert_eq!(
+                    Regex::new(pattern).unwrap().is_match(text),
+                    ::regex::Regex::new(pattern).unwrap().is_match(text),
+                    "`{pattern}` in {text:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gives_up_on_patterns_that_backtrack_too_much() {
+        let mut text = "a".repeat(40);
+
+        assert!(!Regex::new("(a|a)*b").unwrap().replace_all(&mut text, "-"));
+        assert!(!Regex::new("(a|a)*b").unwrap().is_match(&text));
+        assert_eq!(text, "a".repeat(40));
+    }
+
+    #[test]
+    fn refuses_what_it_doesnt_support() {
+        let reason = |pattern| Regex::new(pattern).unwrap_err().reason;
+
+        assert_eq!(reason("(a"), "unclosed `(`");
+        assert_eq!(reason("a)"), "unmatched `)`");
+        assert_eq!(reason("*a"), "nothing to repeat");
+        assert_eq!(reason("[a-"), "unclosed `[`");
+        assert_eq!(reason("[z-a]"), "invalid range in `[`");
+        assert_eq!(
+            reason("a*?"),
+            "only greedy, single quantifiers are supported"
+        );
+        assert_eq!(reason("\\p{L}"), "unsupported escape");
+        assert_eq!(reason("a\\"), "unexpected end");
+    }
+}
@@ -0,0 +1,858 @@
+//! The facts gathered about a panic for report handlers, see [`PanicReport`], and how they're
+//! serialized.
+//!
+//! Serialized reports carry the [`SCHEMA_VERSION`] they were written with, and, with the `serde`
+//! feature, can be read back as a [`SerializedReport`], such as by whatever collects them.
+
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::error::Error;
+use std::io::{self, Write};
+use std::panic::{Location, PanicHookInfo};
+use std::thread::ThreadId;
+use std::time::{Duration, SystemTime};
+
+use crate::breadcrumbs::Breadcrumb;
+#[cfg(feature = "sysinfo")]
+use crate::sysinfo::SystemInfo;
+#[cfg(feature = "all-threads")]
+use crate::ThreadTrace;
+use crate::{crash_thread, filter, process, truncate, AppMetadata, ReportField, ReportLimits};
+
+/// The version of the layout reports are serialized with, written first in every serialized
+/// report as `schema_version`.
+///
+/// Fields are never removed or renamed, and never change meaning, without it being bumped. New
+/// fields are added without bumping it, so readers should ignore fields they don't know of, as
+/// [`SerializedReport`] does.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The type of closures accepted by
+/// [`EvacBuilder::with_report_handler`](crate::EvacBuilder::with_report_handler).
+pub type ReportHandler<T, E = Box<dyn Error>> =
+    Box<dyn Fn(&mut PanicReport<'_>, &mut T) -> Result<(), E> + 'static + Send + Sync>;
+
+/// The facts about a panic that most handlers want, worked out once for all of them. Handlers can
+/// add to it for the handlers after them, see [`PanicReport::annotate`].
+#[derive(Debug)]
+pub struct PanicReport<'a> {
+    info: &'a PanicHookInfo<'a>,
+    pub(crate) message: Option<String>,
+    payload_type: PayloadType,
+    thread_name: Option<String>,
+    thread_id: ThreadId,
+    pid: u32,
+    parent_pid: Option<u32>,
+    process_started: SystemTime,
+    timestamp: SystemTime,
+    backtrace: Option<Backtrace>,
+    app_metadata: Option<AppMetadata>,
+    pub(crate) env_vars: Vec<(String, String)>,
+    pub(crate) args: Vec<String>,
+    pub(crate) breadcrumbs: Vec<Breadcrumb>,
+    #[cfg(feature = "all-threads")]
+    pub(crate) threads: Vec<ThreadTrace>,
+    #[cfg(feature = "sysinfo")]
+    pub(crate) system_info: Option<SystemInfo>,
+    pub(crate) annotations: Vec<(String, String)>,
+    pub(crate) attachments: Vec<Attachment>,
+    pub(crate) limits: Option<ReportLimits>,
+    pub(crate) fingerprint: String,
+}
+
+/// What a panic's payload is, see [`PanicReport::payload_type`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum PayloadType {
+    /// A `&'static str`, as from `panic!` with a plain message.
+    Str,
+    /// A `String`, as from `panic!` with a formatted message.
+    String,
+    /// Anything else, as from [`std::panic::panic_any`].
+    Other,
+}
+
+impl<'a> PanicReport<'a> {
+    pub(crate) fn new(
+        info: &'a PanicHookInfo<'a>,
+        backtrace: Option<Backtrace>,
+        app_metadata: Option<AppMetadata>,
+    ) -> Self {
+        let payload = info.payload();
+        let payload_type = if payload.is::<&str>() {
+            PayloadType::Str
+        } else if payload.is::<String>() {
+            PayloadType::String
+        } else {
+            PayloadType::Other
+        };
+
+        let thread = crash_thread::panicking_thread();
+
+        Self {
+            info,
+            message: filter::message(info).map(str::to_owned),
+            payload_type,
+            thread_name: thread.name().map(str::to_owned),
+            thread_id: thread.id(),
+            pid: std::process::id(),
+            parent_pid: process::parent_id(),
+            process_started: process::started(),
+            timestamp: SystemTime::now(),
+            backtrace,
+            app_metadata,
+            env_vars: vec![],
+            args: vec![],
+            breadcrumbs: vec![],
+            #[cfg(feature = "all-threads")]
+            threads: vec![],
+            #[cfg(feature = "sysinfo")]
+            system_info: None,
+            annotations: vec![],
+            attachments: vec![],
+            limits: None,
+            fingerprint: String::new(),
+        }
+    }
+
+    /// The panic as std reports it, for anything the report doesn't cover.
+    pub fn info(&self) -> &'a PanicHookInfo<'a> {
+        self.info
+    }
+
+    /// The panic's message, if its payload is a string.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// What the panic's payload is.
+    pub fn payload_type(&self) -> PayloadType {
+        self.payload_type
+    }
+
+    /// Where the panic happened.
+    pub fn location(&self) -> Option<&Location<'a>> {
+        self.info.location()
+    }
+
+    /// The name of the thread that panicked, if it has one.
+    pub fn thread_name(&self) -> Option<&str> {
+        self.thread_name.as_deref()
+    }
+
+    /// The ID of the thread that panicked.
+    pub fn thread_id(&self) -> ThreadId {
+        self.thread_id
+    }
+
+    /// The ID of the process that panicked.
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// The ID of the process's parent, where there's such a thing.
+    pub fn parent_pid(&self) -> Option<u32> {
+        self.parent_pid
+    }
+
+    /// When the process started. Where the OS doesn't say, which is anywhere but Linux, it's when
+    /// the first pipeline was built instead.
+    pub fn process_started(&self) -> SystemTime {
+        self.process_started
+    }
+
+    /// How long the process had been running for when it panicked, see
+    /// [`PanicReport::process_started`].
+    pub fn uptime(&self) -> Duration {
+        self.timestamp
+            .duration_since(self.process_started)
+            .unwrap_or_default()
+    }
+
+    /// When the panic started being handled.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    /// Where the panicking thread was when it panicked, if it was captured, see
+    /// [`EvacBuilder::capture_backtrace`](crate::EvacBuilder::capture_backtrace).
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_ref()
+    }
+
+    /// The backtrace as reports show it, without the frames past
+    /// [`ReportLimits::max_frames`], if it's limited.
+    pub fn backtrace_text(&self) -> Option<String> {
+        let backtrace = self.backtrace.as_ref()?.to_string();
+        let max = self
+            .limits
+            .map_or(usize::MAX, |limits| limits.max_frames_or_all());
+
+        // Frames start with their index, such as `  12: main`, and go on with where they are
+        let mut frames = 0;
+        let mut kept = String::with_capacity(backtrace.len());
+        for line in backtrace.split_inclusive('\n') {
+            let frame = line
+                .trim_start()
+                .split_once(": ")
+                .is_some_and(|(index, _)| {
+                    !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit())
+                });
+            if frame {
+                frames += 1;
+            }
+            if frames <= max {
+                kept.push_str(line);
+            }
+        }
+        if frames > max {
+            kept.push_str(&format!("      [truncated: {} more frames]", frames - max));
+            if backtrace.ends_with('\n') {
+                kept.push('\n');
+            }
+        }
+
+        Some(kept)
+    }
+
+    /// What was built, and how, if it was given, see
+    /// [`EvacBuilder::app_metadata`](crate::EvacBuilder::app_metadata).
+    pub fn app_metadata(&self) -> Option<&AppMetadata> {
+        self.app_metadata.as_ref()
+    }
+
+    /// The environment variables, by name, if they were captured, see
+    /// [`EvacBuilder::capture_env`](crate::EvacBuilder::capture_env).
+    pub fn env_vars(&self) -> &[(String, String)] {
+        &self.env_vars
+    }
+
+    /// The command-line arguments, the program's name first, if they were captured, see
+    /// [`EvacBuilder::capture_args`](crate::EvacBuilder::capture_args).
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// What the application was doing leading up to the panic, oldest first, see
+    /// [`breadcrumbs`](crate::breadcrumbs).
+    pub fn breadcrumbs(&self) -> &[Breadcrumb] {
+        &self.breadcrumbs
+    }
+
+    /// Where each of the process's threads was, if they were traced, see
+    /// [`EvacBuilder::capture_all_threads`](crate::EvacBuilder::capture_all_threads).
+    #[cfg(feature = "all-threads")]
+    pub fn threads(&self) -> &[ThreadTrace] {
+        &self.threads
+    }
+
+    /// Facts about the host, if they were looked up, see
+    /// [`EvacBuilder::capture_system_info`](crate::EvacBuilder::capture_system_info).
+    #[cfg(feature = "sysinfo")]
+    pub fn system_info(&self) -> Option<&SystemInfo> {
+        self.system_info.as_ref()
+    }
+
+    /// What identifies the panic, as 16 hexadecimal digits, so that reports of the same panic can
+    /// be grouped together wherever they're collected, see [`Grouping`](crate::Grouping). It's
+    /// the same from one run to the next, and from one build to the next, for as long as what
+    /// it's worked out from is.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// Groups the panic with others given the same `fingerprint`, in place of the one it was
+    /// given, for the handlers that run after this one.
+    pub fn set_fingerprint(&mut self, fingerprint: impl Into<String>) {
+        self.fingerprint = fingerprint.into();
+    }
+
+    /// Adds a note to the report, for the handlers that run after this one.
+    pub fn annotate(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.annotations.push((key.into(), value.into()));
+    }
+
+    /// The notes added by earlier handlers, in the order they were added.
+    pub fn annotations(&self) -> &[(String, String)] {
+        &self.annotations
+    }
+
+    /// Runs `scrub` over each piece of free text in the report that may hold something sensitive:
+    /// the message, the thread's name, the values of environment variables and annotations, the
+    /// arguments, the breadcrumbs' messages, the host's name, and the other threads' names and
+    /// symbols. The backtrace, the location and attachments can't be rewritten, only
+    /// [cleared](PanicReport::clear), if need be.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// EvacBuilder::new()
+    ///   .with_report_handler(|report, _: &mut ()| {
+    ///     if let Ok(home) = std::env::var("HOME") {
+    ///       report.scrub_text(|text| *text = text.replace(&home, "~"));
+    ///     }
+    ///     Ok(())
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn scrub_text(&mut self, mut scrub: impl FnMut(&mut String)) {
+        if let Some(message) = &mut self.message {
+            scrub(message);
+        }
+        if let Some(thread_name) = &mut self.thread_name {
+            scrub(thread_name);
+        }
+        for (_, value) in self.env_vars.iter_mut().chain(&mut self.annotations) {
+            scrub(value);
+        }
+        for arg in &mut self.args {
+            scrub(arg);
+        }
+        for breadcrumb in &mut self.breadcrumbs {
+            scrub(breadcrumb.message.to_mut());
+        }
+        #[cfg(feature = "sysinfo")]
+        if let Some(hostname) = self
+            .system_info
+            .as_mut()
+            .and_then(|host| host.hostname.as_mut())
+        {
+            scrub(hostname);
+        }
+        #[cfg(feature = "all-threads")]
+        for thread in &mut self.threads {
+            let symbols = thread
+                .frames
+                .iter_mut()
+                .filter_map(|frame| frame.symbol.as_mut());
+            for text in thread.name.iter_mut().chain(symbols) {
+                scrub(text);
+            }
+        }
+    }
+
+    /// Removes `field` from the report, for the handlers after this one.
+    pub fn clear(&mut self, field: ReportField) {
+        match field {
+            ReportField::Message => self.message = None,
+            ReportField::ThreadName => self.thread_name = None,
+            ReportField::Backtrace => self.backtrace = None,
+            ReportField::AppMetadata => self.app_metadata = None,
+            ReportField::EnvVars => self.env_vars.clear(),
+            ReportField::Args => self.args.clear(),
+            ReportField::Breadcrumbs => self.breadcrumbs.clear(),
+            #[cfg(feature = "all-threads")]
+            ReportField::Threads => self.threads.clear(),
+            #[cfg(feature = "sysinfo")]
+            ReportField::SystemInfo => self.system_info = None,
+            ReportField::Annotations => self.annotations.clear(),
+            ReportField::Attachments => self.attachments.clear(),
+            // Never captured without their features
+            #[allow(unreachable_patterns)]
+            ReportField::Threads | ReportField::SystemInfo => {}
+        }
+    }
+
+    /// Attaches a file's worth of data to the report, such as a heap profile, for the handlers that
+    /// run after this one to include wherever they send the report. Attaching under a name that's
+    /// already taken replaces what was there.
+    ///
+    /// ## Example
+    /// ```
+    /// # use evac::EvacBuilder;
+    /// # fn heap_stats() -> Vec<u8> { vec![] }
+    /// EvacBuilder::new()
+    ///   .with_report_handler(|report, _: &mut ()| {
+    ///     report.attach("heap-stats.json", heap_stats());
+    ///     Ok(())
+    ///   })
+    ///   .with_report_handler(|report, _| {
+    ///     let dir = std::env::temp_dir();
+    ///     for attachment in report.attachments() {
+    ///       std::fs::write(dir.join(&attachment.name), &attachment.data)?;
+    ///     }
+    ///     Ok(())
+    ///   })
+    ///   .register(())?;
+    /// # Ok::<(), evac::RegisterError>(())
+    /// ```
+    pub fn attach(&mut self, name: impl Into<String>, data: impl Into<Vec<u8>>) {
+        let mut attachment = Attachment {
+            name: name.into(),
+            data: data.into(),
+            truncated: 0,
+        };
+        let replaced = self
+            .attachments
+            .iter()
+            .position(|existing| existing.name == attachment.name);
+
+        // Cut to fit, as per the limits, if any
+        if let Some(limits) = self.limits {
+            let mut size = truncate::size(self);
+            if let Some(replaced) = replaced {
+                let replaced = &self.attachments[replaced];
+                size -= replaced.name.len() + replaced.data.len();
+            }
+            let kept = limits.attachment_room(size + attachment.name.len(), attachment.data.len());
+            attachment.truncated = attachment.data.len() - kept;
+            attachment.data.truncate(kept);
+        }
+
+        match replaced {
+            Some(replaced) => self.attachments[replaced] = attachment,
+            None => self.attachments.push(attachment),
+        }
+    }
+
+    /// The data attached under `name`, if there is any.
+    pub fn attachment(&self, name: &str) -> Option<&[u8]> {
+        self.attachments
+            .iter()
+            .find(|attachment| attachment.name == name)
+            .map(|attachment| &*attachment.data)
+    }
+
+    /// Everything attached by earlier handlers, in the order it was first attached.
+    pub fn attachments(&self) -> &[Attachment] {
+        &self.attachments
+    }
+}
+
+/// Data attached to a report, see [`PanicReport::attach`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Attachment {
+    /// What it's called, such as a file name.
+    pub name: String,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::report::serialize_bytes")
+    )]
+    pub data: Vec<u8>,
+    /// How many bytes were cut off the end of it, see [`ReportLimits`].
+    pub truncated: usize,
+}
+
+/// How the backtrace is captured, see
+/// [`EvacBuilder::capture_backtrace`](crate::EvacBuilder::capture_backtrace).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum BacktraceMode {
+    /// Always captured, whatever `RUST_BACKTRACE` and `RUST_LIB_BACKTRACE` say, with its symbols
+    /// looked up once it's first printed.
+    Forced,
+    /// Captured if `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` ask for it, with its symbols looked up
+    /// straight away, while the process is in the best shape to do so.
+    Resolved,
+    /// Captured if `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` ask for it, with its symbols looked up
+    /// once it's first printed, which keeps the hook quick if nothing prints it.
+    Unresolved,
+}
+
+impl BacktraceMode {
+    /// Captures the current thread's backtrace, if it's wanted.
+    pub(crate) fn capture(self) -> Option<Backtrace> {
+        let backtrace = match self {
+            BacktraceMode::Forced => Backtrace::force_capture(),
+            BacktraceMode::Resolved | BacktraceMode::Unresolved => Backtrace::capture(),
+        };
+        if backtrace.status() != BacktraceStatus::Captured {
+            return None;
+        }
+
+        // Printing it is the only way to have its symbols looked up
+        if self == BacktraceMode::Resolved {
+            let _ = write!(io::sink(), "{backtrace}");
+        }
+
+        Some(backtrace)
+    }
+}
+
+/// Lends the report to the helper thread an isolated handler runs on.
+pub(crate) struct SharedReport<'a, 'b>(pub(crate) &'a mut PanicReport<'b>);
+
+// The report is only `!Send` because of the panic info it holds, which is lent out the same way,
+// see `isolate::run`. The panicking thread is blocked until the helper is done with both.
+unsafe impl Send for SharedReport<'_, '_> {}
+
+/// The fields are written in the order they're listed here, which is kept stable, starting with
+/// the [`SCHEMA_VERSION`], and times are given in milliseconds since the Unix epoch. Attachments
+/// are written as bytes, which formats without their own, such as JSON, write as base64.
+#[cfg(feature = "serde")]
+impl serde::Serialize for PanicReport<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        #[derive(serde::Serialize)]
+        struct At<'a> {
+            file: &'a str,
+            line: u32,
+            column: u32,
+        }
+
+        /// Written as an object, even if its keys repeat.
+        struct Pairs<'a>(&'a [(String, String)]);
+
+        impl serde::Serialize for Pairs<'_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.iter().map(|(key, value)| (key, value)))
+            }
+        }
+
+        let fields =
+            19 + cfg!(feature = "all-threads") as usize + cfg!(feature = "sysinfo") as usize;
+        let mut report = serializer.serialize_struct("PanicReport", fields)?;
+
+        report.serialize_field("schema_version", &SCHEMA_VERSION)?;
+        report.serialize_field("message", &self.message)?;
+        report.serialize_field("payload_type", &self.payload_type)?;
+        report.serialize_field(
+            "location",
+            &self.location().map(|location| At {
+                file: location.file(),
+                line: location.line(),
+                column: location.column(),
+            }),
+        )?;
+        report.serialize_field("thread_name", &self.thread_name)?;
+        report.serialize_field("thread_id", &thread_number(self.thread_id))?;
+        report.serialize_field("pid", &self.pid)?;
+        report.serialize_field("parent_pid", &self.parent_pid)?;
+        report.serialize_field("process_started", &millis(self.process_started))?;
+        report.serialize_field("uptime_ms", &(self.uptime().as_millis() as u64))?;
+        report.serialize_field("timestamp", &millis(self.timestamp))?;
+        report.serialize_field("backtrace", &self.backtrace_text())?;
+        report.serialize_field("app_metadata", &self.app_metadata)?;
+        report.serialize_field("env_vars", &Pairs(&self.env_vars))?;
+        report.serialize_field("args", &self.args)?;
+        report.serialize_field("breadcrumbs", &self.breadcrumbs)?;
+        #[cfg(feature = "all-threads")]
+        report.serialize_field("threads", &self.threads)?;
+        #[cfg(feature = "sysinfo")]
+        report.serialize_field("system_info", &self.system_info)?;
+        report.serialize_field("annotations", &Pairs(&self.annotations))?;
+        report.serialize_field("attachments", &self.attachments)?;
+        report.serialize_field("fingerprint", &self.fingerprint)?;
+
+        report.end()
+    }
+}
+
+/// The number std gives a thread's ID, which it only shows through `Debug`.
+pub(crate) fn thread_number(id: ThreadId) -> Option<u64> {
+    let id = format!("{id:?}");
+    id.strip_prefix("ThreadId(")?
+        .strip_suffix(')')?
+        .parse()
+        .ok()
+}
+
+#[cfg(feature = "serde")]
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// For `#[serde(serialize_with)]`, see [`millis`].
+#[cfg(feature = "serde")]
+pub(crate) fn serialize_millis<S: serde::Serializer>(
+    time: &SystemTime,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(millis(*time))
+}
+
+/// For `#[serde(serialize_with)]`, writing pairs as an object, as annotations are.
+#[cfg(feature = "serde")]
+pub(crate) fn serialize_pairs<S: serde::Serializer>(
+    pairs: &[(String, String)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(pairs.iter().map(|(key, value)| (key, value)))
+}
+
+/// For `#[serde(serialize_with)]`, writing `Vec<u8>` as bytes rather than a list of numbers.
+#[cfg(feature = "serde")]
+pub(crate) fn serialize_bytes<S: serde::Serializer>(
+    bytes: &[u8],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(bytes)
+}
+
+/// A report read back from its serialized form, such as by a server collecting them, whatever
+/// features it was written with.
+///
+/// Fields missing from the report, such as ones added since it was written, or ones it was written
+/// without, are left as their defaults, and fields that aren't known of, such as ones added since
+/// this version of evac, are ignored, so that readers and writers can be updated separately.
+///
+/// ## Example
+/// ```
+/// # use evac::report::{SerializedReport, SCHEMA_VERSION};
+/// # fn parse(_: &str) -> SerializedReport { SerializedReport::default() }
+/// # let line = "";
+/// // With a JSON deserializer, such as `serde_json::from_str`
+/// let report: SerializedReport = parse(line);
+/// if report.schema_version > SCHEMA_VERSION {
+///   eprintln!("report is newer than expected, some fields may be missing");
+/// }
+/// ```
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct SerializedReport {
+    /// The [`SCHEMA_VERSION`] it was written with, or 0 if it was written before they were.
+    pub schema_version: u32,
+    pub message: Option<String>,
+    /// As [`PayloadType`] is named, in `snake_case`, such as `str`.
+    pub payload_type: String,
+    pub location: Option<SerializedLocation>,
+    pub thread_name: Option<String>,
+    pub thread_id: Option<u64>,
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    /// When the process started, in milliseconds since the Unix epoch.
+    pub process_started: u64,
+    pub uptime_ms: u64,
+    /// When the panic was reported, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub backtrace: Option<String>,
+    pub app_metadata: Option<SerializedAppMetadata>,
+    #[serde(deserialize_with = "deserialize_pairs")]
+    pub env_vars: Vec<(String, String)>,
+    pub args: Vec<String>,
+    pub breadcrumbs: Vec<SerializedBreadcrumb>,
+    pub threads: Vec<SerializedThreadTrace>,
+    pub system_info: Option<SerializedSystemInfo>,
+    #[serde(deserialize_with = "deserialize_pairs")]
+    pub annotations: Vec<(String, String)>,
+    pub attachments: Vec<SerializedAttachment>,
+    /// The [fingerprint](PanicReport::fingerprint), or empty if it was written before they were.
+    pub fingerprint: String,
+}
+
+/// Where the panic happened, see [`SerializedReport::location`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct SerializedLocation {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// [`AppMetadata`] as read back, see [`SerializedReport::app_metadata`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct SerializedAppMetadata {
+    pub name: String,
+    pub version: String,
+    pub target: Option<String>,
+    pub profile: String,
+    pub opt_level: Option<String>,
+    pub git_commit: Option<String>,
+}
+
+/// A [`Breadcrumb`] as read back, see [`SerializedReport::breadcrumbs`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct SerializedBreadcrumb {
+    pub category: String,
+    pub message: String,
+    /// When it was added, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// A thread's trace as read back, see [`SerializedReport::threads`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct SerializedThreadTrace {
+    pub tid: i32,
+    pub name: Option<String>,
+    pub state: Option<char>,
+    pub panicking: bool,
+    pub frames: Vec<SerializedStackFrame>,
+    pub truncated_frames: u64,
+}
+
+/// A stack frame as read back, see [`SerializedThreadTrace::frames`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct SerializedStackFrame {
+    pub ip: u64,
+    pub symbol: Option<String>,
+}
+
+/// Facts about the host as read back, see [`SerializedReport::system_info`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct SerializedSystemInfo {
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub kernel: Option<String>,
+    pub arch: String,
+    pub total_memory: Option<u64>,
+    pub available_memory: Option<u64>,
+    pub cpus: Option<usize>,
+    pub hostname: Option<String>,
+    /// The kind of container, in `snake_case`, such as `docker`.
+    pub container: Option<String>,
+}
+
+/// An [`Attachment`] as read back, see [`SerializedReport::attachments`].
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct SerializedAttachment {
+    pub name: String,
+    #[serde(deserialize_with = "deserialize_bytes")]
+    pub data: Vec<u8>,
+    pub truncated: u64,
+}
+
+/// For `#[serde(deserialize_with)]`, reading a map into pairs, in the order they were written.
+#[cfg(feature = "serde")]
+fn deserialize_pairs<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(String, String)>, D::Error> {
+    struct Pairs;
+
+    impl<'de> serde::de::Visitor<'de> for Pairs {
+        type Value = Vec<(String, String)>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("a map of strings")
+        }
+
+        fn visit_map<A: serde::de::MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> Result<Self::Value, A::Error> {
+            let mut pairs = Vec::with_capacity(map.size_hint().unwrap_or(0));
+            while let Some(pair) = map.next_entry()? {
+                pairs.push(pair);
+            }
+
+            Ok(pairs)
+        }
+    }
+
+    deserializer.deserialize_map(Pairs)
+}
+
+/// For `#[serde(deserialize_with)]`, reading bytes however the format wrote them: as bytes, a list
+/// of numbers, or base64, as with JSON.
+#[cfg(feature = "serde")]
+fn deserialize_bytes<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<u8>, D::Error> {
+    struct Bytes;
+
+    impl<'de> serde::de::Visitor<'de> for Bytes {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("bytes, or a base64 string")
+        }
+
+        fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+            Ok(v)
+        }
+
+        fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            crate::json::from_base64(v).ok_or_else(|| E::custom("invalid base64"))
+        }
+
+        fn visit_seq<A: serde::de::SeqAccess<'de>>(
+            self,
+            mut seq: A,
+        ) -> Result<Self::Value, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                bytes.push(byte);
+            }
+
+            Ok(bytes)
+        }
+    }
+
+    deserializer.deserialize_any(Bytes)
+}
+
+/// Reports of real panics, for tests, as there's no other way to get the info they're built from.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::panic;
+    use std::sync::{mpsc, Arc, Mutex, PoisonError};
+    use std::thread;
+
+    use super::PanicReport;
+
+    /// Held while a test's hook is installed, as there's only the one.
+    static HOOK: Mutex<()> = Mutex::new(());
+
+    /// Panics with `message`, and gives what `f` makes of its report, which it's given from inside
+    /// the hook. Asserting in `f` would abort, as it'd panic inside the hook, so assert on what it
+    /// gives instead. Other threads' panics go to the hook that was installed before.
+    pub(crate) fn with_report<R, F>(message: &'static str, f: F) -> R
+    where
+        R: Send + 'static,
+        F: Fn(&mut PanicReport<'_>) -> R + Send + Sync + 'static,
+    {
+        let _hook = HOOK.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let (sender, receiver) = mpsc::channel();
+        let previous = Arc::new(panic::take_hook());
+        let forward = Arc::clone(&previous);
+        let panicking = thread::current().id();
+        panic::set_hook(Box::new(move |info| {
+            if thread::current().id() != panicking {
+                return forward(info);
+            }
+
+            let mut report = PanicReport::new(info, None, Some(crate::app_metadata!()));
+            report.fingerprint = "0123456789abcdef".into();
+            let _ = sender.send(f(&mut report));
+        }));
+
+        let _ = panic::catch_unwind(|| panic!("{message}"));
+
+        drop(panic::take_hook());
+        if let Ok(previous) = Arc::try_unwrap(previous) {
+            panic::set_hook(previous);
+        }
+
+        receiver.recv().expect("the hook gives a report")
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Memory set aside on registration, and given back to the allocator when a panic is handled, see
+/// [`EvacBuilder::memory_reserve`](crate::EvacBuilder::memory_reserve).
+pub(crate) struct Reserve {
+    size: usize,
+    block: Mutex<Option<Vec<u8>>>,
+}
+
+impl Reserve {
+    pub(crate) fn new(size: usize) -> Self {
+        let reserve = Self {
+            size,
+            block: Mutex::new(None),
+        };
+        reserve.refill();
+
+        reserve
+    }
+
+    /// Frees the block, if it's still held. It's put back once the returned guard is dropped.
+    pub(crate) fn release(&self) -> Released<'_> {
+        drop(self.lock().take());
+
+        Released(self)
+    }
+
+    /// Takes the block back, unless memory is too tight to.
+    fn refill(&self) {
+        let mut block = self.lock();
+        if block.is_some() {
+            return;
+        }
+
+        let mut reserved = Vec::new();
+        if reserved.try_reserve_exact(self.size).is_ok() {
+            // Written to, so that the pages are really ours rather than just promised
+            reserved.resize(self.size, 0xAA);
+            *block = Some(reserved);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Vec<u8>>> {
+        self.block.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Held while a panic is being handled, see [`Reserve::release`].
+pub(crate) struct Released<'a>(&'a Reserve);
+
+impl Drop for Released<'_> {
+    fn drop(&mut self) {
+        self.0.refill();
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+#[cfg(any(feature = "otlp", feature = "grpc"))]
+use std::time::Instant;
+
+/// How many more times a failing handler is given a go, see
+/// [`EvacBuilder::handler_retry`](crate::EvacBuilder::handler_retry).
+///
+/// The delay before each retry doubles, starting from the [backoff](Retry::backoff), and is
+/// jittered to somewhere between half of and the full doubled delay, so that processes crashing
+/// together don't retry together. Without a backoff, retries happen straight away. By default,
+/// handlers aren't retried at all.
+///
+/// ## Example
+/// ```
+/// # use std::time::Duration;
+/// # use evac::Retry;
+/// // Waits 50-100ms, then 100-200ms
+/// let retry = Retry::retries(2).backoff(Duration::from_millis(100));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Retry {
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Retry {
+    /// Retries a failing handler up to `retries` times, without waiting in between.
+    pub fn retries(retries: u32) -> Self {
+        Self {
+            retries,
+            backoff: Duration::ZERO,
+        }
+    }
+
+    /// Waits around `backoff` before the first retry, and twice as long before each one after.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+
+        self
+    }
+
+    /// Runs `attempt` until it succeeds or runs out of retries, returning its last result.
+    pub(crate) fn run<E>(&self, attempt: impl FnMut() -> Result<(), E>) -> Result<(), E> {
+        self.run_while(attempt, |_| true)
+    }
+
+    /// As [`Retry::run`], but only retrying errors that are `retryable`.
+    pub(crate) fn run_while<E>(
+        &self,
+        mut attempt: impl FnMut() -> Result<(), E>,
+        retryable: impl Fn(&E) -> bool,
+    ) -> Result<(), E> {
+        let mut result = attempt();
+
+        for retry in 0..self.retries {
+            match &result {
+                Err(e) if retryable(e) => {}
+                _ => break,
+            }
+
+            std::thread::sleep(self.delay(retry));
+            result = attempt();
+        }
+
+        result
+    }
+
+    /// As [`Retry::run_while`], but giving up at `deadline`, rather than starting an attempt, or
+    /// waiting for one, that would end after it. Each attempt is given the time left.
+    #[cfg(any(feature = "otlp", feature = "grpc"))]
+    pub(crate) fn run_within<E>(
+        &self,
+        deadline: Instant,
+        mut attempt: impl FnMut(Duration) -> Result<(), E>,
+        retryable: impl Fn(&E) -> bool,
+    ) -> Option<Result<(), E>> {
+        let remaining = || {
+            deadline
+                .checked_duration_since(Instant::now())
+                .filter(|remaining| !remaining.is_zero())
+        };
+        let mut result = attempt(remaining()?);
+
+        for retry in 0..self.retries {
+            match &result {
+                Err(e) if retryable(e) => {}
+                _ => break,
+            }
+
+            let delay = self.delay(retry);
+            match remaining() {
+                Some(remaining) if remaining > delay => std::thread::sleep(delay),
+                _ => break,
+            }
+            let Some(remaining) = remaining() else {
+                break;
+            };
+            result = attempt(remaining);
+        }
+
+        Some(result)
+    }
+
+    /// How long to wait before the given retry, counting from zero.
+    fn delay(&self, retry: u32) -> Duration {
+        let full = self.backoff.saturating_mul(2u32.saturating_pow(retry));
+
+        full.mul_f64(0.5 + random_fraction() / 2.0)
+    }
+}
+
+/// A random number in `0.0..1.0`. It's good enough for spreading things out, not much else.
+pub(crate) fn random_fraction() -> f64 {
+    // std has no RNG, but hash keys are randomly seeded
+    let random = RandomState::new().build_hasher().finish();
+
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
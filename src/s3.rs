@@ -0,0 +1,100 @@
+//! Names and addresses reports uploaded to S3, or anything compatible with it, see
+//! [`handlers::s3`](crate::handlers::s3).
+
+use std::fmt::Write as _;
+use std::io;
+
+use crate::aws::hex;
+use crate::crypto::random;
+use crate::format::Rfc3339;
+use crate::PanicReport;
+
+/// What keys can be made up of.
+const PLACEHOLDERS: [&str; 5] = ["app", "version", "date", "fingerprint", "uuid"];
+
+/// Gives an error if `pattern` holds a placeholder that isn't known, or isn't closed.
+pub(crate) fn check_key(pattern: &str) -> io::Result<()> {
+    expand(pattern, |_| Ok(String::new())).map(drop)
+}
+
+/// The key to upload `report` under, as per `pattern`.
+pub(crate) fn key(pattern: &str, report: &PanicReport<'_>) -> io::Result<String> {
+    expand(pattern, |name| {
+        Ok(match name {
+            "app" => match report.app_metadata() {
+                Some(app) => app.name.to_string(),
+                None => crate::handlers::executable_name().unwrap_or_else(|| "unknown".to_string()),
+            },
+            "version" => report
+                .app_metadata()
+                .map_or("unknown", |app| app.version)
+                .to_string(),
+            "date" => Rfc3339(report.timestamp()).to_string()[..10].to_string(),
+            "fingerprint" => report.fingerprint().to_string(),
+            _ => uuid()?,
+        })
+    })
+}
+
+fn expand(pattern: &str, value: impl Fn(&str) -> io::Result<String>) -> io::Result<String> {
+    let invalid = |reason: String| io::Error::new(io::ErrorKind::InvalidInput, reason);
+
+    let mut key = String::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('{') {
+        key += &rest[..start];
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| invalid(format!("the key pattern `{pattern}` has an unclosed `{{`")))?;
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(invalid(format!(
+                "`{{{name}}}` isn't a placeholder keys can hold, only {{app}}, {{version}}, \
+                 {{date}}, {{fingerprint}} and {{uuid}} are"
+            )));
+        }
+        key += &value(name)?;
+        rest = &rest[start + end + 1..];
+    }
+    key += rest;
+
+    if key.trim_matches('/').is_empty() {
+        return Err(invalid(format!(
+            "the key pattern `{pattern}` gives empty keys"
+        )));
+    }
+
+    Ok(key)
+}
+
+/// A random version 4 UUID, such as `0f8fad5b-d9cb-469f-a165-70867728950e`.
+fn uuid() -> io::Result<String> {
+    let mut bytes = [0; 16];
+    random(&mut bytes)?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let mut uuid = hex(&bytes);
+    for at in [20, 16, 12, 8] {
+        uuid.insert(at, '-');
+    }
+
+    Ok(uuid)
+}
+
+/// `path`, as it has to be sent and signed, with each segment percent-encoded.
+pub(crate) fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => {
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,174 @@
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use crate::regex::Regex;
+use crate::{filter, PanicReport, PatternError};
+
+type CustomScrubber = Arc<dyn Fn(&mut PanicReport<'_>) + Send + Sync>;
+
+/// What a [`Scrubber`] takes out of reports, before any
+/// [report handler](crate::EvacBuilder::with_report_handler) sees them, see
+/// [`EvacBuilder::scrub`](crate::EvacBuilder::scrub).
+///
+/// It runs in three stages: first, anything not [allowed](Scrubber::allow_fields) is removed,
+/// then every [rule](Scrubber::rule) is applied to the text that's left, see
+/// [`PanicReport::scrub_text`], and then the [custom scrubbers](Scrubber::with) are run, in the
+/// order they were added.
+///
+/// ## Example
+/// ```
+/// # use evac::{ReportField, Scrubber};
+/// let scrubber = Scrubber::new()
+///   .rule(r"[\w.+-]+@[\w-]+\.[\w.]+", "[email]")?
+///   .rule(r"(?i)bearer [a-z0-9._-]+", "Bearer [redacted]")?
+///   .allow_fields([ReportField::Message, ReportField::Breadcrumbs, ReportField::Annotations])
+///   .allow_annotation("request_*")
+///   .with(|report| report.scrub_text(|text| text.truncate(1000)));
+/// # Ok::<(), evac::PatternError>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct Scrubber {
+    rules: Vec<(Regex, String)>,
+    fields: Option<Vec<ReportField>>,
+    annotations: Vec<String>,
+    custom: Vec<CustomScrubber>,
+}
+
+/// Something in a [`PanicReport`] that can be left out of it, see [`Scrubber::allow_fields`] and
+/// [`PanicReport::clear`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ReportField {
+    /// See [`PanicReport::message`].
+    Message,
+    /// See [`PanicReport::thread_name`].
+    ThreadName,
+    /// See [`PanicReport::backtrace`].
+    Backtrace,
+    /// See [`PanicReport::app_metadata`].
+    AppMetadata,
+    /// See [`PanicReport::env_vars`].
+    EnvVars,
+    /// See [`PanicReport::args`].
+    Args,
+    /// See [`PanicReport::breadcrumbs`].
+    Breadcrumbs,
+    /// The traces of every thread, with the `all-threads` feature.
+    Threads,
+    /// The facts about the host, with the `sysinfo` feature.
+    SystemInfo,
+    /// See [`PanicReport::annotations`].
+    Annotations,
+    /// See [`PanicReport::attachments`].
+    Attachments,
+}
+
+impl ReportField {
+    const ALL: [ReportField; 11] = [
+        ReportField::Message,
+        ReportField::ThreadName,
+        ReportField::Backtrace,
+        ReportField::AppMetadata,
+        ReportField::EnvVars,
+        ReportField::Args,
+        ReportField::Breadcrumbs,
+        ReportField::Threads,
+        ReportField::SystemInfo,
+        ReportField::Annotations,
+        ReportField::Attachments,
+    ];
+}
+
+impl Scrubber {
+    /// Leaves reports as they are, until told otherwise.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces every match of the regular expression `pattern`, in any of the report's text, with
+    /// `replacement`, see [`PanicReport::scrub_text`].
+    ///
+    /// Patterns support literals, `.`, classes such as `[a-z_]` and `[^0-9]`, the `\d`, `\w` and
+    /// `\s` shorthands and their negations, `^`, `$` and `\b`, groups, `|`, and the greedy `*`,
+    /// `+`, `?` and `{n,m}` quantifiers. A leading `(?i)` makes ASCII letters match either case.
+    /// Searches that take too long, from patterns that backtrack badly, are given up on, leaving
+    /// the text as it was.
+    pub fn rule(
+        mut self,
+        pattern: &str,
+        replacement: impl Into<String>,
+    ) -> Result<Self, PatternError> {
+        self.rules.push((Regex::new(pattern)?, replacement.into()));
+
+        Ok(self)
+    }
+
+    /// Only keeps `fields`, and any other allowed fields, removing the rest. Everything is kept
+    /// until this is first called.
+    pub fn allow_fields(mut self, fields: impl IntoIterator<Item = ReportField>) -> Self {
+        self.fields.get_or_insert_with(Vec::new).extend(fields);
+
+        self
+    }
+
+    /// Only keeps annotations whose keys match `pattern`, or any of the other allowed patterns,
+    /// with globs in which `*` matches any run of characters and `?` matches any one, ignoring
+    /// case. Every annotation is kept until this is first called.
+    pub fn allow_annotation(mut self, pattern: impl Into<String>) -> Self {
+        self.annotations.push(pattern.into());
+
+        self
+    }
+
+    /// Runs `scrubber` over the report, after everything else, such as to remove what rules
+    /// can't describe.
+    pub fn with<F>(mut self, scrubber: F) -> Self
+    where
+        F: Fn(&mut PanicReport<'_>) + Send + Sync + 'static,
+    {
+        self.custom.push(Arc::new(scrubber));
+
+        self
+    }
+
+    pub(crate) fn scrub(&self, report: &mut PanicReport<'_>) {
+        if let Some(fields) = &self.fields {
+            for field in ReportField::ALL {
+                if !fields.contains(&field) {
+                    report.clear(field);
+                }
+            }
+        }
+        if !self.annotations.is_empty() {
+            report.annotations.retain(|(key, _)| {
+                self.annotations
+                    .iter()
+                    .any(|pattern| filter::name_matches(pattern, key))
+            });
+        }
+
+        if !self.rules.is_empty() {
+            report.scrub_text(|text| {
+                for (regex, replacement) in &self.rules {
+                    regex.replace_all(text, replacement);
+                }
+            });
+        }
+
+        for scrubber in &self.custom {
+            scrubber(report);
+        }
+    }
+}
+
+impl Debug for Scrubber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let rules: Vec<_> = self.rules.iter().map(|(regex, _)| regex).collect();
+        f.debug_struct("Scrubber")
+            .field("rules", &rules)
+            .field("fields", &self.fields)
+            .field("annotations", &self.annotations)
+            .field("custom", &self.custom.len())
+            .finish()
+    }
+}
@@ -0,0 +1,50 @@
+//! Telling systemd the service panicked, see [`handlers::sd_notify`](crate::handlers::sd_notify).
+
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+use crate::PanicReport;
+
+/// The most of the message the status is given, in characters, as it's shown on one line.
+const MAX_MESSAGE: usize = 200;
+
+/// Where to notify systemd, as `NOTIFY_SOCKET` says, or `None` if it doesn't, as when the service
+/// isn't run by it, or isn't of `Type=notify`.
+pub(crate) fn socket() -> io::Result<Option<SocketAddr>> {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET").filter(|socket| !socket.is_empty()) else {
+        return Ok(None);
+    };
+
+    match socket.as_bytes().first() {
+        Some(b'/') => SocketAddr::from_pathname(&socket).map(Some),
+        // In the abstract namespace, as systemd's own sockets usually are
+        #[cfg(target_os = "linux")]
+        Some(b'@') => {
+            use std::os::linux::net::SocketAddrExt;
+
+            SocketAddr::from_abstract_name(&socket.as_bytes()[1..]).map(Some)
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "`NOTIFY_SOCKET` isn't a socket that can be notified",
+        )),
+    }
+}
+
+/// Notifies systemd at `socket` that the service is stopping, as `report` panicked.
+pub(crate) fn notify(socket: &SocketAddr, report: &PanicReport<'_>) -> io::Result<()> {
+    // On one line, as each of the state's assignments takes one
+    let message: String = report
+        .message()
+        .unwrap_or("Box<dyn Any>")
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .take(MAX_MESSAGE)
+        .collect();
+    let state = format!("STATUS=panicked: {message}\nSTOPPING=1\n");
+
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), socket)?;
+
+    Ok(())
+}
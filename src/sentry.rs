@@ -0,0 +1,262 @@
+//! Sends reports to Sentry, or anything that takes its envelopes, such as GlitchTip, see
+//! [`handlers::sentry`](crate::handlers::sentry).
+
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+
+use crate::{fingerprint, http, json, PanicReport};
+
+/// What a DSN says about where events go, and how they're authorized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Dsn {
+    dsn: String,
+    pub(crate) url: http::Url,
+    pub(crate) auth: String,
+}
+
+impl Dsn {
+    /// Parses `dsn`, such as `http://public@sentry.example.com/1`.
+    pub(crate) fn parse(dsn: &str) -> io::Result<Self> {
+        let invalid = |reason| io::Error::new(io::ErrorKind::InvalidInput, reason);
+
+        let (scheme, rest) = dsn
+            .split_once("://")
+            .ok_or_else(|| invalid("the DSN has no scheme"))?;
+        let (key, rest) = rest
+            .split_once('@')
+            .ok_or_else(|| invalid("the DSN has no public key"))?;
+        // Any secret key is only for old servers
+        let key = key.split(':').next().unwrap_or_default();
+        let (address, project) = rest
+            .trim_end_matches('/')
+            .rsplit_once('/')
+            .ok_or_else(|| invalid("the DSN has no project ID"))?;
+        if key.is_empty() || project.is_empty() || !project.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid("the DSN's public key or project ID is missing"));
+        }
+
+        Ok(Self {
+            dsn: dsn.to_string(),
+            url: http::Url::parse(&format!("{scheme}://{address}/api/{project}/envelope/"))?,
+            auth: format!(
+                "Sentry sentry_version=7, sentry_key={key}, sentry_client=evac/{}",
+                env!("CARGO_PKG_VERSION")
+            ),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct Event<'a> {
+    event_id: &'a str,
+    timestamp: f64,
+    platform: &'static str,
+    level: &'static str,
+    logger: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environment: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    server_name: Option<&'a str>,
+    fingerprint: [&'a str; 1],
+    tags: BTreeMap<&'a str, &'a str>,
+    extra: BTreeMap<&'a str, &'a str>,
+    exception: Values<Exception<'a>>,
+    breadcrumbs: Values<Breadcrumb<'a>>,
+}
+
+#[derive(Serialize)]
+struct Values<T> {
+    values: Vec<T>,
+}
+
+#[derive(Serialize)]
+struct Exception<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    value: &'a str,
+    mechanism: Mechanism,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stacktrace: Option<Stacktrace>,
+}
+
+#[derive(Serialize)]
+struct Mechanism {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    handled: bool,
+}
+
+#[derive(Serialize)]
+struct Stacktrace {
+    frames: Vec<Frame>,
+}
+
+#[derive(Serialize)]
+struct Frame {
+    function: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filename: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lineno: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    colno: Option<u32>,
+    in_app: bool,
+}
+
+#[derive(Serialize)]
+struct Breadcrumb<'a> {
+    timestamp: f64,
+    category: &'a str,
+    message: &'a str,
+}
+
+/// `report` as an envelope holding an event for it, tagged with `tags` and `environment`.
+pub(crate) fn envelope(
+    report: &PanicReport<'_>,
+    dsn: &Dsn,
+    environment: Option<&str>,
+    tags: &[(String, String)],
+) -> io::Result<Vec<u8>> {
+    let event_id = event_id();
+    let seconds = |time: std::time::SystemTime| {
+        time.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64()
+    };
+
+    let mut event_tags = BTreeMap::new();
+    event_tags.insert("thread", report.thread_name().unwrap_or("<unnamed>"));
+    if let Some(app) = report.app_metadata() {
+        event_tags.insert("profile", app.profile);
+        if let Some(target) = app.target {
+            event_tags.insert("target", target);
+        }
+        if let Some(commit) = app.git_commit {
+            event_tags.insert("git_commit", commit);
+        }
+    }
+    #[cfg(feature = "sysinfo")]
+    if let Some(host) = report.system_info() {
+        if let Some(os) = &host.os_name {
+            event_tags.insert("os.name", os);
+        }
+        event_tags.insert("arch", host.arch);
+    }
+    for (key, value) in tags {
+        event_tags.insert(key, value);
+    }
+
+    #[cfg(feature = "sysinfo")]
+    let server_name = report
+        .system_info()
+        .and_then(|host| host.hostname.as_deref());
+    #[cfg(not(feature = "sysinfo"))]
+    let server_name = None;
+
+    let event = Event {
+        event_id: &event_id,
+        timestamp: seconds(report.timestamp()),
+        platform: "native",
+        level: "fatal",
+        logger: "evac",
+        release: report
+            .app_metadata()
+            .map(|app| format!("{}@{}", app.name, app.version)),
+        environment,
+        server_name,
+        fingerprint: [report.fingerprint()],
+        tags: event_tags,
+        extra: report
+            .annotations()
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect(),
+        exception: Values {
+            values: vec![Exception {
+                kind: "panic",
+                value: report.message().unwrap_or("Box<dyn Any>"),
+                mechanism: Mechanism {
+                    kind: "panic",
+                    handled: false,
+                },
+                stacktrace: report.backtrace_text().map(|backtrace| Stacktrace {
+                    frames: frames(&backtrace),
+                }),
+            }],
+        },
+        breadcrumbs: Values {
+            values: report
+                .breadcrumbs()
+                .iter()
+                .map(|breadcrumb| Breadcrumb {
+                    timestamp: seconds(breadcrumb.timestamp),
+                    category: &breadcrumb.category,
+                    message: &breadcrumb.message,
+                })
+                .collect(),
+        },
+    };
+    let event = json::to_vec(&event)?;
+
+    #[derive(Serialize)]
+    struct Header<'a> {
+        event_id: &'a str,
+        dsn: &'a str,
+    }
+    #[derive(Serialize)]
+    struct Item {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        length: usize,
+    }
+
+    let mut envelope = json::to_vec(&Header {
+        event_id: &event_id,
+        dsn: &dsn.dsn,
+    })?;
+    envelope.push(b'\n');
+    envelope.extend(json::to_vec(&Item {
+        kind: "event",
+        length: event.len(),
+    })?);
+    envelope.push(b'\n');
+    envelope.extend(event);
+    envelope.push(b'\n');
+
+    Ok(envelope)
+}
+
+/// The frames of `backtrace`, outermost first, as Sentry wants them.
+fn frames(backtrace: &str) -> Vec<Frame> {
+    let mut frames: Vec<Frame> = fingerprint::located(backtrace)
+        .into_iter()
+        .map(|frame| Frame {
+            function: frame.symbol.to_string(),
+            filename: frame.file.map(str::to_string),
+            lineno: frame.line,
+            colno: frame.column,
+            in_app: !fingerprint::is_runtime(frame.symbol),
+        })
+        .collect();
+    frames.reverse();
+
+    frames
+}
+
+/// A random version 4 UUID, without hyphens, as Sentry writes them.
+fn event_id() -> String {
+    // std has no RNG, but hash keys are randomly seeded
+    let random = || RandomState::new().build_hasher().finish();
+    let mut id = (u128::from(random()) << 64) | u128::from(random());
+    id = (id & !(0xf << 76)) | (0x4 << 76);
+    id = (id & !(0x3 << 62)) | (0x2 << 62);
+
+    format!("{id:032x}")
+}
@@ -0,0 +1,59 @@
+//! Logs reports through a `slog::Logger`, see [`handlers::slog`](crate::handlers::slog).
+
+use ::slog::{Logger, Record, Serializer, KV};
+
+use crate::{format, PanicReport};
+
+/// Logs `report` through `logger`, as a critical record tagged `evac`.
+pub(crate) fn log(logger: &Logger, report: &PanicReport<'_>) {
+    let fields = Fields {
+        report,
+        backtrace: report.backtrace_text(),
+        // Keys have to be known up front, so annotations are given together, as `key=value` pairs
+        annotations: report
+            .annotations()
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+
+    ::slog::crit!(logger, #"evac", "{}", format::summary(report); fields);
+}
+
+/// The report's fields, as key-values.
+struct Fields<'a, 'r> {
+    report: &'a PanicReport<'r>,
+    backtrace: Option<String>,
+    annotations: String,
+}
+
+impl KV for Fields<'_, '_> {
+    fn serialize(&self, _: &Record<'_>, serializer: &mut dyn Serializer) -> ::slog::Result {
+        let report = self.report;
+
+        serializer.emit_str("exception.type", "panic")?;
+        serializer.emit_str(
+            "exception.message",
+            report.message().unwrap_or("Box<dyn Any>"),
+        )?;
+        if let Some(backtrace) = &self.backtrace {
+            serializer.emit_str("exception.stacktrace", backtrace)?;
+        }
+        if let Some(location) = report.location() {
+            serializer.emit_str("code.filepath", location.file())?;
+            serializer.emit_u32("code.lineno", location.line())?;
+            serializer.emit_u32("code.column", location.column())?;
+        }
+        serializer.emit_str("thread.name", report.thread_name().unwrap_or("<unnamed>"))?;
+        serializer.emit_str("evac.fingerprint", report.fingerprint())?;
+        if let Some(app) = report.app_metadata() {
+            serializer.emit_str("service.version", app.version)?;
+        }
+        if !self.annotations.is_empty() {
+            serializer.emit_str("evac.annotations", &self.annotations)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,698 @@
+//! Just enough of an SMTP client to email a report through a relay, see
+//! [`handlers::smtp`](crate::handlers::smtp).
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rustls::ClientConfig;
+
+use crate::format::Rfc3339;
+use crate::json::base64;
+use crate::tls::{self, Stream};
+use crate::{PanicReport, ReportFormatter, TextFormatter};
+
+/// The most of the panic's message the subject holds, in characters.
+const MAX_SUBJECT_MESSAGE: usize = 100;
+
+/// Gives an error if `address` can't be sent as a mailbox, such as `crashes@example.com`.
+pub(crate) fn check_address(address: &str) -> io::Result<()> {
+    let valid = address.contains('@')
+        && !address
+            .chars()
+            .any(|c| c.is_ascii_whitespace() || c.is_control() || "<>,;".contains(c));
+    match valid {
+        true => Ok(()),
+        false => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{address}` isn't an email address that can be sent to"),
+        )),
+    }
+}
+
+/// How the connection to the relay is encrypted.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Encryption {
+    /// It isn't.
+    None,
+    /// Upgraded with `STARTTLS`, checking that the relay's certificate is for the name.
+    StartTls(String),
+    /// From the start, checking that the relay's certificate is for the name.
+    Implicit(String),
+}
+
+/// What's sent, to whom, and how.
+pub(crate) struct Envelope<'a> {
+    pub(crate) from: &'a str,
+    pub(crate) to: &'a [String],
+    pub(crate) credentials: Option<(&'a str, &'a str)>,
+    pub(crate) encryption: &'a Encryption,
+    /// Whether the credentials may be sent over a connection that isn't encrypted.
+    pub(crate) allow_insecure_auth: bool,
+}
+
+/// `report` as an email from `from` to `to`, with the report as text for its body, and
+/// `attachment`, named `name`, of `content_type`, attached, line by line, ready to be sent as
+/// `DATA`.
+pub(crate) fn message(
+    report: &PanicReport<'_>,
+    from: &str,
+    to: &[String],
+    attachment: &[u8],
+    name: &str,
+    content_type: &str,
+) -> io::Result<String> {
+    let mut text = Vec::new();
+    TextFormatter::new().format(report, &mut text)?;
+
+    let app = match report.app_metadata() {
+        Some(app) => format!("{} {}", app.name, app.version),
+        None => crate::handlers::executable_name().unwrap_or_else(|| "app".to_string()),
+    };
+    let message: String = report
+        .message()
+        .unwrap_or("Box<dyn Any>")
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .take(MAX_SUBJECT_MESSAGE)
+        .collect();
+
+    let millis = report
+        .timestamp()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let boundary = format!("evac-{}-{millis}", report.fingerprint());
+    let domain = from.rsplit('@').next().unwrap_or("localhost");
+
+    let mut email = format!(
+        "From: <{from}>\r\n\
+         To: {}\r\n\
+         Subject: {}\r\n\
+         Date: {}\r\n\
+         Message-ID: <{millis}.{}.{}@{domain}>\r\n\
+         MIME-Version: 1.0\r\n\
+         Content-Type: multipart/mixed; boundary=\"{boundary}\"\r\n\
+         \r\n",
+        to.iter()
+            .map(|to| format!("<{to}>"))
+            .collect::<Vec<_>>()
+            .join(", "),
+        encode_header(&format!("[{app}] panicked: {message}")),
+        date(report.timestamp()),
+        report.pid(),
+        report.fingerprint(),
+    );
+    email += &format!(
+        "--{boundary}\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         Content-Transfer-Encoding: base64\r\n\
+         \r\n\
+         {}\
+         --{boundary}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Transfer-Encoding: base64\r\n\
+         Content-Disposition: attachment; filename=\"{}\"\r\n\
+         \r\n\
+         {}\
+         --{boundary}--\r\n",
+        wrap(&base64(&text)),
+        name.replace(['"', '\\', '\r', '\n'], "_"),
+        wrap(&base64(attachment)),
+    );
+
+    Ok(email)
+}
+
+/// `value` as a header's value, encoded if it isn't ASCII, or holds anything a header can't, in
+/// as many encoded words as it takes to keep each to the 75 characters they're allowed.
+fn encode_header(value: &str) -> String {
+    if value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        return value.to_string();
+    }
+
+    // 45 bytes encode to 60 characters, which leaves room for the rest of the word
+    let mut words = vec![];
+    let mut chunk = String::new();
+    for c in value.chars() {
+        if chunk.len() + c.len_utf8() > 45 {
+            words.push(format!("=?utf-8?B?{}?=", base64(chunk.as_bytes())));
+            chunk.clear();
+        }
+        chunk.push(c);
+    }
+    words.push(format!("=?utf-8?B?{}?=", base64(chunk.as_bytes())));
+
+    words.join("\r\n ")
+}
+
+/// `encoded` in lines of 76 characters, as MIME allows at most, each ending with a line break.
+fn wrap(encoded: &str) -> String {
+    let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / 76 * 2 + 2);
+    // Base64 is ASCII, so splitting it by bytes never splits a character
+    for line in encoded.as_bytes().chunks(76) {
+        wrapped += std::str::from_utf8(line).unwrap_or_default();
+        wrapped += "\r\n";
+    }
+
+    wrapped
+}
+
+/// `time` as an email's date, such as `Wed, 01 May 2024 12:30:00 +0000`.
+fn date(time: SystemTime) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / 86_400;
+    // Such as `2024-05-01T12:30:00.250Z`
+    let rfc3339 = Rfc3339(time).to_string();
+    let month: usize = rfc3339[5..7].parse().unwrap_or(1);
+
+    format!(
+        "{}, {} {} {} {} +0000",
+        WEEKDAYS[(days % 7) as usize],
+        &rfc3339[8..10],
+        MONTHS[month - 1],
+        &rfc3339[..4],
+        &rfc3339[11..19]
+    )
+}
+
+/// Sends `email` as `envelope` says, through the relay at the first of `addrs` that can be
+/// reached, all within `deadline`.
+pub(crate) fn send(
+    addrs: &[SocketAddr],
+    envelope: &Envelope<'_>,
+    email: &str,
+    deadline: Duration,
+) -> io::Result<()> {
+    let timed_out = || io::Error::new(io::ErrorKind::TimedOut, "sending the email timed out");
+    let deadline = Instant::now() + deadline;
+    let remaining = || {
+        deadline
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or_else(timed_out)
+    };
+
+    // Reads and writes that run out of time fail as would-block on some platforms
+    match exchange(addrs, envelope, email, &remaining, &tls::config) {
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => Err(timed_out()),
+        sent => sent,
+    }
+}
+
+/// Has the relay take `email`, see [`send`], encrypting the connection with what `tls` gives, if
+/// it's to be.
+fn exchange(
+    addrs: &[SocketAddr],
+    envelope: &Envelope<'_>,
+    email: &str,
+    remaining: &dyn Fn() -> io::Result<Duration>,
+    tls: &dyn Fn() -> io::Result<Arc<ClientConfig>>,
+) -> io::Result<()> {
+    let mut stream = None;
+    let mut last = io::Error::new(io::ErrorKind::NotFound, "the relay has no addresses");
+    for address in addrs {
+        match TcpStream::connect_timeout(address, remaining()?) {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(e) => last = e,
+        }
+    }
+    let stream = stream.ok_or(last)?;
+    // As the relay is told who's sending, as an address literal, having no name to go by
+    let hello = match stream.local_addr()? {
+        SocketAddr::V4(local) => format!("[{}]", local.ip()),
+        SocketAddr::V6(local) => format!("[IPv6:{}]", local.ip()),
+    };
+    let stream = match envelope.encryption {
+        Encryption::Implicit(name) => encrypt(stream, name, remaining, tls)?,
+        Encryption::None | Encryption::StartTls(_) => Stream::Plain(stream),
+    };
+
+    let mut session = Session {
+        stream: BufReader::new(stream),
+        remaining,
+    };
+    session.expect(&[220])?;
+    let mut extensions = session.command(&format!("EHLO {hello}"), &[250])?;
+    if let Encryption::StartTls(name) = envelope.encryption {
+        // Rather than carry on in the clear, which is what someone in the way would be after
+        if !extensions
+            .iter()
+            .any(|extension| offers(extension, "STARTTLS"))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the relay doesn't offer STARTTLS, so the email wasn't sent",
+            ));
+        }
+        session.command("STARTTLS", &[220])?;
+        let stream = match session.into_stream()? {
+            Stream::Plain(stream) => encrypt(stream, name, remaining, tls)?,
+            encrypted => encrypted,
+        };
+        session = Session {
+            stream: BufReader::new(stream),
+            remaining,
+        };
+        // What the relay offered before is forgotten, as it could have been tampered with
+        extensions = session.command(&format!("EHLO {hello}"), &[250])?;
+    }
+
+    if let Some((username, password)) = envelope.credentials {
+        if !session.stream.get_ref().encrypted() && !envelope.allow_insecure_auth {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the connection to the relay isn't encrypted, so the credentials weren't sent",
+            ));
+        }
+        let plain = extensions.iter().any(|extension| {
+            offers(extension, "AUTH")
+                && extension
+                    .split_whitespace()
+                    .any(|mechanism| mechanism.eq_ignore_ascii_case("PLAIN"))
+        });
+        if !plain {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the relay doesn't offer to authenticate with AUTH PLAIN",
+            ));
+        }
+        let token = base64(format!("\0{username}\0{password}").as_bytes());
+        session.command(&format!("AUTH PLAIN {token}"), &[235])?;
+    }
+    session.command(&format!("MAIL FROM:<{}>", envelope.from), &[250])?;
+    for to in envelope.to {
+        session.command(&format!("RCPT TO:<{to}>"), &[250, 251])?;
+    }
+    session.command("DATA", &[354])?;
+
+    // Lines starting with a dot have another put in front, so that none ends the email early
+    let mut data = String::with_capacity(email.len() + 5);
+    for line in email.split_inclusive("\r\n") {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data += line;
+    }
+    data += ".";
+    session.command(&data, &[250])?;
+    // Sent, whatever the relay makes of this
+    let _ = session.command("QUIT", &[221]);
+
+    Ok(())
+}
+
+/// Whether the relay's answer to `EHLO` holds `extension`, such as `STARTTLS`.
+fn offers(line: &str, extension: &str) -> bool {
+    line.split_whitespace()
+        .next()
+        .is_some_and(|word| word.eq_ignore_ascii_case(extension))
+}
+
+/// Encrypts `stream`, checking that the relay's certificate is for `name`, within what's left of
+/// the deadline.
+fn encrypt(
+    stream: TcpStream,
+    name: &str,
+    remaining: &dyn Fn() -> io::Result<Duration>,
+    tls: &dyn Fn() -> io::Result<Arc<ClientConfig>>,
+) -> io::Result<Stream> {
+    stream.set_read_timeout(Some(remaining()?))?;
+    stream.set_write_timeout(Some(remaining()?))?;
+
+    Stream::encrypt(stream, name, tls()?)
+}
+
+/// A connection to the relay, with what's left of the deadline.
+struct Session<'a> {
+    stream: BufReader<Stream>,
+    remaining: &'a dyn Fn() -> io::Result<Duration>,
+}
+
+impl Session<'_> {
+    /// The connection, such as to encrypt it, once the relay has answered everything sent.
+    fn into_stream(self) -> io::Result<Stream> {
+        // Anything the relay sent after its answer would pass as having been encrypted
+        if !self.stream.buffer().is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the relay sent more than its answer to STARTTLS",
+            ));
+        }
+
+        Ok(self.stream.into_inner())
+    }
+
+    /// Sends `command`, then reads the relay's reply, see [`Session::expect`].
+    fn command(&mut self, command: &str, codes: &[u16]) -> io::Result<Vec<String>> {
+        let stream = self.stream.get_mut();
+        stream.tcp().set_write_timeout(Some((self.remaining)()?))?;
+        stream.write_all(command.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+
+        self.expect(codes)
+    }
+
+    /// Reads the relay's reply, giving the text of each of its lines, after the first, if its
+    /// code is one of `codes`, or an error with what the relay said otherwise.
+    fn expect(&mut self, codes: &[u16]) -> io::Result<Vec<String>> {
+        let mut lines = vec![];
+        loop {
+            self.stream
+                .get_ref()
+                .tcp()
+                .set_read_timeout(Some((self.remaining)()?))?;
+            let mut line = String::new();
+            if self.stream.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the relay closed the connection",
+                ));
+            }
+            let line = line.trim_end();
+            let code: u16 = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "the relay didn't answer with SMTP",
+                    )
+                })?;
+            let last = line.as_bytes().get(3) != Some(&b'-');
+            lines.push((code, line.get(4..).unwrap_or_default().to_string()));
+
+            if last {
+                break;
+            }
+        }
+
+        let (code, _) = lines[lines.len() - 1];
+        match codes.contains(&code) {
+            true => Ok(lines.into_iter().skip(1).map(|(_, text)| text).collect()),
+            false => Err(io::Error::other(format!(
+                "the relay answered with {code} {}",
+                lines
+                    .iter()
+                    .map(|(_, text)| text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+
+    use rustls::{ServerConfig, ServerConnection, StreamOwned};
+
+    use super::*;
+
+    /// Every line a relay was sent, with whether it came encrypted.
+    type Lines = Vec<(bool, String)>;
+
+    /// How the relay behaves.
+    #[derive(Clone, Copy)]
+    struct Relay {
+        offers_starttls: bool,
+        implicit: bool,
+    }
+
+    /// Runs `relay` on a port of its own, giving the port, settings for clients that trust it, and
+    /// the lines it was sent.
+    fn relay(relay: Relay) -> (u16, Arc<ClientConfig>, JoinHandle<Lines>) {
+        let (server, trusting) = tls::testing::self_signed();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut lines = vec![];
+            let encrypt = |stream: TcpStream, server: &Arc<ServerConfig>| {
+                let connection = ServerConnection::new(Arc::clone(server)).unwrap();
+                BufReader::new(StreamOwned::new(connection, stream))
+            };
+
+            if relay.implicit {
+                converse(&mut encrypt(stream, &server), true, relay, true, &mut lines);
+                return lines;
+            }
+            let mut plain = BufReader::new(stream);
+            if converse(&mut plain, true, relay, false, &mut lines) {
+                let mut encrypted = encrypt(plain.into_inner(), &server);
+                converse(&mut encrypted, false, relay, true, &mut lines);
+            }
+
+            lines
+        });
+
+        (port, trusting, handle)
+    }
+
+    /// Answers the client until it quits, or asks to encrypt the connection, in which case this
+    /// gives `true`.
+    fn converse<S: Read + Write>(
+        stream: &mut BufReader<S>,
+        greet: bool,
+        relay: Relay,
+        encrypted: bool,
+        lines: &mut Lines,
+    ) -> bool {
+        let reply = |stream: &mut BufReader<S>, reply: &str| {
+            let stream = stream.get_mut();
+            stream.write_all(reply.as_bytes()).unwrap();
+            stream.flush().unwrap();
+        };
+        if greet {
+            reply(stream, "220 relay.example.com ESMTP\r\n");
+        }
+
+        let mut in_data = false;
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).unwrap_or(0) == 0 {
+                return false;
+            }
+            let line = line.trim_end().to_string();
+            lines.push((encrypted, line.clone()));
+
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    reply(stream, "250 queued\r\n");
+                }
+                continue;
+            }
+            match line.split_whitespace().next().unwrap_or_default() {
+                "EHLO" => {
+                    let starttls = match relay.offers_starttls && !encrypted {
+                        true => "250-STARTTLS\r\n",
+                        false => "",
+                    };
+                    reply(
+                        stream,
+                        &format!("250-relay.example.com\r\n{starttls}250 AUTH LOGIN PLAIN\r\n"),
+                    );
+                }
+                "STARTTLS" => {
+                    reply(stream, "220 go ahead\r\n");
+                    return true;
+                }
+                "AUTH" => reply(stream, "235 authenticated\r\n"),
+                "MAIL" | "RCPT" => reply(stream, "250 ok\r\n"),
+                "DATA" => {
+                    in_data = true;
+                    reply(stream, "354 go ahead\r\n");
+                }
+                "QUIT" => {
+                    reply(stream, "221 bye\r\n");
+                    return false;
+                }
+                _ => reply(stream, "500 unknown\r\n"),
+            }
+        }
+    }
+
+    /// Sends a short email through the relay on `port`, as `encryption` says.
+    fn send_through(
+        port: u16,
+        trusting: Arc<ClientConfig>,
+        encryption: &Encryption,
+        credentials: Option<(&str, &str)>,
+        allow_insecure_auth: bool,
+    ) -> io::Result<()> {
+        let to = ["oncall@example.com".to_string()];
+        let envelope = Envelope {
+            from: "app@example.com",
+            to: &to,
+            credentials,
+            encryption,
+            allow_insecure_auth,
+        };
+        let addrs = [SocketAddr::from(([127, 0, 0, 1], port))];
+
+        exchange(
+            &addrs,
+            &envelope,
+            "Subject: test\r\n\r\n.leading dot\r\nbody\r\n",
+            &|| Ok(Duration::from_secs(5)),
+            &|| Ok(Arc::clone(&trusting)),
+        )
+    }
+
+    fn sent(lines: &Lines, command: &str) -> Vec<bool> {
+        lines
+            .iter()
+            .filter(|(_, line)| line.starts_with(command))
+            .map(|(encrypted, _)| *encrypted)
+            .collect()
+    }
+
+    #[test]
+    fn sends_in_the_clear_without_credentials() {
+        let relay = Relay {
+            offers_starttls: false,
+            implicit: false,
+        };
+        let (port, trusting, handle) = self::relay(relay);
+
+        send_through(port, trusting, &Encryption::None, None, false).unwrap();
+        let lines = handle.join().unwrap();
+
+        assert_eq!(sent(&lines, "MAIL FROM:<app@example.com>"), [false]);
+        assert_eq!(sent(&lines, "RCPT TO:<oncall@example.com>"), [false]);
+        // The line starting with a dot has another put in front
+        assert_eq!(sent(&lines, "..leading dot"), [false]);
+        assert!(sent(&lines, "AUTH").is_empty());
+    }
+
+    #[test]
+    fn authenticates_once_upgraded_with_starttls() {
+        let relay = Relay {
+            offers_starttls: true,
+            implicit: false,
+        };
+        let (port, trusting, handle) = self::relay(relay);
+
+        let encryption = Encryption::StartTls("localhost".to_string());
+        send_through(port, trusting, &encryption, Some(("app", "secret")), false).unwrap();
+        let lines = handle.join().unwrap();
+
+        assert_eq!(sent(&lines, "EHLO"), [false, true]);
+        assert_eq!(sent(&lines, "STARTTLS"), [false]);
+        assert_eq!(sent(&lines, "AUTH PLAIN"), [true]);
+        assert_eq!(sent(&lines, "DATA"), [true]);
+    }
+
+    #[test]
+    fn authenticates_over_implicit_tls() {
+        let relay = Relay {
+            offers_starttls: false,
+            implicit: true,
+        };
+        let (port, trusting, handle) = self::relay(relay);
+
+        let encryption = Encryption::Implicit("localhost".to_string());
+        send_through(port, trusting, &encryption, Some(("app", "secret")), false).unwrap();
+        let lines = handle.join().unwrap();
+
+        assert_eq!(sent(&lines, "EHLO"), [true]);
+        assert_eq!(sent(&lines, "AUTH PLAIN"), [true]);
+    }
+
+    #[test]
+    fn gives_up_if_the_relay_doesnt_offer_starttls() {
+        let relay = Relay {
+            offers_starttls: false,
+            implicit: false,
+        };
+        let (port, trusting, handle) = self::relay(relay);
+
+        let encryption = Encryption::StartTls("localhost".to_string());
+        let refused =
+            send_through(port, trusting, &encryption, Some(("app", "secret")), false).unwrap_err();
+        let lines = handle.join().unwrap();
+
+        assert_eq!(refused.kind(), io::ErrorKind::Unsupported);
+        assert!(sent(&lines, "AUTH").is_empty());
+        assert!(sent(&lines, "MAIL").is_empty());
+    }
+
+    #[test]
+    fn keeps_credentials_off_a_connection_in_the_clear() {
+        let relay = Relay {
+            offers_starttls: false,
+            implicit: false,
+        };
+        let (port, trusting, handle) = self::relay(relay);
+
+        let refused = send_through(
+            port,
+            trusting,
+            &Encryption::None,
+            Some(("app", "secret")),
+            false,
+        )
+        .unwrap_err();
+        let lines = handle.join().unwrap();
+
+        assert_eq!(refused.kind(), io::ErrorKind::PermissionDenied);
+        assert!(sent(&lines, "AUTH").is_empty());
+    }
+
+    #[test]
+    fn sends_credentials_in_the_clear_if_allowed_to() {
+        let relay = Relay {
+            offers_starttls: false,
+            implicit: false,
+        };
+        let (port, trusting, handle) = self::relay(relay);
+
+        send_through(
+            port,
+            trusting,
+            &Encryption::None,
+            Some(("app", "secret")),
+            true,
+        )
+        .unwrap();
+        let lines = handle.join().unwrap();
+
+        assert_eq!(sent(&lines, "AUTH PLAIN"), [false]);
+    }
+
+    #[test]
+    fn encodes_subjects_that_arent_ascii() {
+        assert_eq!(encode_header("plain"), "plain");
+        assert_eq!(encode_header("héllo"), "=?utf-8?B?aMOpbGxv?=");
+        assert!(encode_header(&"é".repeat(40))
+            .split("\r\n ")
+            .all(|word| word.len() <= 75));
+    }
+
+    #[test]
+    fn dates_emails_as_rfc_5322_does() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_714_566_600);
+        assert_eq!(date(time), "Wed, 01 May 2024 12:30:00 +0000");
+    }
+}
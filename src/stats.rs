@@ -0,0 +1,256 @@
+//! Keeping count of crashes across runs, per version and fingerprint, such as for the app to
+//! turn off a feature that keeps crashing it, see
+//! [`EvacBuilder::crash_stats`](crate::EvacBuilder::crash_stats).
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::PanicReport;
+
+/// Where crashes are counted, once a pipeline counting them has been registered.
+static STORE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// The crashes counted so far, by this process and the ones before it, or `None` until a builder
+/// with [`EvacBuilder::crash_stats`](crate::EvacBuilder::crash_stats) has been registered.
+///
+/// ## Example
+/// ```
+/// # use std::time::Duration;
+/// # use evac::stats::CrashStats;
+/// # use evac::EvacBuilder;
+/// # let store = std::env::temp_dir().join("evac-stats-example");
+/// const WEEK: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+///
+/// EvacBuilder::new()
+///   .with_handler(|_, _: &mut ()| Ok(()))
+///   .app_metadata(evac::app_metadata!())
+///   .crash_stats(CrashStats::new(store))
+///   .register(())?;
+///
+/// let crashes = evac::stats::summary()
+///   .map(|stats| stats.within(WEEK).for_version(env!("CARGO_PKG_VERSION")).total())
+///   .unwrap_or(0);
+/// if crashes >= 5 {
+///   eprintln!("this build has crashed {crashes} times this week, turning off the new renderer");
+/// }
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+pub fn summary() -> Option<Summary> {
+    let store = STORE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()?;
+
+    Some(Summary {
+        crashes: read(&store),
+    })
+}
+
+/// Where crashes are counted, and how many of them are kept.
+///
+/// ## Example
+/// ```
+/// # use std::time::Duration;
+/// # use evac::stats::CrashStats;
+/// let stats = CrashStats::new("/var/lib/my-app/crash-stats")
+///   .max_crashes(500)
+///   .max_age(Duration::from_secs(30 * 24 * 60 * 60));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CrashStats {
+    path: PathBuf,
+    max_crashes: usize,
+    max_age: Duration,
+}
+
+impl CrashStats {
+    /// Counts crashes in the file at `path`, keeping the latest 1000 of them, for up to 90 days.
+    /// The file holds a line for each.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_crashes: 1000,
+            max_age: Duration::from_secs(90 * 24 * 60 * 60),
+        }
+    }
+
+    /// Keeps the latest `crashes`, forgetting any before them.
+    pub fn max_crashes(mut self, crashes: usize) -> Self {
+        self.max_crashes = crashes;
+
+        self
+    }
+
+    /// Forgets crashes more than `age` old.
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = age;
+
+        self
+    }
+
+    /// Makes the store what [`summary`] reads from.
+    pub(crate) fn start(self) -> Self {
+        *STORE.lock().unwrap_or_else(PoisonError::into_inner) = Some(self.path.clone());
+
+        self
+    }
+
+    /// Counts the crash `report` is of.
+    pub(crate) fn record(&self, report: &PanicReport<'_>) {
+        // Read again, rather than kept, so that other instances' crashes aren't written over
+        let mut crashes = read(&self.path);
+        crashes.push(Crash {
+            timestamp: report.timestamp(),
+            version: report.app_metadata().map(|app| app.version.to_string()),
+            fingerprint: report.fingerprint().to_string(),
+        });
+
+        let now = SystemTime::now();
+        crashes.retain(|crash| {
+            now.duration_since(crash.timestamp)
+                .map_or(true, |age| age <= self.max_age)
+        });
+        let excess = crashes.len().saturating_sub(self.max_crashes);
+        crashes.drain(..excess);
+
+        let mut recorded = String::new();
+        for crash in &crashes {
+            let millis = crash
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            let version = crash.version.as_deref().unwrap_or("-");
+            recorded += &format!(
+                "{millis}\t{}\t{}\n",
+                clean(version),
+                clean(&crash.fingerprint)
+            );
+        }
+
+        // Written under another name first, so that a crash partway through loses nothing
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        // There's nowhere to report this to, and it mustn't hold up the handlers
+        let _ = fs::write(&temp, recorded).and_then(|()| fs::rename(&temp, &self.path));
+    }
+}
+
+/// The crashes in the store at `path`, oldest first. A missing or mangled store just means
+/// there's nothing to go on.
+fn read(path: &Path) -> Vec<Crash> {
+    let recorded = fs::read_to_string(path).unwrap_or_default();
+
+    let mut crashes: Vec<Crash> = recorded
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let millis = fields.next()?.parse().ok()?;
+            let version = fields.next()?;
+            let fingerprint = fields.next()?;
+
+            Some(Crash {
+                timestamp: UNIX_EPOCH + Duration::from_millis(millis),
+                version: (version != "-").then(|| version.to_string()),
+                fingerprint: fingerprint.to_string(),
+            })
+        })
+        .collect();
+    crashes.sort_by_key(|crash| crash.timestamp);
+
+    crashes
+}
+
+/// `field` without anything that would end it early.
+fn clean(field: &str) -> String {
+    field.replace(['\t', '\n', '\r'], " ")
+}
+
+/// A crash that was counted, see [`summary`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Crash {
+    /// When it happened.
+    pub timestamp: SystemTime,
+    /// The app's version, if its [`AppMetadata`](crate::AppMetadata) was known.
+    pub version: Option<String>,
+    /// The report's [fingerprint](PanicReport::fingerprint).
+    pub fingerprint: String,
+}
+
+/// The crashes counted, which can be narrowed down and tallied by version and fingerprint, see
+/// [`summary`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    crashes: Vec<Crash>,
+}
+
+impl Summary {
+    /// Every crash, oldest first.
+    pub fn crashes(&self) -> &[Crash] {
+        &self.crashes
+    }
+
+    /// How many crashes there were.
+    pub fn total(&self) -> usize {
+        self.crashes.len()
+    }
+
+    /// Only the crashes in the last `period`, such as the last week.
+    pub fn within(&self, period: Duration) -> Summary {
+        let now = SystemTime::now();
+
+        self.only(|crash| {
+            now.duration_since(crash.timestamp)
+                .map_or(true, |age| age <= period)
+        })
+    }
+
+    /// Only the crashes of `version`, such as `env!("CARGO_PKG_VERSION")`, for the running build.
+    pub fn for_version(&self, version: &str) -> Summary {
+        self.only(|crash| crash.version.as_deref() == Some(version))
+    }
+
+    /// Only the crashes with `fingerprint`, such as to tell whether one keeps happening.
+    pub fn for_fingerprint(&self, fingerprint: &str) -> Summary {
+        self.only(|crash| crash.fingerprint == fingerprint)
+    }
+
+    /// How many crashes there were of each version, leaving out those of no known version.
+    pub fn by_version(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for version in self
+            .crashes
+            .iter()
+            .filter_map(|crash| crash.version.as_deref())
+        {
+            *counts.entry(version).or_default() += 1;
+        }
+
+        counts
+    }
+
+    /// How many crashes there were with each fingerprint.
+    pub fn by_fingerprint(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for crash in &self.crashes {
+            *counts.entry(crash.fingerprint.as_str()).or_default() += 1;
+        }
+
+        counts
+    }
+
+    fn only(&self, keep: impl Fn(&Crash) -> bool) -> Summary {
+        Summary {
+            crashes: self
+                .crashes
+                .iter()
+                .filter(|crash| keep(crash))
+                .cloned()
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Counts panics with StatsD, see [`handlers::statsd`](crate::handlers::statsd).
+
+use std::io::{self, Cursor, Write};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+use crate::PanicReport;
+
+/// The most a datagram holds, which keeps clear of any MTU, and fits on the stack.
+const MAX_DATAGRAM: usize = 512;
+
+/// A socket for the server, and the counter's name.
+#[derive(Debug)]
+pub(crate) struct Counter {
+    socket: UdpSocket,
+    metric: String,
+}
+
+impl Counter {
+    /// Connects to the server at `addr`, for the counter `{prefix}.panics`, or `panics`.
+    pub(crate) fn new(addr: impl ToSocketAddrs, prefix: &str) -> io::Result<Self> {
+        if prefix.contains([':', '|', '@', '#', '\n']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("`{prefix}` can't be part of a metric's name"),
+            ));
+        }
+
+        let addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "the address resolves to nothing")
+        })?;
+        let local: SocketAddr = match addr {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0; 16], 0).into(),
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+        // So that a full send buffer drops the datagram, rather than holding up other handlers
+        socket.set_nonblocking(true)?;
+
+        let metric = match prefix.trim_end_matches('.') {
+            "" => "panics".to_string(),
+            prefix => format!("{prefix}.panics"),
+        };
+
+        Ok(Self { socket, metric })
+    }
+
+    /// Counts `report`, tagged with the app, its version, and the fingerprint, as far as they fit.
+    pub(crate) fn count(&self, report: &PanicReport<'_>) -> io::Result<()> {
+        let mut buf = [0; MAX_DATAGRAM];
+        let mut out = Cursor::new(&mut buf[..]);
+        write!(out, "{}:1|c", self.metric)?;
+
+        let app = report.app_metadata();
+        let tags = [
+            ("app", app.map(|app| app.name)),
+            ("version", app.map(|app| app.version)),
+            ("fingerprint", Some(report.fingerprint())),
+        ];
+        let mut separator = "|#";
+        for (name, value) in tags {
+            let Some(value) = value else {
+                continue;
+            };
+            // A tag that doesn't fit is left out whole
+            let start = out.position();
+            if tag(&mut out, separator, name, value).is_err() {
+                out.set_position(start);
+                break;
+            }
+            separator = ",";
+        }
+
+        let len = out.position() as usize;
+        self.socket.send(&buf[..len]).map(drop)
+    }
+}
+
+/// Writes `{separator}{name}:{value}`, with anything in `value` that would end the tag as `_`.
+fn tag(out: &mut Cursor<&mut [u8]>, separator: &str, name: &str, value: &str) -> io::Result<()> {
+    write!(out, "{separator}{name}:")?;
+    for c in value.chars() {
+        let c = match c {
+            ',' | '|' | '#' | '\n' | '\r' => '_',
+            c => c,
+        };
+        write!(out, "{c}")?;
+    }
+
+    Ok(())
+}
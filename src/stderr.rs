@@ -0,0 +1,51 @@
+use std::fmt::{self, Write as _};
+use std::io::{self, Write as _};
+
+/// How much of a message is put together before any of it is written.
+const BUFFER: usize = 1024;
+
+/// Prints a line to `stderr` without allocating. It's formatted into a buffer on the stack first,
+/// so that it's written in one go unless it's too long for the buffer.
+pub(crate) fn print_line(args: fmt::Arguments<'_>) {
+    let mut line = Line {
+        stderr: io::stderr().lock(),
+        buffer: [0; BUFFER],
+        len: 0,
+    };
+
+    // There's nowhere left to report a failure to write to stderr
+    let _ = line.write_fmt(args);
+    let _ = line.write_str("\n");
+    line.flush();
+}
+
+struct Line<'a> {
+    stderr: io::StderrLock<'a>,
+    buffer: [u8; BUFFER],
+    len: usize,
+}
+
+impl Line<'_> {
+    fn flush(&mut self) {
+        let _ = self.stderr.write_all(&self.buffer[..self.len]);
+        self.len = 0;
+    }
+}
+
+impl fmt::Write for Line<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.len + s.len() > BUFFER {
+            self.flush();
+
+            // Too long to be worth buffering at all
+            if s.len() > BUFFER {
+                return self.stderr.write_all(s.as_bytes()).map_err(|_| fmt::Error);
+            }
+        }
+
+        self.buffer[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len += s.len();
+
+        Ok(())
+    }
+}
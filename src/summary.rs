@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+/// What became of each handler during a panic. Given to finalizers and completion callbacks, see
+/// [`EvacBuilder::with_finalizer`](crate::EvacBuilder::with_finalizer) and
+/// [`EvacBuilder::on_complete`](crate::EvacBuilder::on_complete).
+#[derive(Clone, Debug, Default)]
+pub struct PipelineSummary<'a> {
+    outcomes: Vec<HandlerOutcome<'a>>,
+    total_duration: Duration,
+}
+
+/// How a single handler fared.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandlerOutcome<'a> {
+    /// The handler's name, if it was added as a named handler.
+    pub name: Option<&'a str>,
+    /// The handler's position in the order the handlers ran in.
+    pub index: usize,
+    /// Whether the handler returned `Ok`.
+    pub succeeded: bool,
+    /// The error the handler returned, or the message it panicked with, if it failed.
+    pub error: Option<String>,
+    /// How long the handler took, including any retries.
+    pub duration: Duration,
+}
+
+impl<'a> PipelineSummary<'a> {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self {
+            outcomes: Vec::with_capacity(capacity),
+            total_duration: Duration::ZERO,
+        }
+    }
+
+    pub(crate) fn record(&mut self, outcome: HandlerOutcome<'a>) {
+        self.total_duration += outcome.duration;
+        self.outcomes.push(outcome);
+    }
+
+    /// Every handler that ran, in the order they ran in.
+    pub fn outcomes(&self) -> &[HandlerOutcome<'a>] {
+        &self.outcomes
+    }
+
+    /// How many handlers ran. Handlers skipped by an
+    /// [`ErrorPolicy::StopChain`](crate::ErrorPolicy::StopChain) aren't counted.
+    pub fn ran(&self) -> usize {
+        self.outcomes.len()
+    }
+
+    /// The handlers that succeeded.
+    pub fn succeeded(&self) -> impl Iterator<Item = &HandlerOutcome<'a>> {
+        self.outcomes.iter().filter(|outcome| outcome.succeeded)
+    }
+
+    /// The handlers that failed.
+    pub fn failed(&self) -> impl Iterator<Item = &HandlerOutcome<'a>> {
+        self.outcomes.iter().filter(|outcome| !outcome.succeeded)
+    }
+
+    /// Whether every handler succeeded.
+    pub fn all_succeeded(&self) -> bool {
+        self.outcomes.iter().all(|outcome| outcome.succeeded)
+    }
+
+    /// How long the handlers took altogether.
+    pub fn total_duration(&self) -> Duration {
+        self.total_duration
+    }
+}
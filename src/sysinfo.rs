@@ -0,0 +1,197 @@
+//! Facts about the host the process is running on, for telling apart crashes that only happen
+//! in some environments, see
+//! [`EvacBuilder::capture_system_info`](crate::EvacBuilder::capture_system_info).
+
+use std::time::{Duration, Instant};
+
+/// Facts about the host, as far as they could be found within the time given. Anything that
+/// couldn't be found, or wasn't got to in time, is left as `None`.
+///
+/// ## Example
+/// ```
+/// # use std::time::Duration;
+/// # use evac::sysinfo::SystemInfo;
+/// let info = SystemInfo::gather(Duration::from_millis(50));
+/// eprintln!("{} on {}", info.os_name.as_deref().unwrap_or("unknown"), info.arch);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct SystemInfo {
+    /// The OS, or the distribution for Linux, such as `Ubuntu`.
+    pub os_name: Option<String>,
+    /// The OS's version, such as `24.04`.
+    pub os_version: Option<String>,
+    /// The kernel's release, such as `6.8.0-31-generic`.
+    pub kernel: Option<String>,
+    /// The CPU architecture the process was built for, such as `x86_64`.
+    pub arch: &'static str,
+    /// How much memory the host has, in bytes.
+    pub total_memory: Option<u64>,
+    /// How much memory is left for new processes, in bytes.
+    pub available_memory: Option<u64>,
+    /// How many CPUs the process can run on.
+    pub cpus: Option<usize>,
+    pub hostname: Option<String>,
+    /// The kind of container the process is running in, if it looks to be in one.
+    pub container: Option<Container>,
+}
+
+/// A kind of container, see [`SystemInfo::container`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum Container {
+    Docker,
+    Podman,
+    Kubernetes,
+    Lxc,
+    /// A container that couldn't be told apart from the others.
+    Other,
+}
+
+impl SystemInfo {
+    /// Gathers as much as can be found within `budget`. Each fact is only looked up while there's
+    /// still time left, so a slow lookup leaves the ones after it out, rather than holding up the
+    /// handlers.
+    pub fn gather(budget: Duration) -> Self {
+        let deadline = Instant::now() + budget;
+        let in_time = || Instant::now() < deadline;
+
+        let mut info = SystemInfo {
+            arch: std::env::consts::ARCH,
+            cpus: std::thread::available_parallelism()
+                .ok()
+                .map(|cpus| cpus.get()),
+            ..SystemInfo::default()
+        };
+
+        if in_time() {
+            (info.os_name, info.os_version) = os();
+        }
+        if in_time() {
+            (info.kernel, info.hostname) = uname();
+        }
+        if in_time() {
+            (info.total_memory, info.available_memory) = memory();
+        }
+        if in_time() {
+            info.container = container();
+        }
+
+        info
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn os() -> (Option<String>, Option<String>) {
+    let Ok(release) = std::fs::read_to_string("/etc/os-release") else {
+        return (Some("Linux".to_string()), None);
+    };
+
+    let field = |key: &str| {
+        release.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.strip_prefix('=')?;
+            Some(value.trim_matches('"').to_string())
+        })
+    };
+
+    (field("NAME"), field("VERSION_ID"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn os() -> (Option<String>, Option<String>) {
+    (Some(std::env::consts::OS.to_string()), None)
+}
+
+/// The kernel's release, and the host's name.
+#[cfg(unix)]
+fn uname() -> (Option<String>, Option<String>) {
+    use std::ffi::CStr;
+
+    // SAFETY: `uname` fills in `name`, with each field nul-terminated
+    unsafe {
+        let mut name = std::mem::zeroed::<libc::utsname>();
+        if libc::uname(&mut name) != 0 {
+            return (None, None);
+        }
+
+        let field = |field: &[libc::c_char]| {
+            Some(
+                CStr::from_ptr(field.as_ptr())
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        };
+
+        (field(&name.release), field(&name.nodename))
+    }
+}
+
+#[cfg(not(unix))]
+fn uname() -> (Option<String>, Option<String>) {
+    (None, std::env::var("COMPUTERNAME").ok())
+}
+
+/// The host's total and available memory, in bytes.
+#[cfg(target_os = "linux")]
+fn memory() -> (Option<u64>, Option<u64>) {
+    let Ok(meminfo) = std::fs::read_to_string("/proc/meminfo") else {
+        return (None, None);
+    };
+
+    // Given in kibibytes
+    let field = |key: &str| {
+        meminfo.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.strip_prefix(':')?;
+            let kib: u64 = value.trim().trim_end_matches("kB").trim().parse().ok()?;
+            Some(kib * 1024)
+        })
+    };
+
+    (field("MemTotal"), field("MemAvailable"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn memory() -> (Option<u64>, Option<u64>) {
+    (None, None)
+}
+
+#[cfg(target_os = "linux")]
+fn container() -> Option<Container> {
+    use std::path::Path;
+
+    // Kubernetes is checked first, as its pods are also run by one of the others
+    if std::env::var_os("KUBERNETES_SERVICE_HOST").is_some() {
+        return Some(Container::Kubernetes);
+    }
+    if Path::new("/run/.containerenv").exists() {
+        return Some(Container::Podman);
+    }
+    if Path::new("/.dockerenv").exists() {
+        return Some(Container::Docker);
+    }
+
+    let cgroup = std::fs::read_to_string("/proc/1/cgroup").unwrap_or_default();
+    if cgroup.contains("kubepods") {
+        Some(Container::Kubernetes)
+    } else if cgroup.contains("docker") {
+        Some(Container::Docker)
+    } else if cgroup.contains("lxc") {
+        Some(Container::Lxc)
+    } else if std::env::var_os("container").is_some() {
+        // Set by systemd-nspawn, LXC and others
+        Some(Container::Other)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn container() -> Option<Container> {
+    None
+}
@@ -0,0 +1,123 @@
+//! Sends reports to the local syslog daemon, see [`handlers::syslog`](crate::handlers::syslog).
+
+use std::io::{self, Write};
+use std::os::unix::net::{UnixDatagram, UnixStream};
+
+use crate::{format, PanicReport};
+
+/// Where the daemon listens, on Linux, macOS, and the BSDs, in that order.
+const SOCKETS: [&str; 3] = ["/dev/log", "/var/run/syslog", "/var/run/log"];
+
+/// How long a message is kept to, as daemons may drop longer ones.
+const MAX_LEN: usize = 2048;
+
+/// The severity messages are sent with, `crit`.
+const CRITICAL: u8 = 2;
+
+/// What kind of program a message is from, as syslog knows it, see
+/// [`handlers::syslog`](crate::handlers::syslog).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Facility {
+    /// Any user program, the usual choice.
+    User,
+    /// The mail system.
+    Mail,
+    /// A system daemon.
+    Daemon,
+    /// Security and authorization.
+    Auth,
+    /// The syslog daemon itself.
+    Syslog,
+    /// The printing system.
+    Lpr,
+    /// Network news.
+    News,
+    /// UUCP.
+    Uucp,
+    /// The clock daemon.
+    Cron,
+    /// Private security and authorization.
+    AuthPriv,
+    /// The FTP daemon.
+    Ftp,
+    /// Reserved for local use, such as by the site's own programs, as are the ones after it.
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl Facility {
+    fn code(self) -> u8 {
+        match self {
+            Self::User => 1,
+            Self::Mail => 2,
+            Self::Daemon => 3,
+            Self::Auth => 4,
+            Self::Syslog => 5,
+            Self::Lpr => 6,
+            Self::News => 7,
+            Self::Uucp => 8,
+            Self::Cron => 9,
+            Self::AuthPriv => 10,
+            Self::Ftp => 11,
+            Self::Local0 => 16,
+            Self::Local1 => 17,
+            Self::Local2 => 18,
+            Self::Local3 => 19,
+            Self::Local4 => 20,
+            Self::Local5 => 21,
+            Self::Local6 => 22,
+            Self::Local7 => 23,
+        }
+    }
+}
+
+/// Sends a summary of `report`, on one line, to the daemon.
+pub(crate) fn send(report: &PanicReport<'_>, facility: Facility, ident: &str) -> io::Result<()> {
+    // Left to the daemon to timestamp, as they all do for local messages
+    let mut message = format!(
+        "<{}>{ident}[{}]: {} (fingerprint {})",
+        facility.code() * 8 + CRITICAL,
+        report.pid(),
+        format::summary(report),
+        report.fingerprint()
+    );
+
+    if message.len() > MAX_LEN {
+        let mut end = MAX_LEN;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+    }
+
+    let mut last = io::Error::new(io::ErrorKind::NotFound, "no syslog socket found");
+    for socket in SOCKETS {
+        match deliver(socket, message.as_bytes()) {
+            Ok(()) => return Ok(()),
+            Err(e) => last = e,
+        }
+    }
+
+    Err(last)
+}
+
+fn deliver(socket: &str, message: &[u8]) -> io::Result<()> {
+    let datagram = UnixDatagram::unbound()?;
+    match datagram.send_to(message, socket) {
+        Ok(_) => Ok(()),
+        // Some daemons only listen for streams, where messages end with a NUL, as glibc sends them
+        Err(e) if e.raw_os_error() == Some(libc::EPROTOTYPE) => {
+            let mut stream = UnixStream::connect(socket)?;
+            stream.write_all(message)?;
+            stream.write_all(&[0])
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,48 @@
+//! Telling Kubernetes why the container died, see
+//! [`handlers::k8s_termination_log`](crate::handlers::k8s_termination_log).
+
+use crate::PanicReport;
+
+/// Where Kubernetes reads the message from, unless the pod's `terminationMessagePath` says
+/// otherwise.
+pub(crate) const DEFAULT_PATH: &str = "/dev/termination-log";
+
+/// The most Kubernetes keeps of the message, in bytes.
+const MAX_LEN: usize = 4096;
+
+/// What happened, as much of it as fits: the panic, the app, the fingerprint, then as many of the
+/// backtrace's lines as there's room left for.
+pub(crate) fn message(report: &PanicReport<'_>) -> String {
+    let mut message = crate::format::summary(report);
+    if let Some(app) = report.app_metadata() {
+        message += &format!("\napp: {} {}", app.name, app.version);
+    }
+    message += &format!("\nfingerprint: {}", report.fingerprint());
+    // Even the summary may not fit, with a long enough message
+    if message.len() > MAX_LEN {
+        let mut end = MAX_LEN;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+
+        return message;
+    }
+
+    if let Some(backtrace) = report.backtrace_text() {
+        let heading = "\nbacktrace:";
+        if message.len() + heading.len() < MAX_LEN {
+            message += heading;
+            // Whole lines only, so that none is cut off partway
+            for line in backtrace.lines() {
+                if message.len() + 1 + line.len() > MAX_LEN {
+                    break;
+                }
+                message.push('\n');
+                message += line;
+            }
+        }
+    }
+
+    message
+}
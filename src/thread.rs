@@ -0,0 +1,197 @@
+//! Handlers that only apply to panics on the current thread, layered on top of the registered
+//! pipeline, such as for a subsystem whose threads need their own handling.
+
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::panic::PanicHookInfo;
+use std::rc::Rc;
+
+use crate::HandlerError;
+
+/// The type of closures accepted by [`ThreadHandlers`]. As they never leave the thread, they
+/// needn't be `Send` or `Sync`.
+pub type ThreadHandler = Box<dyn Fn(&PanicHookInfo<'_>) -> Result<(), Box<dyn Error>> + 'static>;
+
+thread_local! {
+    /// The innermost of the current thread's overrides.
+    static TOP: RefCell<Option<Rc<Layer>>> = const { RefCell::new(None) };
+    /// Whether the overrides have run for the panic the thread is handling, if it's handling one.
+    static RAN: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+/// Changes to the registered pipeline for panics on one thread, see [`with_handlers`].
+#[derive(Default)]
+pub struct ThreadHandlers {
+    handlers: Vec<ThreadHandler>,
+    suppressed: Vec<String>,
+    suppress_all: bool,
+}
+
+impl ThreadHandlers {
+    /// Constructs a set of overrides that doesn't change anything yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a handler, which runs after the pipeline's own handlers, on the panicking thread. If
+    /// the pipeline's handlers run on a [crash thread](crate::EvacBuilder::crash_thread), that's
+    /// once they're done there, finalizers included.
+    ///
+    /// It runs once per panic, even with several
+    /// [incremental](crate::EvacBuilder::register_incremental) pipelines: the first one to handle
+    /// the panic runs it, and gives its errors to that pipeline's
+    /// [error sink](crate::EvacBuilder::error_sink).
+    pub fn with_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&PanicHookInfo<'_>) -> Result<(), Box<dyn Error>> + 'static,
+    {
+        self.handlers.push(Box::new(handler));
+
+        self
+    }
+
+    /// Skips the pipeline's handler registered under `name`.
+    pub fn suppress(mut self, name: impl Into<String>) -> Self {
+        self.suppressed.push(name.into());
+
+        self
+    }
+
+    /// Skips all of the pipeline's handlers, leaving only the ones added here. Finalizers and
+    /// completion callbacks still run.
+    pub fn suppress_all(mut self) -> Self {
+        self.suppress_all = true;
+
+        self
+    }
+}
+
+/// Applies `handlers` to panics on the current thread while `f` runs. Overrides can be nested,
+/// in which case the inner ones add to the outer ones: their handlers run after the outer ones',
+/// and they skip whatever the outer ones skip.
+///
+/// ## Example
+/// ```
+/// # use evac::thread::ThreadHandlers;
+/// # use evac::EvacBuilder;
+/// EvacBuilder::new()
+///   .with_named_handler("upload", |_, _: &mut ()| Ok(()))
+///   .register(())?;
+///
+/// std::thread::spawn(|| {
+///   let audio = ThreadHandlers::new()
+///     .suppress("upload")
+///     .with_handler(|_| {
+///       eprintln!("resetting the audio device");
+///       Ok(())
+///     });
+///
+///   evac::thread::with_handlers(audio, || {
+///     // The audio subsystem's work
+///   });
+/// });
+/// # Ok::<(), evac::RegisterError>(())
+/// ```
+pub fn with_handlers<R>(handlers: ThreadHandlers, f: impl FnOnce() -> R) -> R {
+    let parent = TOP.with(|top| top.borrow_mut().take());
+    let layer = Rc::new(Layer { handlers, parent });
+    TOP.with(|top| *top.borrow_mut() = Some(layer));
+
+    // Put back on the way out, panicking or not. By then, the hook is done with this panic
+    let _restore = Restore;
+
+    f()
+}
+
+struct Restore;
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let _ = TOP.try_with(|top| {
+            let mut top = top.borrow_mut();
+            let parent = top.as_ref().and_then(|layer| layer.parent.clone());
+            *top = parent;
+        });
+    }
+}
+
+/// One call to [`with_handlers`]'s overrides, along with the ones it's nested in.
+pub(crate) struct Layer {
+    handlers: ThreadHandlers,
+    parent: Option<Rc<Layer>>,
+}
+
+impl Layer {
+    /// The current thread's overrides, if it has any.
+    pub(crate) fn current() -> Option<Rc<Layer>> {
+        TOP.try_with(|top| top.try_borrow().ok().and_then(|top| top.clone()))
+            .ok()
+            .flatten()
+    }
+
+    /// Which of the pipeline's handlers are to be skipped, as every layer says.
+    pub(crate) fn suppressed(&self) -> Suppressed<'_> {
+        Suppressed {
+            all: self.layers().any(|layer| layer.handlers.suppress_all),
+            names: self
+                .layers()
+                .flat_map(|layer| &layer.handlers.suppressed)
+                .map(String::as_str)
+                .collect(),
+        }
+    }
+
+    /// Runs every layer's handlers, outermost first, giving their errors to `report`. Only the
+    /// first pipeline to get this far with a panic runs them, so that they run once, however many
+    /// pipelines handle it.
+    pub(crate) fn run<E>(&self, info: &PanicHookInfo<'_>, report: impl Fn(&HandlerError<'_, E>)) {
+        let ran = RAN
+            .try_with(|ran| ran.replace(ran.get().map(|_| true)))
+            .ok()
+            .flatten();
+        if ran == Some(true) {
+            return;
+        }
+
+        let mut layers: Vec<_> = self.layers().collect();
+        layers.reverse();
+        let handlers = layers.iter().flat_map(|layer| &layer.handlers.handlers);
+        for (index, handler) in handlers.enumerate() {
+            if let Err(e) = handler(info) {
+                report(&HandlerError::Thread { index, error: &*e });
+            }
+        }
+    }
+
+    fn layers(&self) -> impl Iterator<Item = &Layer> {
+        std::iter::successors(Some(self), |layer| layer.parent.as_deref())
+    }
+}
+
+/// Handles a panic on the current thread with `f`, during which the thread's overrides run at
+/// most once. Handling nested in it, as by the hooks of earlier registrations, is part of it.
+pub(crate) fn per_panic(f: impl FnOnce()) {
+    let outermost = RAN
+        .try_with(|ran| ran.get().is_none() && ran.replace(Some(false)).is_none())
+        .unwrap_or(false);
+
+    f();
+
+    if outermost {
+        let _ = RAN.try_with(|ran| ran.set(None));
+    }
+}
+
+/// Which of the pipeline's handlers a thread's overrides skip, taken on the panicking thread, so
+/// that it can be checked wherever the handlers run.
+pub(crate) struct Suppressed<'a> {
+    all: bool,
+    names: Vec<&'a str>,
+}
+
+impl Suppressed<'_> {
+    /// Whether the pipeline's handler registered under `name` is to be skipped.
+    pub(crate) fn suppresses(&self, name: Option<&str>) -> bool {
+        self.all || name.is_some_and(|name| self.names.contains(&name))
+    }
+}
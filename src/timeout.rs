@@ -0,0 +1,147 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::isolate;
+
+/// Returned from a handler added with
+/// [`EvacBuilder::with_handler_timeout`](crate::EvacBuilder::with_handler_timeout) when it doesn't
+/// succeed in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimeoutError {
+    /// The handler was still running at the deadline, and was left behind.
+    Elapsed {
+        /// How long the handler was given.
+        timeout: Duration,
+    },
+    /// The handler returned an error, or panicked, in time.
+    Failed {
+        /// The error's message.
+        message: String,
+    },
+}
+
+impl Display for TimeoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutError::Elapsed { timeout } => {
+                write!(f, "the handler was abandoned after {timeout:?}")
+            }
+            TimeoutError::Failed { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl Error for TimeoutError {}
+
+/// Runs `handler` on a thread of its own, against a copy of `ctx`, and waits up to `timeout` for it
+/// to finish. The copy is only written back if it does, as otherwise the handler may still be
+/// using it. If no thread can be spawned, `handler` is run on the current thread without a timeout.
+pub(crate) fn run<T, F, E>(
+    timeout: Duration,
+    handler: &Arc<F>,
+    message: &str,
+    ctx: &mut T,
+) -> Result<(), TimeoutError>
+where
+    T: Clone + Send + 'static,
+    F: Fn(&str, &mut T) -> Result<(), E> + Send + Sync + 'static,
+    E: Display,
+{
+    let (sender, receiver) = mpsc::sync_channel(1);
+
+    let spawned = {
+        let handler = Arc::clone(handler);
+        let message = message.to_owned();
+        let mut copy = ctx.clone();
+
+        thread::Builder::new()
+            .name("evac-timed-handler".into())
+            .spawn(move || {
+                isolate::mark_isolated();
+
+                // Errors needn't be `Send`, so only their messages make it back
+                let result = isolate::catch(|| handler(&message, &mut copy))
+                    .and_then(|result| result.map_err(|e| e.to_string()));
+
+                // The receiver is gone if we took too long, and nobody's left to tell
+                let _ = sender.send(result.map(|()| copy));
+            })
+    };
+
+    if spawned.is_err() {
+        return handler(message, ctx).map_err(|e| TimeoutError::Failed {
+            message: e.to_string(),
+        });
+    }
+
+    let result = match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => return Err(TimeoutError::Elapsed { timeout }),
+        Err(RecvTimeoutError::Disconnected) => Err("the handler's thread exited early".into()),
+    };
+
+    match result {
+        Ok(copy) => {
+            *ctx = copy;
+            Ok(())
+        }
+        Err(message) => Err(TimeoutError::Failed { message }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(200);
+
+    #[test]
+    fn keeps_what_a_handler_did_in_time() {
+        let handler = Arc::new(|message: &str, seen: &mut Vec<String>| {
+            seen.push(message.to_string());
+            Ok::<_, String>(())
+        });
+        let mut seen = vec![];
+
+        assert_eq!(run(TIMEOUT, &handler, "disk on fire", &mut seen), Ok(()));
+        assert_eq!(seen, ["disk on fire"]);
+    }
+
+    #[test]
+    fn leaves_a_handler_behind_at_the_deadline() {
+        let handler = Arc::new(|_: &str, count: &mut u32| {
+            *count += 1;
+            thread::sleep(TIMEOUT * 10);
+            Ok::<_, String>(())
+        });
+        let mut count = 0;
+
+        assert_eq!(
+            run(TIMEOUT, &handler, "disk on fire", &mut count),
+            Err(TimeoutError::Elapsed { timeout: TIMEOUT })
+        );
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn passes_on_errors_and_panics_as_their_message() {
+        let failing = Arc::new(|_: &str, _: &mut ()| Err("connection refused"));
+        let panicking = Arc::new(|_: &str, _: &mut ()| -> Result<(), String> { panic!("boom") });
+
+        assert_eq!(
+            run(TIMEOUT, &failing, "", &mut ()),
+            Err(TimeoutError::Failed {
+                message: "connection refused".into()
+            })
+        );
+        assert!(matches!(
+            run(TIMEOUT, &panicking, "", &mut ()),
+            Err(TimeoutError::Failed { message }) if message.contains("boom")
+        ));
+    }
+}
@@ -0,0 +1,211 @@
+//! Encrypted connections for the clients that send reports over the network, with rustls, trusting
+//! the certificate authorities Mozilla does.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, OnceLock};
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+/// What every connection is set up with, built once, and ahead of any panic if
+/// [`config`] is called when a handler is built.
+pub(crate) fn config() -> io::Result<Arc<ClientConfig>> {
+    static CONFIG: OnceLock<Result<Arc<ClientConfig>, rustls::Error>> = OnceLock::new();
+
+    // With a provider of its own, rather than the process's, which may not have been picked
+    let config = CONFIG.get_or_init(|| {
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let config =
+            ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()?
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+
+        Ok(Arc::new(config))
+    });
+
+    config.clone().map_err(io::Error::other)
+}
+
+/// `host` as the name a server's certificate is checked against, or an error if it can't be one.
+pub(crate) fn server_name(host: &str) -> io::Result<ServerName<'static>> {
+    ServerName::try_from(host.to_string()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{host}` isn't a name a certificate can be checked against"),
+        )
+    })
+}
+
+/// A connection, encrypted or not.
+pub(crate) enum Stream {
+    Plain(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Stream {
+    /// Encrypts `stream`, as `config` says, checking that it's connected to `host`, and finishing
+    /// the handshake within the stream's timeouts.
+    pub(crate) fn encrypt(
+        stream: TcpStream,
+        host: &str,
+        config: Arc<ClientConfig>,
+    ) -> io::Result<Self> {
+        let connection =
+            ClientConnection::new(config, server_name(host)?).map_err(io::Error::other)?;
+
+        let mut tls = StreamOwned::new(connection, stream);
+        while tls.conn.is_handshaking() {
+            tls.conn.complete_io(&mut tls.sock)?;
+        }
+
+        Ok(Self::Tls(Box::new(tls)))
+    }
+
+    /// Whether the connection is encrypted.
+    #[cfg(feature = "smtp")]
+    pub(crate) fn encrypted(&self) -> bool {
+        matches!(self, Self::Tls(_))
+    }
+
+    /// The connection underneath, such as for setting its timeouts.
+    pub(crate) fn tcp(&self) -> &TcpStream {
+        match self {
+            Self::Plain(stream) => stream,
+            Self::Tls(tls) => tls.get_ref(),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.read(buf),
+            Self::Tls(tls) => tls.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(stream) => stream.write(buf),
+            Self::Tls(tls) => tls.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(stream) => stream.flush(),
+            Self::Tls(tls) => tls.flush(),
+        }
+    }
+}
+
+/// Certificates for servers that tests run, on `localhost`.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::sync::Arc;
+
+    use rustls::pki_types::PrivateKeyDer;
+    use rustls::{ClientConfig, RootCertStore, ServerConfig};
+
+    /// Settings for a server with a certificate for `localhost` of its own, and for clients that
+    /// trust only it.
+    pub(crate) fn self_signed() -> (Arc<ServerConfig>, Arc<ClientConfig>) {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let certificate = certified.cert.der().clone();
+        let key = PrivateKeyDer::Pkcs8(certified.key_pair.serialize_der().into());
+
+        let server = ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![certificate.clone()], key)
+            .unwrap();
+
+        let mut roots = RootCertStore::empty();
+        roots.add(certificate).unwrap();
+        let client = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        (Arc::new(server), Arc::new(client))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    use rustls::ServerConnection;
+
+    use super::*;
+
+    /// A server for `localhost`, answering `pong` to whatever it's sent, and settings for clients
+    /// that trust it.
+    fn server() -> (u16, Arc<ClientConfig>, JoinHandle<io::Result<Vec<u8>>>) {
+        let (config, trusting) = testing::self_signed();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept()?;
+            let connection = ServerConnection::new(config).map_err(io::Error::other)?;
+            let mut tls = StreamOwned::new(connection, stream);
+            let mut received = [0; 4];
+            tls.read_exact(&mut received)?;
+            tls.write_all(b"pong")?;
+            tls.conn.send_close_notify();
+            tls.flush()?;
+
+            Ok(received.to_vec())
+        });
+
+        (port, trusting, server)
+    }
+
+    #[test]
+    fn talks_to_a_trusted_server() {
+        let (port, trusting, server) = server();
+
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut stream = Stream::encrypt(stream, "localhost", trusting).unwrap();
+        stream.write_all(b"ping").unwrap();
+        let mut answer = vec![];
+        stream.read_to_end(&mut answer).unwrap();
+
+        assert_eq!(answer, b"pong");
+        assert_eq!(server.join().unwrap().unwrap(), b"ping");
+    }
+
+    #[test]
+    fn refuses_a_certificate_no_authority_vouches_for() {
+        let (port, _, server) = server();
+
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let refused = Stream::encrypt(stream, "localhost", config().unwrap())
+            .err()
+            .unwrap();
+
+        assert_eq!(refused.kind(), io::ErrorKind::InvalidData);
+        assert!(server.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn refuses_a_server_answering_for_another_name() {
+        let (port, trusting, server) = server();
+
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+
+        assert!(Stream::encrypt(stream, "example.com", trusting).is_err());
+        assert!(server.join().unwrap().is_err());
+    }
+}
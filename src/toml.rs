@@ -0,0 +1,294 @@
+//! Writes reports as TOML, for people to read and attach to bug reports, see
+//! [`handlers::friendly`](crate::handlers::friendly). Keys are named as in the serialized report,
+//! of the same [`SCHEMA_VERSION`], but values are written for people rather than for
+//! [`SerializedReport`](crate::report::SerializedReport): times are TOML's own, rather than
+//! milliseconds, thread frames are text, and attachments are only described, by their size.
+//! Values that aren't known are left out, as TOML has no null.
+
+use std::io::{self, Write};
+
+use crate::format::Rfc3339;
+use crate::report::{thread_number, SCHEMA_VERSION};
+use crate::{PanicReport, PayloadType};
+
+/// Writes `report` as a TOML document.
+pub(crate) fn write(report: &PanicReport<'_>, out: &mut dyn Write) -> io::Result<()> {
+    // Plain keys have to come before any tables
+    writeln!(out, "schema_version = {SCHEMA_VERSION}")?;
+    write!(out, "message = ")?;
+    string(out, report.message().unwrap_or("Box<dyn Any>"))?;
+    writeln!(out)?;
+    write!(out, "payload_type = ")?;
+    string(out, payload_type(report.payload_type()))?;
+    writeln!(out)?;
+    if let Some(thread) = report.thread_name() {
+        write!(out, "thread_name = ")?;
+        string(out, thread)?;
+        writeln!(out)?;
+    }
+    if let Some(id) = thread_number(report.thread_id()) {
+        writeln!(out, "thread_id = {id}")?;
+    }
+    writeln!(out, "pid = {}", report.pid())?;
+    if let Some(parent) = report.parent_pid() {
+        writeln!(out, "parent_pid = {parent}")?;
+    }
+    writeln!(
+        out,
+        "process_started = {}",
+        Rfc3339(report.process_started())
+    )?;
+    writeln!(out, "timestamp = {}", Rfc3339(report.timestamp()))?;
+    writeln!(out, "uptime_ms = {}", report.uptime().as_millis())?;
+    write!(out, "fingerprint = ")?;
+    string(out, report.fingerprint())?;
+    writeln!(out)?;
+    if !report.args().is_empty() {
+        write!(out, "args = [")?;
+        for (i, arg) in report.args().iter().enumerate() {
+            if i > 0 {
+                write!(out, ", ")?;
+            }
+            string(out, arg)?;
+        }
+        writeln!(out, "]")?;
+    }
+    if let Some(backtrace) = report.backtrace_text() {
+        write!(out, "backtrace = ")?;
+        multiline(out, &backtrace)?;
+        writeln!(out)?;
+    }
+
+    if let Some(location) = report.location() {
+        writeln!(out, "\n[location]")?;
+        write!(out, "file = ")?;
+        string(out, location.file())?;
+        writeln!(out)?;
+        writeln!(out, "line = {}", location.line())?;
+        writeln!(out, "column = {}", location.column())?;
+    }
+
+    if let Some(app) = report.app_metadata() {
+        writeln!(out, "\n[app_metadata]")?;
+        let fields = [
+            ("name", Some(app.name)),
+            ("version", Some(app.version)),
+            ("target", app.target),
+            ("profile", Some(app.profile)),
+            ("opt_level", app.opt_level),
+            ("git_commit", app.git_commit),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                pair(out, key, value)?;
+            }
+        }
+    }
+
+    #[cfg(feature = "sysinfo")]
+    if let Some(host) = report.system_info() {
+        writeln!(out, "\n[system_info]")?;
+        let fields = [
+            ("os_name", host.os_name.as_deref()),
+            ("os_version", host.os_version.as_deref()),
+            ("kernel", host.kernel.as_deref()),
+            ("arch", Some(host.arch)),
+            ("hostname", host.hostname.as_deref()),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                pair(out, key, value)?;
+            }
+        }
+        if let Some(memory) = host.total_memory {
+            writeln!(out, "total_memory = {memory}")?;
+        }
+        if let Some(memory) = host.available_memory {
+            writeln!(out, "available_memory = {memory}")?;
+        }
+        if let Some(cpus) = host.cpus {
+            writeln!(out, "cpus = {cpus}")?;
+        }
+        if let Some(container) = host.container {
+            pair(out, "container", &format!("{container:?}").to_lowercase())?;
+        }
+    }
+
+    for (title, pairs) in [
+        ("annotations", report.annotations()),
+        ("env_vars", report.env_vars()),
+    ] {
+        if pairs.is_empty() {
+            continue;
+        }
+
+        writeln!(out, "\n[{title}]")?;
+        for (key, value) in pairs {
+            pair(out, key, value)?;
+        }
+    }
+
+    for breadcrumb in report.breadcrumbs() {
+        writeln!(out, "\n[[breadcrumbs]]")?;
+        pair(out, "category", &breadcrumb.category)?;
+        pair(out, "message", &breadcrumb.message)?;
+        writeln!(out, "timestamp = {}", Rfc3339(breadcrumb.timestamp))?;
+    }
+
+    // Only described, as they needn't be text
+    for attachment in report.attachments() {
+        writeln!(out, "\n[[attachments]]")?;
+        pair(out, "name", &attachment.name)?;
+        writeln!(out, "size = {}", attachment.data.len())?;
+        if attachment.truncated > 0 {
+            writeln!(out, "truncated = {}", attachment.truncated)?;
+        }
+    }
+
+    #[cfg(feature = "all-threads")]
+    for thread in report.threads() {
+        writeln!(out, "\n[[threads]]")?;
+        writeln!(out, "tid = {}", thread.tid)?;
+        if let Some(name) = &thread.name {
+            pair(out, "name", name)?;
+        }
+        if let Some(state) = thread.state {
+            pair(out, "state", &state.to_string())?;
+        }
+        writeln!(out, "panicking = {}", thread.panicking)?;
+        write!(out, "frames = [")?;
+        for (i, frame) in thread.frames.iter().enumerate() {
+            if i > 0 {
+                write!(out, ", ")?;
+            }
+            match &frame.symbol {
+                Some(symbol) => string(out, &format!("{:#x} {symbol}", frame.ip))?,
+                None => string(out, &format!("{:#x}", frame.ip))?,
+            }
+        }
+        writeln!(out, "]")?;
+        if thread.truncated_frames > 0 {
+            writeln!(out, "truncated_frames = {}", thread.truncated_frames)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The name a payload type is serialized under.
+fn payload_type(payload_type: PayloadType) -> &'static str {
+    match payload_type {
+        PayloadType::Str => "str",
+        PayloadType::String => "string",
+        PayloadType::Other => "other",
+    }
+}
+
+/// Writes `key = "value"`, quoting the key, as it may not be a bare one.
+fn pair(out: &mut dyn Write, key: &str, value: &str) -> io::Result<()> {
+    string(out, key)?;
+    write!(out, " = ")?;
+    string(out, value)?;
+    writeln!(out)
+}
+
+/// Writes `s` as a basic string, on one line.
+fn string(out: &mut dyn Write, s: &str) -> io::Result<()> {
+    write!(out, "\"")?;
+    escaped(out, s, false)?;
+    write!(out, "\"")
+}
+
+/// Writes `s` as a multi-line basic string, keeping its line breaks.
+fn multiline(out: &mut dyn Write, s: &str) -> io::Result<()> {
+    // A line break straight after the opening quotes is dropped by readers
+    writeln!(out, "\"\"\"")?;
+    escaped(out, s, true)?;
+    write!(out, "\"\"\"")
+}
+
+fn escaped(out: &mut dyn Write, s: &str, multiline: bool) -> io::Result<()> {
+    for c in s.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' if multiline => writeln!(out)?,
+            '\n' => write!(out, "\\n")?,
+            '\r' => write!(out, "\\r")?,
+            '\t' => write!(out, "\\t")?,
+            c if c.is_control() => write!(out, "\\u{:04X}", c as u32)?,
+            c => write!(out, "{c}")?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::testing;
+
+    fn document(report: &mut PanicReport<'_>) -> String {
+        report.annotations.push(("user.id".into(), "42".into()));
+        report.env_vars.push(("PATH".into(), "/usr/bin".into()));
+        report.args.push("--verbose".into());
+
+        let mut out = Vec::new();
+        let _ = write(report, &mut out);
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    #[test]
+    fn writes_a_document_toml_reads_back() {
+        let written = testing::with_report("a \"quoted\"\tmessage\nover two lines", document);
+        let table: ::toml::Table = written.parse().unwrap();
+
+        assert_eq!(
+            table["schema_version"].as_integer(),
+            Some(SCHEMA_VERSION.into())
+        );
+        assert_eq!(
+            table["message"].as_str(),
+            Some("a \"quoted\"\tmessage\nover two lines")
+        );
+        assert_eq!(table["payload_type"].as_str(), Some("string"));
+        assert_eq!(table["fingerprint"].as_str(), Some("0123456789abcdef"));
+        assert!(table["timestamp"].is_datetime());
+        assert!(table["location"]["file"]
+            .as_str()
+            .unwrap()
+            .ends_with("report.rs"));
+        assert_eq!(table["annotations"]["user.id"].as_str(), Some("42"));
+        assert_eq!(table["env_vars"]["PATH"].as_str(), Some("/usr/bin"));
+        assert_eq!(table["args"][0].as_str(), Some("--verbose"));
+        assert_eq!(table["app_metadata"]["name"].as_str(), Some("evac"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn names_keys_as_the_serialized_report_does() {
+        let (written, serialized) = testing::with_report("keys", |report| {
+            let written = document(report);
+            (written, serde_json::to_value(&*report).ok())
+        });
+        let table: ::toml::Table = written.parse().unwrap();
+        let serialized = serialized.unwrap();
+
+        for (key, value) in &table {
+            let matching = &serialized[key];
+            assert!(
+                !matching.is_null(),
+                "`{key}` isn't in the serialized report"
+            );
+            if let (Some(table), Some(object)) = (value.as_table(), matching.as_object()) {
+                for key in table.keys() {
+                    assert!(
+                        object.contains_key(key),
+                        "`{key}` isn't in the serialized report"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! Emits reports as events of the `tracing` crate, see
+//! [`handlers::tracing`](crate::handlers::tracing).
+
+use ::tracing::Level;
+
+use crate::{format, PanicReport};
+
+/// Emits `report` as an error event, in whichever span is current.
+pub(crate) fn emit(report: &PanicReport<'_>) {
+    let location = report.location();
+    let backtrace = report.backtrace_text();
+    // Fields have to be named up front, so annotations are given together, as `key=value` pairs
+    let annotations = report
+        .annotations()
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    ::tracing::event!(
+        target: "evac",
+        Level::ERROR,
+        exception.message = report.message().unwrap_or("Box<dyn Any>"),
+        exception.stacktrace = backtrace.as_deref(),
+        code.filepath = location.map(|location| location.file()),
+        code.lineno = location.map(|location| location.line()),
+        code.column = location.map(|location| location.column()),
+        thread.name = report.thread_name().unwrap_or("<unnamed>"),
+        evac.fingerprint = report.fingerprint(),
+        service.version = report.app_metadata().map(|app| app.version),
+        evac.annotations = (!annotations.is_empty()).then_some(annotations.as_str()),
+        "{}",
+        format::summary(report),
+    );
+}
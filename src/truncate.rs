@@ -0,0 +1,278 @@
+use crate::PanicReport;
+
+/// Caps on how big reports get, so that a pathological panic, such as one with a message
+/// gigabytes long, can't fill the disk or go over what a collector accepts, see
+/// [`EvacBuilder::report_limits`](crate::EvacBuilder::report_limits).
+///
+/// Whatever's cut short says so, so that reports never look complete when they aren't: text ends
+/// with `[truncated: N more bytes]`, the backtrace with `[truncated: N more frames]`, and thread
+/// traces and attachments count what was cut off them, in `truncated_frames` and
+/// [`Attachment::truncated`](crate::Attachment::truncated).
+///
+/// ## Example
+/// ```
+/// # use evac::ReportLimits;
+/// let limits = ReportLimits::new()
+///   .max_message_len(4 * 1024)
+///   .max_frames(100)
+///   .max_attachment_bytes(1024 * 1024)
+///   .max_report_size(4 * 1024 * 1024);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct ReportLimits {
+    max_message_len: Option<usize>,
+    max_frames: Option<usize>,
+    max_attachment_bytes: Option<usize>,
+    max_report_size: Option<usize>,
+}
+
+impl ReportLimits {
+    /// No limits, until told otherwise.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cuts the panic's message, and each breadcrumb's, to `len` bytes, or just under if that
+    /// would split a character.
+    pub fn max_message_len(mut self, len: usize) -> Self {
+        self.max_message_len = Some(len);
+
+        self
+    }
+
+    /// Cuts the backtrace, and each thread's trace, to its innermost `frames` frames.
+    pub fn max_frames(mut self, frames: usize) -> Self {
+        self.max_frames = Some(frames);
+
+        self
+    }
+
+    /// Cuts each attachment to its first `bytes` bytes, including those attached by handlers.
+    pub fn max_attachment_bytes(mut self, bytes: usize) -> Self {
+        self.max_attachment_bytes = Some(bytes);
+
+        self
+    }
+
+    /// Keeps the report to about `bytes` bytes, once everything else is cut short, by dropping
+    /// attachments, the latest first, then the traces of threads but the one that panicked, then
+    /// breadcrumbs, the oldest first, then environment variables, for as long as it's over. What
+    /// was dropped is noted in an `evac.truncated` annotation. Attachments added afterwards, by
+    /// handlers, are cut to fit in what's left.
+    ///
+    /// Sizes are reckoned from the length of the report's text and data, as formats add their
+    /// own overhead, such as JSON's escapes, and base64 for attachments.
+    pub fn max_report_size(mut self, bytes: usize) -> Self {
+        self.max_report_size = Some(bytes);
+
+        self
+    }
+
+    pub(crate) fn max_frames_or_all(&self) -> usize {
+        self.max_frames.unwrap_or(usize::MAX)
+    }
+
+    /// How much of an attachment of `len` bytes is kept, in a report that's `size` bytes without
+    /// it.
+    pub(crate) fn attachment_room(&self, size: usize, len: usize) -> usize {
+        let room = self
+            .max_report_size
+            .map_or(usize::MAX, |max| max.saturating_sub(size));
+
+        len.min(self.max_attachment_bytes.unwrap_or(usize::MAX))
+            .min(room)
+    }
+
+    pub(crate) fn apply(self, report: &mut PanicReport<'_>) {
+        report.limits = Some(self);
+
+        if let Some(max) = self.max_message_len {
+            if let Some(message) = &mut report.message {
+                truncate_text(message, max);
+            }
+            for breadcrumb in &mut report.breadcrumbs {
+                if breadcrumb.message.len() > max {
+                    truncate_text(breadcrumb.message.to_mut(), max);
+                }
+            }
+        }
+
+        #[cfg(feature = "all-threads")]
+        if let Some(max) = self.max_frames {
+            for thread in &mut report.threads {
+                if thread.frames.len() > max {
+                    thread.truncated_frames += thread.frames.len() - max;
+                    thread.frames.truncate(max);
+                }
+            }
+        }
+
+        if let Some(max) = self.max_attachment_bytes {
+            for attachment in &mut report.attachments {
+                if attachment.data.len() > max {
+                    attachment.truncated += attachment.data.len() - max;
+                    attachment.data.truncate(max);
+                }
+            }
+        }
+
+        if let Some(max) = self.max_report_size {
+            fit(report, max);
+        }
+    }
+}
+
+/// Cuts `text` to `max` bytes, or just under if that would split a character, marking it as
+/// such.
+fn truncate_text(text: &mut String, max: usize) {
+    if text.len() <= max {
+        return;
+    }
+
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let removed = text.len() - end;
+    text.truncate(end);
+    text.push_str(&format!(" [truncated: {removed} more bytes]"));
+}
+
+/// How big `report` is, roughly, see [`ReportLimits::max_report_size`].
+pub(crate) fn size(report: &PanicReport<'_>) -> usize {
+    // Everything of a fixed size, such as the timestamp and process IDs, and field names
+    const FIXED: usize = 512;
+
+    let pairs = |pairs: &[(String, String)]| -> usize {
+        pairs
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .sum()
+    };
+
+    FIXED
+        + report.message().map_or(0, str::len)
+        + report.thread_name().map_or(0, str::len)
+        + report
+            .backtrace_text()
+            .map_or(0, |backtrace| backtrace.len())
+        + report
+            .app_metadata()
+            .map_or(0, |app| format!("{app:?}").len())
+        + pairs(report.env_vars())
+        + report.args().iter().map(String::len).sum::<usize>()
+        + report
+            .breadcrumbs()
+            .iter()
+            .map(breadcrumb_size)
+            .sum::<usize>()
+        + thread_sizes(report)
+        + pairs(report.annotations())
+        + report
+            .attachments()
+            .iter()
+            .map(|attachment| attachment.name.len() + attachment.data.len())
+            .sum::<usize>()
+}
+
+fn breadcrumb_size(breadcrumb: &crate::breadcrumbs::Breadcrumb) -> usize {
+    // With its timestamp
+    32 + breadcrumb.category.len() + breadcrumb.message.len()
+}
+
+#[cfg(feature = "all-threads")]
+fn thread_size(thread: &crate::ThreadTrace) -> usize {
+    let frames: usize = thread
+        .frames
+        .iter()
+        // With its instruction pointer
+        .map(|frame| 20 + frame.symbol.as_ref().map_or(0, String::len))
+        .sum();
+
+    32 + thread.name.as_ref().map_or(0, String::len) + frames
+}
+
+fn thread_sizes(report: &PanicReport<'_>) -> usize {
+    #[cfg(feature = "all-threads")]
+    return report.threads().iter().map(thread_size).sum();
+
+    #[cfg(not(feature = "all-threads"))]
+    {
+        let _ = report;
+        0
+    }
+}
+
+/// Drops what it takes to bring `report` down to about `max` bytes, see
+/// [`ReportLimits::max_report_size`].
+fn fit(report: &mut PanicReport<'_>, max: usize) {
+    let mut size = size(report);
+    if size <= max {
+        return;
+    }
+    let mut dropped = vec![];
+
+    let mut attachments = 0;
+    while size > max {
+        let Some(attachment) = report.attachments.pop() else {
+            break;
+        };
+        size -= attachment.name.len() + attachment.data.len();
+        attachments += 1;
+    }
+    if attachments > 0 {
+        dropped.push(format!("{attachments} attachments"));
+    }
+
+    #[cfg(feature = "all-threads")]
+    {
+        let mut threads = 0;
+        while size > max {
+            let Some(index) = report.threads.iter().rposition(|thread| !thread.panicking) else {
+                break;
+            };
+            size -= thread_size(&report.threads.remove(index));
+            threads += 1;
+        }
+        if threads > 0 {
+            dropped.push(format!("{threads} thread traces"));
+        }
+    }
+
+    let mut breadcrumbs = 0;
+    while size > max && breadcrumbs < report.breadcrumbs.len() {
+        size -= breadcrumb_size(&report.breadcrumbs[breadcrumbs]);
+        breadcrumbs += 1;
+    }
+    if breadcrumbs > 0 {
+        report.breadcrumbs.drain(..breadcrumbs);
+        dropped.push(format!("{breadcrumbs} breadcrumbs"));
+    }
+
+    let mut env_vars = 0;
+    while size > max {
+        let Some((key, value)) = report.env_vars.pop() else {
+            break;
+        };
+        size -= key.len() + value.len();
+        env_vars += 1;
+    }
+    if env_vars > 0 {
+        dropped.push(format!("{env_vars} environment variables"));
+    }
+
+    if !dropped.is_empty() {
+        report.annotate(
+            "evac.truncated",
+            format!(
+                "over the {max}-byte limit, so dropped {}",
+                dropped.join(", ")
+            ),
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What the watchdog does once a pipeline runs past its deadline, see
+/// [`EvacBuilder::deadline`](crate::EvacBuilder::deadline).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum DeadlineAction {
+    /// Abort the process, as per [`std::process::abort`].
+    Abort,
+    /// Exit the process with the given code, as per [`std::process::exit`].
+    Exit(i32),
+}
+
+/// Enforces a pipeline's deadline from a thread of its own.
+pub(crate) struct Watchdog {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+/// How many panics can be handled at once before keeping track of them allocates.
+const CONCURRENT: usize = 8;
+
+struct State {
+    /// When each panic being handled right now started being handled, oldest first.
+    started: Vec<Instant>,
+    /// Set once the pipeline is dropped.
+    shutdown: bool,
+}
+
+impl Watchdog {
+    /// Spawns the watchdog thread. Returns `None` if it can't be spawned.
+    pub(crate) fn spawn(deadline: Duration, action: DeadlineAction) -> Option<Self> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                started: Vec::with_capacity(CONCURRENT),
+                shutdown: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let watched = Arc::clone(&shared);
+        thread::Builder::new()
+            .name("evac-watchdog".into())
+            .spawn(move || watched.watch(deadline, action))
+            .ok()?;
+
+        Some(Self { shared })
+    }
+
+    /// Starts a clock for the panic being handled, which stops once the returned guard is dropped.
+    pub(crate) fn start(&self) -> Running<'_> {
+        let mut state = self.shared.lock();
+        let started = Instant::now();
+        state.started.push(started);
+        // Only the oldest clock is watched, so the watchdog only needs to know if it's this one
+        if state.started.len() == 1 {
+            self.shared.changed.notify_all();
+        }
+
+        Running {
+            shared: &self.shared,
+            started,
+        }
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+/// Held while a panic is being handled, see [`Watchdog::start`].
+pub(crate) struct Running<'a> {
+    shared: &'a Shared,
+    started: Instant,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        // Clocks that started at the same time are as good as each other
+        let Some(index) = state.started.iter().position(|&s| s == self.started) else {
+            return;
+        };
+        state.started.remove(index);
+        if index == 0 {
+            self.shared.changed.notify_all();
+        }
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn watch(&self, deadline: Duration, action: DeadlineAction) {
+        let mut state = self.lock();
+
+        loop {
+            if state.shutdown {
+                return;
+            }
+
+            let Some(&oldest) = state.started.first() else {
+                state = self
+                    .changed
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+                continue;
+            };
+
+            let remaining = deadline.saturating_sub(oldest.elapsed());
+            if remaining.is_zero() {
+                break;
+            }
+
+            state = self
+                .changed
+                .wait_timeout(state, remaining)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+
+        drop(state);
+        let _ = io::stderr().write_all(b"evac: handling the panic ran past its deadline\n");
+
+        match action {
+            DeadlineAction::Abort => std::process::abort(),
+            DeadlineAction::Exit(code) => std::process::exit(code),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times_each_panic_from_when_it_started() {
+        let watchdog = Watchdog::spawn(Duration::from_secs(3600), DeadlineAction::Abort).unwrap();
+
+        let first = watchdog.start();
+        let second = watchdog.start();
+        let third = watchdog.start();
+        let second_started = second.started;
+        drop(first);
+        let oldest = watchdog.shared.lock().started.first().copied();
+        drop(third);
+        let left = watchdog.shared.lock().started.clone();
+        drop(second);
+
+        assert_eq!(oldest, Some(second_started));
+        assert_eq!(left, [second_started]);
+        assert!(watchdog.shared.lock().started.is_empty());
+    }
+}
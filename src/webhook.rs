@@ -0,0 +1,122 @@
+//! Posts crash summaries to chat, see [`handlers::webhook`](crate::handlers::webhook).
+
+use std::io;
+
+use serde::Serialize;
+
+use crate::{format, json, PanicReport};
+
+/// How long the panic message in a summary can be, in characters, to keep well within what chat
+/// services take, such as Discord's 2000.
+const MAX_MESSAGE: usize = 1000;
+
+/// What kind of incoming webhook [`handlers::webhook`](crate::handlers::webhook) posts to, which
+/// decides how the summary is sent and formatted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum WebhookTemplate {
+    /// A Slack incoming webhook, sent as `text`, in Slack's own markup.
+    Slack,
+    /// A Discord webhook, sent as `content`, in Markdown, mentioning no one, whatever the message
+    /// holds.
+    Discord,
+    /// A Microsoft Teams incoming webhook, sent as the `text` of a message card, in Markdown.
+    Teams,
+}
+
+#[derive(Serialize)]
+struct Slack<'a> {
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+struct Discord<'a> {
+    content: &'a str,
+    allowed_mentions: AllowedMentions,
+}
+
+#[derive(Serialize)]
+struct AllowedMentions {
+    parse: [&'static str; 0],
+}
+
+#[derive(Serialize)]
+struct Teams<'a> {
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    #[serde(rename = "@context")]
+    context: &'static str,
+    summary: &'a str,
+    text: &'a str,
+}
+
+/// The body of a post about `report`, as `template` wants it.
+pub(crate) fn body(report: &PanicReport<'_>, template: WebhookTemplate) -> io::Result<Vec<u8>> {
+    let text = text(report, template);
+
+    match template {
+        WebhookTemplate::Slack => json::to_vec(&Slack { text: &text }),
+        WebhookTemplate::Discord => json::to_vec(&Discord {
+            content: &text,
+            allowed_mentions: AllowedMentions { parse: [] },
+        }),
+        WebhookTemplate::Teams => json::to_vec(&Teams {
+            kind: "MessageCard",
+            context: "https://schema.org/extensions",
+            summary: &title(report),
+            text: &text,
+        }),
+    }
+}
+
+/// Such as `my-app 1.2.0 crashed on build-04`.
+fn title(report: &PanicReport<'_>) -> String {
+    let title = match report.app_metadata() {
+        Some(app) => format!("{} {} crashed", app.name, app.version),
+        None => match crate::handlers::executable_name() {
+            Some(name) => format!("{name} crashed"),
+            None => "The program crashed".to_string(),
+        },
+    };
+    #[cfg(feature = "sysinfo")]
+    if let Some(host) = report
+        .system_info()
+        .and_then(|host| host.hostname.as_deref())
+    {
+        return format!("{title} on {host}");
+    }
+
+    title
+}
+
+fn text(report: &PanicReport<'_>, template: WebhookTemplate) -> String {
+    let mut summary = format::summary(report);
+    if let Some((end, _)) = summary.char_indices().nth(MAX_MESSAGE) {
+        summary.truncate(end);
+        summary += "…";
+    }
+    // Nothing in it can close the code span early
+    let summary = summary.replace('`', "'");
+
+    let title = title(report);
+    let fingerprint = report.fingerprint();
+    let text = match template {
+        WebhookTemplate::Slack => format!("*{title}*\n`{summary}`\nfingerprint `{fingerprint}`"),
+        WebhookTemplate::Discord => {
+            format!("**{title}**\n`{summary}`\nfingerprint `{fingerprint}`")
+        }
+        // Teams only breaks lines between paragraphs
+        WebhookTemplate::Teams => {
+            format!("**{title}**\n\n`{summary}`\n\nfingerprint `{fingerprint}`")
+        }
+    };
+
+    match template {
+        // Slack takes these as markup of its own
+        WebhookTemplate::Slack => text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;"),
+        _ => text,
+    }
+}
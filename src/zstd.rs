@@ -0,0 +1,419 @@
+//! Just enough of a zstd compressor for reports, see RFC 8878. Literals are left as they are, and
+//! sequences use the predefined codes, which is most of what zstd gains on text this size.
+
+use crate::compress::{self, Limits, Match};
+
+const MAGIC: u32 = 0xfd2f_b528;
+const BLOCK_SIZE: usize = 128 * 1024;
+
+/// Where each literal length code's lengths start, and how many extra bits follow it.
+const LITERAL_LENGTHS: [(u32, u8); 36] = [
+    (0, 0),
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 0),
+    (12, 0),
+    (13, 0),
+    (14, 0),
+    (15, 0),
+    (16, 1),
+    (18, 1),
+    (20, 1),
+    (22, 1),
+    (24, 2),
+    (28, 2),
+    (32, 3),
+    (40, 3),
+    (48, 4),
+    (64, 6),
+    (128, 7),
+    (256, 8),
+    (512, 9),
+    (1024, 10),
+    (2048, 11),
+    (4096, 12),
+    (8192, 13),
+    (16384, 14),
+    (32768, 15),
+    (65536, 16),
+];
+
+/// Where each match length code's lengths start, past the 32 codes for 3 to 34, and how many
+/// extra bits follow it.
+const MATCH_LENGTHS: [(u32, u8); 21] = [
+    (35, 1),
+    (37, 1),
+    (39, 1),
+    (41, 1),
+    (43, 2),
+    (47, 2),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 5),
+    (131, 7),
+    (259, 8),
+    (515, 9),
+    (1027, 10),
+    (2051, 11),
+    (4099, 12),
+    (8195, 13),
+    (16387, 14),
+    (32771, 15),
+    (65539, 16),
+];
+
+const LITERAL_LENGTH_DISTRIBUTION: [i16; 36] = [
+    4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1, 1, 1,
+    -1, -1, -1, -1,
+];
+const MATCH_LENGTH_DISTRIBUTION: [i16; 53] = [
+    1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+];
+const OFFSET_DISTRIBUTION: [i16; 29] = [
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+];
+
+/// Compresses `data` into a single zstd frame, at `level` from 1 to 19.
+pub(crate) fn compress(data: &[u8], level: u32) -> Vec<u8> {
+    let mut out = MAGIC.to_le_bytes().to_vec();
+
+    // A single segment, with the content size in as few bytes as it fits in
+    let (size_flag, size_bytes) = match data.len() {
+        0..=255 => (0, 1),
+        256..=65_791 => (1, 2),
+        len if len <= u32::MAX as usize => (2, 4),
+        _ => (3, 8),
+    };
+    out.push(size_flag << 6 | 1 << 5);
+    let size = match size_flag {
+        1 => data.len() as u64 - 256,
+        _ => data.len() as u64,
+    };
+    out.extend(&size.to_le_bytes()[..size_bytes]);
+
+    let limits = Limits {
+        min_len: 3,
+        max_len: 131_074,
+        max_distance: BLOCK_SIZE,
+        boundary: Some(BLOCK_SIZE),
+        depth: 4 << level.min(12),
+        lazy: level >= 3,
+    };
+    let matches = compress::find_matches(data, &limits);
+
+    let blocks = data.len().div_ceil(BLOCK_SIZE).max(1);
+    let mut matches = matches.into_iter().peekable();
+    for i in 0..blocks {
+        let start = i * BLOCK_SIZE;
+        let end = (start + BLOCK_SIZE).min(data.len());
+        let last = (i + 1 == blocks) as u32;
+
+        let mut block_matches = vec![];
+        while let Some(found) = matches.next_if(|found| found.pos < end) {
+            block_matches.push(found);
+        }
+
+        let compressed = compressed_block(&data[start..end], start, &block_matches);
+        match compressed {
+            Some(compressed) if compressed.len() < end - start => {
+                let header = last | 2 << 1 | (compressed.len() as u32) << 3;
+                out.extend(&header.to_le_bytes()[..3]);
+                out.extend(compressed);
+            }
+            _ => {
+                let header = last | ((end - start) as u32) << 3;
+                out.extend(&header.to_le_bytes()[..3]);
+                out.extend(&data[start..end]);
+            }
+        }
+    }
+
+    out
+}
+
+/// A block's literals and sequences, for `block` starting at `offset` in the data, if it has any
+/// matches.
+fn compressed_block(block: &[u8], offset: usize, matches: &[Match]) -> Option<Vec<u8>> {
+    if matches.is_empty() {
+        return None;
+    }
+
+    // Literals are whatever the matches don't cover
+    let mut literals: Vec<u8> = Vec::with_capacity(block.len());
+    let mut sequences = Vec::with_capacity(matches.len());
+    let mut pos = 0;
+    for found in matches {
+        let start = found.pos - offset;
+        literals.extend(&block[pos..start]);
+        sequences.push(Sequence {
+            literal_length: (start - pos) as u32,
+            match_length: found.len as u32,
+            // With no repeat offsets, which take up 1 to 3
+            offset: found.distance as u32 + 3,
+        });
+        pos = start + found.len;
+    }
+    literals.extend(&block[pos..]);
+
+    let mut out = Vec::with_capacity(block.len());
+    let len = literals.len() as u32;
+    match len {
+        0..=31 => out.push((len << 3) as u8),
+        32..=4095 => out.extend((1 << 2 | len << 4).to_le_bytes()[..2].iter()),
+        _ => out.extend((3 << 2 | len << 4).to_le_bytes()[..3].iter()),
+    }
+    out.extend(literals);
+
+    let count = sequences.len();
+    match count {
+        0..=127 => out.push(count as u8),
+        128..=0x7eff => out.extend([(count >> 8) as u8 + 128, count as u8]),
+        _ => {
+            out.push(255);
+            out.extend((count as u16 - 0x7f00).to_le_bytes());
+        }
+    }
+    // Predefined codes for all three
+    out.push(0);
+
+    let literal_lengths = Table::new(&LITERAL_LENGTH_DISTRIBUTION, 6);
+    let match_lengths = Table::new(&MATCH_LENGTH_DISTRIBUTION, 6);
+    let offsets = Table::new(&OFFSET_DISTRIBUTION, 5);
+
+    // Sequences are written last to first, so that they're read first to last
+    let codes: Vec<_> = sequences.iter().map(Sequence::codes).collect();
+    let mut bits = BitWriter::default();
+    let (last, rest) = codes.split_last()?;
+    let mut literal_length_state = literal_lengths.first_state(last.literal_length.0);
+    let mut match_length_state = match_lengths.first_state(last.match_length.0);
+    let mut offset_state = offsets.first_state(last.offset.0);
+    last.write_extra(&mut bits);
+    for codes in rest.iter().rev() {
+        offsets.encode(&mut offset_state, codes.offset.0, &mut bits);
+        match_lengths.encode(&mut match_length_state, codes.match_length.0, &mut bits);
+        literal_lengths.encode(&mut literal_length_state, codes.literal_length.0, &mut bits);
+        codes.write_extra(&mut bits);
+    }
+    bits.write(match_length_state as u64, 6);
+    bits.write(offset_state as u64, 5);
+    bits.write(literal_length_state as u64, 6);
+    out.extend(bits.finish());
+
+    Some(out)
+}
+
+struct Sequence {
+    literal_length: u32,
+    match_length: u32,
+    offset: u32,
+}
+
+/// Each of a sequence's codes, with the extra bits that follow it and how many there are.
+struct Codes {
+    literal_length: (usize, u32, u8),
+    match_length: (usize, u32, u8),
+    offset: (usize, u32, u8),
+}
+
+impl Sequence {
+    fn codes(&self) -> Codes {
+        let literal_length = {
+            let code =
+                LITERAL_LENGTHS.partition_point(|&(start, _)| start <= self.literal_length) - 1;
+            let (start, bits) = LITERAL_LENGTHS[code];
+            (code, self.literal_length - start, bits)
+        };
+        let match_length = match self.match_length {
+            len @ 3..=34 => (len as usize - 3, 0, 0),
+            len => {
+                let code = MATCH_LENGTHS.partition_point(|&(start, _)| start <= len) - 1;
+                let (start, bits) = MATCH_LENGTHS[code];
+                (code + 32, len - start, bits)
+            }
+        };
+        let offset = {
+            let code = 31 - self.offset.leading_zeros();
+            (code as usize, self.offset - (1 << code), code as u8)
+        };
+
+        Codes {
+            literal_length,
+            match_length,
+            offset,
+        }
+    }
+}
+
+impl Codes {
+    fn write_extra(&self, bits: &mut BitWriter) {
+        bits.write(self.literal_length.1 as u64, self.literal_length.2);
+        bits.write(self.match_length.1 as u64, self.match_length.2);
+        bits.write(self.offset.1 as u64, self.offset.2);
+    }
+}
+
+/// An FSE table, built from a distribution the way decoders build it.
+struct Table {
+    accuracy_log: u8,
+    /// For each state, its symbol, how many bits are read for the next state, and what they're
+    /// added to.
+    states: Vec<(usize, u8, u32)>,
+}
+
+impl Table {
+    fn new(distribution: &[i16], accuracy_log: u8) -> Self {
+        let size = 1usize << accuracy_log;
+        let mut symbols = vec![0; size];
+
+        // Symbols with a probability of less than 1 take the last states
+        let mut high = size;
+        for (symbol, &probability) in distribution.iter().enumerate() {
+            if probability == -1 {
+                high -= 1;
+                symbols[high] = symbol;
+            }
+        }
+
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut pos = 0;
+        for (symbol, &probability) in distribution.iter().enumerate() {
+            for _ in 0..probability.max(0) {
+                symbols[pos] = symbol;
+                pos = (pos + step) & (size - 1);
+                while pos >= high {
+                    pos = (pos + step) & (size - 1);
+                }
+            }
+        }
+
+        let mut next: Vec<u32> = distribution
+            .iter()
+            .map(|&probability| probability.max(1) as u32)
+            .collect();
+        let states = symbols
+            .into_iter()
+            .map(|symbol| {
+                let state = next[symbol];
+                next[symbol] += 1;
+
+                let bits = accuracy_log - (31 - state.leading_zeros()) as u8;
+                (symbol, bits, (state << bits) - size as u32)
+            })
+            .collect();
+
+        Self {
+            accuracy_log,
+            states,
+        }
+    }
+
+    /// Any state for `symbol`, for the last sequence, whose state is written as it is.
+    fn first_state(&self, symbol: usize) -> usize {
+        self.states
+            .iter()
+            .position(|&(state_symbol, _, _)| state_symbol == symbol)
+            .unwrap()
+    }
+
+    /// Moves from `state` to one for `symbol`, writing the bits a decoder at that one reads to get
+    /// back to `state`.
+    fn encode(&self, state: &mut usize, symbol: usize, bits: &mut BitWriter) {
+        let target = *state as u32;
+        let (from, &(_, nb_bits, baseline)) = self
+            .states
+            .iter()
+            .enumerate()
+            .find(|&(_, &(state_symbol, nb_bits, baseline))| {
+                state_symbol == symbol && (baseline..baseline + (1 << nb_bits)).contains(&target)
+            })
+            .unwrap();
+        debug_assert!(nb_bits <= self.accuracy_log);
+
+        bits.write((target - baseline) as u64, nb_bits);
+        *state = from;
+    }
+}
+
+/// Packs bits least significant first, to be read back from the end, as zstd's bitstreams are.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    pending: u64,
+    pending_bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, bits: u8) {
+        self.pending |= value << self.pending_bits;
+        self.pending_bits += bits as u32;
+        while self.pending_bits >= 8 {
+            self.bytes.push(self.pending as u8);
+            self.pending >>= 8;
+            self.pending_bits -= 8;
+        }
+    }
+
+    /// Ends it with a 1 bit, for decoders to find where it starts.
+    fn finish(mut self) -> Vec<u8> {
+        self.write(1, 1);
+        if self.pending_bits > 0 {
+            self.bytes.push(self.pending as u8);
+        }
+
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress::testing::samples;
+
+    #[test]
+    fn compresses_what_zstd_decompresses() {
+        for (sample, data) in samples() {
+            for level in [1, 2, 3, 9, 19] {
+                let compressed = compress(&data, level);
+
+                assert_eq!(
+                    ::zstd::decode_all(&compressed[..]).unwrap(),
+                    data,
+                    "{sample} at level {level}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gives_the_content_size() {
+        for (sample, data) in samples() {
+            let size = ::zstd::zstd_safe::get_frame_content_size(&compress(&data, 3));
+
+            assert_eq!(size.ok(), Some(Some(data.len() as u64)), "{sample}");
+        }
+    }
+
+    #[test]
+    fn makes_what_repeats_smaller() {
+        for (sample, data) in samples() {
+            let compressed = compress(&data, 3).len();
+
+            // Blocks are stored as they are rather than grow, at the cost of their headers
+            assert!(compressed <= data.len() + 16, "{sample}");
+            if sample == "repeated text" || sample == "a run" {
+                assert!(compressed < data.len() / 50, "{sample}");
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Registering incrementally mustn't deadlock with a panic being handled on another thread, as
+//! std holds its lock on the hook while the panic runs it, and the hook reads the dispatch table.
+
+use std::panic;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use evac::{EvacBuilder, LocalEvacBuilder};
+
+const ROUNDS: usize = 500;
+
+#[test]
+fn registering_while_panicking_does_not_deadlock() {
+    // Keeps the panics below from flooding the output
+    panic::set_hook(Box::new(|_| {}));
+
+    let (done, finished) = mpsc::channel();
+    let mut threads = vec![];
+    for _ in 0..2 {
+        threads.push(thread::spawn(|| {
+            for _ in 0..ROUNDS {
+                let _ = panic::catch_unwind(|| panic!("racing registrations"));
+            }
+        }));
+        threads.push(thread::spawn(|| {
+            for _ in 0..ROUNDS {
+                let handle = EvacBuilder::new()
+                    .with_handler(|_, _: &mut ()| Ok(()))
+                    .register_incremental(())
+                    .unwrap();
+                handle.uninstall();
+            }
+        }));
+        threads.push(thread::spawn(|| {
+            for _ in 0..ROUNDS {
+                let handle = LocalEvacBuilder::new()
+                    .with_handler(|_, _: &mut ()| Ok(()))
+                    .register(());
+                let _ = panic::catch_unwind(|| panic!("racing local registrations"));
+                handle.uninstall();
+            }
+        }));
+    }
+    thread::spawn(move || {
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let _ = done.send(());
+    });
+
+    // Failing the usual way would panic, which would wait on the deadlocked hook in turn
+    if finished.recv_timeout(Duration::from_secs(60)).is_err() {
+        eprintln!("registering and panicking at the same time deadlocked");
+        std::process::exit(1);
+    }
+}
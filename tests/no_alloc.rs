@@ -0,0 +1,82 @@
+//! Dispatching a panic to its handlers mustn't allocate, as the panic may well have come from
+//! running out of memory.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use evac::{EvacBuilder, Position};
+
+/// Counts the allocations made on a thread while it's counting.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.with(Cell::get) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if COUNTING.with(Cell::get) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[derive(Debug)]
+struct Failed;
+
+impl std::fmt::Display for Failed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("failed")
+    }
+}
+
+#[test]
+fn dispatch_does_not_allocate() {
+    // Stops counting once the pipeline is done with the panic
+    panic::set_hook(Box::new(|_| COUNTING.with(|counting| counting.set(false))));
+
+    let _guard = EvacBuilder::<u32, Failed>::default()
+        .preserve_existing_hook(Position::After)
+        .with_handler(|_, count| {
+            *count += 1;
+            Ok(())
+        })
+        .with_named_handler("failing", |_, _| Err(Failed))
+        .with_handler(|_, count| {
+            *count += 1;
+            Ok(())
+        })
+        .register_scoped(0)
+        .unwrap();
+
+    // Warm up anything std sets up on a thread's first panic
+    let _ = panic::catch_unwind(|| panic!("warming up"));
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let _ = panic::catch_unwind(|| {
+        COUNTING.with(|counting| counting.set(true));
+        panic!("counted");
+    });
+    COUNTING.with(|counting| counting.set(false));
+
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed) - before, 0);
+}